@@ -1,16 +1,32 @@
 use rustc_hash::FxHashMap;
 use serde::Deserialize;
 use std::{
-	fs::File,
+	fmt,
+	str::FromStr,
+	ffi::OsString,
+	fs::{
+		canonicalize, read, remove_file, rename, File, Permissions,
+	},
 	io::{
-		Error as IoError, Result as IoResult,
+		Error as IoError, ErrorKind as IoErrorKind, Result as IoResult,
 		Read, Write, Seek,
 	},
-	path::PathBuf,
+	path::{
+		Path, PathBuf,
+	},
+};
+use crate::{
+	migration::{
+		migrate, MigrationError, SCHEMA_VERSION,
+	},
+	journaled_changes, lock_path, merge_layers, CompilerNames, ConfigLayer, Journal, JournalEntry, JournalError,
+	LayerFile, LockFile, SelectorBuf, Suggestions, TomlDiagnostic, ValueOrigin, NO_CREATE_CONFIG_VAR,
 };
 use toml_edit::{
-	de::from_document,
-	DocumentMut, TomlError,
+	de::{
+		from_document, Error as DeError,
+	},
+	DocumentMut, Item, TableLike, TomlError, Value,
 };
 
 /// Configuration for the main Rookup CLI and Rookup proxies.
@@ -20,24 +36,138 @@ use toml_edit::{
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 pub struct ConfigData {
+	/// Version of the format of this file, which Rookup uses to upgrade it after changes to the format.
+	/// 
+	/// This shouldn't be changed manually.
+	#[serde(default)]
+	pub schema_version: u32,
 	/// Selector for the toolchain to use by default when invoking Rookup proxies.
-	pub default: String,
+	#[serde(with = "crate::toolchain::selector_buf")]
+	pub default: SelectorBuf,
+	/// Whether `rookup-spcomp` should append a line for every compiler invocation to `invocations.log` in the state
+	/// directory of Rookup.
+	/// 
+	/// Each line has the time of invocation, the toolchain, a digest of the arguments, the exit code, and the duration,
+	/// separated by tabs.
+	#[serde(default)]
+	pub log_invocations: bool,
+	/// Whether `rookup-spcomp` should print the toolchain, the duration, and the exit code of every compilation to
+	/// standard error after the compiler exits.
+	#[serde(default)]
+	pub time_compiles: bool,
+	/// Whether `rookup-spcomp` should run a compiler found in `PATH` (like `spcomp`) that isn't managed by Rookup, with a
+	/// warning, when the selected toolchain isn't installed.
+	#[serde(default)]
+	pub fallback_to_system: bool,
+	/// List of include directories that `rookup-spcomp` passes to the compiler with `-i`, after the other arguments.
+	/// 
+	/// Relative paths are resolved against the directory of the configuration file that sets this, which is the
+	/// project root for `rookup.toml`.
+	#[serde(default)]
+	pub include_dirs: Vec<String>,
 	/// Map of aliases to their associated version.
 	pub aliases: FxHashMap<String, String>,
+	/// Map of aliases to how `rookup update` may change them.
+	/// 
+	/// `track` (the default for aliases that aren't listed here) follows the latest build of the alias's branch.
+	/// `pin` keeps the alias at its version, and `rookup update` skips it.
+	/// `frozen` makes `rookup update` fail if it would change the alias's version.
+	#[serde(default)]
+	pub alias_policies: FxHashMap<String, AliasPolicy>,
+	/// Map of operating systems (for e.g. `windows`) to commands that should run compilers built for them, when they
+	/// aren't the host's (for e.g. `["wine"]`).
+	#[serde(default)]
+	pub runners: FxHashMap<String, Vec<String>>,
+	/// Map of targets, as `<architecture>-<operating system>` (for e.g. `aarch64-windows`), to file names of the compiler
+	/// executable in toolchains for them, in order of preference.
+	/// 
+	/// Targets that aren't listed here use the names that Rookup expects by default (for e.g. `spcomp64.exe`).
+	#[serde(default)]
+	pub compilers: FxHashMap<String, Vec<String>>,
+	/// Name of the source in `sources` to use instead of the `source` table.
+	/// 
+	/// This can also be set for a single command with `rookup --source <name>`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub default_source: Option<String>,
 	/// See [`Source`].
 	pub source: Source,
+	/// Map of names to other sources, which are tables with the same keys as the `source` table (for e.g.
+	/// `[sources.corp-mirror]`).
+	#[serde(default)]
+	pub sources: FxHashMap<String, Source>,
+	/// See [`Hooks`].
+	#[serde(default)]
+	pub hooks: Hooks,
 }
 
 impl Default for ConfigData {
 	fn default() -> Self {
 		Self {
+			schema_version: SCHEMA_VERSION,
 			default: "stable".into(),
+			log_invocations: false,
+			time_compiles: false,
+			fallback_to_system: false,
+			include_dirs: Vec::new(),
 			aliases: Default::default(),
+			alias_policies: Default::default(),
+			runners: Default::default(),
+			compilers: Default::default(),
+			default_source: None,
 			source: Default::default(),
+			sources: Default::default(),
+			hooks: Default::default(),
+		}
+	}
+}
+
+impl ConfigData {
+	/// Return the policy of `alias`, which is [`AliasPolicy::Track`] unless another one is set.
+	pub fn alias_policy(&self, alias: &str) -> AliasPolicy {
+		self.alias_policies.get(alias).copied().unwrap_or_default()
+	}
+}
+
+/// Enumeration of ways that `rookup update` may change an alias.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AliasPolicy {
+	/// Follow the latest build of the alias's branch.
+	#[default]
+	Track,
+	/// Never change the alias.
+	Pin,
+	/// Fail instead of changing the alias.
+	Frozen,
+}
+
+impl FromStr for AliasPolicy {
+	type Err = AliasPolicyFromStrError;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"track" => Ok(Self::Track),
+			"pin" => Ok(Self::Pin),
+			"frozen" => Ok(Self::Frozen),
+			_ => Err(AliasPolicyFromStrError(s.into())),
 		}
 	}
 }
 
+impl fmt::Display for AliasPolicy {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Track => "track",
+			Self::Pin => "pin",
+			Self::Frozen => "frozen",
+		})
+	}
+}
+
+/// Error returned when parsing an [`AliasPolicy`] from an unknown string.
+#[derive(Debug, thiserror::Error)]
+#[error("unknown alias policy {0:?} (expected `track`, `pin`, or `frozen`)")]
+pub struct AliasPolicyFromStrError(pub String);
+
 /// Configuration for downloading SourcePawn toolchains from an external server.
 // TODO: Documentation for this should be public!
 #[derive(documented::Documented, documented::DocumentedFields, field_access::FieldAccess, serde::Serialize)]
@@ -45,27 +175,378 @@ impl Default for ConfigData {
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 pub struct Source {
+	/// Kind of server that toolchains are fetched from.
+	/// 
+	/// `smdrop` is a static file server with SourceMod builds laid out like `https://sm.alliedmods.net/smdrop/`.
+	/// `sourcepawn` is a GitHub releases API endpoint with standalone SourcePawn compiler builds, like
+	/// `https://api.github.com/repos/alliedmodders/sourcepawn/releases`.
+	/// `command` runs the program specified by `command` to list and fetch SourceMod builds.
+	/// `oci` is a repository in a container registry with SourceMod builds pushed as OCI artifacts (for e.g. with ORAS),
+	/// tagged with their versions, like `https://ghcr.io/example/sourcemod`.
+	#[serde(default)]
+	pub kind: SourceKind,
 	/// Root URL for a static file server to fetch SourceMod (with SourcePawn packaged) from.
+	/// 
+	/// For `sourcepawn` sources, this is the URL of the releases API endpoint.
+	/// For `oci` sources, this is the URL of the repository, made of the registry and the repository name.
 	pub root_url: String,
 	/// Maximum size, in bytes, that is allowed to be downloaded from the server.
 	pub max_download_size: u64,
+	/// Maximum rate, in bytes per second, at which archives are downloaded, or `0` for no limit.
+	#[serde(default)]
+	pub max_download_rate: u64,
+	/// Size, in bytes, above which a downloaded archive is written to a temporary file in the cache directory instead of
+	/// being kept in memory.
+	#[serde(default = "default_spool_threshold")]
+	pub spool_threshold: u64,
+	/// Maximum number of redirects to follow for a single request.
+	/// 
+	/// If this is `0`, requests that are redirected fail.
+	#[serde(default = "default_max_redirects")]
+	pub max_redirects: u32,
+	/// Whether requests may be redirected to a different host than the one they were made to.
+	#[serde(default = "default_allow_cross_host_redirects")]
+	pub allow_cross_host_redirects: bool,
+	/// Maximum total size, in bytes, of the files in a downloaded archive.
+	#[serde(default = "default_max_unpacked_size")]
+	pub max_unpacked_size: u64,
+	/// Maximum size, in bytes, of a single file in a downloaded archive.
+	#[serde(default = "default_max_entry_size")]
+	pub max_entry_size: u64,
+	/// Maximum number of files in a downloaded archive.
+	#[serde(default = "default_max_entries")]
+	pub max_entries: u64,
+	/// Permissions given to the compiler and other executable files extracted from archives on Unix-like systems,
+	/// written in octal (for e.g. `755`).
+	/// 
+	/// Permissions not allowed by the umask of the user are still removed.
+	#[serde(default)]
+	pub executable_mode: FileMode,
+	/// Optional parts of SourceMod distributions to extract into the `extras` directory of installed toolchains.
+	/// 
+	/// `scripts` are the `.sp` sources of the stock plugins, `plugins` are the compiled stock plugins, and
+	/// `translations` are the translation phrase files.
+	#[serde(default)]
+	pub extras: Vec<Extra>,
+	/// Parts of toolchains to install, which may be `compiler` (the compiler executable) and `includes` (the `include`
+	/// directory).
+	/// 
+	/// All parts are installed if this is empty.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub components: Vec<Component>,
+	/// Operating system to select archives for (for e.g. `linux` or `windows`), instead of the host's.
+	/// 
+	/// Toolchains for other operating systems should be installed into a separate toolchain home.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub target_os: Option<String>,
+	/// Program and arguments to run for `command` sources.
+	/// 
+	/// The program is run with `branches` appended to print a JSON array of branch names, with `versions <branch> <os>`
+	/// appended to print a JSON array of objects with `version` and `url` fields, and with `fetch <url>` appended to
+	/// write the archive to standard output.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub command: Vec<String>,
+	/// What to do when a downloaded archive has a different SHA-256 digest than when it was first downloaded, as
+	/// recorded in `pins.toml` next to the configuration file.
+	/// 
+	/// May be `fail` or `warn`.
+	#[serde(default)]
+	pub pin_mismatch: PinMismatch,
+	/// See [`Signing`].
+	#[serde(default)]
+	pub signing: Signing,
+}
+
+/// Keys for verifying signatures (`.minisig` or `.asc` files next to archives) published by the server.
+/// 
+/// Archives with signatures are only installed if the signatures are valid for any of the keys.
+// TODO: Documentation for this should be public!
+#[derive(documented::Documented, documented::DocumentedFields, field_access::FieldAccess, serde::Serialize)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct Signing {
+	/// Minisign public keys (for e.g. `RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3`).
+	#[serde(default)]
+	pub minisign_keys: Vec<String>,
+	/// Path to an OpenPGP keyring to verify `.asc` signatures against with `gpgv`.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub gpg_keyring: Option<PathBuf>,
+	/// Whether archives without a valid signature should be refused.
+	#[serde(default)]
+	pub required: bool,
+}
+
+/// Commands that Rookup runs after some of its operations.
+/// 
+/// Every command is split into arguments like a POSIX shell would (without expanding anything), after which
+/// placeholders in the arguments are replaced. Commands are run in the current directory, and Rookup fails if any of
+/// them fails.
+// TODO: Documentation for this should be public!
+#[derive(documented::Documented, documented::DocumentedFields, field_access::FieldAccess, serde::Serialize)]
+#[derive(Default, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct Hooks {
+	/// Commands to run after a toolchain is installed or updated, where `{version}` is replaced with the version of the
+	/// toolchain and `{path}` with the path to it (for e.g. `["./scripts/after-toolchain.sh {version} {path}"]`).
+	#[serde(default)]
+	pub post_install: Vec<String>,
+}
+
+/// Enumeration of actions to take when a downloaded archive doesn't match its pinned digest.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PinMismatch {
+	/// Refuse to install the archive.
+	#[default]
+	Fail,
+	/// Print a warning, and install the archive anyway.
+	Warn,
 }
 
 impl Default for Source {
 	fn default() -> Self {
 		Self {
+			kind: Default::default(),
 			root_url: "https://sm.alliedmods.net/smdrop/".into(),
 			max_download_size: 75_000_000,
+			max_download_rate: 0,
+			spool_threshold: default_spool_threshold(),
+			max_redirects: default_max_redirects(),
+			allow_cross_host_redirects: default_allow_cross_host_redirects(),
+			max_unpacked_size: default_max_unpacked_size(),
+			max_entry_size: default_max_entry_size(),
+			max_entries: default_max_entries(),
+			executable_mode: FileMode::default(),
+			extras: Vec::new(),
+			components: Vec::new(),
+			target_os: None,
+			command: Vec::new(),
+			pin_mismatch: PinMismatch::Fail,
+			signing: Default::default(),
+		}
+	}
+}
+
+const fn default_spool_threshold() -> u64 {
+	32_000_000
+}
+
+const fn default_max_redirects() -> u32 {
+	10
+}
+
+const fn default_allow_cross_host_redirects() -> bool {
+	true
+}
+
+const fn default_max_unpacked_size() -> u64 {
+	1_000_000_000
+}
+
+const fn default_max_entry_size() -> u64 {
+	250_000_000
+}
+
+const fn default_max_entries() -> u64 {
+	100_000
+}
+
+/// Unix file permissions, which are written as an octal string in the configuration file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, serde::Serialize)]
+#[serde(try_from = "FileModeRepr", into = "String")]
+pub struct FileMode(pub u32);
+
+impl FileMode {
+	/// Largest valid mode, with all permission bits and the setuid, setgid, and sticky bits set.
+	pub const MAX: u32 = 0o7777;
+}
+
+impl Default for FileMode {
+	#[inline]
+	fn default() -> Self {
+		Self(0o755)
+	}
+}
+
+impl FromStr for FileMode {
+	type Err = FileModeFromStrError;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let digits = s.strip_prefix("0o").unwrap_or(s);
+		match u32::from_str_radix(digits, 8) {
+			Ok(mode) if mode <= Self::MAX && !digits.starts_with('+') => Ok(Self(mode)),
+			_ => Err(FileModeFromStrError(s.into())),
 		}
 	}
 }
 
+impl fmt::Display for FileMode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{:o}", self.0)
+	}
+}
+
+impl From<FileMode> for String {
+	#[inline]
+	fn from(value: FileMode) -> Self {
+		value.to_string()
+	}
+}
+
+/// Representation of a [`FileMode`] in the configuration file, which may also be an integer (like `0o755`).
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FileModeRepr {
+	Integer(u32),
+	String(String),
+}
+
+impl TryFrom<FileModeRepr> for FileMode {
+	type Error = FileModeFromStrError;
+	fn try_from(value: FileModeRepr) -> Result<Self, Self::Error> {
+		match value {
+			FileModeRepr::Integer(mode) if mode <= Self::MAX => Ok(Self(mode)),
+			FileModeRepr::Integer(mode) => Err(FileModeFromStrError(format!("{mode:o}"))),
+			FileModeRepr::String(s) => s.parse(),
+		}
+	}
+}
+
+/// Error returned when parsing an invalid [`FileMode`].
+#[derive(Debug, thiserror::Error)]
+#[error("invalid file mode {0:?} (expected octal permissions, like `755`)")]
+pub struct FileModeFromStrError(pub String);
+
+/// Enumeration of kinds of servers that toolchains can be fetched from.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SourceKind {
+	/// Static file server with SourceMod builds.
+	#[default]
+	Smdrop,
+	/// GitHub releases of standalone SourcePawn compiler builds.
+	Sourcepawn,
+	/// External program that lists and fetches toolchains.
+	Command,
+	/// Repository in a container registry with archives pushed as OCI artifacts.
+	Oci,
+}
+
+/// Enumeration of optional parts of SourceMod distributions that can be extracted along with the compiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Extra {
+	/// `.sp` sources of the stock plugins.
+	Scripts,
+	/// Compiled stock plugins.
+	Plugins,
+	/// Translation phrase files.
+	Translations,
+}
+
+impl FromStr for Extra {
+	type Err = ExtraFromStrError;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"scripts" => Ok(Self::Scripts),
+			"plugins" => Ok(Self::Plugins),
+			"translations" => Ok(Self::Translations),
+			_ => Err(ExtraFromStrError(s.into())),
+		}
+	}
+}
+
+impl fmt::Display for Extra {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Scripts => "scripts",
+			Self::Plugins => "plugins",
+			Self::Translations => "translations",
+		})
+	}
+}
+
+/// Error returned when parsing an [`Extra`] from an unknown string.
+#[derive(Debug, thiserror::Error)]
+#[error("unknown extra {0:?} (expected `scripts`, `plugins`, or `translations`)")]
+pub struct ExtraFromStrError(pub String);
+
+/// Enumeration of parts of toolchains that can be installed separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Component {
+	/// Compiler executable.
+	Compiler,
+	/// `include` directory.
+	Includes,
+}
+
+impl Component {
+	/// All components, which are installed if none are specified.
+	pub const ALL: [Self; 2] = [Self::Compiler, Self::Includes];
+
+	/// Return `components`, or [`Self::ALL`] if it's empty.
+	pub fn or_all(components: &[Self]) -> &[Self] {
+		if components.is_empty() { &Self::ALL } else { components }
+	}
+
+	/// Return the component that the file at `path` (relative to the toolchain directory) is part of, or [`None`] if
+	/// it isn't part of any (like extras).
+	pub fn of(path: &Path, compilers: &CompilerNames) -> Option<Self> {
+		if path.starts_with("include") {
+			Some(Self::Includes)
+		} else if path.to_str().is_some_and(move |p| compilers.contains(p)) {
+			Some(Self::Compiler)
+		} else {
+			None
+		}
+	}
+}
+
+impl FromStr for Component {
+	type Err = ComponentFromStrError;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"compiler" => Ok(Self::Compiler),
+			"includes" => Ok(Self::Includes),
+			_ => Err(ComponentFromStrError(s.into())),
+		}
+	}
+}
+
+impl fmt::Display for Component {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Compiler => "compiler",
+			Self::Includes => "includes",
+		})
+	}
+}
+
+/// Error returned when parsing a [`Component`] from an unknown string.
+#[derive(Debug, thiserror::Error)]
+#[error("unknown component {0:?} (expected `compiler` or `includes`)")]
+pub struct ComponentFromStrError(pub String);
+
 /// Structure that holds the configuration file along with its path and structured data.
 #[derive(Debug)]
 pub struct Config {
 	pub path: PathBuf,
-	pub file: File,
+	/// Handle to the configuration file, or [`None`] if it doesn't exist and built-in defaults are used instead (see
+	/// [`without_file`](Self::without_file)).
+	pub file: Option<File>,
 	pub with_doc: ConfigDoc,
+	/// Path to the journal that changes are recorded in when the configuration file is [rewritten](Self::rewrite), or
+	/// [`None`] if they aren't recorded.
+	pub journal: Option<PathBuf>,
+	/// Schema version that the configuration file was upgraded from when it was read, if it was of an older one.
+	/// 
+	/// See [`save_migration`](Self::save_migration).
+	pub migrated_from: Option<u32>,
+	/// Lock on the configuration file, which is held from before it's read until this is dropped if it was opened for
+	/// writing, so that no other process changes it between reading and [rewriting](Self::rewrite) it.
+	pub lock: Option<LockFile>,
 }
 
 /// Error that occurred while opening a [`Config`].
@@ -83,6 +564,15 @@ pub enum ConfigError {
 		error: IoError,
 		config_home: PathBuf,
 	},
+	#[error("failed to lock {config_path}: {error}")]
+	ConfigLock {
+		error: IoError,
+		config_path: PathBuf,
+	},
+	#[error("{config_path} doesn't exist, and creating it is disabled by `{NO_CREATE_CONFIG_VAR}`")]
+	ConfigMissing {
+		config_path: PathBuf,
+	},
 	#[error("failed to create default config at {config_path}: {error}")]
 	ConfigCreateDefault {
 		error: IoError,
@@ -94,11 +584,44 @@ pub enum ConfigError {
 		file: File,
 		config_path: PathBuf,
 	},
-	#[error("failed to parse {config_path}: {error}")]
+	#[error("{}", TomlDiagnostic::new(config_path, text, error))]
 	ConfigParse {
 		error: Box<TomlError>,
 		file: File,
 		config_path: PathBuf,
+		/// Contents of the configuration file.
+		text: String,
+	},
+	#[error("failed to upgrade {config_path}: {error}")]
+	ConfigMigrate {
+		error: MigrationError,
+		file: File,
+		config_path: PathBuf,
+	},
+	#[error("failed to read {config_path}: {error}")]
+	LayerOpen {
+		error: IoError,
+		config_path: PathBuf,
+	},
+	#[error("{}", TomlDiagnostic::new(config_path, text, error))]
+	LayerParse {
+		error: Box<TomlError>,
+		config_path: PathBuf,
+		/// Contents of the configuration file.
+		text: String,
+	},
+	#[error("{config_path} is invalid: {error}")]
+	LayerInvalid {
+		error: String,
+		config_path: PathBuf,
+	},
+	#[error("invalid configuration in environment variables: {error}")]
+	EnvInvalid {
+		error: String,
+	},
+	#[error("invalid configuration on the command line: {error}")]
+	ArgsInvalid {
+		error: String,
 	},
 }
 
@@ -125,22 +648,62 @@ impl Config {
 			);
 			buffer
 		};
-		let config = handle_err!(
-			text.parse::<DocumentMut>().and_then(ConfigDoc::from_document);
+		let mut document = handle_err!(
+			text.parse::<DocumentMut>();
 			error => ConfigError::ConfigParse {
 				error: Box::new(error),
 				file,
 				config_path,
+				text,
+			}
+		);
+		let migrated_from = handle_err!(
+			migrate(&mut document);
+			error => ConfigError::ConfigMigrate {
+				error,
+				file,
+				config_path,
+			}
+		);
+		let config = handle_err!(
+			ConfigDoc::from_document(document);
+			error => ConfigError::ConfigParse {
+				error: Box::new(with_span(error, &text)),
+				file,
+				config_path,
+				text,
 			}
 		);
 		Ok(Config {
 			path: config_path,
-			file,
+			file: Some(file),
 			with_doc: config,
+			journal: None,
+			migrated_from,
+			lock: None,
 		})
 	}
 
+	/// Return the configuration in `document` for the configuration file at `config_path`, which doesn't exist, so that
+	/// it can be read without being created.
+	/// 
+	/// [Rewriting](Self::rewrite) it fails.
+	pub fn without_file(config_path: PathBuf, document: DocumentMut) -> Result<Self, TomlError> {
+		Ok(Config {
+			path: config_path,
+			file: None,
+			with_doc: ConfigDoc::from_document(document)?,
+			journal: None,
+			migrated_from: None,
+			lock: None,
+		})
+	}
+
+	/// Open the configuration file at `config_path`, and [save](Self::save_migration) it if it was upgraded.
+	/// 
+	/// If `write` is `true`, the file is [locked](Self::lock) until the configuration is dropped.
 	pub fn open(config_path: PathBuf, write: bool) -> Result<Self, ConfigError> {
+		let lock = if write { Some(Self::lock_file(&config_path)?) } else { None };
 		let file = handle_err!(
 			File::options().read(true).write(write).open(&config_path);
 			error => ConfigError::ConfigOpen {
@@ -148,16 +711,158 @@ impl Config {
 				config_path,
 			}
 		);
-		Self::with_file(file, config_path)
+		let mut config = Self::with_file(file, config_path)?;
+		config.lock = lock;
+		config.save_migration();
+		Ok(config)
+	}
+
+	/// Take the lock on the configuration file at `config_path`, waiting for other processes that hold it.
+	pub fn lock_file(config_path: &Path) -> Result<LockFile, ConfigError> {
+		LockFile::acquire(lock_path(config_path), &|_| {})
+			.map_err(move |error| ConfigError::ConfigLock {
+				error,
+				config_path: config_path.into(),
+			})
 	}
 
+	/// Write the configuration file back if it was upgraded from an older schema version when it was read.
+	/// 
+	/// Failures are ignored, since the configuration file is upgraded again whenever it's read until this succeeds.
+	pub fn save_migration(&mut self) {
+		if self.migrated_from.is_some() && self.rewrite().is_ok() {
+			self.migrated_from = None;
+		}
+	}
+
+	/// Write the configuration document to the configuration file, returning the text that was written.
+	/// 
+	/// The previous contents of the configuration file are first copied to its [backup](Self::backup_path).
+	/// 
+	/// The text is written to a temporary file next to the configuration file, which then replaces it, so that the
+	/// configuration file is never left partially written. If the configuration file is a symbolic link, the file it
+	/// points to is replaced instead.
+	/// 
+	/// Other processes rewriting the configuration file at the same time are waited for.
+	/// 
+	/// Changes to the default selector and aliases are recorded in the [journal](Self::journal), if there is one.
+	/// Failures to record them are ignored, since the journal is only used to review and undo changes.
+	#[inline]
 	pub fn rewrite(&mut self) -> IoResult<String> {
+		self.write_document(true)
+	}
+
+	/// Same as [`rewrite`](Self::rewrite), but without recording the changes in the journal, for undoing ones that were
+	/// recorded.
+	#[inline]
+	pub fn rewrite_unrecorded(&mut self) -> IoResult<String> {
+		self.write_document(false)
+	}
+
+	fn write_document(&mut self, record: bool) -> IoResult<String> {
+		let Some(file) = self.file.as_ref() else {
+			return Err(IoError::new(IoErrorKind::NotFound, format!("{} doesn't exist", self.path.display())))
+		};
+		// Configurations opened for writing already hold the lock.
+		let _lock = match self.lock {
+			Some(..) => None,
+			None => Some(LockFile::acquire(lock_path(&self.path), &|_| {})?),
+		};
+		let permissions = file.metadata()?.permissions();
+		let previous = read(&self.path)?;
+		replace_file(&self.backup_path(), &previous, permissions.clone())?;
+
 		let data = self.with_doc.document().to_string();
-		self.file.rewind()?;
-		self.file.write_all(data.as_bytes())?;
-		self.file.set_len(data.len() as _)?;
+		let path = canonicalize(&self.path).unwrap_or_else(|_| self.path.clone());
+		// The old handle still refers to the replaced file.
+		self.file = Some(replace_file(&path, data.as_bytes(), permissions)?);
+
+		if record {
+			if let Ok(previous) = String::from_utf8_lossy(&previous).parse::<DocumentMut>() {
+				let _ = self.record_changes(&previous);
+			}
+		}
 		Ok(data)
 	}
+
+	/// Record the changes from `previous` to the configuration document in the journal, if there are any.
+	fn record_changes(&self, previous: &DocumentMut) -> Result<(), JournalError> {
+		let changes = journaled_changes(previous, self.with_doc.document());
+		if changes.is_empty() {
+			return Ok(())
+		}
+		let Some(path) = self.journal.as_deref() else {
+			return Ok(())
+		};
+		let mut journal = Journal::read(path)?;
+		journal.push(JournalEntry::now(changes));
+		journal.write(path)
+	}
+
+	/// Return the path to the backup of the configuration file, which has its contents from before it was last
+	/// rewritten.
+	pub fn backup_path(&self) -> PathBuf {
+		let mut name = self.path.file_name().map(OsString::from).unwrap_or_else(move || "config.toml".into());
+		name.push(".bak");
+		self.path.with_file_name(name)
+	}
+}
+
+/// Atomically replace the file at `path` with one that has `data` and `permissions`, returning a handle to it, which is
+/// positioned at its start.
+fn replace_file(path: &Path, data: &[u8], permissions: Permissions) -> IoResult<File> {
+	fn write_temp(temp_path: &Path, data: &[u8], permissions: Permissions) -> IoResult<File> {
+		let mut file = File::options().read(true).write(true).create(true).truncate(true).open(temp_path)?;
+		file.set_permissions(permissions)?;
+		file.write_all(data)?;
+		file.sync_all()?;
+		file.rewind()?;
+		Ok(file)
+	}
+
+	let temp_path = temp_path_for(path);
+	match write_temp(&temp_path, data, permissions).and_then(|file| rename(&temp_path, path).map(move |_| file)) {
+		Ok(file) => {
+			sync_parent(path);
+			Ok(file)
+		}
+		Err(e) => {
+			let _ = remove_file(&temp_path);
+			Err(e)
+		}
+	}
+}
+
+/// Return the path of the temporary file that `path` is written to before being replaced by it.
+fn temp_path_for(path: &Path) -> PathBuf {
+	let mut name = OsString::from(".");
+	name.push(path.file_name().unwrap_or_else(move || "config.toml".as_ref()));
+	name.push(format!(".tmp-{}", std::process::id()));
+	path.with_file_name(name)
+}
+
+/// Flush the rename of `path` to disk, on platforms where directories can be opened.
+fn sync_parent(path: &Path) {
+	#[cfg(unix)]
+	if let Some(parent) = path.parent() {
+		let _ = File::open(parent).and_then(move |dir| dir.sync_all());
+	}
+	#[cfg(not(unix))]
+	let _ = path;
+}
+
+/// Return `error`, or, if it doesn't point to where in `text` it occurred, the same error with that location.
+/// 
+/// Errors from deserializing [`ConfigData`] out of a [`DocumentMut`] don't know their location, so `text` is
+/// deserialized again to find it.
+fn with_span(error: TomlError, text: &str) -> TomlError {
+	if error.span().is_some() {
+		return error
+	}
+	match toml_edit::de::from_str::<ConfigData>(text) {
+		Err(spanned) if spanned.span().is_some() => spanned.into(),
+		_ => error,
+	}
 }
 
 /// Main container for configuration data that holds both the formatted TOML document and the structured in-memory
@@ -166,6 +871,9 @@ impl Config {
 pub struct ConfigDoc {
 	document: DocumentMut,
 	data: ConfigData,
+	/// Configuration files that are merged with the document, which are empty unless [`set_layers`](Self::set_layers)
+	/// was called.
+	layers: Vec<LayerFile>,
 }
 
 impl From<ConfigDoc> for ConfigData {
@@ -178,12 +886,76 @@ impl From<ConfigDoc> for ConfigData {
 impl ConfigDoc {
 	pub fn from_document(document: DocumentMut) -> Result<Self, TomlError> {
 		// FIXME: This shouldn't copy the entire document!
-		let data = from_document(document.clone())?;
+		let data = data_from_document(document.clone())?;
 		Ok(Self {
 			document,
 			data,
+			layers: Vec::new(),
 		})
 	}
+
+	/// Merge the document (which is of the [user layer](crate::ConfigLayer::User)) with `layers`, deserializing the data from
+	/// the merged document.
+	/// 
+	/// The document itself (and so the configuration file written by [`Config::rewrite`]) isn't changed.
+	pub fn set_layers(&mut self, layers: Vec<LayerFile>) -> Result<(), ConfigError> {
+		let (merged, ..) = merge_layers(&self.document, None, &layers);
+		match data_from_document(merged) {
+			Ok(data) => {
+				self.data = data;
+				self.layers = layers;
+				Ok(())
+			}
+			Err(error) => {
+				// Blame the first layer that makes the configuration invalid.
+				let layer = layers.iter()
+					.find(|layer| data_from_document(merge_layers(&self.document, None, core::slice::from_ref(layer)).0).is_err())
+					.or(layers.last());
+				let error = error.to_string();
+				Err(match layer {
+					Some(LayerFile { path: Some(config_path), .. }) => {
+						ConfigError::LayerInvalid { error, config_path: config_path.clone() }
+					}
+					Some(LayerFile { layer: ConfigLayer::CommandLine, .. }) => ConfigError::ArgsInvalid { error },
+					_ => ConfigError::EnvInvalid { error },
+				})
+			}
+		}
+	}
+
+	/// Merge the document with `layer` as well, which takes precedence over the layers that it's already merged with.
+	pub fn add_layer(&mut self, layer: LayerFile) -> Result<(), ConfigError> {
+		let mut layers = self.layers.clone();
+		layers.push(layer);
+		self.set_layers(layers)
+	}
+
+	/// Return the configuration files merged with the document.
+	#[inline]
+	pub fn layers(&self) -> &[LayerFile] {
+		&self.layers
+	}
+
+	/// Return every value of the merged configuration, and which layer it came from, given that the document is the
+	/// configuration file at `path`.
+	pub fn origins(&self, path: &Path) -> Vec<ValueOrigin> {
+		merge_layers(&self.document, Some(path), &self.layers).1
+	}
+
+	/// Return the directories of the `include-dirs` setting, resolved against the directory of the configuration file
+	/// that sets it, given that the document is the configuration file at `path`.
+	/// 
+	/// Directories set by an environment variable are resolved against `current_dir`.
+	pub fn include_dirs(&self, path: &Path, current_dir: &Path) -> Vec<PathBuf> {
+		if self.data.include_dirs.is_empty() {
+			return Vec::new()
+		}
+		let base = self.origins(path).into_iter()
+			.find(move |o| o.key == "include-dirs")
+			.and_then(move |o| Some(o.path?.parent()?.to_path_buf()))
+			.unwrap_or_else(move || current_dir.to_path_buf());
+		self.data.include_dirs.iter().map(move |dir| base.join(dir)).collect()
+	}
 	
 	#[inline]
 	pub const fn document(&self) -> &DocumentMut {
@@ -195,13 +967,220 @@ impl ConfigDoc {
 		&self.data
 	}
 
-	pub fn set_default(&mut self, default: impl Clone + Into<String>) {
-		self.document["default"] = default.clone().into().into();
-		self.data.default = default.into();
+	pub fn set_default(&mut self, default: impl Into<SelectorBuf>) {
+		let default = default.into();
+		self.document["default"] = default.to_string().into();
+		self.data.default = default;
 	}
 
 	pub fn set_alias(&mut self, alias: impl AsRef<str> + Into<String>, version: impl Clone + Into<String>) {
 		self.document["aliases"][alias.as_ref()] = version.clone().into().into();
 		self.data.aliases.insert(alias.into(), version.into());
 	}
+
+	/// Remove `alias` and its policy, returning the version it was set to, if it was set.
+	pub fn remove_alias(&mut self, alias: &str) -> Option<String> {
+		if let Some(aliases) = self.document.get_mut("aliases").and_then(Item::as_table_like_mut) {
+			aliases.remove(alias);
+		}
+		self.set_alias_policy(alias, AliasPolicy::Track);
+		self.data.aliases.remove(alias)
+	}
+
+	/// Set the policy of `alias`, which is removed from the document if it's the default, [`AliasPolicy::Track`].
+	pub fn set_alias_policy(&mut self, alias: &str, policy: AliasPolicy) {
+		if policy == AliasPolicy::Track {
+			if let Some(policies) = self.document.get_mut("alias-policies").and_then(Item::as_table_like_mut) {
+				policies.remove(alias);
+			}
+			self.data.alias_policies.remove(alias);
+		} else {
+			// Configuration files from before policies existed don't have the table yet.
+			let policies = self.document.entry("alias-policies").or_insert_with(toml_edit::table);
+			policies[alias] = policy.to_string().into();
+			self.data.alias_policies.insert(alias.into(), policy);
+		}
+	}
+
+	/// Set the root URL of the source that toolchains are downloaded from.
+	pub fn set_root_url(&mut self, root_url: impl Clone + Into<String>) {
+		self.document["source"]["root-url"] = root_url.clone().into().into();
+		self.data.source.root_url = root_url.into();
+	}
+
+	/// Set the maximum size of downloaded archives, which is limited to [`i64::MAX`] bytes, as TOML can't represent
+	/// larger integers.
+	pub fn set_max_download_size(&mut self, size: u64) {
+		let size = i64::try_from(size).unwrap_or(i64::MAX);
+		self.document["source"]["max-download-size"] = size.into();
+		self.data.source.max_download_size = size as _;
+	}
+
+	/// Set the value at `path` of keys (like `["source", "root-url"]`), creating tables along the way if needed.
+	/// 
+	/// The data is deserialized from the document again afterwards. If the document wouldn't be a valid configuration
+	/// anymore, or if `path` goes through a value that isn't a table, then nothing is changed and an error is returned.
+	/// 
+	/// # Panics
+	/// Panics if `path` is empty.
+	pub fn set_path(&mut self, path: &[&str], value: impl Into<Value>) -> Result<(), TomlError> {
+		let (key, tables) = path.split_last().expect("path should not be empty");
+		let mut document = self.document.clone();
+		let mut table: &mut dyn TableLike = document.as_table_mut();
+		for (i, name) in tables.iter().enumerate() {
+			table = table.entry(name).or_insert_with(toml_edit::table).as_table_like_mut()
+				.ok_or_else(|| <DeError as serde::de::Error>::custom(format!("`{}` is not a table", path[..=i].join("."))))?;
+		}
+		table.insert(key, Item::Value(value.into()));
+
+		self.data = data_from_document(merge_layers(&document, None, &self.layers).0)?;
+		self.document = document;
+		Ok(())
+	}
+}
+
+/// Deserialize configuration data from `document`, replacing the `source` table with the source selected by
+/// `default-source`, if any.
+fn data_from_document(document: DocumentMut) -> Result<ConfigData, TomlError> {
+	let mut data: ConfigData = from_document(document)?;
+	if let Some(name) = data.default_source.as_deref() {
+		let source = data.sources.get(name).cloned().ok_or_else(|| {
+			let suggestions = Suggestions::new(name, data.sources.keys().map(String::as_str));
+			<DeError as serde::de::Error>::custom(format!("`default-source` is {name:?}, but there's no such table in `sources`{suggestions}"))
+		})?;
+		data.source = source;
+	}
+	Ok(data)
+}
+
+#[test]
+fn file_modes_are_parsed() {
+	assert_eq!("755".parse::<FileMode>().unwrap(), FileMode(0o755));
+	assert_eq!("0o700".parse::<FileMode>().unwrap(), FileMode(0o700));
+	assert!("789".parse::<FileMode>().is_err());
+	assert!("17777".parse::<FileMode>().is_err());
+	assert_eq!(FileMode(0o750).to_string(), "750");
+}
+
+#[test]
+fn rewrite_replaces_file() {
+	let dir = crate::TempDir::new("rookup-config-test").unwrap();
+	let path = dir.join("config.toml");
+	std::fs::write(&path, concat!(
+		"default = \"1.11\"\n\n[aliases]\n\n",
+		"[source]\nroot-url = \"https://sm.alliedmods.net/smdrop\"\nmax-download-size = 1000\n",
+	)).unwrap();
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600)).unwrap();
+	}
+
+	let mut config = Config::open(path.clone(), false).unwrap();
+	config.journal = Some(crate::journal_path(dir.join("state")));
+	config.with_doc.set_default("1.12");
+	let data = config.rewrite().unwrap();
+	assert_eq!(std::fs::read_to_string(&path).unwrap(), data);
+	assert!(data.contains("\"1.12\""));
+	let mut read = String::new();
+	config.file.as_ref().unwrap().read_to_string(&mut read).unwrap();
+	assert_eq!(read, data);
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		assert_eq!(path.metadata().unwrap().permissions().mode() & 0o777, 0o600);
+	}
+	assert!(std::fs::read_to_string(config.backup_path()).unwrap().contains("\"1.11\""));
+	let journal = Journal::read(config.journal.as_deref().unwrap()).unwrap();
+	assert_eq!(journal.entries.len(), 1);
+	assert_eq!(journal.entries[0].changes[0].new.as_deref(), Some("1.12"));
+	assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+
+	// Configurations opened for writing keep the file locked until they're dropped.
+	let mut config = Config::open(path.clone(), true).unwrap();
+	assert!(crate::inspect_lock(&lock_path(&path)).unwrap().is_some());
+	config.with_doc.set_default("1.13");
+	config.rewrite().unwrap();
+	assert!(crate::inspect_lock(&lock_path(&path)).unwrap().is_some());
+	drop(config);
+	assert_eq!(crate::inspect_lock(&lock_path(&path)).unwrap(), None);
+}
+
+#[test]
+fn setters_keep_data_in_sync() {
+	let document: DocumentMut = concat!(
+		"default = \"stable\"\n\n[aliases]\nwork = \"1.12\"\n\n",
+		"[source]\nroot-url = \"https://sm.alliedmods.net/smdrop\"\nmax-download-size = 1000\n",
+	).parse().unwrap();
+	let mut doc = ConfigDoc::from_document(document).unwrap();
+
+	doc.set_alias_policy("work", AliasPolicy::Frozen);
+	doc.set_alias_policy("prod", AliasPolicy::Pin);
+	doc.set_alias_policy("prod", AliasPolicy::Track);
+	assert_eq!(doc.data().alias_policy("work"), AliasPolicy::Frozen);
+	assert_eq!(doc.data().alias_policy("prod"), AliasPolicy::Track);
+	assert_eq!(doc.remove_alias("work").as_deref(), Some("1.12"));
+	assert_eq!(doc.remove_alias("work"), None);
+	doc.set_max_download_size(u64::MAX);
+	doc.set_path(&["source", "max-redirects"], 3).unwrap();
+	assert!(doc.set_path(&["source", "max-redirects"], "three").is_err());
+	assert!(doc.set_path(&["default", "x"], 1).is_err());
+
+	let reparsed = ConfigDoc::from_document(doc.document().to_string().parse().unwrap()).unwrap();
+	assert!(reparsed.data().aliases.is_empty());
+	assert!(reparsed.data().alias_policies.is_empty());
+	assert_eq!(reparsed.data().source.max_download_size, i64::MAX as u64);
+	assert_eq!(reparsed.data().source.max_redirects, 3);
+	assert_eq!(doc.data().source.max_redirects, 3);
+}
+
+#[test]
+fn include_dirs_are_resolved_against_their_file() {
+	let document: DocumentMut = concat!(
+		"default = \"stable\"\ninclude-dirs = [\"user\"]\n\n[aliases]\n\n",
+		"[source]\nroot-url = \"https://sm.alliedmods.net/smdrop\"\nmax-download-size = 1000\n",
+	).parse().unwrap();
+	let mut doc = ConfigDoc::from_document(document).unwrap();
+	let user_path = Path::new("/home/user/.config/rookup/config.toml");
+	let cwd = Path::new("/home/user/plugin/scripting");
+	assert_eq!(doc.include_dirs(user_path, cwd), [PathBuf::from("/home/user/.config/rookup/user")]);
+
+	doc.set_layers(vec![LayerFile {
+		layer: crate::ConfigLayer::Project,
+		path: Some("/home/user/plugin/rookup.toml".into()),
+		document: "include-dirs = [\"include\", \"third_party/inc\", \"/opt/inc\"]\n".parse().unwrap(),
+	}]).unwrap();
+	assert_eq!(doc.include_dirs(user_path, cwd), [
+		PathBuf::from("/home/user/plugin/include"),
+		PathBuf::from("/home/user/plugin/third_party/inc"),
+		PathBuf::from("/opt/inc"),
+	]);
+}
+
+#[test]
+fn default_source_selects_named_source() {
+	let document: DocumentMut = concat!(
+		"default = \"stable\"\ndefault-source = \"corp-mirror\"\n\n[aliases]\n\n",
+		"[source]\nroot-url = \"https://sm.alliedmods.net/smdrop\"\nmax-download-size = 1000\n\n",
+		"[sources.corp-mirror]\nroot-url = \"https://mirror.example.com/smdrop\"\nmax-download-size = 2000\n",
+	).parse().unwrap();
+	let mut doc = ConfigDoc::from_document(document).unwrap();
+	assert_eq!(doc.data().source.root_url, "https://mirror.example.com/smdrop");
+	assert_eq!(doc.data().sources["corp-mirror"].max_download_size, 2000);
+
+	assert!(doc.set_path(&["default-source"], "corp-mirorr").unwrap_err().to_string().contains("corp-mirror"));
+	doc.set_layers(vec![LayerFile {
+		layer: crate::ConfigLayer::Env,
+		path: None,
+		document: "default-source = \"upstream\"\n".parse().unwrap(),
+	}]).unwrap_err();
+
+	// Sources can also be selected on the command line.
+	doc.set_layers(Vec::new()).unwrap();
+	doc.add_layer(LayerFile::from_args([("default-source", Value::from("corp-mirror"))]).unwrap()).unwrap();
+	assert_eq!(doc.data().source.root_url, "https://mirror.example.com/smdrop");
+	assert_eq!(doc.layers().last().map(move |l| l.layer), Some(ConfigLayer::CommandLine));
+	let error = doc.add_layer(LayerFile::from_args([("default-source", Value::from("upstream"))]).unwrap());
+	assert!(matches!(error, Err(ConfigError::ArgsInvalid { .. })));
+	assert!(LayerFile::from_args([]).is_none());
 }
@@ -0,0 +1,85 @@
+//! Human-readable rendering of errors in TOML files.
+
+use std::{
+	fmt,
+	path::Path,
+};
+use toml_edit::TomlError;
+
+use crate::suggest::edit_distance;
+
+/// [`Display`](fmt::Display)able error in the TOML file at `path` with contents `source`, rendered with the location
+/// and contents of the offending line, and a hint about the nearest valid key if there is one.
+#[derive(Debug, Clone, Copy)]
+pub struct TomlDiagnostic<'a> {
+	pub path: &'a Path,
+	pub source: &'a str,
+	pub error: &'a TomlError,
+}
+
+impl<'a> TomlDiagnostic<'a> {
+	pub const fn new(path: &'a Path, source: &'a str, error: &'a TomlError) -> Self {
+		Self {
+			path,
+			source,
+			error,
+		}
+	}
+}
+
+impl fmt::Display for TomlDiagnostic<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let message = self.error.message();
+		write!(f, "failed to parse {}: {message}", self.path.display())?;
+
+		if let Some(span) = self.error.span() {
+			let start = span.start.min(self.source.len());
+			let line_start = self.source[..start].rfind('\n').map(move |i| i + 1).unwrap_or(0);
+			let line_end = self.source[start..].find('\n').map(move |i| start + i).unwrap_or(self.source.len());
+			let line = self.source[line_start..line_end].trim_end_matches('\r');
+			let line_number = self.source[..line_start].matches('\n').count() + 1;
+			let column = self.source[line_start..start].chars().count() + 1;
+			let width = self.source[start..span.end.clamp(start, line_end)].chars().count().max(1);
+
+			let gutter = " ".repeat(line_number.to_string().len());
+			writeln!(f)?;
+			writeln!(f, "{gutter}--> {}:{line_number}:{column}", self.path.display())?;
+			writeln!(f, "{gutter} |")?;
+			writeln!(f, "{line_number} | {line}")?;
+			write!(f, "{gutter} | {}{}", " ".repeat(column - 1), "^".repeat(width))?;
+		}
+
+		if let Some(suggestion) = suggest_key(message) {
+			writeln!(f)?;
+			write!(f, "help: did you mean `{suggestion}`?")?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Return the expected key or variant that is closest to the unknown one mentioned in `message`, if it is close enough.
+fn suggest_key(message: &str) -> Option<&str> {
+	let rest = message.strip_prefix("unknown field `")
+		.or_else(move || message.strip_prefix("unknown variant `"))?;
+	let (unknown, rest) = rest.split_once('`')?;
+	let (.., expected) = rest.split_once("expected ")?;
+	expected.split('`')
+		.skip(1).step_by(2)
+		.map(move |candidate| (candidate, edit_distance(unknown, candidate)))
+		.filter(move |(candidate, distance)| *distance <= (candidate.len() / 3).max(2))
+		.min_by_key(move |(.., distance)| *distance)
+		.map(move |(candidate, ..)| candidate)
+}
+
+#[test]
+fn keys_are_suggested() {
+	let message = "unknown field `defualt`, expected one of `default`, `aliases`, `source`";
+	assert_eq!(suggest_key(message), Some("default"));
+	let message = "unknown variant `smdorp`, expected `smdrop` or `sourcepawn`";
+	assert_eq!(suggest_key(message), Some("smdrop"));
+	let message = "unknown field `something`, expected one of `default`, `aliases`, `source`";
+	assert_eq!(suggest_key(message), None);
+	assert_eq!(suggest_key("missing field `aliases`"), None);
+	assert_eq!(edit_distance("kitten", "sitting"), 3);
+}
@@ -0,0 +1,223 @@
+//! Documentation of configuration types, scraped from their doc comments.
+
+use core::any::{
+	Any, TypeId,
+};
+use documented::*;
+use field_access::*;
+use rustc_hash::{
+	FxBuildHasher, FxHashMap,
+};
+use toml_edit::{
+	ser::{
+		to_document, Error as SerError,
+	},
+	Item, TableLike,
+};
+
+use crate::ConfigData;
+
+/// Documentation for an item's field.
+#[derive(Debug)]
+pub struct FieldDoc {
+	pub name: &'static str,
+	pub docs: &'static str,
+	pub type_id: TypeId,
+}
+
+/// Documentation for an item, with associated extra data of type `D`.
+#[derive(Debug)]
+pub struct ItemDoc<D> {
+	pub item_docs: &'static str,
+	pub fields: Vec<FieldDoc>,
+	pub extra: D,
+}
+
+impl<D> ItemDoc<D> {
+	/// Use an example value of type `T` to scrape its documentation.
+	pub fn new<T: ?Sized + WithDocs>(example: &T, extra: D) -> Self {
+		macro_rules! unwrap_cuz_field_name {
+			($o:expr) => {
+				$o.expect("`field_names` should return fields that always exist")
+			};
+		}
+
+		let fields = example.field_names().iter()
+			.map(move |name| {
+				let any = unwrap_cuz_field_name!(example.field_as_any(name));
+				let docs = unwrap_cuz_field_name!(T::get_field_docs(name).ok());
+				FieldDoc {
+					name,
+					docs,
+					type_id: any.type_id(),
+				}
+			})
+			.collect();
+
+		Self {
+			item_docs: T::DOCS,
+			fields,
+			extra,
+		}
+	}
+
+	/// Look up a field by its name in `kebab-case`.
+	pub fn field_kebab_case(&self, query: &str) -> Option<&FieldDoc> {
+		self.fields.iter()
+			.find(move |f| f.name.split('_').eq(query.split('-')))
+	}
+}
+
+/// Trait for documented types that can be used to construct [`ItemDoc`].
+pub trait WithDocs: Any + Documented + DocumentedFields + AnyFieldAccess {}
+impl<T: Any + Documented + DocumentedFields + AnyFieldAccess> WithDocs for T {}
+
+/// Map of types (identified by their [`TypeId`]s) to their [`ItemDoc`].
+/// 
+/// Each type must be [`registered`](Self::register_with) to appear in the map, because [`ItemDoc`] requires an example
+/// value to get field [`TypeId`]s.
+#[derive(Default, Debug)]
+#[repr(transparent)]
+pub struct ItemDocMap<D = ()>(pub FxHashMap<TypeId, ItemDoc<D>>);
+impl<D> ItemDocMap<D> {
+	/// Create a new empty map.
+	#[inline]
+	pub fn new() -> Self {
+		Self(FxHashMap::with_hasher(FxBuildHasher))
+	}
+
+	/// Register type `T` in the map, given `extra` associated data and an `example` value.
+	pub fn register_with<'a, T: ?Sized + WithDocs>(&'a mut self, example: &T, extra: D) -> &'a mut ItemDoc<D> {
+		let type_id = example.type_id();
+		self.0.entry(type_id)
+			.or_insert_with(move || ItemDoc::new::<T>(example, extra))
+	}
+
+	/// Try to get the [`ItemDoc`] associated with `T`.
+	#[inline]
+	pub fn get<T: ?Sized + Any>(&self) -> Option<&ItemDoc<D>> {
+		self.0.get(&TypeId::of::<T>())
+	}
+
+	/// Try to get the [`ItemDoc`] associated with `type_id`.
+	#[inline]
+	pub fn get_by_id(&self, type_id: TypeId) -> Option<&ItemDoc<D>> {
+		self.0.get(&type_id)
+	}
+}
+
+impl ItemDocMap<()> {
+	// TODO: Remove this method?
+	#[allow(dead_code)]
+	/// Register type `T` in the map, given an `example` value and no extra associated data.
+	#[inline]
+	pub fn register<'a, T: ?Sized + WithDocs>(&'a mut self, example: &T) -> &'a mut ItemDoc<()> {
+		self.register_with(example, ())
+	}
+}
+
+/// Extra data associated with the documentation of types in the configuration file.
+#[derive(Debug)]
+pub struct ConfigDocContext {
+	/// Whether fields of this type are documented with the documentation of the type, instead of their own.
+	pub uses_item_docs: bool,
+}
+
+impl ItemDocMap<ConfigDocContext> {
+	/// Create a map with the documentation of every type in the configuration file, given the example `config`.
+	pub fn for_config(config: &ConfigData) -> Self {
+		let mut doc_map = Self::new();
+		doc_map.register_with(
+			&config.source.signing,
+			ConfigDocContext {
+				uses_item_docs: true,
+			},
+		);
+		doc_map.register_with(
+			&config.source,
+			ConfigDocContext {
+				uses_item_docs: true,
+			},
+		);
+		doc_map.register_with(
+			&config.hooks,
+			ConfigDocContext {
+				uses_item_docs: true,
+			},
+		);
+		doc_map.register_with(
+			config,
+			ConfigDocContext {
+				uses_item_docs: false,
+			},
+		);
+		doc_map
+	}
+
+	/// Return the documentation to show for `field`.
+	pub fn docs_of(&self, field: &FieldDoc) -> &'static str {
+		match self.get_by_id(field.type_id) {
+			Some(field_doc) if field_doc.extra.uses_item_docs => field_doc.item_docs,
+			_ => field.docs,
+		}
+	}
+}
+
+/// Documentation of a key of the configuration file.
+#[derive(Debug, Clone)]
+pub struct KeyDoc {
+	/// Dotted path to the key (for e.g. `source.root-url`).
+	pub key: String,
+	pub docs: &'static str,
+	/// Default value of the key, or [`None`] if it isn't set by default.
+	pub default: Option<Item>,
+}
+
+impl KeyDoc {
+	/// Return the name of the TOML type of the default value (for e.g. `integer`), or [`None`] if there's no default
+	/// value.
+	pub fn type_name(&self) -> Option<&'static str> {
+		self.default.as_ref().map(Item::type_name)
+	}
+}
+
+/// Return the documentation of every key of the configuration file (including keys of tables, like `source`) in the
+/// order that they're declared in.
+pub fn config_keys() -> Result<Vec<KeyDoc>, SerError> {
+	fn push_keys(
+		doc_map: &ItemDocMap<ConfigDocContext>, item_doc: &ItemDoc<ConfigDocContext>, defaults: Option<&dyn TableLike>,
+		prefix: &str, keys: &mut Vec<KeyDoc>,
+	) {
+		for field in item_doc.fields.iter() {
+			let name = field.name.replace('_', "-");
+			let key = format!("{prefix}{name}");
+			let default = defaults.and_then(|t| t.get(&name));
+			keys.push(KeyDoc {
+				key: key.clone(),
+				docs: doc_map.docs_of(field),
+				default: default.cloned(),
+			});
+			if let Some(field_doc) = doc_map.get_by_id(field.type_id) {
+				push_keys(doc_map, field_doc, default.and_then(Item::as_table_like), &format!("{key}."), keys);
+			}
+		}
+	}
+
+	let config = ConfigData::default();
+	let doc_map = ItemDocMap::for_config(&config);
+	let defaults = to_document(&config)?;
+	let mut keys = Vec::new();
+	let root = doc_map.get::<ConfigData>().expect("`ConfigData` should be registered");
+	push_keys(&doc_map, root, Some(defaults.as_table() as _), "", &mut keys);
+	Ok(keys)
+}
+
+#[test]
+fn config_keys_are_documented() {
+	let keys = config_keys().unwrap();
+	let root_url = keys.iter().find(move |k| k.key == "source.root-url").unwrap();
+	assert!(root_url.docs.starts_with("Root URL"));
+	assert_eq!(root_url.type_name(), Some("string"));
+	assert!(keys.iter().any(move |k| k.key == "source.signing.required"));
+	assert!(keys.iter().all(move |k| !k.docs.is_empty()));
+}
@@ -0,0 +1,216 @@
+//! Journal of changes to the default selector and aliases in the configuration file, which can be reviewed with
+//! `rookup history` and reverted with `rookup undo`.
+
+use serde::{
+	Deserialize, Serialize,
+};
+use std::{
+	env::args_os,
+	fs::{
+		create_dir_all, read_to_string, write,
+	},
+	io::{
+		Error as IoError, ErrorKind as IoErrorKind,
+	},
+	path::{
+		Path, PathBuf,
+	},
+	time::{
+		Duration, SystemTime, UNIX_EPOCH,
+	},
+};
+use toml_edit::{
+	de::{
+		from_str, Error as DeError,
+	},
+	ser::{
+		to_string_pretty, Error as SerError,
+	},
+	DocumentMut, Item,
+};
+
+use crate::{
+	AliasPolicy, ConfigData, ConfigDoc,
+};
+
+/// Number of changes that are kept in the journal, after which the oldest ones are dropped.
+pub const JOURNAL_LEN: usize = 50;
+
+/// Tables of the configuration file whose values are recorded in the journal, along with the `default` key.
+const JOURNALED_TABLES: [&str; 2] = ["aliases", "alias-policies"];
+
+/// Consume the state home directory and return the path to the journal of the configuration file.
+pub fn journal_path(mut state_home: PathBuf) -> PathBuf {
+	state_home.push("history.toml");
+	state_home
+}
+
+/// Changes to the configuration file, from oldest to newest.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Journal {
+	#[serde(default)]
+	pub entries: Vec<JournalEntry>,
+}
+
+/// Changes that were written to the configuration file at once.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+	/// Unix timestamp of when the changes were written.
+	pub time: u64,
+	/// Command line of the process that made the changes.
+	pub command: String,
+	pub changes: Vec<Change>,
+}
+
+/// Change of a single value in the configuration file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change {
+	/// Dotted path to the value, which is `default`, or the name of an alias in `aliases` or `alias-policies` (for e.g.
+	/// `aliases.work`).
+	pub key: String,
+	/// Value before the change, or [`None`] if it wasn't set.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub old: Option<String>,
+	/// Value after the change, or [`None`] if it was removed.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub new: Option<String>,
+}
+
+impl JournalEntry {
+	/// Create an entry for `changes` made now by this process.
+	pub fn now(changes: Vec<Change>) -> Self {
+		let args: Vec<_> = args_os().enumerate()
+			.map(move |(i, arg)| match i {
+				// The program is named without the directory it's in.
+				0 => Path::new(&arg).file_stem().unwrap_or(&arg).to_string_lossy().into_owned(),
+				_ => arg.to_string_lossy().into_owned(),
+			})
+			.collect();
+		Self {
+			time: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+			command: args.join(" "),
+			changes,
+		}
+	}
+
+	/// Return when the changes were written.
+	#[inline]
+	pub fn time(&self) -> SystemTime {
+		UNIX_EPOCH + Duration::from_secs(self.time)
+	}
+}
+
+impl Journal {
+	/// Read the journal at `path`, returning an empty one if it doesn't exist.
+	pub fn read(path: &Path) -> Result<Self, JournalError> {
+		match read_to_string(path) {
+			Ok(text) => Ok(from_str(&text)?),
+			Err(e) if e.kind() == IoErrorKind::NotFound => Ok(Self::default()),
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	/// Write this journal to `path`, creating the directories up to it.
+	pub fn write(&self, path: &Path) -> Result<(), JournalError> {
+		if let Some(parent) = path.parent() {
+			create_dir_all(parent)?;
+		}
+		write(path, to_string_pretty(self)?)?;
+		Ok(())
+	}
+
+	/// Add `entry` as the newest entry, dropping the oldest ones if there are more than [`JOURNAL_LEN`].
+	pub fn push(&mut self, entry: JournalEntry) {
+		self.entries.push(entry);
+		let excess = self.entries.len().saturating_sub(JOURNAL_LEN);
+		self.entries.drain(..excess);
+	}
+}
+
+/// Error that occurred while reading or writing a [`Journal`].
+#[derive(Debug, thiserror::Error)]
+pub enum JournalError {
+	#[error("{0}")]
+	Io(#[from] IoError),
+	#[error("{0}")]
+	De(#[from] DeError),
+	#[error("{0}")]
+	Ser(#[from] SerError),
+}
+
+/// Return the value of the dotted `key` of a [`Change`] in `document`, or [`None`] if it isn't set.
+pub fn journaled_value(document: &DocumentMut, key: &str) -> Option<String> {
+	let item = match key.split_once('.') {
+		Some((table, name)) => document.get(table)?.as_table_like()?.get(name)?,
+		None => document.get(key)?,
+	};
+	item.as_str().map(String::from)
+}
+
+/// Return the changes to the default selector and aliases from `old` to `new`, sorted by their keys.
+pub fn journaled_changes(old: &DocumentMut, new: &DocumentMut) -> Vec<Change> {
+	let mut keys = vec![String::from("default")];
+	for document in [old, new] {
+		for table in JOURNALED_TABLES {
+			let Some(table_like) = document.get(table).and_then(Item::as_table_like) else { continue };
+			keys.extend(table_like.iter().map(move |(name, ..)| format!("{table}.{name}")));
+		}
+	}
+	keys.sort();
+	keys.dedup();
+	keys.into_iter()
+		.filter_map(move |key| {
+			let (old, new) = (journaled_value(old, &key), journaled_value(new, &key));
+			(old != new).then_some(Change { key, old, new })
+		})
+		.collect()
+}
+
+impl ConfigDoc {
+	/// Set the value that `change` changed back to its old value.
+	///
+	/// Keys that aren't recorded in the journal are ignored.
+	pub fn revert(&mut self, change: &Change) {
+		let old = change.old.as_deref();
+		match change.key.split_once('.') {
+			None if change.key == "default" => {
+				self.set_default(old.map_or_else(move || ConfigData::default().default, Into::into));
+			}
+			Some(("aliases", alias)) => match old {
+				Some(version) => self.set_alias(alias, version),
+				None => {
+					self.remove_alias(alias);
+				}
+			},
+			Some(("alias-policies", alias)) => {
+				let policy = old.and_then(move |p| p.parse().ok()).unwrap_or(AliasPolicy::Track);
+				self.set_alias_policy(alias, policy);
+			}
+			_ => {}
+		}
+	}
+}
+
+#[test]
+fn changes_are_reverted() {
+	let old: DocumentMut = concat!(
+		"default = \"stable\"\n\n[aliases]\nwork = \"1.12\"\nold = \"1.11\"\n\n",
+		"[source]\nroot-url = \"https://sm.alliedmods.net/smdrop\"\nmax-download-size = 1000\n",
+	).parse().unwrap();
+	let mut config = ConfigDoc::from_document(old.clone()).unwrap();
+	config.set_default(":1.12");
+	config.set_alias("work", "1.13");
+	config.remove_alias("old");
+	config.set_alias("new", "1.12");
+	config.set_alias_policy("new", AliasPolicy::Pin);
+
+	let changes = journaled_changes(&old, config.document());
+	let keys: Vec<_> = changes.iter().map(move |c| c.key.as_str()).collect();
+	assert_eq!(keys, ["alias-policies.new", "aliases.new", "aliases.old", "aliases.work", "default"]);
+	assert_eq!(changes[3], Change { key: "aliases.work".into(), old: Some("1.12".into()), new: Some("1.13".into()) });
+
+	for change in changes.iter().rev() {
+		config.revert(change);
+	}
+	assert_eq!(journaled_changes(&old, config.document()), []);
+}
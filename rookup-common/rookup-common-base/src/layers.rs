@@ -0,0 +1,418 @@
+//! Merging of the user's configuration file with system-wide and project configuration files.
+
+use std::{
+	env::var_os,
+	ffi::OsString,
+	fmt,
+	fs::read_to_string,
+	io::ErrorKind as IoErrorKind,
+	path::{
+		Path, PathBuf,
+	},
+};
+use toml_edit::{
+	ser::to_document,
+	DocumentMut, Item, TableLike, Value,
+};
+
+use crate::{
+	migration::{
+		migrate, SCHEMA_VERSION_KEY,
+	},
+	doc_map::config_keys,
+	ConfigData, ConfigError, HOME_DIR,
+};
+
+/// Name of the environment variable that overrides the path to the system-wide configuration file.
+pub const SYSTEM_CONFIG_VAR: &str = "ROOKUP_SYSTEM_CONFIG";
+
+/// File name of project configuration files.
+pub const PROJECT_CONFIG_NAME: &str = "rookup.toml";
+
+/// Prefix of the names of environment variables that override configuration keys.
+pub const ENV_VAR_PREFIX: &str = "ROOKUP_";
+
+/// Layer of configuration that a value comes from, in order of increasing precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConfigLayer {
+	/// Value that Rookup uses when no configuration file sets another one.
+	Default,
+	/// System-wide configuration file, for e.g. `/etc/rookup/config.toml`.
+	System,
+	/// Configuration file of the user, which is the only one that Rookup changes.
+	User,
+	/// `rookup.toml` in the current directory or any of its ancestors.
+	Project,
+	/// Environment variables named after keys (for e.g. `ROOKUP_SOURCE_ROOT_URL` for `source.root-url`).
+	Env,
+	/// Options given on the command line (for e.g. `--source` for `default-source`).
+	CommandLine,
+}
+
+impl fmt::Display for ConfigLayer {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Default => "default",
+			Self::System => "system",
+			Self::User => "user",
+			Self::Project => "project",
+			Self::Env => "environment",
+			Self::CommandLine => "command line",
+		})
+	}
+}
+
+/// Configuration file of a layer other than the user's, which only has to set the keys that it overrides.
+#[derive(Debug, Clone)]
+pub struct LayerFile {
+	pub layer: ConfigLayer,
+	/// Path to the configuration file, or [`None`] for [`ConfigLayer::Env`] and [`ConfigLayer::CommandLine`].
+	pub path: Option<PathBuf>,
+	pub document: DocumentMut,
+}
+
+impl LayerFile {
+	/// Read the configuration file of `layer` at `path`, returning [`None`] if it doesn't exist.
+	pub fn read(layer: ConfigLayer, path: PathBuf) -> Result<Option<Self>, ConfigError> {
+		let text = match read_to_string(&path) {
+			Ok(text) => text,
+			Err(e) if e.kind() == IoErrorKind::NotFound => return Ok(None),
+			Err(error) => return Err(ConfigError::LayerOpen { error, config_path: path }),
+		};
+		let mut document = match text.parse::<DocumentMut>() {
+			Ok(document) => document,
+			Err(error) => return Err(ConfigError::LayerParse { error: Box::new(error), config_path: path, text }),
+		};
+		if let Err(error) = migrate(&mut document) {
+			return Err(ConfigError::LayerInvalid { error: error.to_string(), config_path: path })
+		}
+		Ok(Some(Self {
+			layer,
+			path: Some(path),
+			document,
+		}))
+	}
+
+	/// Read the overrides of configuration keys from environment variables, returning [`None`] if none are set.
+	pub fn from_env() -> Result<Option<Self>, ConfigError> {
+		Self::from_vars(var_os)
+	}
+
+	/// Read the overrides of configuration keys from the variables returned by `var`, which is given the names returned by
+	/// [`env_var_name`].
+	/// 
+	/// Values of keys that are strings by default are used as is, and other values are parsed as TOML values (for e.g.
+	/// `true` or `["a", "b"]`). Values of keys without a default value that can't be parsed are used as strings.
+	/// Tables (like `aliases`) can't be overridden.
+	pub fn from_vars(mut var: impl FnMut(String) -> Option<OsString>) -> Result<Option<Self>, ConfigError> {
+		let mut document = DocumentMut::new();
+		for key in config_keys().unwrap_or_default() {
+			if key.key == SCHEMA_VERSION_KEY || key.default.as_ref().is_some_and(Item::is_table_like) {
+				continue
+			}
+			let name = env_var_name(&key.key);
+			let Some(text) = var(name.clone()) else {
+				continue
+			};
+			let Some(text) = text.to_str() else {
+				return Err(ConfigError::EnvInvalid { error: format!("`{name}` isn't valid Unicode") })
+			};
+			let value = match key.default.as_ref().and_then(Item::as_value) {
+				Some(Value::String(..)) => Value::from(text),
+				Some(default) => text.trim().parse::<Value>().ok()
+					.filter(|value| value.type_name() == default.type_name())
+					.ok_or_else(move || ConfigError::EnvInvalid {
+						error: format!("`{name}` must be a TOML {}, but it is `{text}`", default.type_name()),
+					})?,
+				None => text.trim().parse::<Value>().unwrap_or_else(move |_| Value::from(text)),
+			};
+			let path: Vec<String> = key.key.split('.').map(String::from).collect();
+			set_leaf(document.as_table_mut(), &path, Item::Value(value));
+		}
+		if document.is_empty() {
+			return Ok(None)
+		}
+		Ok(Some(Self {
+			layer: ConfigLayer::Env,
+			path: None,
+			document,
+		}))
+	}
+}
+
+impl LayerFile {
+	/// Return the overrides of configuration keys given on the command line as `values`, which are pairs of dotted
+	/// keys and their values, or [`None`] if there are none.
+	pub fn from_args<'a>(values: impl IntoIterator<Item = (&'a str, Value)>) -> Option<Self> {
+		let mut document = DocumentMut::new();
+		for (key, value) in values {
+			let path: Vec<String> = key.split('.').map(String::from).collect();
+			set_leaf(document.as_table_mut(), &path, Item::Value(value));
+		}
+		(!document.is_empty()).then_some(Self {
+			layer: ConfigLayer::CommandLine,
+			path: None,
+			document,
+		})
+	}
+}
+
+/// Return the name of the environment variable that overrides the configuration key `key`, given as a dotted path (for
+/// e.g. `ROOKUP_SOURCE_MAX_DOWNLOAD_SIZE` for `source.max-download-size`).
+pub fn env_var_name(key: &str) -> String {
+	let mut name = String::from(ENV_VAR_PREFIX);
+	name.extend(key.chars().map(move |c| match c {
+		'-' | '.' => '_',
+		c => c.to_ascii_uppercase(),
+	}));
+	name
+}
+
+/// Return the path to the system-wide configuration file, which may not exist.
+///
+/// This is `ROOKUP_SYSTEM_CONFIG` if it's set, `%ProgramData%\rookup\config.toml` on Windows, and
+/// `/etc/rookup/config.toml` elsewhere.
+pub fn system_config_path() -> Option<PathBuf> {
+	if let Some(path) = var_os(SYSTEM_CONFIG_VAR) {
+		return Some(path.into())
+	}
+	#[cfg(windows)]
+	let dir = PathBuf::from(var_os("ProgramData")?);
+	#[cfg(not(windows))]
+	let dir = PathBuf::from("/etc");
+	Some(dir.join(HOME_DIR).join("config.toml"))
+}
+
+/// Find the project configuration file that applies in `dir`, which is the nearest `rookup.toml` in it or any of its
+/// ancestors.
+pub fn find_project_config(dir: &Path) -> Option<PathBuf> {
+	dir.ancestors()
+		.map(move |dir| dir.join(PROJECT_CONFIG_NAME))
+		.find(move |path| path.is_file())
+}
+
+/// Read the system-wide configuration file and the project configuration file for the current directory, if they exist,
+/// and the overrides from environment variables, if any are set.
+pub fn read_layers() -> Result<Vec<LayerFile>, ConfigError> {
+	let mut layers = Vec::new();
+	if let Some(path) = system_config_path() {
+		layers.extend(LayerFile::read(ConfigLayer::System, path)?);
+	}
+	let project = std::env::current_dir().ok().and_then(move |dir| find_project_config(&dir));
+	if let Some(path) = project {
+		layers.extend(LayerFile::read(ConfigLayer::Project, path)?);
+	}
+	layers.extend(LayerFile::from_env()?);
+	Ok(layers)
+}
+
+/// Value of a key of the merged configuration and the layer that it came from.
+#[derive(Debug, Clone)]
+pub struct ValueOrigin {
+	/// Dotted path to the key (for e.g. `source.root-url`).
+	pub key: String,
+	pub value: Item,
+	pub layer: ConfigLayer,
+	/// Path to the file that the value was read from, unless it's a default value or it was read from an environment
+	/// variable.
+	pub path: Option<PathBuf>,
+}
+
+/// Merge `layers` with the user's configuration `user` (found at `user_path`), returning the merged document and the
+/// origin of every value in it.
+///
+/// Layers before [`ConfigLayer::User`] only override values of `user` that are the same as the defaults, since the
+/// configuration file of the user is created with every default value. Later layers override every value.
+pub fn merge_layers(
+	user: &DocumentMut, user_path: Option<&Path>, layers: &[LayerFile],
+) -> (DocumentMut, Vec<ValueOrigin>) {
+	let defaults = to_document(&ConfigData::default()).ok();
+	let defaults = defaults.as_ref().map(move |d| d.as_table() as &dyn TableLike);
+
+	let mut merged = user.clone();
+	let mut origins = Vec::new();
+	for (path, item) in leaves(user.as_table()) {
+		let is_default = defaults.and_then(|d| get_leaf(d, &path)).is_some_and(|d| items_eq(d, item));
+		origins.push(ValueOrigin {
+			key: path.join("."),
+			value: item.clone(),
+			layer: if is_default { ConfigLayer::Default } else { ConfigLayer::User },
+			path: (!is_default).then(|| user_path.map(Path::to_path_buf)).flatten(),
+		});
+	}
+
+	for layer in layers {
+		for (path, item) in leaves(layer.document.as_table()) {
+			let key = path.join(".");
+			if key == SCHEMA_VERSION_KEY {
+				continue
+			}
+			let existing = origins.iter().position(|o| o.key == key);
+			if layer.layer < ConfigLayer::User
+				&& existing.is_some_and(|i| origins[i].layer >= ConfigLayer::User)
+			{
+				continue
+			}
+			set_leaf(merged.as_table_mut(), &path, item.clone());
+			let origin = ValueOrigin {
+				key,
+				value: item.clone(),
+				layer: layer.layer,
+				path: layer.path.clone(),
+			};
+			match existing {
+				Some(i) => origins[i] = origin,
+				None => origins.push(origin),
+			}
+		}
+	}
+
+	// Keys that no file sets still have their default values.
+	for (path, item) in defaults.map(leaves).unwrap_or_default() {
+		let key = path.join(".");
+		if !origins.iter().any(|o| o.key == key) {
+			origins.push(ValueOrigin {
+				key,
+				value: item.clone(),
+				layer: ConfigLayer::Default,
+				path: None,
+			});
+		}
+	}
+	(merged, origins)
+}
+
+/// Return every value in `table` that isn't a table, with the path of keys to it.
+fn leaves(table: &dyn TableLike) -> Vec<(Vec<String>, &Item)> {
+	fn push<'a>(table: &'a dyn TableLike, prefix: &[String], out: &mut Vec<(Vec<String>, &'a Item)>) {
+		for (key, item) in table.iter() {
+			let mut path = prefix.to_vec();
+			path.push(key.into());
+			match item.as_table_like() {
+				Some(table) => push(table, &path, out),
+				None => out.push((path, item)),
+			}
+		}
+	}
+
+	let mut out = Vec::new();
+	push(table, &[], &mut out);
+	out
+}
+
+fn get_leaf<'a>(table: &'a dyn TableLike, path: &[String]) -> Option<&'a Item> {
+	let (key, tables) = path.split_last()?;
+	let mut table = table;
+	for name in tables {
+		table = table.get(name)?.as_table_like()?;
+	}
+	table.get(key)
+}
+
+/// Set the value at `path` in `table` to `item`, replacing anything in the way with tables.
+fn set_leaf(table: &mut dyn TableLike, path: &[String], item: Item) {
+	let Some((key, tables)) = path.split_last() else {
+		return
+	};
+	let mut table = table;
+	for name in tables {
+		let entry = table.entry(name).or_insert_with(toml_edit::table);
+		if !entry.is_table_like() {
+			*entry = toml_edit::table();
+		}
+		table = entry.as_table_like_mut().expect("entry should be a table");
+	}
+	match table.get_mut(key) {
+		// Keep the comments of the value that is replaced.
+		Some(Item::Value(existing)) if item.is_value() => {
+			let decor = existing.decor().clone();
+			*existing = item.into_value().expect("item should be a value");
+			*existing.decor_mut() = decor;
+		}
+		_ => {
+			table.insert(key, item);
+		}
+	}
+}
+
+/// Return `true` if `a` and `b` have the same value, regardless of formatting.
+fn items_eq(a: &Item, b: &Item) -> bool {
+	match (a.as_value(), b.as_value()) {
+		(Some(a), Some(b)) => values_eq(a, b),
+		_ => false,
+	}
+}
+
+fn values_eq(a: &Value, b: &Value) -> bool {
+	match (a, b) {
+		(Value::String(a), Value::String(b)) => a.value() == b.value(),
+		(Value::Integer(a), Value::Integer(b)) => a.value() == b.value(),
+		(Value::Float(a), Value::Float(b)) => a.value() == b.value(),
+		(Value::Boolean(a), Value::Boolean(b)) => a.value() == b.value(),
+		(Value::Datetime(a), Value::Datetime(b)) => a.value() == b.value(),
+		(Value::Array(a), Value::Array(b)) => a.len() == b.len() && a.iter().zip(b.iter()).all(move |(a, b)| values_eq(a, b)),
+		(Value::InlineTable(a), Value::InlineTable(b)) => {
+			a.len() == b.len() && a.iter().all(move |(k, a)| b.get(k).is_some_and(move |b| values_eq(a, b)))
+		}
+		_ => false,
+	}
+}
+
+#[test]
+fn layers_are_merged() {
+	let user: DocumentMut = concat!(
+		"default = \"1.12\"\n\n[aliases]\nwork = \"1.12\"\n\n",
+		"[source]\n# Where to download from.\nroot-url = \"https://sm.alliedmods.net/smdrop/\"\nmax-download-size = 1000\n",
+	).parse().unwrap();
+	let system = LayerFile {
+		layer: ConfigLayer::System,
+		path: Some("/etc/rookup/config.toml".into()),
+		document: "default = \"stable\"\n[source]\nroot-url = \"https://mirror.example.com/\"\n".parse().unwrap(),
+	};
+	let project = LayerFile {
+		layer: ConfigLayer::Project,
+		path: Some("rookup.toml".into()),
+		document: "aliases = { work = \"1.11\" }\n".parse().unwrap(),
+	};
+
+	let (merged, origins) = merge_layers(&user, Some(Path::new("config.toml")), &[system, project]);
+	assert_eq!(merged["default"].as_str(), Some("1.12"));
+	assert_eq!(merged["source"]["root-url"].as_str(), Some("https://mirror.example.com/"));
+	assert!(merged.to_string().contains("# Where to download from.\nroot-url = \"https://mirror.example.com/\""));
+	assert_eq!(merged["aliases"]["work"].as_str(), Some("1.11"));
+
+	let layer_of = |key: &str| origins.iter().find(move |o| o.key == key).map(move |o| o.layer);
+	assert_eq!(layer_of("default"), Some(ConfigLayer::User));
+	assert_eq!(layer_of("source.root-url"), Some(ConfigLayer::System));
+	assert_eq!(layer_of("source.max-download-size"), Some(ConfigLayer::User));
+	assert_eq!(layer_of("aliases.work"), Some(ConfigLayer::Project));
+	assert_eq!(layer_of("source.max-redirects"), Some(ConfigLayer::Default));
+}
+
+#[test]
+fn env_vars_override_keys() {
+	assert_eq!(env_var_name("source.max-download-size"), "ROOKUP_SOURCE_MAX_DOWNLOAD_SIZE");
+
+	let vars = [
+		("ROOKUP_DEFAULT", "1.12"),
+		("ROOKUP_SOURCE_ROOT_URL", "https://mirror.example.com/"),
+		("ROOKUP_SOURCE_MAX_DOWNLOAD_SIZE", "1000"),
+		("ROOKUP_SOURCE_COMMAND", "[\"fetch\", \"{url}\"]"),
+		("ROOKUP_SOURCE_TARGET_OS", "linux"),
+		("ROOKUP_ALIASES", "{ work = \"1.11\" }"),
+	];
+	let var = move |name: String| vars.iter().find(|(n, ..)| *n == name).map(move |(_, v)| OsString::from(v));
+	let layer = LayerFile::from_vars(var).unwrap().unwrap();
+	let document = &layer.document;
+	assert_eq!(document["default"].as_str(), Some("1.12"));
+	assert_eq!(document["source"]["root-url"].as_str(), Some("https://mirror.example.com/"));
+	assert_eq!(document["source"]["max-download-size"].as_integer(), Some(1000));
+	assert_eq!(document["source"]["command"].as_array().map(toml_edit::Array::len), Some(2));
+	assert_eq!(document["source"]["target-os"].as_str(), Some("linux"));
+	assert!(document.get("aliases").is_none());
+
+	let invalid = LayerFile::from_vars(move |name| (name == "ROOKUP_SOURCE_MAX_REDIRECTS").then(move || "ten".into()));
+	assert!(matches!(invalid, Err(ConfigError::EnvInvalid { .. })));
+	assert!(LayerFile::from_vars(move |_| None).unwrap().is_none());
+}
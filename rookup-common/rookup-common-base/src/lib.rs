@@ -1,6 +1,13 @@
 use std::{
-	env::var_os,
-	path::PathBuf,
+	env::{
+		consts::{
+			ARCH, OS,
+		},
+		var_os,
+	},
+	path::{
+		Path, PathBuf,
+	},
 };
 
 pub use documented;
@@ -9,6 +16,22 @@ pub use toml_edit;
 
 mod config;
 pub use config::*;
+mod diagnostic;
+pub use diagnostic::*;
+pub mod doc_map;
+mod journal;
+pub use journal::*;
+mod layers;
+pub use layers::*;
+mod lock;
+pub use lock::*;
+pub mod migration;
+mod receipt;
+pub use receipt::*;
+mod suggest;
+pub use suggest::*;
+mod temp;
+pub use temp::*;
 mod toolchain;
 pub use toolchain::*;
 pub mod version;
@@ -17,6 +40,19 @@ mod spcomp_exe;
 
 const HOME_DIR: &str = "rookup";
 
+/// Name of the environment variable that overrides the configuration directory.
+pub const CONFIG_HOME_VAR: &str = "ROOKUP_CONFIG_HOME";
+
+/// Name of the environment variable that, if set to a non-empty value, keeps Rookup from creating the configuration
+/// file when it doesn't exist.
+pub const NO_CREATE_CONFIG_VAR: &str = "ROOKUP_NO_CREATE_CONFIG";
+
+/// Name of the environment variable that overrides the cache directory.
+pub const CACHE_HOME_VAR: &str = "ROOKUP_CACHE_HOME";
+
+/// Name of the environment variable that overrides the state directory.
+pub const STATE_HOME_VAR: &str = "ROOKUP_STATE_HOME";
+
 /// Consume a parent directory and return the home directory for Rookup.
 fn home(mut parent_dir: PathBuf) -> PathBuf {
 	parent_dir.push(HOME_DIR);
@@ -29,6 +65,18 @@ pub fn config_file_path(mut config_home: PathBuf) -> PathBuf {
 	config_home
 }
 
+/// Consume the config home directory and return the path to the backup of the config file.
+pub fn config_backup_path(mut config_home: PathBuf) -> PathBuf {
+	config_home.push("config.toml.bak");
+	config_home
+}
+
+/// Consume the config home directory and return the path to the file with pinned archive checksums.
+pub fn pins_file_path(mut config_home: PathBuf) -> PathBuf {
+	config_home.push("pins.toml");
+	config_home
+}
+
 /// Consume the (either cache or data) home directory and return the path to the toolchain directory.
 /// 
 /// The toolchains are stored in cache because they are intended to be easily re-created if lost by re-downloading the
@@ -38,17 +86,127 @@ fn toolchain_home_path(mut home: PathBuf) -> PathBuf {
 	home
 }
 
+/// Return the path to the cache directory of Rookup, or [`None`] if it couldn't be determined.
+pub fn cache_home() -> Option<PathBuf> {
+	var_os(CACHE_HOME_VAR).map(PathBuf::from)
+		.or_else(move || dirs::cache_dir().map(home))
+}
+
+/// Return the path to the state directory of Rookup, which has logs and history that should persist but aren't worth
+/// backing up, or [`None`] if it couldn't be determined.
+/// 
+/// This is `$XDG_STATE_HOME/rookup` on Linux. Other platforms have no such directory, so the local data directory is
+/// used instead.
+pub fn state_home() -> Option<PathBuf> {
+	var_os(STATE_HOME_VAR).map(PathBuf::from)
+		.or_else(move || dirs::state_dir().or_else(dirs::data_local_dir).map(home))
+}
+
+/// Consume the state home directory and return the path to the compiler invocation log.
+/// 
+/// Older versions of Rookup kept the log in the cache home, under the same name.
+pub fn invocation_log_path(mut state_home: PathBuf) -> PathBuf {
+	state_home.push("invocations.log");
+	state_home
+}
+
+/// Consume the cache home directory and return the path to the directory that large downloads are written to.
+pub fn downloads_path(mut cache_home: PathBuf) -> PathBuf {
+	cache_home.push("downloads");
+	cache_home
+}
+
+/// Name of the environment variable that overrides the path to the `.netrc` file.
+pub const NETRC_VAR: &str = "NETRC";
+
+/// Return the path to the `.netrc` file with credentials for remote hosts, or [`None`] if it couldn't be determined.
+/// 
+/// This is `NETRC` if it's set, `%USERPROFILE%\_netrc` on Windows, and `~/.netrc` elsewhere.
+pub fn netrc_path() -> Option<PathBuf> {
+	if let Some(path) = var_os(NETRC_VAR) {
+		return Some(path.into())
+	}
+	let name = if cfg!(windows) { "_netrc" } else { ".netrc" };
+	dirs::home_dir().map(move |home| home.join(name))
+}
+
+/// Name of the environment variable that overrides the directory of the Docker configuration file.
+pub const DOCKER_CONFIG_VAR: &str = "DOCKER_CONFIG";
+
+/// Return the path to the Docker configuration file with credentials for container registries, or [`None`] if it
+/// couldn't be determined.
+/// 
+/// This is `config.json` in `DOCKER_CONFIG` if it's set, or in `~/.docker` otherwise.
+pub fn docker_config_path() -> Option<PathBuf> {
+	let dir = var_os(DOCKER_CONFIG_VAR).map(PathBuf::from)
+		.or_else(move || dirs::home_dir().map(move |home| home.join(".docker")))?;
+	Some(dir.join("config.json"))
+}
+
 /// Return the path to the configuration directory, or [`None`] if it couldn't be determined.
 pub fn config_home() -> Option<PathBuf> {
-	var_os("ROOKUP_CONFIG_HOME").map(PathBuf::from)
+	var_os(CONFIG_HOME_VAR).map(PathBuf::from)
 		.or_else(move || dirs::config_dir().map(home))
 }
 
+/// Return `true` if creating the configuration file when it doesn't exist is disabled with [`NO_CREATE_CONFIG_VAR`].
+pub fn config_creation_disabled() -> bool {
+	var_os(NO_CREATE_CONFIG_VAR).is_some_and(move |v| !v.is_empty())
+}
+
 /// File name of the compiler executable that is to be used by this target.
 pub const SPCOMP_EXE: &str = spcomp_exe::spcomp_exe!();
 
-/// Return `true` if `file_name` is the appropriate compiler executable for this target.
+/// Return `true` if `file_name` is an appropriate compiler executable for this target.
+/// 
+/// See [`CompilerNames::defaults`] for more information.
 #[inline]
 pub fn is_compiler(file_name: &str) -> bool {
-	file_name == SPCOMP_EXE
+	CompilerNames::defaults(OS, ARCH).contains(file_name)
+}
+
+/// File names that the compiler executable of a toolchain may have, in order of preference.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CompilerNames(pub Vec<String>);
+
+impl CompilerNames {
+	/// Return the default compiler names for the `os` operating system and `arch` architecture (as in
+	/// [`std::env::consts`]).
+	/// 
+	/// For architectures other than x86 and x86-64, a name suffixed with the architecture (for e.g. `spcomp64_aarch64`)
+	/// is preferred, falling back to the name for the pointer width of this target, which may run through emulation.
+	/// On x86-64, the 32-bit `spcomp` is used if `spcomp64` isn't available, since some older branches don't ship it.
+	pub fn defaults(os: &str, arch: &str) -> Self {
+		let stem = spcomp_exe::spcomp_exe_stem!();
+		let stems = match arch {
+			"x86_64" => vec!["spcomp64".to_string(), "spcomp".to_string()],
+			"x86" => vec!["spcomp".to_string()],
+			_ => vec![format!("{stem}_{arch}"), stem.to_string()],
+		};
+		let extension = if os == "windows" { ".exe" } else { "" };
+		Self(stems.into_iter().map(move |s| s + extension).collect())
+	}
+
+	/// Return the compiler names for the `os` operating system on the architecture of this target, taking the
+	/// `compilers` setting of `data` into account.
+	pub fn new(data: &ConfigData, os: &str) -> Self {
+		data.compilers.get(&format!("{ARCH}-{os}"))
+			.map(move |names| Self(names.clone()))
+			.unwrap_or_else(move || Self::defaults(os, ARCH))
+	}
+
+	/// Return `true` if `file_name` is any of these names.
+	pub fn contains(&self, file_name: &str) -> bool {
+		self.0.iter().any(move |n| n == file_name)
+	}
+
+	/// Return the path to the most preferred compiler that exists in the directory at `dir`.
+	pub fn find_in(&self, dir: &Path) -> Option<PathBuf> {
+		self.0.iter().map(move |n| dir.join(n)).find(move |p| p.is_file())
+	}
+
+	/// Return the most preferred name.
+	pub fn preferred(&self) -> &str {
+		self.0.first().map(String::as_str).unwrap_or(SPCOMP_EXE)
+	}
 }
@@ -0,0 +1,271 @@
+//! Lock files that keep processes from changing the same files at the same time.
+//!
+//! Every lock file records the process that holds it, so that locks left behind by processes that crashed can be
+//! recognized and cleared instead of being waited on forever.
+
+use std::{
+	ffi::OsString,
+	fmt,
+	fs::{
+		create_dir_all, hard_link, read_to_string, remove_file, rename, File,
+	},
+	io::{
+		ErrorKind as IoErrorKind, Result as IoResult, Write,
+	},
+	path::{
+		Path, PathBuf,
+	},
+	thread::sleep,
+	time::{
+		Duration, SystemTime, UNIX_EPOCH,
+	},
+};
+
+/// How often a lock held by another process is checked while waiting for it.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Age after which a lock file that doesn't name its owner is stale, since its owner writes it right after creating
+/// it.
+const UNOWNED_LOCK_AGE: Duration = Duration::from_secs(10);
+
+/// Age after which a lock is stale on platforms where it can't be checked whether its owner is still running.
+pub const STALE_LOCK_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Return the path to the lock file for `path`, which is a hidden file next to it.
+pub fn lock_path(path: &Path) -> PathBuf {
+	let mut name = OsString::from(".");
+	if let Some(file_name) = path.file_name() {
+		name.push(file_name);
+	}
+	name.push(".lock");
+	path.with_file_name(name)
+}
+
+/// Process that holds a lock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockOwner {
+	pub pid: u32,
+	/// When the lock was taken.
+	pub since: SystemTime,
+}
+
+impl LockOwner {
+	fn current() -> Self {
+		Self {
+			pid: std::process::id(),
+			since: SystemTime::now(),
+		}
+	}
+
+	/// Parse the contents of a lock file, which are the process ID and the Unix timestamp of when it was locked.
+	fn parse(text: &str) -> Option<Self> {
+		let (pid, since) = text.trim().split_once(' ')?;
+		let since = UNIX_EPOCH + Duration::from_secs(since.parse().ok()?);
+		Some(Self {
+			pid: pid.parse().ok().filter(move |&pid| pid != 0)?,
+			since,
+		})
+	}
+
+	/// Return `true` if the process that holds the lock may still be running.
+	/// 
+	/// Process IDs are reused, so a process with the ID of the owner only counts if it was started before the lock was
+	/// taken. Where it can't be checked when processes were started, locks older than [`STALE_LOCK_AGE`] are stale.
+	pub fn is_alive(&self) -> bool {
+		#[cfg(unix)]
+		{
+			let Ok(pid) = libc::pid_t::try_from(self.pid) else { return false };
+			// SAFETY: Signal 0 isn't sent, it only checks whether the process exists.
+			let exists = unsafe { libc::kill(pid, 0) } == 0
+				// The process exists, but belongs to another user.
+				|| std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM);
+			if !exists {
+				return false
+			}
+		}
+		match process_start_time(self.pid) {
+			// Start times are only known to about a second, and when locks were taken is rounded down to seconds.
+			Some(started) => started <= self.since + START_TIME_TOLERANCE,
+			None => self.since.elapsed().map_or(true, move |age| age < STALE_LOCK_AGE),
+		}
+	}
+}
+
+/// Difference between the start time of a process and when its lock was taken that is still attributed to
+/// measurement error, instead of the process ID having been reused.
+const START_TIME_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// Return when the process with the ID `pid` was started, or [`None`] if that can't be determined.
+#[cfg(target_os = "linux")]
+fn process_start_time(pid: u32) -> Option<SystemTime> {
+	let stat = read_to_string(format!("/proc/{pid}/stat")).ok()?;
+	// The name of the program is in parentheses and may contain spaces, so fields are counted from after it, starting
+	// at the third one. The start time is the 22nd, in clock ticks since the system booted.
+	let ticks: u64 = stat.rsplit_once(')')?.1.split_whitespace().nth(19)?.parse().ok()?;
+	let boot_time: u64 = read_to_string("/proc/stat").ok()?.lines()
+		.find_map(move |line| line.strip_prefix("btime "))?
+		.trim().parse().ok()?;
+	// SAFETY: `sysconf` has no preconditions.
+	let ticks_per_second = u64::try_from(unsafe { libc::sysconf(libc::_SC_CLK_TCK) }).ok().filter(move |&t| t > 0)?;
+	Some(UNIX_EPOCH + Duration::from_secs(boot_time) + Duration::from_millis(ticks * 1000 / ticks_per_second))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_start_time(_pid: u32) -> Option<SystemTime> {
+	None
+}
+
+impl fmt::Display for LockOwner {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let since = self.since.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+		write!(f, "{} {since}", self.pid)
+	}
+}
+
+/// What is known about an existing lock file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LockInfo {
+	/// Process that holds the lock, or [`None`] if the lock file doesn't name one.
+	pub owner: Option<LockOwner>,
+	/// `true` if the lock was left behind by a process that is gone, and can be cleared.
+	pub is_stale: bool,
+}
+
+/// Return what is known about the lock file at `path`, or [`None`] if there is none.
+pub fn inspect_lock(path: &Path) -> IoResult<Option<LockInfo>> {
+	let text = match read_to_string(path) {
+		Ok(text) => text,
+		Err(e) if e.kind() == IoErrorKind::NotFound => return Ok(None),
+		Err(e) => return Err(e),
+	};
+	let owner = LockOwner::parse(&text);
+	let is_stale = match owner {
+		Some(owner) => !owner.is_alive(),
+		None => {
+			let modified = path.metadata()?.modified()?;
+			modified.elapsed().is_ok_and(move |age| age >= UNOWNED_LOCK_AGE)
+		}
+	};
+	Ok(Some(LockInfo {
+		owner,
+		is_stale,
+	}))
+}
+
+/// Remove the lock file at `path` if it's stale, returning `true` if it was removed.
+/// 
+/// Another process may clear the lock and take it between checking and removing it, so the lock file is first moved
+/// to a name of its own, and only removed if it's still stale there. Otherwise, it's put back.
+pub fn clear_stale_lock(path: &Path) -> IoResult<bool> {
+	if !inspect_lock(path)?.is_some_and(move |info| info.is_stale) {
+		return Ok(false)
+	}
+	let moved = stale_lock_path(path);
+	match rename(path, &moved) {
+		Ok(()) => {}
+		// Another process cleared it first.
+		Err(e) if e.kind() == IoErrorKind::NotFound => return Ok(false),
+		Err(e) => return Err(e),
+	}
+	if inspect_lock(&moved)?.is_none_or(move |info| info.is_stale) {
+		remove_file(&moved)?;
+		return Ok(true)
+	}
+	// Linking it back fails instead of replacing the lock if yet another process took it in the meantime.
+	let restored = hard_link(&moved, path);
+	remove_file(&moved)?;
+	match restored {
+		Err(e) if e.kind() != IoErrorKind::AlreadyExists => Err(e),
+		_ => Ok(false),
+	}
+}
+
+/// Return a path next to the lock file at `path` that only this call uses, to move it to while clearing it.
+fn stale_lock_path(path: &Path) -> PathBuf {
+	let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+	let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+	name.push(format!(".stale-{}-{nanos:08x}", std::process::id()));
+	path.with_file_name(name)
+}
+
+/// Lock held by this process, which is released when dropped.
+#[derive(Debug)]
+pub struct LockFile {
+	path: PathBuf,
+}
+
+impl LockFile {
+	/// Create the lock file at `path` (and the directories up to it), recording this process as its owner.
+	///
+	/// If the lock is held by another process that is still running, `wait` is called with its owner, and this blocks
+	/// until the lock is released. Locks left behind by processes that are gone are cleared.
+	pub fn acquire(path: PathBuf, wait: &dyn Fn(Option<&LockOwner>)) -> IoResult<Self> {
+		if let Some(parent) = path.parent() {
+			create_dir_all(parent)?;
+		}
+		let mut waited = false;
+		loop {
+			match File::options().write(true).create_new(true).open(&path) {
+				Ok(mut file) => {
+					// The lock is released if its owner couldn't be written.
+					let lock = Self { path };
+					file.write_all(LockOwner::current().to_string().as_bytes())?;
+					return Ok(lock)
+				}
+				Err(e) if e.kind() == IoErrorKind::AlreadyExists => {}
+				Err(e) => return Err(e),
+			}
+
+			match inspect_lock(&path)? {
+				Some(LockInfo { is_stale: true, .. }) => {
+					clear_stale_lock(&path)?;
+				}
+				Some(LockInfo { owner, .. }) => {
+					if !waited {
+						wait(owner.as_ref());
+						waited = true;
+					}
+					sleep(POLL_INTERVAL);
+				}
+				// The lock was released in the meantime.
+				None => {}
+			}
+		}
+	}
+}
+
+impl Drop for LockFile {
+	fn drop(&mut self) {
+		let _ = remove_file(&self.path);
+	}
+}
+
+#[test]
+fn stale_locks_are_cleared() {
+	let dir = crate::TempDir::new("rookup-lock-test").unwrap();
+	let path = dir.join("lock");
+	let lock = LockFile::acquire(path.clone(), &|_| panic!("lock is free")).unwrap();
+	let info = inspect_lock(&path).unwrap().unwrap();
+	assert_eq!(info.owner.map(move |o| o.pid), Some(std::process::id()));
+	assert!(!info.is_stale);
+	assert!(!clear_stale_lock(&path).unwrap());
+	drop(lock);
+	assert_eq!(inspect_lock(&path).unwrap(), None);
+
+	// Process IDs are never this large on any platform that Rookup runs on.
+	std::fs::write(&path, format!("{} 0", i32::MAX)).unwrap();
+	assert!(inspect_lock(&path).unwrap().unwrap().is_stale);
+	let lock = LockFile::acquire(path.clone(), &|_| panic!("lock is stale")).unwrap();
+	drop(lock);
+	assert!(!path.exists());
+
+	// Locks named after a running process are stale if they were taken before it started, since its ID was reused.
+	let reused = LockOwner { pid: std::process::id(), since: UNIX_EPOCH };
+	assert!(!reused.is_alive());
+	assert!(LockOwner::current().is_alive());
+
+	std::fs::write(&path, format!("{} 0", i32::MAX)).unwrap();
+	assert!(clear_stale_lock(&path).unwrap());
+	assert!(!path.exists());
+	assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
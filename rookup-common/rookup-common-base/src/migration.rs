@@ -0,0 +1,110 @@
+//! Upgrades of configuration files written for older versions of Rookup.
+//!
+//! Every configuration file records the version of its format in `schema-version`. When a key is renamed or moved, the
+//! version is incremented, and a migration that rewrites documents of the previous version is added to
+//! [`MIGRATIONS`], so that configuration files keep working (and keep their formatting and comments) after upgrading.
+
+use toml_edit::{
+	DocumentMut, TableLike,
+};
+
+/// Version of the format of configuration files written by this version of Rookup.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Name of the key that holds the version of the format of a configuration file.
+pub const SCHEMA_VERSION_KEY: &str = "schema-version";
+
+/// Function that upgrades a configuration document to the next version of the format.
+pub type Migration = fn(&mut DocumentMut) -> Result<(), MigrationError>;
+
+/// Migrations in order, where the one at index `n` upgrades documents of version `n` to version `n + 1`.
+pub const MIGRATIONS: [Migration; SCHEMA_VERSION as usize] = [
+	// Version 0 is any configuration file from before `schema-version` was added, which is already valid in version 1.
+	|_| Ok(()),
+];
+
+/// Upgrade `document` to [`SCHEMA_VERSION`], returning the version it was upgraded from, or [`None`] if it already was
+/// of that version.
+///
+/// Documents without a `schema-version` are considered to be of version 0.
+pub fn migrate(document: &mut DocumentMut) -> Result<Option<u32>, MigrationError> {
+	let version = match document.get(SCHEMA_VERSION_KEY) {
+		None => 0,
+		Some(item) => item.as_integer()
+			.and_then(move |v| u32::try_from(v).ok())
+			.ok_or_else(move || MigrationError::InvalidVersion(item.to_string().trim().into()))?,
+	};
+	if version == SCHEMA_VERSION {
+		return Ok(None)
+	}
+	if version > SCHEMA_VERSION {
+		return Err(MigrationError::TooNew(version))
+	}
+
+	for migration in &MIGRATIONS[version as usize..] {
+		migration(document)?;
+	}
+	document[SCHEMA_VERSION_KEY] = i64::from(SCHEMA_VERSION).into();
+	Ok(Some(version))
+}
+
+/// Rename the key `from` in `table` to `to`, keeping its value and comments.
+///
+/// Returns `false` if `table` has no key `from`. An existing key `to` is replaced.
+pub fn rename_key(table: &mut dyn TableLike, from: &str, to: &str) -> bool {
+	let Some(decor) = table.get_key_value(from).map(move |(key, ..)| key.leaf_decor().clone()) else {
+		return false
+	};
+	let item = table.remove(from).expect("key should exist");
+	table.insert(to, item);
+	if let Some(mut new_key) = table.key_mut(to) {
+		*new_key.leaf_decor_mut() = decor;
+	}
+	true
+}
+
+/// Move the key `from` in `table` into the table `to` (which is created if needed) as `key`.
+///
+/// Returns `false` if `table` has no key `from`.
+pub fn move_key(table: &mut dyn TableLike, from: &str, to: &str, key: &str) -> Result<bool, MigrationError> {
+	let Some(item) = table.remove(from) else {
+		return Ok(false)
+	};
+	let target = table.entry(to).or_insert_with(toml_edit::table);
+	let target = target.as_table_like_mut().ok_or_else(move || MigrationError::NotATable(to.into()))?;
+	target.insert(key, item);
+	Ok(true)
+}
+
+/// Error that occurred in [`migrate`].
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+	#[error("`{SCHEMA_VERSION_KEY}` must be a non-negative integer, but it is {0}")]
+	InvalidVersion(String),
+	#[error(
+		"configuration file is of schema version {0}, which is newer than the latest version supported by this version \
+		of Rookup ({SCHEMA_VERSION}); upgrade Rookup to use it"
+	)]
+	TooNew(u32),
+	#[error("`{0}` must be a table")]
+	NotATable(String),
+}
+
+#[test]
+fn documents_are_migrated() {
+	let mut document: DocumentMut = "# Default toolchain.\ndefault = \"stable\"\n".parse().unwrap();
+	assert_eq!(migrate(&mut document).unwrap(), Some(0));
+	assert_eq!(document[SCHEMA_VERSION_KEY].as_integer(), Some(SCHEMA_VERSION as _));
+	assert_eq!(migrate(&mut document).unwrap(), None);
+
+	assert!(rename_key(document.as_table_mut(), "default", "selector"));
+	assert!(!rename_key(document.as_table_mut(), "default", "selector"));
+	assert!(document.to_string().contains("# Default toolchain.\nselector = \"stable\""));
+	assert!(move_key(document.as_table_mut(), "selector", "source", "default").unwrap());
+	assert_eq!(document["source"]["default"].as_str(), Some("stable"));
+
+	document[SCHEMA_VERSION_KEY] = i64::from(SCHEMA_VERSION + 1).into();
+	assert!(matches!(migrate(&mut document), Err(MigrationError::TooNew(..))));
+	document[SCHEMA_VERSION_KEY] = "one".into();
+	assert!(matches!(migrate(&mut document), Err(MigrationError::InvalidVersion(..))));
+}
@@ -0,0 +1,169 @@
+//! Install receipts that record how a toolchain was installed.
+
+use serde::{
+	Deserialize, Serialize,
+};
+use std::{
+	fmt,
+	fs::{
+		read_to_string, write,
+	},
+	io::{
+		Error as IoError, ErrorKind as IoErrorKind,
+	},
+	path::Path,
+};
+use crate::{
+	CompilerNames, Component,
+};
+use toml_edit::{
+	de::{
+		from_str, Error as DeError,
+	},
+	ser::{
+		to_string_pretty, Error as SerError,
+	},
+};
+
+/// Name of the receipt file that is put into the directory of every toolchain installed by Rookup.
+pub const RECEIPT_FILE: &str = "rookup-receipt.toml";
+
+/// Record of how a toolchain was installed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Receipt {
+	/// Version of the installed toolchain.
+	pub version: String,
+	/// Where the toolchain was installed from.
+	pub origin: Origin,
+	/// Operating system that the toolchain was built for, if known.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub target_os: Option<String>,
+	/// Parts of the toolchain that were installed, or empty if all of them were.
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	pub components: Vec<Component>,
+	/// Signature that the archive was verified with, if any.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub signature: Option<VerifiedSignature>,
+	/// File name of the compiler executable in the toolchain directory, if known.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub compiler: Option<String>,
+	/// Command to run the compiler with (for e.g. `["wine"]`), overriding the `runners` setting of the configuration
+	/// file.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub runner: Option<Vec<String>>,
+}
+
+/// Record of a valid signature of an installed archive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum VerifiedSignature {
+	/// Minisign signature made with `key`.
+	Minisign {
+		key: String,
+	},
+	/// OpenPGP signature made with a key in `keyring`.
+	Gpg {
+		keyring: String,
+	},
+}
+
+impl fmt::Display for VerifiedSignature {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Minisign { key } => write!(f, "minisign key {key}"),
+			Self::Gpg { keyring } => write!(f, "OpenPGP keyring {keyring}"),
+		}
+	}
+}
+
+/// Enumeration of places toolchains can be installed from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Origin {
+	/// Toolchain was downloaded from an archive on a remote server.
+	Upstream {
+		url: String,
+		/// SHA-256 digest of the archive, as a lowercase hexadecimal string.
+		#[serde(default, skip_serializing_if = "Option::is_none")]
+		sha256: Option<String>,
+	},
+	/// Toolchain was copied from a directory on another machine, like a custom toolchain that was exported.
+	Directory {
+		path: String,
+	},
+}
+
+impl fmt::Display for Origin {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Upstream { url, .. } => f.write_str(url),
+			Self::Directory { path } => f.write_str(path),
+		}
+	}
+}
+
+impl Receipt {
+	/// Read the receipt of the toolchain located at `toolchain_path`, returning [`None`] if there is none.
+	pub fn read(toolchain_path: &Path) -> Result<Option<Self>, ReceiptError> {
+		match read_to_string(toolchain_path.join(RECEIPT_FILE)) {
+			Ok(text) => Self::parse(&text).map(Some),
+			Err(e) if e.kind() == IoErrorKind::NotFound => Ok(None),
+			Err(e) => Err(e.into()),
+		}
+	}
+
+	/// Parse the contents of a receipt file.
+	#[inline]
+	pub fn parse(text: &str) -> Result<Self, ReceiptError> {
+		Ok(from_str(text)?)
+	}
+
+	/// Return the contents of the receipt file for this receipt.
+	#[inline]
+	pub fn to_toml(&self) -> Result<String, ReceiptError> {
+		Ok(to_string_pretty(self)?)
+	}
+
+	/// Return the parts of the toolchain that were installed.
+	#[inline]
+	pub fn components(&self) -> &[Component] {
+		Component::or_all(&self.components)
+	}
+
+	/// Return the names that the compiler executable of this toolchain may have.
+	/// 
+	/// This is the recorded compiler name if there is one, followed by the default names for the recorded operating
+	/// system in case it went missing.
+	pub fn compiler_names(&self) -> CompilerNames {
+		let os = self.target_os.as_deref().unwrap_or(std::env::consts::OS);
+		let mut names = CompilerNames::defaults(os, std::env::consts::ARCH);
+		if let Some(compiler) = self.compiler.as_ref() {
+			names.0.retain(move |n| n != compiler);
+			names.0.insert(0, compiler.clone());
+		}
+		names
+	}
+
+	/// Return `true` if this toolchain was built for an operating system other than the host's.
+	pub fn is_foreign(&self) -> bool {
+		self.target_os.as_deref().is_some_and(move |os| os != std::env::consts::OS)
+	}
+
+	/// Write this receipt into the toolchain located at `toolchain_path`.
+	pub fn write(&self, toolchain_path: &Path) -> Result<(), ReceiptError> {
+		write(toolchain_path.join(RECEIPT_FILE), self.to_toml()?)?;
+		Ok(())
+	}
+}
+
+/// Error that occurred while reading or writing a [`Receipt`].
+#[derive(Debug, thiserror::Error)]
+pub enum ReceiptError {
+	#[error("{0}")]
+	Io(#[from] IoError),
+	#[error("failed to parse receipt: {0}")]
+	Parse(#[from] DeError),
+	#[error("failed to serialize receipt: {0}")]
+	Serialize(#[from] SerError),
+}
@@ -0,0 +1,99 @@
+//! "Did you mean" suggestions for names that couldn't be resolved.
+
+use std::fmt;
+
+use crate::{
+	version::VersionReq,
+	Selector,
+};
+
+/// Maximum number of suggestions returned by [`Suggestions::new`].
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Names that are similar to a name that couldn't be resolved, most similar first.
+///
+/// These are displayed as a hint to append to an error message, which is empty if there are no suggestions.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Suggestions(pub Vec<String>);
+
+impl Suggestions {
+	/// Return the names out of `candidates` that are similar to `name`, ignoring case.
+	pub fn new<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Self {
+		let max_distance = (name.chars().count() / 3).max(1);
+		let mut similar: Vec<(usize, &str)> = candidates.into_iter()
+			.filter(move |candidate| *candidate != name)
+			.map(move |candidate| (edit_distance(name, candidate), candidate))
+			.filter(move |(distance, ..)| *distance <= max_distance)
+			.collect();
+		similar.sort();
+		similar.dedup_by(move |a, b| a.1 == b.1);
+		Self(similar.into_iter().take(MAX_SUGGESTIONS).map(move |(.., name)| name.to_string()).collect())
+	}
+
+	/// Return the suggestions for the alias `alias`, which are `candidates` that are similar to it, and a
+	/// [super-version selector](Selector::Super) if `alias` looks like a version (for e.g. `:1.12` for `1.12`).
+	pub fn for_alias<'a>(alias: &str, candidates: impl IntoIterator<Item = &'a str>) -> Self {
+		let mut suggestions = Self::new(alias, candidates);
+		if alias.starts_with(|c: char| c.is_ascii_digit()) && alias.parse::<VersionReq>().is_ok() {
+			suggestions.0.insert(0, format!("{}{alias}", Selector::SUPER_PREFIX));
+			suggestions.0.truncate(MAX_SUGGESTIONS);
+		}
+		suggestions
+	}
+
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+impl fmt::Display for Suggestions {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let Some((last, rest)) = self.0.split_last() else {
+			return Ok(())
+		};
+		f.write_str("; did you mean ")?;
+		for suggestion in rest {
+			write!(f, "`{suggestion}`, ")?;
+		}
+		if !rest.is_empty() {
+			f.write_str("or ")?;
+		}
+		write!(f, "`{last}`?")
+	}
+}
+
+/// Return the Levenshtein distance between `a` and `b`, ignoring case.
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+	let b: Vec<char> = b.chars().flat_map(char::to_lowercase).collect();
+	let mut row: Vec<usize> = (0..=b.len()).collect();
+	for (i, a) in a.chars().flat_map(char::to_lowercase).enumerate() {
+		let mut diagonal = row[0];
+		row[0] = i + 1;
+		for (j, b) in b.iter().enumerate() {
+			let substitution = diagonal + usize::from(a != *b);
+			diagonal = row[j + 1];
+			row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+		}
+	}
+	row[b.len()]
+}
+
+#[test]
+fn similar_names_are_suggested() {
+	assert_eq!(edit_distance("stabel", "stable"), 2);
+	assert_eq!(edit_distance("Latest", "latest"), 0);
+
+	let candidates = ["latest", "stable", "installed", "work", "1.12"];
+	assert_eq!(Suggestions::new("stabel", candidates).0, ["stable"]);
+	assert_eq!(Suggestions::new("wrk", candidates).0, ["work"]);
+	assert!(Suggestions::new("something", candidates).is_empty());
+	assert_eq!(Suggestions::for_alias("1.13", candidates).0, [":1.13", "1.12"]);
+
+	assert_eq!(Suggestions::new("stabel", candidates).to_string(), "; did you mean `stable`?");
+	assert_eq!(
+		Suggestions(vec!["a".into(), "b".into(), "c".into()]).to_string(),
+		"; did you mean `a`, `b`, or `c`?",
+	);
+	assert_eq!(Suggestions::default().to_string(), "");
+}
@@ -0,0 +1,72 @@
+//! Temporary directories.
+
+use std::{
+	env::temp_dir,
+	fs::{
+		create_dir, remove_dir_all,
+	},
+	io::Result as IoResult,
+	path::{
+		Path, PathBuf,
+	},
+	sync::atomic::{
+		AtomicU64, Ordering as AtomicOrdering,
+	},
+	time::{
+		SystemTime, UNIX_EPOCH,
+	},
+};
+
+/// Directory in the temporary directory of the system, which is removed along with everything in it when dropped.
+#[derive(Debug)]
+pub struct TempDir {
+	path: PathBuf,
+}
+
+impl TempDir {
+	/// Create a new, empty directory in the temporary directory of the system with a name that starts with `prefix`.
+	///
+	/// The name is unique to this call, so directories created with the same prefix (for e.g. by tests running in
+	/// parallel) never collide.
+	pub fn new(prefix: &str) -> IoResult<Self> {
+		static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+		let n = COUNTER.fetch_add(1, AtomicOrdering::Relaxed);
+		let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+		let path = temp_dir().join(format!("{prefix}-{}-{n}-{nanos:08x}", std::process::id()));
+		create_dir(&path)?;
+		Ok(Self {
+			path,
+		})
+	}
+
+	/// Return the path to the directory.
+	#[inline]
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Return the path to `name` in the directory.
+	#[inline]
+	pub fn join<P: AsRef<Path>>(&self, name: P) -> PathBuf {
+		self.path.join(name)
+	}
+}
+
+impl Drop for TempDir {
+	fn drop(&mut self) {
+		let _ = remove_dir_all(&self.path);
+	}
+}
+
+#[test]
+fn temp_dirs_are_unique_and_removed() {
+	let a = TempDir::new("rookup-temp-test").unwrap();
+	let b = TempDir::new("rookup-temp-test").unwrap();
+	assert_ne!(a.path(), b.path());
+	std::fs::write(a.join("file"), "contents").unwrap();
+	let path = a.path().to_path_buf();
+	drop(a);
+	assert!(!path.exists());
+	assert!(b.path().is_dir());
+}
@@ -1,7 +1,10 @@
 //! Definitions for Rookup toolchains.
 
 use std::{
-	env::var_os,
+	convert::Infallible,
+	env::{
+		split_paths, var_os,
+	},
 	ffi::{
 		OsStr, OsString,
 	},
@@ -9,11 +12,14 @@ use std::{
 		self, Write,
 	},
 	fs::{
-		read_dir, ReadDir,
+		canonicalize, read_dir, ReadDir,
 	},
 	io::Result as IoResult,
 	ops::Deref,
-	path::PathBuf,
+	path::{
+		absolute, is_separator, Path, PathBuf,
+	},
+	str::FromStr,
 };
 
 use crate::{
@@ -21,24 +27,66 @@ use crate::{
 		ConfigError, ConfigData,
 	},
 	version::{
-		Version, version_ord,
+		version_ord, VersionReq,
 	},
-	home, toolchain_home_path,
+	home, toolchain_home_path, CompilerNames, Component, Receipt, Suggestions,
 };
 
 /// Path to the global includes directory.
 pub const INCLUDES_PATH: &str = "includes";
 
-/// Return the path to the toolchain directory, or [`None`] if it couldn't be determined.
+/// Path to the directory with extra files of a toolchain, relative to the toolchain directory.
+pub const EXTRAS_PATH: &str = "extras";
+
+/// Path to the directory with the license files of a toolchain, relative to the toolchain directory.
+pub const LICENSES_PATH: &str = "licenses";
+
+/// Return `true` if a file named `file_name` in a distribution has license terms (for e.g. `LICENSE.txt` or
+/// `GPLv3.txt`).
+pub fn is_license_file(file_name: &str) -> bool {
+	let stem = file_name.split('.').next().unwrap_or_default().to_ascii_lowercase();
+	["license", "licence", "copying", "gpl"].iter().any(move |prefix| stem.starts_with(prefix))
+}
+
+/// Name of the environment variable that overrides the toolchain directories.
+pub const TOOLCHAIN_HOME_VAR: &str = "ROOKUP_TOOLCHAIN_HOME";
+
+/// Name of the environment variable that overrides the custom toolchain directories.
+pub const CUSTOM_TOOLCHAIN_HOME_VAR: &str = "ROOKUP_CUSTOM_TOOLCHAIN_HOME";
+
+/// Return the paths to the toolchain directories.
+/// 
+/// `ROOKUP_TOOLCHAIN_HOME` may specify multiple directories, separated the same way as in `PATH`.
+pub fn toolchain_homes() -> Vec<PathBuf> {
+	homes_from_env(TOOLCHAIN_HOME_VAR)
+		.unwrap_or_else(move || dirs::cache_dir().map(home).map(toolchain_home_path).into_iter().collect())
+}
+
+/// Return the path to the toolchain directory that toolchains are installed to, or [`None`] if it couldn't be
+/// determined.
+/// 
+/// This is the first directory out of [`toolchain_homes`].
 pub fn toolchain_home() -> Option<PathBuf> {
-	var_os("ROOKUP_TOOLCHAIN_HOME").map(PathBuf::from)
-		.or_else(move || dirs::cache_dir().map(home).map(toolchain_home_path))
+	toolchain_homes().into_iter().next()
+}
+
+/// Return the paths to the custom toolchain directories.
+/// 
+/// `ROOKUP_CUSTOM_TOOLCHAIN_HOME` may specify multiple directories, separated the same way as in `PATH`.
+pub fn custom_toolchain_homes() -> Vec<PathBuf> {
+	homes_from_env(CUSTOM_TOOLCHAIN_HOME_VAR)
+		.unwrap_or_else(move || dirs::data_dir().map(home).map(toolchain_home_path).into_iter().collect())
 }
 
-/// Return the path to the custom toolchain directory, or [`None`] if it couldn't be determined.
+/// Return the path to the first custom toolchain directory, or [`None`] if it couldn't be determined.
 pub fn custom_toolchain_home() -> Option<PathBuf> {
-	var_os("ROOKUP_CUSTOM_TOOLCHAIN_HOME").map(PathBuf::from)
-		.or_else(move || dirs::data_dir().map(home).map(toolchain_home_path))
+	custom_toolchain_homes().into_iter().next()
+}
+
+/// Return the non-empty paths in the PATH-like environment variable `key`, or [`None`] if it isn't set.
+fn homes_from_env(key: &str) -> Option<Vec<PathBuf>> {
+	let value = var_os(key)?;
+	Some(split_paths(&value).filter(move |p| !p.as_os_str().is_empty()).collect())
 }
 
 macro_rules! res_unwrap_or_return {
@@ -50,29 +98,59 @@ macro_rules! res_unwrap_or_return {
 	};
 }
 
-/// Parsed toolchain selector of the format `':' super_version | alias`.
+/// Parsed toolchain selector of the format `':' version_req | 'path:' path | path | alias`.
+/// 
+/// See [`VersionReq`] for the format of `version_req`. A plain version like `1.12` selects the latest version that is a
+/// sub-version of it.
+/// 
+/// Absolute paths and paths with a directory separator (like `./spcomp`) are recognized without the `path:` prefix.
 // TODO: Documentation for this should be public!
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Selector<'a> {
 	Super(&'a str),
 	Alias(&'a str),
+	/// Path to an unmanaged toolchain directory.
+	Path(&'a str),
 }
 
 impl<'a> Selector<'a> {
 	pub const SUPER_PREFIX: char = ':';
+	pub const PATH_PREFIX: &'static str = "path:";
+	/// Built-in alias that selects the latest installed toolchain in any home, which never needs the remote.
+	/// 
+	/// It can't be set in the configuration file.
+	pub const INSTALLED: &'static str = "installed";
 
 	pub fn parse(s: &'a str) -> Self {
-		s.strip_prefix(Self::SUPER_PREFIX)
-			.map(Self::Super)
-			.unwrap_or(Self::Alias(s))
+		if let Some(path) = s.strip_prefix(Self::PATH_PREFIX) {
+			Self::Path(path)
+		} else if let Some(version) = s.strip_prefix(Self::SUPER_PREFIX) {
+			Self::Super(version)
+		} else if Self::is_path_like(s) {
+			Self::Path(s)
+		} else {
+			Self::Alias(s)
+		}
+	}
+
+	/// Return `true` if `s` is recognized as a path without the [`PATH_PREFIX`](Self::PATH_PREFIX).
+	fn is_path_like(s: &str) -> bool {
+		Path::new(s).is_absolute() || s.contains(is_separator) || s == "." || s == ".."
 	}
 
 	pub fn test(&self, data: &ConfigData, version: &str) -> bool {
 		match self {
+			Self::Alias(Self::INSTALLED) => {
+				let compilers = CompilerNames::new(data, std::env::consts::OS);
+				find_latest_installed(&compilers).is_some_and(move |(name, ..)| name == version)
+			}
 			Self::Alias(name) => {
 				data.aliases.get(*name).is_some_and(move |a| a == version)
 			}
-			Self::Super(super_version) => version.is_sub_version_of(super_version),
+			Self::Super(super_version) => {
+				super_version.parse::<VersionReq>().is_ok_and(move |r| r.matches(version))
+			}
+			Self::Path(..) => false,
 		}
 	}
 
@@ -95,7 +173,44 @@ impl Deref for Selector<'_> {
 		match self {
 			Self::Super(s) => s,
 			Self::Alias(s) => s,
+			Self::Path(s) => s,
+		}
+	}
+}
+
+/// Selectors are serialized as strings in the same format that [`Selector::parse`] accepts.
+impl serde::Serialize for Selector<'_> {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(self)
+	}
+}
+
+/// Selectors borrow the strings that they're parsed from, so they can only be deserialized from strings that are
+/// borrowed from the input as they are, and not from ones that had to be unescaped, for example. [`SelectorBuf`] can be
+/// deserialized from any string.
+impl<'de: 'a, 'a> serde::Deserialize<'de> for Selector<'a> {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		struct Visitor;
+
+		impl<'de> serde::de::Visitor<'de> for Visitor {
+			type Value = Selector<'de>;
+
+			fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+				f.write_str("a selector string borrowed from the input")
+			}
+
+			fn visit_borrowed_str<E: serde::de::Error>(self, s: &'de str) -> Result<Self::Value, E> {
+				Ok(Selector::parse(s))
+			}
+
+			fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Self::Value, E> {
+				Err(E::custom(format_args!(
+					"selector {s:?} isn't borrowed from the input, so it can only be deserialized as a `SelectorBuf`",
+				)))
+			}
 		}
+
+		deserializer.deserialize_str(Visitor)
 	}
 }
 
@@ -107,17 +222,125 @@ impl fmt::Display for Selector<'_> {
 				f.write_str(s)
 			}
 			Self::Alias(s) => f.write_str(s),
+			Self::Path(s) => {
+				if !Self::is_path_like(s) {
+					f.write_str(Self::PATH_PREFIX)?;
+				}
+				f.write_str(s)
+			}
 		}
 	}
 }
 
+/// Owned version of [`Selector`], for keeping selectors (for e.g. in [`ConfigData`]) without borrowing the strings that
+/// they were parsed from.
+/// 
+/// It's parsed with [`FromStr`] and formatted with [`Display`](fmt::Display) in the same format as [`Selector`], so
+/// parsing a formatted selector gives it back.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SelectorBuf {
+	Super(String),
+	Alias(String),
+	/// Path to an unmanaged toolchain directory.
+	Path(String),
+}
+
+impl SelectorBuf {
+	#[inline]
+	pub fn parse(s: &str) -> Self {
+		Selector::parse(s).into()
+	}
+
+	/// Borrow this as a [`Selector`].
+	pub fn as_selector(&self) -> Selector<'_> {
+		match self {
+			Self::Super(s) => Selector::Super(s),
+			Self::Alias(s) => Selector::Alias(s),
+			Self::Path(s) => Selector::Path(s),
+		}
+	}
+}
+
+impl From<Selector<'_>> for SelectorBuf {
+	fn from(selector: Selector<'_>) -> Self {
+		match selector {
+			Selector::Super(s) => Self::Super(s.into()),
+			Selector::Alias(s) => Self::Alias(s.into()),
+			Selector::Path(s) => Self::Path(s.into()),
+		}
+	}
+}
+
+impl<'a> From<&'a SelectorBuf> for Selector<'a> {
+	#[inline]
+	fn from(selector: &'a SelectorBuf) -> Self {
+		selector.as_selector()
+	}
+}
+
+impl FromStr for SelectorBuf {
+	type Err = Infallible;
+	#[inline]
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Ok(Self::parse(s))
+	}
+}
+
+impl From<&str> for SelectorBuf {
+	#[inline]
+	fn from(s: &str) -> Self {
+		Self::parse(s)
+	}
+}
+
+impl fmt::Display for SelectorBuf {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		fmt::Display::fmt(&self.as_selector(), f)
+	}
+}
+
+/// Owned selectors are serialized as strings in the same format as [`Selector`]s, and can be deserialized from any
+/// string.
+impl serde::Serialize for SelectorBuf {
+	#[inline]
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		selector_buf::serialize(self, serializer)
+	}
+}
+
+impl<'de> serde::Deserialize<'de> for SelectorBuf {
+	#[inline]
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		selector_buf::deserialize(deserializer)
+	}
+}
+
+/// (De)serialization of [`SelectorBuf`]s, which configuration files use through `#[serde(with)]`.
+pub(crate) mod selector_buf {
+	use serde::Deserialize;
+	use std::borrow::Cow;
+
+	use super::SelectorBuf;
+
+	pub fn serialize<S: serde::Serializer>(selector: &SelectorBuf, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(selector)
+	}
+
+	pub fn deserialize<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<SelectorBuf, D::Error> {
+		<Cow<'de, str>>::deserialize(deserializer).map(move |s| SelectorBuf::parse(&s))
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct FoundToolchain {
 	pub name: String,
 	pub kinded: FoundToolchainKinded,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
 pub enum FoundToolchainKinded {
 	Latest {
 		home: PathBuf,
@@ -125,6 +348,9 @@ pub enum FoundToolchainKinded {
 	Aliased {
 		path: PathBuf,
 	},
+	Path {
+		path: PathBuf,
+	},
 }
 
 impl FoundToolchain {
@@ -134,16 +360,64 @@ impl FoundToolchain {
 				home.push(self.name);
 				home
 			}
-			FoundToolchainKinded::Aliased { path } => path,
+			FoundToolchainKinded::Aliased { path } | FoundToolchainKinded::Path { path } => path,
+		}
+	}
+}
+
+/// Step taken by [`find_toolchain_traced`] while searching for a toolchain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindStep<'a> {
+	/// Alias `alias` was resolved to `version`.
+	Alias {
+		alias: &'a str,
+		version: &'a str,
+	},
+	/// Toolchain home `home` was searched for the toolchain at `path`, which `found` tells whether it has a compiler.
+	Home {
+		home: &'a Path,
+		path: &'a Path,
+		found: bool,
+	},
+}
+
+impl fmt::Display for FindStep<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match *self {
+			Self::Alias { alias, version } => write!(f, "alias {alias} is set to {version}"),
+			Self::Home { home, path, found } => {
+				let (home, version) = (home.display(), path.file_name().unwrap_or_default().to_string_lossy());
+				match found {
+					true => write!(f, "toolchain home {home} has {version}"),
+					false if path.exists() => write!(f, "{version} in toolchain home {home} has no compiler"),
+					false => write!(f, "toolchain home {home} doesn't have {version}"),
+				}
+			}
 		}
 	}
 }
 
 /// Search for a toolchain using `selector`, given `config`.
+#[inline]
 pub fn find_toolchain(config: &ConfigData, selector: Selector<'_>) -> Result<FoundToolchain, FindToolchainError> {
+	find_toolchain_traced(config, selector, &mut |_| {})
+}
+
+/// Search for a toolchain using `selector`, given `config`, reporting every step of resolving aliases to `trace`.
+pub fn find_toolchain_traced(
+	config: &ConfigData, selector: Selector<'_>, trace: &mut dyn FnMut(FindStep<'_>),
+) -> Result<FoundToolchain, FindToolchainError> {
+	let compilers = CompilerNames::new(config, std::env::consts::OS);
 	match selector {
+		Selector::Alias(Selector::INSTALLED) => {
+			let (name, home) = find_latest_installed(&compilers).ok_or(FindToolchainError::NoneInstalled)?;
+			Ok(FoundToolchain {
+				name,
+				kinded: FoundToolchainKinded::Latest { home },
+			})
+		}
 		Selector::Super(s) => {
-			let (name, home) = find_latest_toolchain_of(s)
+			let (name, home) = find_latest_toolchain_of(s, &compilers)
 				.ok_or_else(move || FindToolchainError::LatestNotFound(s.to_string()))?;
 			Ok(FoundToolchain {
 				name,
@@ -152,8 +426,9 @@ pub fn find_toolchain(config: &ConfigData, selector: Selector<'_>) -> Result<Fou
 		}
 		Selector::Alias(s) => {
 			let version = config.aliases.get(s)
-				.ok_or_else(move || FindToolchainError::NoAliasDefault(s.to_string()))?;
-			let path = find_toolchain_path(OsStr::new(version))
+				.ok_or_else(move || FindToolchainError::NoAliasDefault(s.to_string(), alias_suggestions(config, s)))?;
+			trace(FindStep::Alias { alias: s, version });
+			let path = find_toolchain_path_traced(OsStr::new(version), &compilers, trace)
 				.ok_or_else(move || FindToolchainError::NotFound {
 					version: version.to_string(),
 					alias: s.to_string(),
@@ -163,9 +438,32 @@ pub fn find_toolchain(config: &ConfigData, selector: Selector<'_>) -> Result<Fou
 				kinded: FoundToolchainKinded::Aliased { path },
 			})
 		}
+		Selector::Path(s) => {
+			let path = absolute(s).unwrap_or_else(move |_| PathBuf::from(s));
+			if !has_compiler(&path, &compilers) {
+				return Err(FindToolchainError::NoCompiler(path))
+			}
+			Ok(FoundToolchain {
+				name: path.file_name().map(move |n| n.to_string_lossy().into_owned()).unwrap_or_else(move || s.into()),
+				kinded: FoundToolchainKinded::Path { path },
+			})
+		}
 	}
 }
 
+/// Return suggestions for the alias `alias` that has no version set, out of the aliases in `config`, the built-in ones,
+/// and installed toolchains.
+fn alias_suggestions(config: &ConfigData, alias: &str) -> Suggestions {
+	let installed: Vec<String> = ToolchainVersions::new()
+		.flat_map(move |(.., result)| result.into_iter().flatten().flatten())
+		.map(move |name| name.to_string_lossy().into_owned())
+		.collect();
+	let candidates = config.aliases.keys().map(String::as_str)
+		.chain([Selector::INSTALLED])
+		.chain(installed.iter().map(String::as_str));
+	Suggestions::for_alias(alias, candidates)
+}
+
 /// Error that occurred in [`find_toolchain`].
 #[derive(Debug, thiserror::Error)]
 pub enum FindToolchainError {
@@ -178,71 +476,208 @@ pub enum FindToolchainError {
 	},
 	#[error("{0}")]
 	Config(#[from] ConfigError),
-	#[error("alias {0:?} has no default version set")]
-	NoAliasDefault(String),
+	#[error("alias {0:?} has no default version set{1}")]
+	NoAliasDefault(String, Suggestions),
+	#[error("no compiler was found in toolchain directory {0:?}")]
+	NoCompiler(PathBuf),
+	#[error("no toolchain is installed")]
+	NoneInstalled,
 }
 
-/// Return `true` if a toolchain of `version` is installed.
-pub fn is_installed(version: &OsStr) -> bool {
-	ToolchainHomes::new().any(move |home| home.join(version).exists())
+/// Return `true` if a toolchain of `version` is installed with all of `components` (or all components, if empty).
+/// 
+/// Toolchain directories that are missing any of them (for e.g. because of a failed extraction) are not considered
+/// installed.
+pub fn is_installed(version: &OsStr, components: &[Component], compilers: &CompilerNames) -> bool {
+	find_installed(version, components, compilers).is_some()
+}
+
+/// Find the location of a toolchain of `version` that is installed with all of `components` (or all components, if
+/// empty).
+/// 
+/// See [`is_installed`] for which toolchains are considered installed.
+pub fn find_installed(version: &OsStr, components: &[Component], compilers: &CompilerNames) -> Option<PathBuf> {
+	ToolchainHomes::new()
+		.map(move |home| home.join(version))
+		.find(move |path| has_components(path, components, compilers))
 }
 
 /// Find the location of an installed toolchain of the specified `version`.
-pub fn  find_toolchain_path(version: &OsStr) -> Option<PathBuf> {
+/// 
+/// See [`is_installed`] for which toolchains are considered installed.
+#[inline]
+pub fn find_toolchain_path(version: &OsStr, compilers: &CompilerNames) -> Option<PathBuf> {
+	find_toolchain_path_traced(version, compilers, &mut |_| {})
+}
+
+/// Find the location of an installed toolchain of the specified `version`, reporting every home searched to `trace`.
+fn find_toolchain_path_traced(
+	version: &OsStr, compilers: &CompilerNames, trace: &mut dyn FnMut(FindStep<'_>),
+) -> Option<PathBuf> {
 	ToolchainHomes::new().find_map(move |home| {
 		let path = home.join(version);
-		path.exists().then_some(path)
+		let found = has_compiler(&path, compilers);
+		trace(FindStep::Home { home: &home, path: &path, found });
+		found.then_some(path)
+	})
+}
+
+/// Return `true` if the toolchain located at `toolchain_path` was installed with all of `components` (or all components,
+/// if empty), and still has them.
+pub fn has_components(toolchain_path: &Path, components: &[Component], compilers: &CompilerNames) -> bool {
+	let installed = match Receipt::read(toolchain_path) {
+		Ok(Some(receipt)) => receipt.components,
+		_ => Vec::new(),
+	};
+	Component::or_all(components).iter().all(move |c| {
+		Component::or_all(&installed).contains(c) && match c {
+			Component::Compiler => has_compiler(toolchain_path, compilers),
+			Component::Includes => toolchain_path.join("include").is_dir(),
+		}
 	})
 }
 
+/// Return `true` if the toolchain located at `toolchain_path` contains an executable compiler.
+/// 
+/// The compiler is looked up as recorded in the toolchain's [`Receipt`], if any (see [`Receipt::compiler_names`]), and
+/// then by `compilers` (usually from [`CompilerNames::new`]), which toolchains without a receipt are only checked for.
+pub fn has_compiler(toolchain_path: &Path, compilers: &CompilerNames) -> bool {
+	let recorded = match Receipt::read(toolchain_path) {
+		Ok(Some(receipt)) => receipt.compiler_names().0,
+		_ => Vec::new(),
+	};
+	recorded.iter().chain(compilers.0.iter()).any(move |n| is_executable(&toolchain_path.join(n)))
+}
+
+/// Return `true` if `path` is a file that can be executed.
+#[cfg(unix)]
+pub fn is_executable(path: &Path) -> bool {
+	use std::os::unix::fs::PermissionsExt;
+	path.metadata().is_ok_and(move |m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+/// Return `true` if `path` is a file that can be executed.
+#[cfg(not(unix))]
+pub fn is_executable(path: &Path) -> bool {
+	path.is_file()
+}
+
 /// Find the location of an installed toolchain of the specified `super_version` (e.g. `1.12`).
-pub fn find_latest_toolchain_of(super_version: &str) -> Option<(String, PathBuf)> {
-	ToolchainVersions::new()
+/// 
+/// `super_version` may also be any [`VersionReq`], in which case the latest installed version matching it is found.
+pub fn find_latest_toolchain_of(super_version: &str, compilers: &CompilerNames) -> Option<(String, PathBuf)> {
+	let req = super_version.parse::<VersionReq>().ok()?;
+	find_latest_toolchain_matching(&req, compilers)
+}
+
+/// Find the location of the latest installed toolchain that matches `req`.
+pub fn find_latest_toolchain_matching(req: &VersionReq, compilers: &CompilerNames) -> Option<(String, PathBuf)> {
+	find_latest_toolchain_matching_in(ToolchainVersions::new(), req, compilers)
+}
+
+/// Find the location of the latest toolchain that matches `req` in any of the homes of `versions`.
+/// 
+/// If the latest version is installed in multiple homes, the home that comes first is returned.
+pub fn find_latest_toolchain_matching_in(
+	versions: ToolchainVersions, req: &VersionReq, compilers: &CompilerNames,
+) -> Option<(String, PathBuf)> {
+	find_latest_toolchain_in(versions, move |name| req.matches(name), compilers)
+}
+
+/// Find the location of the latest installed toolchain that is named after a version (like `1.12.0.7200`, but not a
+/// custom toolchain like `dev`), for [`Selector::INSTALLED`].
+pub fn find_latest_installed(compilers: &CompilerNames) -> Option<(String, PathBuf)> {
+	let is_version = move |name: &str| name.starts_with(|c: char| c.is_ascii_digit());
+	find_latest_toolchain_in(ToolchainVersions::new(), is_version, compilers)
+}
+
+fn find_latest_toolchain_in(
+	versions: ToolchainVersions, filter: impl Fn(&str) -> bool, compilers: &CompilerNames,
+) -> Option<(String, PathBuf)> {
+	versions
 		.flat_map(move |(home, result)| result.map(move |names| (home, names)))
-		.find_map(move |(home, names)| {
+		.flat_map(move |(home, names)| {
 			names.flatten()
-				.map(move |name| name.to_string_lossy().into_owned())
-				.filter(move |name| name.as_str().is_sub_version_of(super_version))
-				.max_by(version_ord)
-				.map(move |name| (name, home))
+				.map(move |name| (name.to_string_lossy().into_owned(), home.clone()))
+		})
+		.filter(move |(name, home)| filter(name) && has_compiler(&home.join(name), compilers))
+		.reduce(move |latest, candidate| {
+			if version_ord(&candidate.0, &latest.0).is_gt() {
+				candidate
+			} else {
+				latest
+			}
 		})
 }
 
 /// Iterator over installed toolchain locations and iterators over toolchains installed in those locations.
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Default, Debug, Clone)]
 pub struct ToolchainVersions {
 	homes: ToolchainHomes,
 }
 
 impl ToolchainVersions {
-	pub const fn new() -> Self {
+	pub fn new() -> Self {
 		Self {
 			homes: ToolchainHomes::new(),
 		}
 	}
+
+	/// Create an iterator over toolchains installed in the specified `homes`.
+	pub fn with_homes(homes: ToolchainHomes) -> Self {
+		Self {
+			homes,
+		}
+	}
 }
 
 impl Iterator for ToolchainVersions {
 	type Item = (PathBuf, IoResult<DirNames>);
 	fn next(&mut self) -> Option<Self::Item> {
 		let home = self.homes.next()?;
-		let dirs = read_dir(&home).map(DirNames);
+		let dirs = read_dir(&home).map(move |entries| DirNames::new(entries).follow_symlinks(true));
 		Some((home, dirs))
 	}
 }
 
 /// Iterator over directories located inside of another directory.
+/// 
+/// Hidden directories (with names starting with `.`) are skipped, since Rookup uses them for in-progress installs.
 #[derive(Debug)]
-#[repr(transparent)]
-pub struct DirNames(pub ReadDir);
+pub struct DirNames {
+	entries: ReadDir,
+	follow_symlinks: bool,
+}
+
+impl DirNames {
+	/// Create an iterator over the directories in `entries`, skipping symbolic links.
+	pub const fn new(entries: ReadDir) -> Self {
+		Self {
+			entries,
+			follow_symlinks: false,
+		}
+	}
+
+	/// Set whether symbolic links to directories are yielded too.
+	/// 
+	/// Links that are broken, form a cycle, or point back to the directory being read (or one of its ancestors) are
+	/// skipped.
+	pub const fn follow_symlinks(mut self, follow: bool) -> Self {
+		self.follow_symlinks = follow;
+		self
+	}
+}
+
 impl Iterator for DirNames {
 	type Item = IoResult<OsString>;
 	fn next(&mut self) -> Option<Self::Item> {
 		loop {
-			match self.0.next() {
+			match self.entries.next() {
 				Some(Ok(entry)) => {
+					if entry.file_name().as_encoded_bytes().starts_with(b".") {
+						continue
+					}
 					let file_type = res_unwrap_or_return!(entry.file_type());
-					if file_type.is_dir() {
+					if file_type.is_dir() || (self.follow_symlinks && file_type.is_symlink() && is_dir_link(&entry.path())) {
 						break Some(Ok(entry.file_name()))
 					}
 				}
@@ -253,35 +688,139 @@ impl Iterator for DirNames {
 	}
 }
 
+/// Return `true` if the symbolic link at `path` resolves to a directory that doesn't contain the link itself.
+fn is_dir_link(path: &Path) -> bool {
+	// Fails for broken links and cycles of links.
+	let Ok(target) = canonicalize(path) else {
+		return false
+	};
+	let parent = path.parent().and_then(move |p| canonicalize(p).ok());
+	target.is_dir() && parent.is_some_and(move |parent| !parent.starts_with(&target))
+}
+
 /// Iterator over possible locations for installed toolchains.
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum ToolchainHomes {
-	#[default]
-	Custom,
-	Cached,
-	Done,
+/// 
+/// Custom toolchain directories are yielded first, followed by the toolchain directories.
+#[derive(Default, Debug, Clone)]
+pub struct ToolchainHomes {
+	homes: std::vec::IntoIter<PathBuf>,
 }
 
 impl ToolchainHomes {
-	#[inline]
-	pub const fn new() -> Self {
-		Self::Custom
+	pub fn new() -> Self {
+		let mut homes = custom_toolchain_homes();
+		homes.extend(toolchain_homes());
+		Self {
+			homes: homes.into_iter(),
+		}
+	}
+
+	/// Create an iterator over the specified `homes`, in order.
+	pub fn from_homes(homes: Vec<PathBuf>) -> Self {
+		Self {
+			homes: homes.into_iter(),
+		}
 	}
 }
 
 impl Iterator for ToolchainHomes {
 	type Item = PathBuf;
+	#[inline]
 	fn next(&mut self) -> Option<Self::Item> {
-		match self {
-			Self::Custom => {
-				*self = Self::Cached;
-				custom_toolchain_home()
-			}
-			Self::Cached => {
-				*self = Self::Done;
-				toolchain_home()
-			}
-			Self::Done => None,
-		}
+		self.homes.next()
+	}
+}
+
+#[test]
+fn latest_toolchain_is_found_across_homes() {
+	use std::fs::create_dir_all;
+
+	let root = crate::TempDir::new("rookup-find-latest").unwrap();
+	let make_toolchain = |path: PathBuf| {
+		create_dir_all(&path).unwrap();
+		let mut options = std::fs::File::options();
+		#[cfg(unix)]
+		std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o755);
+		options.create(true).truncate(true).write(true).open(path.join(crate::SPCOMP_EXE)).unwrap();
+	};
+	let custom = root.join("custom");
+	let cached = root.join("cached");
+	for path in [
+		custom.join("1.12.0.7100"), custom.join("1.11.0.6970"),
+		cached.join("1.12.0.7200"), cached.join("1.13.0.7300"), cached.join("1.11.0.6970"),
+	] {
+		make_toolchain(path);
 	}
+	// Failed install without a compiler.
+	create_dir_all(cached.join("1.14.0.7400")).unwrap();
+
+	let find = |req: &str| {
+		let homes = ToolchainHomes::from_homes(vec![custom.clone(), cached.clone(), root.join("missing")]);
+		let compilers = CompilerNames::defaults(std::env::consts::OS, std::env::consts::ARCH);
+		find_latest_toolchain_matching_in(ToolchainVersions::with_homes(homes), &req.parse().unwrap(), &compilers)
+	};
+	assert_eq!(find("1.12"), Some(("1.12.0.7200".into(), cached.clone())));
+	assert_eq!(find("*"), Some(("1.13.0.7300".into(), cached.clone())));
+	assert_eq!(find("1.11"), Some(("1.11.0.6970".into(), custom.clone())));
+	assert_eq!(find("<1.12.0.7200"), Some(("1.12.0.7100".into(), custom.clone())));
+	assert_eq!(find("1.14"), None);
+}
+
+#[test]
+fn configured_compiler_names_are_found() {
+	let root = crate::TempDir::new("rookup-compiler-names").unwrap();
+	let mut options = std::fs::File::options();
+	#[cfg(unix)]
+	std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o755);
+	options.create(true).truncate(true).write(true).open(root.join("mycomp")).unwrap();
+
+	let defaults = CompilerNames::defaults(std::env::consts::OS, std::env::consts::ARCH);
+	assert!(!has_compiler(root.path(), &defaults));
+	assert!(has_compiler(root.path(), &CompilerNames(vec!["mycomp".into()])));
+
+	let mut data = ConfigData::default();
+	let selector = format!("path:{}", root.path().display());
+	assert!(matches!(find_toolchain(&data, Selector::parse(&selector)), Err(FindToolchainError::NoCompiler(..))));
+	let target = format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS);
+	data.compilers.insert(target, vec!["mycomp".into()]);
+	assert_eq!(find_toolchain(&data, Selector::parse(&selector)).unwrap().into_path(), root.path());
+}
+
+#[test]
+fn path_selectors_are_parsed() {
+	assert_eq!(Selector::parse(":1.12"), Selector::Super("1.12"));
+	assert_eq!(Selector::parse("stable"), Selector::Alias("stable"));
+	assert_eq!(Selector::parse("./spcomp"), Selector::Path("./spcomp"));
+	assert_eq!(Selector::parse("path:spcomp"), Selector::Path("spcomp"));
+	assert_eq!(Selector::Path("spcomp").to_string(), "path:spcomp");
+	assert_eq!(Selector::Path("./spcomp").to_string(), "./spcomp");
+}
+
+#[test]
+fn owned_selectors_round_trip() {
+	for s in [":1.12", "stable", "./spcomp", "path:spcomp", "/opt/spcomp", ":"] {
+		let selector: SelectorBuf = s.parse().unwrap();
+		assert_eq!(selector.as_selector(), Selector::parse(s));
+		assert_eq!(selector.to_string(), s);
+		assert_eq!(SelectorBuf::from(Selector::from(&selector)), selector);
+	}
+}
+
+#[test]
+fn selectors_are_deserialized() {
+	use serde::{
+		de::value::{
+			BorrowedStrDeserializer, Error as ValueError, StrDeserializer,
+		},
+		Deserialize,
+	};
+
+	let borrowed = BorrowedStrDeserializer::<ValueError>::new(":1.12");
+	assert_eq!(Selector::deserialize(borrowed).unwrap(), Selector::Super("1.12"));
+	// Strings that were unescaped, for example, only live as long as the deserializer needs them.
+	let unescaped = String::from("path:C:\\spcomp");
+	let error = Selector::deserialize(StrDeserializer::<ValueError>::new(&unescaped)).unwrap_err();
+	assert!(error.to_string().contains("SelectorBuf"));
+	let selector = SelectorBuf::deserialize(StrDeserializer::<ValueError>::new(&unescaped)).unwrap();
+	assert_eq!(selector, SelectorBuf::Path("C:\\spcomp".into()));
 }
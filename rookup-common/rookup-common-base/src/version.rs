@@ -2,8 +2,11 @@
 
 use core::{
 	cmp::Ordering,
+	fmt,
 	hash::Hash,
-	str::Split,
+	str::{
+		FromStr, Split,
+	},
 };
 
 /// Trait for objects that can be treated as SemVer version strings with parts that can be iterated on.
@@ -19,35 +22,59 @@ pub trait Version {
 	type Iter<'a>: Iterator<Item = Self::Part<'a>> where Self: 'a;
 
 	/// Return an iterator over parts of a version.
+	/// 
+	/// This does not include the pre-release suffix, which is returned by [`pre_release`](Version::pre_release)
+	/// instead.
 	fn iter_parts(&self) -> Self::Iter<'_>;
 
+	/// Return the pre-release suffix of this version (for e.g. `rc1` in `1.12.0-rc1`), if there is one.
+	#[inline]
+	fn pre_release(&self) -> Option<PreRelease<'_>> {
+		None
+	}
+
 	/// Return the relation of this version to `other`.
 	fn relation_to(&self, other: &Self) -> Relation {
 		let mut self_parts = self.iter_parts();
 		let mut other_parts = other.iter_parts();
-		loop {
+		let relation = loop {
 			match (self_parts.next(), other_parts.next()) {
 				(None, None) => break Relation::Equal,
 				(Some(..), None) => break Relation::IsSubVersionOf,
 				(None, Some(..)) => break Relation::IsSuperVersionOf,
 				(Some(s), Some(o)) => {
 					if s != o {
-						break Relation::Different
+						return Relation::Different
 					}
 				}
 			}
+		};
+
+		match (relation, self.pre_release(), other.pre_release()) {
+			(relation, None, None) => relation,
+			(Relation::Equal, Some(s), Some(o)) if s == o => Relation::Equal,
+			(Relation::Equal, Some(..), None) => Relation::IsPreReleaseOf,
+			(Relation::Equal, None, Some(..)) => Relation::IsReleaseOf,
+			// A pre-release of a more specific version is still part of a less specific version.
+			(Relation::IsSubVersionOf, Some(..), None) => Relation::IsSubVersionOf,
+			(Relation::IsSuperVersionOf, None, Some(..)) => Relation::IsSuperVersionOf,
+			_ => Relation::Different,
 		}
 	}
 
 	/// Return `true` if this version is a sub-version of `other`.
+	/// 
+	/// Pre-releases are considered to be sub-versions of their release (for e.g. `1.12.0-rc1` is a sub-version of
+	/// `1.12.0`).
 	#[inline]
 	fn is_sub_version_of(&self, other: &Self) -> bool {
-		matches!(self.relation_to(other), Relation::Equal | Relation::IsSubVersionOf)
+		matches!(self.relation_to(other), Relation::Equal | Relation::IsSubVersionOf | Relation::IsPreReleaseOf)
 	}
 }
 
 /// Enumeration of kinds of relationships one version has to another.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum Relation {
 	/// All version parts are equal to ones of the other version (for e.g. `1.12.0.7192` vs `1.12.0.7192`).
 	Equal,
@@ -57,15 +84,89 @@ pub enum Relation {
 	IsSubVersionOf,
 	/// The other version is a sub-version of this version (for e.g. `1.12` vs `1.12.0.7192`).
 	IsSuperVersionOf,
+	/// This version is a pre-release of the other version (for e.g. `1.12.0-rc1` vs `1.12.0`).
+	IsPreReleaseOf,
+	/// The other version is a pre-release of this version (for e.g. `1.12.0` vs `1.12.0-rc1`).
+	IsReleaseOf,
 }
 
 /// Standard [`Ord`] implementation for [`Version`]s.
+/// 
+/// Pre-releases are ordered below the release they precede.
 pub fn version_ord<V: Version + ?Sized>(a: &V, b: &V) -> Ordering {
 	let mut ord = Ordering::Equal;
 	for (a, b) in a.iter_parts().zip(b.iter_parts()) {
 		ord = ord.then(a.cmp(&b));
 	}
-	ord
+	ord.then_with(move || match (a.pre_release(), b.pre_release()) {
+		(None, None) => Ordering::Equal,
+		(Some(..), None) => Ordering::Less,
+		(None, Some(..)) => Ordering::Greater,
+		(Some(a), Some(b)) => a.cmp(&b),
+	})
+}
+
+/// Return the version string without its pre-release suffix and build metadata.
+#[inline]
+fn core_of(s: &str) -> &str {
+	s.split(['-', '+']).next().unwrap_or(s)
+}
+
+/// Pre-release suffix of a version (for e.g. `rc1` in `1.12.0-rc1`).
+/// 
+/// Pre-releases are ordered by their alphabetic parts lexicographically and by their numeric parts numerically, so
+/// `alpha` < `beta` < `rc1` < `rc2` < `rc10`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct PreRelease<'a>(pub &'a str);
+impl<'a> PreRelease<'a> {
+	/// Return the pre-release suffix of the version string `s`, ignoring build metadata.
+	pub fn of(s: &'a str) -> Option<Self> {
+		let (_, suffix) = s.split_once('-')?;
+		let suffix = suffix.split_once('+').map(move |(p, ..)| p).unwrap_or(suffix);
+		(!suffix.is_empty()).then_some(Self(suffix))
+	}
+
+	/// Return an iterator over alternating alphabetic and numeric chunks of this pre-release.
+	fn chunks(self) -> impl Iterator<Item = &'a str> {
+		let mut rest = self.0;
+		core::iter::from_fn(move || {
+			let first = rest.chars().next()?;
+			let is_digit = first.is_ascii_digit();
+			let end = rest.find(move |c: char| c.is_ascii_digit() != is_digit).unwrap_or(rest.len());
+			let (chunk, tail) = rest.split_at(end);
+			rest = tail;
+			Some(chunk)
+		})
+	}
+}
+impl Ord for PreRelease<'_> {
+	fn cmp(&self, other: &Self) -> Ordering {
+		let mut a = self.chunks();
+		let mut b = other.chunks();
+		loop {
+			match (a.next(), b.next()) {
+				(None, None) => break Ordering::Equal,
+				(None, Some(..)) => break Ordering::Less,
+				(Some(..), None) => break Ordering::Greater,
+				(Some(a), Some(b)) => {
+					let ord = if a.starts_with(|c: char| c.is_ascii_digit()) {
+						Part(a.trim_start_matches('0')).cmp(&Part(b.trim_start_matches('0')))
+					} else {
+						a.cmp(b)
+					};
+					if ord.is_ne() {
+						break ord
+					}
+				}
+			}
+		}
+	}
+}
+impl PartialOrd for PreRelease<'_> {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
 }
 
 /// Helper trait for getting the length of a version part.
@@ -127,7 +228,11 @@ impl Version for str {
 	type Iter<'a> = VersionStrSplit<'a> where Self: 'a;
 	#[inline]
 	fn iter_parts(&self) -> Self::Iter<'_> {
-		VersionStrSplit(self.split('.'))
+		VersionStrSplit(core_of(self).split('.'))
+	}
+	#[inline]
+	fn pre_release(&self) -> Option<PreRelease<'_>> {
+		PreRelease::of(self)
 	}
 }
 
@@ -136,7 +241,11 @@ impl Version for String {
 	type Iter<'a> = VersionStrSplit<'a>;
 	#[inline]
 	fn iter_parts(&self) -> Self::Iter<'_> {
-		VersionStrSplit(self.split('.'))
+		self.as_str().iter_parts()
+	}
+	#[inline]
+	fn pre_release(&self) -> Option<PreRelease<'_>> {
+		self.as_str().pre_release()
 	}
 }
 
@@ -151,3 +260,263 @@ impl<'a> Iterator for VersionStrSplit<'a> {
 		self.0.next().map(Part)
 	}
 }
+
+/// Requirement that versions can be checked against, made up of comma-separated comparators (for e.g. `^1.12`,
+/// `>=1.11, <1.13`, or `=1.12.0.7200`).
+/// 
+/// A version without an operator (or with `=`) matches all of its sub-versions, so `1.12` matches `1.12.0.7200`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VersionReq {
+	pub comparators: Vec<Comparator>,
+}
+
+/// Single comparator in a [`VersionReq`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Comparator {
+	pub op: Op,
+	pub version: String,
+}
+
+/// Operator of a [`Comparator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Op {
+	/// `=1.12` or `1.12`: matches the version and all of its sub-versions.
+	Exact,
+	/// `>1.12`: matches versions greater than the version and all of its sub-versions.
+	Greater,
+	/// `>=1.12`: matches versions greater than the version, or its sub-versions.
+	GreaterEq,
+	/// `<1.12`: matches versions less than the version and all of its sub-versions.
+	Less,
+	/// `<=1.12`: matches versions less than the version, or its sub-versions.
+	LessEq,
+	/// `^1.12`: matches versions at least as new as the version that don't change the first non-zero part.
+	Caret,
+	/// `~1.12.0`: matches versions at least as new as the version that don't change the first two parts.
+	Tilde,
+	/// `*`: matches any version.
+	Wildcard,
+}
+
+impl Op {
+	/// Return the textual representation of this operator.
+	pub const fn as_str(self) -> &'static str {
+		match self {
+			Self::Exact => "=",
+			Self::Greater => ">",
+			Self::GreaterEq => ">=",
+			Self::Less => "<",
+			Self::LessEq => "<=",
+			Self::Caret => "^",
+			Self::Tilde => "~",
+			Self::Wildcard => "*",
+		}
+	}
+}
+
+impl Comparator {
+	/// Return `true` if `version` satisfies this comparator.
+	pub fn matches(&self, version: &str) -> bool {
+		let wanted = self.version.as_str();
+		match self.op {
+			Op::Exact => version.is_sub_version_of(wanted),
+			Op::Greater => version_ord(version, wanted).is_gt(),
+			Op::GreaterEq => version_ord(version, wanted).is_ge(),
+			Op::Less => version_ord(version, wanted).is_lt(),
+			Op::LessEq => version_ord(version, wanted).is_le(),
+			Op::Caret => {
+				let n_fixed = wanted.iter_parts().position(move |p| !p.0.trim_start_matches('0').is_empty())
+					.map(move |i| i + 1)
+					.unwrap_or(usize::MAX);
+				version_ord(version, wanted).is_ge() && same_prefix(version, wanted, n_fixed)
+			}
+			Op::Tilde => version_ord(version, wanted).is_ge() && same_prefix(version, wanted, 2),
+			Op::Wildcard => true,
+		}
+	}
+
+	/// Return `true` if a version that is a sub-version of `prefix` (like a branch) may satisfy this comparator.
+	pub fn may_match_within(&self, prefix: &str) -> bool {
+		let wanted = self.version.as_str();
+		match self.op {
+			Op::Exact => wanted.is_sub_version_of(prefix) || prefix.is_sub_version_of(wanted),
+			// Sub-versions of `prefix` range from below `wanted` to above it if `wanted` is more specific than
+			// `prefix`. Otherwise, they all compare to `wanted` in the same way as `prefix` does.
+			Op::Greater | Op::GreaterEq | Op::Less | Op::LessEq => {
+				wanted.relation_to(prefix) == Relation::IsSubVersionOf || self.matches(prefix)
+			}
+			_ => self.matches(prefix),
+		}
+	}
+}
+
+/// Return `true` if the first `n` parts of `a` and `b` are equal.
+fn same_prefix(a: &str, b: &str, n: usize) -> bool {
+	a.iter_parts().zip(b.iter_parts()).take(n).all(move |(a, b)| a == b)
+}
+
+impl VersionReq {
+	/// Return `true` if `version` satisfies all comparators of this requirement.
+	pub fn matches<V: ?Sized + Version + AsRef<str>>(&self, version: &V) -> bool {
+		let version = version.as_ref();
+		self.comparators.iter().all(move |c| c.matches(version))
+	}
+
+	/// Return `true` if a version that is a sub-version of `prefix` (like a branch) may satisfy this requirement.
+	pub fn may_match_within(&self, prefix: &str) -> bool {
+		self.comparators.iter().all(move |c| c.may_match_within(prefix))
+	}
+}
+
+impl FromStr for Comparator {
+	type Err = VersionReqError;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = s.trim();
+		if s == "*" {
+			return Ok(Self {
+				op: Op::Wildcard,
+				version: String::new(),
+			})
+		}
+
+		const OPS: [Op; 7] = [Op::GreaterEq, Op::LessEq, Op::Greater, Op::Less, Op::Exact, Op::Caret, Op::Tilde];
+		let (op, version) = OPS.iter()
+			.find_map(move |op| s.strip_prefix(op.as_str()).map(move |v| (*op, v)))
+			.unwrap_or((Op::Exact, s));
+
+		let version = version.trim();
+		if version.is_empty() {
+			return Err(VersionReqError::MissingVersion(s.into()))
+		}
+		if !version.chars().all(move |c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '+')) {
+			return Err(VersionReqError::InvalidVersion(version.into()))
+		}
+		Ok(Self {
+			op,
+			version: version.into(),
+		})
+	}
+}
+
+impl FromStr for VersionReq {
+	type Err = VersionReqError;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if s.trim().is_empty() {
+			return Err(VersionReqError::Empty)
+		}
+		let comparators: Result<Vec<_>, _> = s.split(',').map(Comparator::from_str).collect();
+		Ok(Self {
+			comparators: comparators?,
+		})
+	}
+}
+
+impl fmt::Display for Comparator {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.op {
+			Op::Wildcard => f.write_str("*"),
+			op => write!(f, "{}{}", op.as_str(), self.version),
+		}
+	}
+}
+
+impl fmt::Display for VersionReq {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut comparators = self.comparators.iter();
+		if let Some(first) = comparators.next() {
+			fmt::Display::fmt(first, f)?;
+		}
+		for comparator in comparators {
+			write!(f, ", {comparator}")?;
+		}
+		Ok(())
+	}
+}
+
+/// Error that occurred while parsing a [`VersionReq`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum VersionReqError {
+	#[error("version requirement is empty")]
+	Empty,
+	#[error("comparator {0:?} is missing a version")]
+	MissingVersion(String),
+	#[error("{0:?} is not a valid version")]
+	InvalidVersion(String),
+}
+
+#[test]
+fn pre_releases_work() {
+	use Relation::*;
+
+	assert!(version_ord("1.12.0-rc1", "1.12.0").is_lt());
+	assert!(version_ord("1.12.0-rc1", "1.12.0-rc2").is_lt());
+	assert!(version_ord("1.12.0-rc2", "1.12.0-rc10").is_lt());
+	assert!(version_ord("1.12.0-beta", "1.12.0-rc1").is_lt());
+	assert!(version_ord("1.12.0-alpha", "1.12.0-alpha1").is_lt());
+	assert!(version_ord("1.12.0+build5", "1.12.0").is_eq());
+	assert!(version_ord("1.11.0", "1.12.0-rc1").is_lt());
+
+	assert_eq!("1.12.0-rc1".relation_to("1.12.0"), IsPreReleaseOf);
+	assert_eq!("1.12.0".relation_to("1.12.0-rc1"), IsReleaseOf);
+	assert_eq!("1.12.0-rc1".relation_to("1.12.0-rc1"), Equal);
+	assert_eq!("1.12.0-rc1".relation_to("1.12.0-rc2"), Different);
+	assert_eq!("1.12.0-rc1".relation_to("1.12"), IsSubVersionOf);
+	assert_eq!("1.12".relation_to("1.12.0-rc1"), IsSuperVersionOf);
+
+	assert!("1.12.0-rc1".is_sub_version_of("1.12"));
+	assert!("1.12.0-rc1".is_sub_version_of("1.12.0"));
+	assert!(!"1.12.0".is_sub_version_of("1.12.0-rc1"));
+}
+
+#[test]
+fn version_reqs_work() {
+	fn req(s: &str) -> VersionReq {
+		s.parse().unwrap()
+	}
+
+	assert!(req("1.12").matches("1.12.0.7200"));
+	assert!(req("=1.12.0.7200").matches("1.12.0.7200"));
+	assert!(!req("=1.12.0.7200").matches("1.12.0.7201"));
+	assert!(req(">=1.11, <1.13").matches("1.11.0.6970"));
+	assert!(req(">=1.11, <1.13").matches("1.12.0.7200"));
+	assert!(!req(">=1.11, <1.13").matches("1.13.0.7300"));
+	assert!(!req(">1.12").matches("1.12.0.7200"));
+	assert!(req("<=1.12").matches("1.12.0.7200"));
+	assert!(req("^1.12").matches("1.13.0.7300"));
+	assert!(!req("^1.12").matches("2.0.0.1"));
+	assert!(!req("^0.5").matches("0.6.0"));
+	assert!(req("~1.12.0").matches("1.12.0.7200"));
+	assert!(!req("~1.12.0").matches("1.13.0.7300"));
+	assert!(req("*").matches("1.13.0.7300"));
+
+	assert!(req("=1.12.0.7200").may_match_within("1.12"));
+	assert!(!req(">=1.13").may_match_within("1.12"));
+
+	assert_eq!(req(">=1.11,<1.13").to_string(), ">=1.11, <1.13");
+	assert_eq!("".parse::<VersionReq>(), Err(VersionReqError::Empty));
+	assert!(">=".parse::<VersionReq>().is_err());
+}
+
+#[test]
+fn ordering_reqs_select_branches() {
+	fn may_match(req: &str, prefix: &str) -> bool {
+		req.parse::<VersionReq>().unwrap().may_match_within(prefix)
+	}
+
+	assert!(may_match(">1.12.0.7200", "1.12"));
+	assert!(!may_match(">1.12.0.7200", "1.11"));
+	assert!(may_match(">1.11", "1.12"));
+	assert!(!may_match(">1.12", "1.12"));
+	assert!(may_match(">=1.12.0.7000", "1.12"));
+	assert!(may_match(">=1.12", "1.12"));
+	assert!(!may_match(">=1.12.0.7000", "1.11"));
+	assert!(may_match("<1.12.0.7000", "1.12"));
+	assert!(may_match("<1.12.0.7000", "1.11"));
+	assert!(!may_match("<1.12.0.7000", "1.13"));
+	assert!(!may_match("<1.12", "1.12"));
+	assert!(may_match("<=1.12.0.7000", "1.12"));
+	assert!(may_match("<=1.12", "1.12"));
+	assert!(!may_match("<=1.11.0.6970", "1.12"));
+	assert!(may_match(">=1.11, <1.13", "1.12"));
+	assert!(!may_match(">=1.11, <1.12", "1.12"));
+}
@@ -12,28 +12,10 @@ use crate::{
 	anyhow::Result as AResult,
 };
 
-#[derive(Debug)]
-struct DocContext {
-	pub uses_item_docs: bool,
-}
-
 /// Create a documented `config.toml` with default values.
 pub fn create_default_config() -> AResult<DocumentMut> {
-	let mut doc_map = ItemDocMap::new();
-
 	let config = ConfigData::default();
-	doc_map.register_with(
-		&config.source,
-		DocContext {
-			uses_item_docs: true,
-		},
-	);
-	doc_map.register_with(
-		&config,
-		DocContext {
-			uses_item_docs: false,
-		},
-	);
+	let doc_map = ItemDocMap::for_config(&config);
 
 	let mut config_toml = to_document(&config)?;
 	{
@@ -46,10 +28,7 @@ pub fn create_default_config() -> AResult<DocumentMut> {
 			while let Some((mut key, item)) = iter.next() {
 				if let Some(field) = table_doc.field_kebab_case(key.get()) {
 					let field_doc = doc_map.get_by_id(field.type_id);
-					let doc_string = match field_doc {
-						Some(field_doc) if field_doc.extra.uses_item_docs => field_doc.item_docs,
-						_ => field.docs,
-					};
+					let doc_string = doc_map.docs_of(field);
 
 					let doc = to_toml_doc(doc_string, had_empty_table);
 					had_empty_table = false;
@@ -1,4 +1,4 @@
-pub mod doc_map;
+pub use rookup_common_base::doc_map;
 mod default_config;
 pub use default_config::*;
 
@@ -1,23 +1,32 @@
 use std::{
 	env::{
-		var, VarError,
+		join_paths, split_paths, var, var_os, JoinPathsError, VarError,
 	},
+	ffi::OsString,
 	fs::{
 		File, create_dir_all,
 	},
 	io::{
 		Write, Seek, Result as IoResult,
 	},
-	path::Path,
+	path::{
+		Path, PathBuf,
+	},
 };
 
 pub use rookup_common_base::*;
 
-/// Return the name and source (as [`ToolchainSource`]) of the current toolchain.
-pub fn current_toolchain(data: &ConfigData) -> Result<(String, ToolchainSource), CurrentToolchainError> {
-	match var("ROOKUP_TOOLCHAIN") {
+mod proxy;
+pub use proxy::*;
+
+/// Name of the environment variable that overrides the current toolchain.
+pub const TOOLCHAIN_VAR: &str = "ROOKUP_TOOLCHAIN";
+
+/// Return the selector and source (as [`ToolchainSource`]) of the current toolchain.
+pub fn current_toolchain(data: &ConfigData) -> Result<(SelectorBuf, ToolchainSource), CurrentToolchainError> {
+	match var(TOOLCHAIN_VAR) {
 		Ok(toolchain) => {
-			return Ok((toolchain, ToolchainSource::Env))
+			return Ok((SelectorBuf::parse(&toolchain), ToolchainSource::Env))
 		}
 		Err(VarError::NotPresent) => {}
 		Err(VarError::NotUnicode(..)) => return Err(CurrentToolchainError::ToString),
@@ -31,14 +40,24 @@ pub trait ConfigExt: Sized {
 	fn open_default(with_write: bool) -> Result<Self, ConfigError>;
 
 	/// Open the configuration file at its default path, writing a file with default values if necessary.
+	/// 
+	/// If the configuration file doesn't exist, it's only created if `with_write` is `true` and creating it isn't
+	/// [disabled](config_creation_disabled). Otherwise, the built-in defaults are read without a file (see
+	/// [`Config::without_file`]), or, if `with_write` is `true`, this fails.
 	fn open_create(with_write: bool) -> Result<Self, ConfigError>;
 }
+
+/// Contents of the configuration file that is created when there is none.
+const DEFAULT_CONFIG: &str = include_str!(concat!(env!("OUT_DIR"), "/config.toml"));
 impl ConfigExt for Config {
 	fn open_default(with_write: bool) -> Result<Self, ConfigError> {
 		let Some(config_home) = config_home() else {
 			return Err(ConfigError::ConfigPath)
 		};
-		Self::open(config_file_path(config_home.clone()), with_write)
+		let mut config = Self::open(config_file_path(config_home.clone()), with_write)?;
+		config.journal = state_home().map(journal_path);
+		config.with_doc.set_layers(read_layers()?)?;
+		Ok(config)
 	}
 
 	fn open_create(with_write: bool) -> Result<Self, ConfigError> {
@@ -47,6 +66,19 @@ impl ConfigExt for Config {
 		};
 		
 		let config_path = config_file_path(config_home.clone());
+		let lock = if with_write { Some(Self::lock_file(&config_path)?) } else { None };
+		if !config_path.exists() && (!with_write || config_creation_disabled()) {
+			if with_write {
+				return Err(ConfigError::ConfigMissing { config_path })
+			}
+			let document = DEFAULT_CONFIG.parse().expect("built-in configuration should be valid TOML");
+			let mut config = Self::without_file(config_path, document)
+				.expect("built-in configuration should be valid");
+			config.journal = state_home().map(journal_path);
+			config.with_doc.set_layers(read_layers()?)?;
+			return Ok(config)
+		}
+
 		let file = if !config_path.exists() {
 			create_dir_all(&config_home)
 				.map_err(|error| ConfigError::ConfigCreateHome {
@@ -60,7 +92,7 @@ impl ConfigExt for Config {
 					.write(true)
 					.read(true)
 					.open(config_path)?;
-				file.write_all(include_bytes!(concat!(env!("OUT_DIR"), "/config.toml")))?;
+				file.write_all(DEFAULT_CONFIG.as_bytes())?;
 				file.flush()?;
 				file.rewind()?;
 				Ok(file)
@@ -81,12 +113,124 @@ impl ConfigExt for Config {
 				})?
 		};
 
-		Self::with_file(file, config_path)
+		let mut config = Self::with_file(file, config_path)?;
+		config.lock = lock;
+		config.journal = state_home().map(journal_path);
+		config.save_migration();
+		config.with_doc.set_layers(read_layers()?)?;
+		Ok(config)
 	}
 }
 
+/// Command that runs the compiler of a toolchain.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub struct CompilerCommand {
+	/// Program and arguments that the compiler should be run with, which are empty for native toolchains.
+	pub runner: Vec<String>,
+	/// Path to the compiler executable.
+	pub compiler: PathBuf,
+	/// Name of the most preferred compiler, if it is missing and [`compiler`](Self::compiler) is a fallback.
+	pub fallback_from: Option<String>,
+}
+
+impl CompilerCommand {
+	/// Return the command that runs the compiler of the toolchain at `toolchain_path`, taking its receipt and the
+	/// `runners` setting in `data` into account.
+	/// 
+	/// If the receipt doesn't record the name of the compiler, the names from the `compilers` setting are tried (see
+	/// [`CompilerNames::new`]).
+	/// The first of the names that exists in the toolchain is used.
+	pub fn new(data: &ConfigData, toolchain_path: PathBuf) -> Result<Self, ReceiptError> {
+		let receipt = Receipt::read(&toolchain_path)?;
+		let (names, runner) = match receipt {
+			Some(receipt @ Receipt { compiler: Some(..), .. }) => {
+				let os = receipt.target_os.as_deref().unwrap_or(std::env::consts::OS);
+				let runner = receipt.runner.clone().or_else(|| foreign_runner(data, os));
+				(receipt.compiler_names(), runner)
+			}
+			Some(Receipt { runner, target_os, .. }) => {
+				let os = target_os.as_deref().unwrap_or(std::env::consts::OS);
+				(CompilerNames::new(data, os), runner.or_else(|| foreign_runner(data, os)))
+			}
+			None => (CompilerNames::new(data, std::env::consts::OS), None),
+		};
+		let (compiler, fallback_from) = match names.find_in(&toolchain_path) {
+			Some(compiler) => {
+				let is_preferred = compiler.file_name().is_some_and(|n| *n == *names.preferred());
+				(compiler, (!is_preferred).then(|| names.preferred().to_string()))
+			}
+			None => (toolchain_path.join(names.preferred()), None),
+		};
+		Ok(Self {
+			runner: runner.unwrap_or_default(),
+			compiler,
+			fallback_from,
+		})
+	}
+
+	/// Create a [`Command`](std::process::Command) that runs the compiler.
+	pub fn to_command(&self) -> std::process::Command {
+		match self.runner.split_first() {
+			Some((program, args)) => {
+				let mut command = std::process::Command::new(program);
+				command.args(args).arg(&self.compiler);
+				command
+			}
+			None => std::process::Command::new(&self.compiler),
+		}
+	}
+}
+
+/// Name of the environment variable that is set to the directory of the toolchain in [`toolchain_env`].
+pub const TOOLCHAIN_DIR_VAR: &str = "ROOKUP_TOOLCHAIN_DIR";
+
+/// Name of the environment variable that is set to the include directories of the toolchain in [`toolchain_env`],
+/// separated the same way as in `PATH`.
+pub const INCLUDE_PATH_VAR: &str = "ROOKUP_INCLUDE_PATH";
+
+/// Return environment variables that make commands use the toolchain selected by `selector`, located at
+/// `toolchain_path`.
+/// 
+/// The toolchain directory is prepended to `PATH`, and `ROOKUP_TOOLCHAIN` is set to `selector` so that
+/// `rookup-spcomp` selects the same toolchain.
+pub fn toolchain_env(selector: Selector<'_>, toolchain_path: &Path) -> Result<Vec<(&'static str, OsString)>, JoinPathsError> {
+	let mut path = vec![toolchain_path.to_path_buf()];
+	if let Some(old_path) = var_os("PATH") {
+		path.extend(split_paths(&old_path));
+	}
+
+	Ok(vec![
+		(TOOLCHAIN_VAR, selector.to_string().into()),
+		(TOOLCHAIN_DIR_VAR, toolchain_path.into()),
+		(INCLUDE_PATH_VAR, join_paths([toolchain_path.join("include")])?),
+		("PATH", join_paths(path)?),
+	])
+}
+
+/// Return environment variables that point build systems at the compiler `spcomp` of the toolchain located at
+/// `toolchain_path`.
+/// 
+/// `SPCOMP` is set to `spcomp`, `SPCOMP_DIR` to the toolchain directory, and `SOURCEMOD_INCLUDE` to its `include`
+/// directory.
+pub fn build_env(spcomp: &Path, toolchain_path: &Path) -> Vec<(&'static str, OsString)> {
+	vec![
+		("SPCOMP", spcomp.into()),
+		("SPCOMP_DIR", toolchain_path.into()),
+		("SOURCEMOD_INCLUDE", toolchain_path.join("include").into()),
+	]
+}
+
+/// Return the runner configured in `data` for compilers built for `os`, if it isn't the host's.
+fn foreign_runner(data: &ConfigData, os: &str) -> Option<Vec<String>> {
+	(os != std::env::consts::OS).then(move || data.runners.get(os).cloned()).flatten()
+}
+
 /// Enumeration of sources that specify the current toolchain.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum ToolchainSource {
 	/// Current toolchain is specified by an environment variable.
 	Env,
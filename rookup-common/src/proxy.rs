@@ -0,0 +1,148 @@
+//! Errors of the `rookup-spcomp` proxy, each of which has a stable exit code.
+
+use std::{
+	fmt,
+	io::Error as IoError,
+	path::PathBuf,
+};
+
+use crate::{
+	ConfigError, CurrentToolchainError, FindToolchainError, ReceiptError, ToolchainSource,
+};
+
+/// Error that made the proxy fail before the compiler could finish.
+///
+/// See [`exit_code`](Self::exit_code) for the exit code that the proxy exits with for each of these.
+#[derive(Debug, thiserror::Error)]
+pub enum ProxyError {
+	#[error("{0}")]
+	Config(#[from] ConfigError),
+	#[error("failed to get current toolchain: {0}")]
+	CurrentToolchain(#[from] CurrentToolchainError),
+	/// Current toolchain couldn't be resolved for a reason other than it not being installed.
+	#[error("{0}")]
+	Resolve(FindToolchainError),
+	#[error("{0}")]
+	NotInstalled(NotInstalled),
+	#[error("failed to read toolchain receipt: {0}")]
+	Receipt(#[from] ReceiptError),
+	/// Compiler executable of the toolchain doesn't exist.
+	#[error("compiler {} doesn't exist", compiler.display())]
+	CompilerMissing {
+		compiler: PathBuf,
+	},
+	/// Compiler couldn't be started.
+	#[error("{}: {error}", compiler.display())]
+	Spawn {
+		error: IoError,
+		compiler: PathBuf,
+	},
+	#[error("failed to wait for compiler: {0}")]
+	Wait(IoError),
+}
+
+impl ProxyError {
+	/// Exit code for an invalid configuration, or a current toolchain that can't be determined.
+	pub const CONFIG_EXIT_CODE: u8 = 78;
+	/// Exit code for a selector that doesn't resolve to a toolchain.
+	pub const RESOLVE_EXIT_CODE: u8 = 64;
+	/// Exit code for a toolchain that isn't installed.
+	pub const NOT_INSTALLED_EXIT_CODE: u8 = 69;
+	/// Exit code for a toolchain receipt that can't be read.
+	pub const RECEIPT_EXIT_CODE: u8 = 74;
+	/// Exit code for a compiler that doesn't exist, as for commands that aren't found by shells.
+	pub const COMPILER_MISSING_EXIT_CODE: u8 = 127;
+	/// Exit code for a compiler that can't be run, as for commands that can't be executed by shells.
+	pub const SPAWN_EXIT_CODE: u8 = 126;
+	/// Exit code for a compiler that was started, but couldn't be waited for.
+	pub const WAIT_EXIT_CODE: u8 = 71;
+
+	/// Convert an error of resolving the toolchain selected by `source` to a [`ProxyError`].
+	pub fn from_find(error: FindToolchainError, source: ToolchainSource) -> Self {
+		let kind = match error {
+			FindToolchainError::LatestNotFound(version) => NotInstalledKind::LatestCompatibleWith { version },
+			FindToolchainError::NotFound { version, alias } => NotInstalledKind::Aliased { version, alias },
+			FindToolchainError::NoneInstalled => NotInstalledKind::AnyInstalled,
+			FindToolchainError::Config(error) => return Self::Config(error),
+			error => return Self::Resolve(error),
+		};
+		Self::NotInstalled(NotInstalled { source, kind })
+	}
+
+	/// Return the exit code that the proxy should exit with because of this error.
+	///
+	/// These are distinct from the ones used by compilers, which only exit with `0` or `1`.
+	pub const fn exit_code(&self) -> u8 {
+		match self {
+			Self::Config(..) | Self::CurrentToolchain(..) => Self::CONFIG_EXIT_CODE,
+			Self::Resolve(FindToolchainError::NoCompiler(..)) | Self::CompilerMissing { .. } => {
+				Self::COMPILER_MISSING_EXIT_CODE
+			}
+			Self::Resolve(..) => Self::RESOLVE_EXIT_CODE,
+			Self::NotInstalled(..) => Self::NOT_INSTALLED_EXIT_CODE,
+			Self::Receipt(..) => Self::RECEIPT_EXIT_CODE,
+			Self::Spawn { .. } => Self::SPAWN_EXIT_CODE,
+			Self::Wait(..) => Self::WAIT_EXIT_CODE,
+		}
+	}
+}
+
+/// Toolchain selected by [`source`](Self::source) that isn't installed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotInstalled {
+	pub source: ToolchainSource,
+	pub kind: NotInstalledKind,
+}
+
+/// Enumeration of ways that a toolchain that isn't installed was selected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotInstalledKind {
+	LatestCompatibleWith {
+		version: String,
+	},
+	Aliased {
+		version: String,
+		alias: String,
+	},
+	/// The latest installed toolchain was selected, but none are installed.
+	AnyInstalled,
+}
+
+impl fmt::Display for NotInstalled {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self.source {
+			ToolchainSource::Env => "the `ROOKUP_TOOLCHAIN` environment variable",
+			ToolchainSource::Config => "the Rookup configuration file",
+		})?;
+		match &self.kind {
+			NotInstalledKind::LatestCompatibleWith { version } => {
+				write!(f, " specifies that a toolchain of the latest version compatible with {version:?}")?;
+			}
+			NotInstalledKind::Aliased { version, alias } => {
+				write!(f, " specifies that a toolchain of version {version:?} (as specified by alias {alias:?})")?;
+			}
+			NotInstalledKind::AnyInstalled => {
+				return f.write_str(" specifies that the latest installed toolchain should be used, but none are installed")
+			}
+		}
+		f.write_str(" should be used, but that toolchain is not installed")
+	}
+}
+
+#[test]
+fn proxy_errors_have_exit_codes() {
+	let error = ProxyError::from_find(FindToolchainError::NoneInstalled, ToolchainSource::Env);
+	assert_eq!(error.exit_code(), ProxyError::NOT_INSTALLED_EXIT_CODE);
+	assert_eq!(
+		error.to_string(),
+		"the `ROOKUP_TOOLCHAIN` environment variable specifies that the latest installed toolchain should be used, but \
+		none are installed",
+	);
+
+	let error = ProxyError::from_find(FindToolchainError::NoCompiler("dev".into()), ToolchainSource::Config);
+	assert_eq!(error.exit_code(), ProxyError::COMPILER_MISSING_EXIT_CODE);
+	let error = ProxyError::from_find(
+		FindToolchainError::NoAliasDefault("wrk".into(), Default::default()), ToolchainSource::Config,
+	);
+	assert_eq!(error.exit_code(), ProxyError::RESOLVE_EXIT_CODE);
+}
@@ -0,0 +1,52 @@
+//! Utilities for measuring disk usage.
+
+use std::{
+	fmt,
+	fs::{
+		read_dir, symlink_metadata,
+	},
+	io::Result as IoResult,
+	path::Path,
+};
+
+/// Return the total size, in bytes, of all files inside of the directory at `path`, not following symbolic links.
+pub fn dir_size(path: &Path) -> IoResult<u64> {
+	let mut total = 0;
+	let mut to_visit = vec![path.to_path_buf()];
+	while let Some(dir) = to_visit.pop() {
+		for entry in read_dir(&dir)? {
+			let entry = entry?;
+			let metadata = symlink_metadata(entry.path())?;
+			if metadata.is_dir() {
+				to_visit.push(entry.path());
+			} else {
+				total += metadata.len();
+			}
+		}
+	}
+	Ok(total)
+}
+
+/// [`Display`](fmt::Display)able size in bytes, formatted with binary units (for e.g. `12.3 MiB`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct ByteSize(pub u64);
+
+impl fmt::Display for ByteSize {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+		if self.0 < 1024 {
+			return write!(f, "{} B", self.0)
+		}
+		let mut size = self.0 as f64 / 1024.0;
+		let mut unit = UNITS[0];
+		for next in &UNITS[1..] {
+			if size < 1024.0 {
+				break
+			}
+			size /= 1024.0;
+			unit = next;
+		}
+		write!(f, "{size:.1} {unit}")
+	}
+}
@@ -0,0 +1,103 @@
+//! Errors of the operations that this crate exposes to frontends.
+
+use rookup_common::version::VersionReqError;
+use std::{
+	ffi::OsString,
+	io::Error as IoError,
+	path::PathBuf,
+};
+
+use crate::install::InstallError;
+
+/// Error that occurred in an operation on toolchains, like installing, updating, or removing them.
+#[derive(Debug, thiserror::Error)]
+pub enum OpsError {
+	/// Toolchain home couldn't be determined.
+	#[error("couldn't get toolchain destination directory")]
+	NoToolchainHome,
+	/// State home couldn't be determined.
+	#[error("couldn't determine state directory")]
+	NoStateHome,
+	/// Contents of the toolchain home at `path` couldn't be read.
+	#[error("failed to read directory contents of {path:?}: {error}")]
+	ReadDir {
+		path: PathBuf,
+		error: IoError,
+	},
+	/// Toolchain in the toolchain home has a name that isn't valid UTF-8.
+	#[error("installed version name {name:?} is not UTF-8")]
+	NonUtf8Name {
+		name: OsString,
+	},
+	/// Path to an archive isn't valid UTF-8, so it can't be used as a URL.
+	#[error("{path:?} isn't valid UTF-8")]
+	NonUtf8Path {
+		path: PathBuf,
+	},
+	/// Invocation log at `path` couldn't be read.
+	#[error("failed to read invocation log at {path:?}: {error}")]
+	InvocationLog {
+		path: PathBuf,
+		error: IoError,
+	},
+	/// Toolchain at `path` couldn't be removed.
+	#[error("failed to recursively delete toolchain at {path:?}: {error}")]
+	Remove {
+		path: PathBuf,
+		error: IoError,
+	},
+	/// Source listed no versions for `branch`.
+	#[error("received no versions for branch {branch:?}")]
+	NoVersions {
+		branch: String,
+	},
+	/// Selector has a version requirement that couldn't be parsed.
+	#[error("invalid version requirement {requested:?}: {error}")]
+	InvalidRequirement {
+		requested: String,
+		error: VersionReqError,
+	},
+	/// No version of `branch` satisfies the requirement `requested`.
+	#[error("couldn't find version {requested:?} in branch {branch:?}")]
+	VersionNotFound {
+		requested: String,
+		branch: String,
+	},
+	/// Selector is a path, which doesn't refer to any remote version.
+	#[error("path {path:?} doesn't refer to a remote version")]
+	NotRemote {
+		path: String,
+	},
+	/// Version couldn't be determined from the file name of an archive, and none was given.
+	#[error("couldn't determine version from {file_name:?} (use `--as-version` to specify it)")]
+	UnknownVersion {
+		file_name: String,
+	},
+	/// Version is empty or would refer to a path outside of the toolchain home.
+	#[error("{version:?} is not a valid version name")]
+	InvalidVersionName {
+		version: String,
+	},
+	/// Archive that the source lists as being larger than `max-download-size` wasn't downloaded.
+	#[error("archive at {url:?} is listed as {size} bytes, more than `max-download-size` ({max_bytes})")]
+	TooLarge {
+		url: String,
+		size: u64,
+		max_bytes: u64,
+	},
+	/// Source failed to list branches or versions.
+	#[error(transparent)]
+	Source(anyhow::Error),
+	/// Receipt of a toolchain archive couldn't be read.
+	#[error(transparent)]
+	Package(anyhow::Error),
+	/// Pin database couldn't be read.
+	#[error(transparent)]
+	Pins(anyhow::Error),
+	/// Toolchain couldn't be installed.
+	#[error(transparent)]
+	Install(#[from] InstallError),
+	/// Toolchain was installed, but a `post-install` hook failed.
+	#[error(transparent)]
+	Hook(anyhow::Error),
+}
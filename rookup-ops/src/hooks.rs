@@ -0,0 +1,51 @@
+//! Running of the commands in the `hooks` table of the configuration file.
+
+use anyhow::{
+	anyhow, bail, Context, Result as AResult,
+};
+use rookup_common::Hooks;
+use std::{
+	path::Path,
+	process::Command,
+};
+
+/// Run the `post-install` hooks for the toolchain of version `version` that was installed at `path`, calling `report`
+/// with each hook before running it.
+pub fn post_install(hooks: &Hooks, version: &str, path: &Path, report: &dyn Fn(&str)) -> AResult<()> {
+	let path = path.to_string_lossy();
+	for hook in hooks.post_install.iter() {
+		let args = expand(hook, &[("{version}", version), ("{path}", &path)])?;
+		report(hook);
+		run(hook, &args)?;
+	}
+	Ok(())
+}
+
+/// Split `hook` into arguments, replacing every placeholder in `substitutions` with its value.
+fn expand(hook: &str, substitutions: &[(&str, &str)]) -> AResult<Vec<String>> {
+	let args = shlex::split(hook).with_context(|| anyhow!("hook {hook:?} has unbalanced quotes"))?;
+	if args.is_empty() {
+		bail!("hook {hook:?} is empty")
+	}
+	Ok(args.into_iter()
+		.map(move |arg| substitutions.iter().fold(arg, move |arg, (from, to)| arg.replace(from, to)))
+		.collect())
+}
+
+fn run(hook: &str, args: &[String]) -> AResult<()> {
+	let (program, args) = args.split_first().expect("hook should have a program");
+	let status = Command::new(program).args(args)
+		.status().with_context(|| anyhow!("failed to run hook {hook:?}"))?;
+	if !status.success() {
+		bail!("hook {hook:?} failed with {status}")
+	}
+	Ok(())
+}
+
+#[test]
+fn hooks_are_expanded() {
+	let args = expand("./after.sh '{version} done' {path}", &[("{version}", "1.12.0.7200"), ("{path}", "/t/1.12")]).unwrap();
+	assert_eq!(args, ["./after.sh", "1.12.0.7200 done", "/t/1.12"]);
+	assert!(expand("echo 'unbalanced", &[]).is_err());
+	assert!(expand("  ", &[]).is_err());
+}
@@ -0,0 +1,127 @@
+//! Importing toolchains from existing SourceMod installations (for e.g. on a game server) as custom toolchains.
+
+use anyhow::{
+	anyhow, bail, Context, Result as AResult,
+};
+use rookup_common::{
+	custom_toolchain_home, has_compiler, CompilerNames,
+};
+use std::{
+	fs::{
+		copy, create_dir_all, read_dir, rename,
+	},
+	path::{
+		absolute, Component, Path, PathBuf,
+	},
+};
+
+use crate::{
+	install::{
+		remove_if_exists, sibling_path,
+	},
+	sp_from_sm::{
+		map_entry, SM_ROOT, SM_SP_ROOT,
+	},
+};
+
+/// Return the root of the SourceMod installation that `dir` is in, which is the directory with `addons/sourcemod`.
+///
+/// `dir` may be the root itself, its `addons/sourcemod` directory, or the `scripting` directory in that.
+pub fn sourcemod_root(dir: &Path) -> Option<PathBuf> {
+	let dir = absolute(dir).ok()?;
+	dir.ancestors().take(4).find(move |a| a.join(SM_SP_ROOT).is_dir()).map(Path::to_path_buf)
+}
+
+/// Copy the compiler, includes, and licenses of the SourceMod installation that `dir` is in into a new custom toolchain
+/// named `name`, returning the path to it.
+///
+/// Files are mapped the same way as when installing SourceMod archives, and the toolchain is only created if it has a
+/// compiler out of `compilers` and an `include` directory with `sourcemod.inc`.
+pub fn import_toolchain(dir: &Path, name: &str, compilers: &CompilerNames) -> AResult<PathBuf> {
+	let mut components = Path::new(name).components();
+	if !matches!((components.next(), components.next()), (Some(Component::Normal(..)), None)) || name.starts_with('.') {
+		bail!("{name:?} can't be used as the name of a toolchain")
+	}
+	let root = sourcemod_root(dir)
+		.with_context(|| anyhow!("{dir:?} isn't in a SourceMod installation (expected {SM_SP_ROOT:?} in it)"))?;
+
+	let destination = custom_toolchain_home().context("couldn't get custom toolchain directory")?.join(name);
+	if destination.exists() {
+		bail!("a toolchain named {name:?} already exists at {destination:?}")
+	}
+	let staging = sibling_path(&destination, "staging");
+	remove_if_exists(&staging)
+		.with_context(|| anyhow!("failed to remove leftover staging directory {staging:?}"))?;
+	let result = copy_toolchain(&root, &staging, compilers)
+		.and_then(|()| {
+			rename(&staging, &destination).with_context(|| anyhow!("failed to move {staging:?} to {destination:?}"))
+		});
+	if let Err(e) = result {
+		return Err(match remove_if_exists(&staging) {
+			Ok(()) => e,
+			Err(rollback) => e.context(format!("failed to remove partially copied files in {staging:?}: {rollback}")),
+		})
+	}
+	Ok(destination)
+}
+
+/// Copy the files of the SourceMod installation at `root` that belong in a toolchain into `staging`, and check that
+/// they make up a working toolchain.
+fn copy_toolchain(root: &Path, staging: &Path, compilers: &CompilerNames) -> AResult<()> {
+	let mut files = Vec::new();
+	// Only license files are taken from the root of SourceMod, so none of the other directories are walked.
+	let sm_root = root.join(SM_ROOT);
+	for entry in read_dir(&sm_root).with_context(|| anyhow!("failed to iterate over {sm_root:?}"))? {
+		let entry = entry.with_context(|| anyhow!("failed to iterate over {sm_root:?}"))?;
+		if entry.path().is_file() {
+			files.push(Path::new(SM_ROOT).join(entry.file_name()));
+		}
+	}
+	collect_files(root, Path::new(SM_SP_ROOT), &mut files)?;
+
+	for file in files {
+		let Some(path) = map_entry(&file, compilers, &[]) else { continue };
+		let destination_path = staging.join(path);
+		if let Some(parent) = destination_path.parent() {
+			create_dir_all(parent).with_context(|| anyhow!("failed to create directories up to {destination_path:?}"))?;
+		}
+		copy(root.join(&file), &destination_path)
+			.with_context(|| anyhow!("failed to copy {:?} to {destination_path:?}", root.join(&file)))?;
+	}
+
+	let scripting = root.join(SM_SP_ROOT.trim_end_matches('/'));
+	if !has_compiler(staging, compilers) {
+		bail!("{scripting:?} doesn't contain a compiler (expected any of {:?})", compilers.0)
+	}
+	if !staging.join("include/sourcemod.inc").is_file() {
+		bail!("{scripting:?} doesn't contain `include/sourcemod.inc`")
+	}
+	Ok(())
+}
+
+/// Append the paths of the files in the directory at `relative` in `root` to `files`, relative to `root`.
+fn collect_files(root: &Path, relative: &Path, files: &mut Vec<PathBuf>) -> AResult<()> {
+	let dir = root.join(relative);
+	for entry in read_dir(&dir).with_context(|| anyhow!("failed to iterate over {dir:?}"))? {
+		let entry = entry.with_context(|| anyhow!("failed to iterate over {dir:?}"))?;
+		let path = relative.join(entry.file_name());
+		// Symbolic links are followed, since game server images often link shared files into place.
+		let metadata = root.join(&path).metadata().with_context(|| anyhow!("failed to read metadata of {path:?}"))?;
+		if metadata.is_dir() {
+			collect_files(root, &path, files)?;
+		} else if metadata.is_file() {
+			files.push(path);
+		}
+	}
+	Ok(())
+}
+
+#[test]
+fn sourcemod_root_is_found_from_subdirectories() {
+	let root = rookup_common::TempDir::new("rookup-import-test").unwrap();
+	create_dir_all(root.join(SM_SP_ROOT).join("include")).unwrap();
+	assert_eq!(sourcemod_root(root.path()).as_deref(), Some(root.path()));
+	assert_eq!(sourcemod_root(&root.join(SM_ROOT)).as_deref(), Some(root.path()));
+	assert_eq!(sourcemod_root(&root.join(SM_SP_ROOT)).as_deref(), Some(root.path()));
+	assert_eq!(sourcemod_root(&root.join(SM_SP_ROOT).join("include")), None);
+}
@@ -0,0 +1,115 @@
+//! Everything known about what a selector selects, as shown by `rookup info`.
+
+use rookup_common::{
+	version::version_ord,
+	find_toolchain, AliasPolicy, ConfigData, FindToolchainError, Receipt, ReceiptError, Selector,
+};
+use std::path::PathBuf;
+
+use crate::{
+	disk::dir_size,
+	source::Provider,
+	branch_of, select_remote_version, OpsError, RelevantUrl,
+};
+
+/// Everything known about what a selector selects, locally and on the remote.
+#[derive(Debug)]
+pub struct SelectorInfo {
+	/// Alias that the selector refers to, if it's one that is set in the configuration file.
+	pub alias: Option<AliasInfo>,
+	/// Installed toolchain that the selector selects, or why it doesn't select one.
+	pub installed: Result<InstalledInfo, FindToolchainError>,
+	/// Latest version on the remote that the selector selects, or why it couldn't be found, if the selector can refer
+	/// to remote versions at all.
+	pub remote: Option<Result<RemoteInfo, OpsError>>,
+}
+
+/// Alias set in the configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasInfo {
+	pub name: String,
+	pub version: String,
+	pub policy: AliasPolicy,
+}
+
+/// Installed toolchain that a selector selects.
+#[derive(Debug)]
+pub struct InstalledInfo {
+	pub name: String,
+	pub path: PathBuf,
+	/// Size of the toolchain in bytes, if it could be measured.
+	pub size: Option<u64>,
+	/// Receipt written when the toolchain was installed, which custom toolchains don't have.
+	pub receipt: Result<Option<Receipt>, ReceiptError>,
+}
+
+/// Latest version on the remote that a selector selects.
+#[derive(Debug, Clone)]
+pub struct RemoteInfo {
+	pub branch: String,
+	pub latest: RelevantUrl,
+}
+
+impl SelectorInfo {
+	/// Collect everything known about `selector`, given the configuration `data`, querying `provider` for versions
+	/// for the `os` target.
+	pub fn collect(provider: &dyn Provider, data: &ConfigData, selector: Selector<'_>, os: &str) -> Self {
+		let alias = selector.to_alias().and_then(move |alias| Some(AliasInfo {
+			name: alias.to_string(),
+			version: data.aliases.get(alias)?.clone(),
+			policy: data.alias_policy(alias),
+		}));
+
+		let installed = find_toolchain(data, selector).map(move |found| {
+			let name = found.name.clone();
+			let path = found.into_path();
+			InstalledInfo {
+				size: dir_size(&path).ok(),
+				receipt: Receipt::read(&path),
+				name,
+				path,
+			}
+		});
+
+		let has_remote = match selector {
+			Selector::Alias(Selector::INSTALLED) | Selector::Path(..) => false,
+			// Aliases of custom toolchains don't belong to any branch.
+			Selector::Alias(..) => alias.as_ref().is_none_or(move |a| branch_of(&a.version).is_some()),
+			Selector::Super(..) => true,
+		};
+		let remote = has_remote.then(move || {
+			select_remote_version(provider, data, selector, os)
+				.map(move |(branch, latest)| RemoteInfo { branch, latest })
+		});
+
+		Self {
+			alias,
+			installed,
+			remote,
+		}
+	}
+
+	/// Return the branch that the selected version belongs to, if it's known.
+	pub fn branch(&self) -> Option<String> {
+		match (&self.remote, &self.installed) {
+			(Some(Ok(remote)), ..) => Some(remote.branch.clone()),
+			(.., Ok(installed)) => branch_of(&installed.name),
+			_ => self.alias.as_ref().and_then(move |a| branch_of(&a.version)),
+		}
+	}
+
+	/// Return `true` if the remote has a newer version than the one that is selected, or [`None`] if that isn't known.
+	///
+	/// The selected version is the one an alias is set to, or otherwise the installed one.
+	pub fn update_available(&self) -> Option<bool> {
+		let Some(Ok(remote)) = &self.remote else {
+			return None
+		};
+		let current = match (&self.alias, &self.installed) {
+			(Some(alias), ..) => alias.version.as_str(),
+			(None, Ok(installed)) => installed.name.as_str(),
+			(None, Err(..)) => return None,
+		};
+		Some(version_ord(current, remote.latest.version()).is_lt())
+	}
+}
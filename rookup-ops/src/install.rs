@@ -0,0 +1,1055 @@
+//! Transactional installation of toolchains from remote archives.
+
+use anyhow::{
+	anyhow, bail, Context, Result as AResult,
+};
+use rookup_common::{
+	cache_home, downloads_path, lock_path, CompilerNames, Component, Extra, FileMode, LockFile, LockOwner, Origin,
+	PinMismatch, Receipt, Signing, VerifiedSignature,
+	RECEIPT_FILE,
+};
+use filetime::{
+	set_file_mtime, FileTime,
+};
+use std::{
+	cell::Cell,
+	env::var,
+	ffi::OsString,
+	fmt,
+	fs::{
+		DirEntry, File, Metadata, Permissions,
+		create_dir_all, hard_link, read_dir, remove_dir_all, rename, set_permissions,
+	},
+	io::{
+		copy as io_copy,
+		Error as IoError, ErrorKind as IoErrorKind, Read, Seek, SeekFrom, Write,
+	},
+	path::{
+		Path, PathBuf,
+	},
+	rc::Rc,
+	str::FromStr,
+	thread::sleep,
+	time::{
+		Duration, Instant,
+	},
+	sync::{
+		atomic::{
+			AtomicBool, Ordering as AtomicOrdering,
+		},
+		Arc,
+	},
+};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+use sha2::{
+	Digest, Sha256,
+};
+
+use crate::{
+	pins::{
+		Pin, PinCheck, PinDatabase,
+	},
+	signing, smdrop,
+	source::{
+		self, Provider,
+	},
+	spool::Spool,
+	hex,
+};
+
+/// Installation of a single toolchain version into `destination`.
+/// 
+/// The toolchain is first extracted into a staging directory next to `destination`, which then replaces the previous
+/// installation (if any) only once extraction has succeeded.
+pub struct InstallVersion<'a> {
+	pub provider: &'a dyn Provider,
+	pub layout: source::Layout,
+	pub os: &'a str,
+	/// Names of the compiler executable to look for in the archive.
+	pub compilers: &'a CompilerNames,
+	pub extras: &'a [Extra],
+	/// Components to install, or all of them if empty.
+	pub components: &'a [Component],
+	/// `true` if the permissions and modification times of the installed files should be normalized once they are
+	/// extracted, so that installing the same archive gives the same files on every machine.
+	/// 
+	/// Files are dated to `SOURCE_DATE_EPOCH` if it's set, or to [`DETERMINISTIC_TIME`] otherwise.
+	pub deterministic: bool,
+	/// Command to run the compiler with (for e.g. `["wine"]`), which is recorded in the install receipt, or empty if
+	/// it should be taken from the `runners` setting of the configuration instead.
+	pub runner: &'a [String],
+	pub version: &'a str,
+	pub url: &'a str,
+	pub max_bytes: u64,
+	/// Maximum rate, in bytes per second, at which the archive is downloaded, or `0` for no limit.
+	pub max_rate: u64,
+	/// SHA-256 digest that the archive must have, as a hexadecimal string.
+	pub expected_sha256: Option<&'a str>,
+	/// Size of the archive in bytes above which it is written to a temporary file instead of being kept in memory.
+	pub spool_threshold: u64,
+	/// Limits on the contents of the archive.
+	pub limits: smdrop::Limits,
+	/// Permissions of the compiler and other executable files.
+	pub executable_mode: FileMode,
+	/// What to do if the archive doesn't match its pinned digest.
+	pub pin_mismatch: PinMismatch,
+	/// Keys to verify signatures of the archive with.
+	pub signing: &'a Signing,
+	pub destination: PathBuf,
+	/// Function to report progress of the installation to.
+	pub progress: &'a dyn Fn(InstallEvent<'_>),
+	/// Token that is checked while downloading and extracting the archive, to abort the installation early.
+	pub cancel: CancelToken,
+}
+
+/// Token for cancelling an installation from another thread.
+#[derive(Default, Debug, Clone)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+	/// Request cancellation of all installations using this token.
+	#[inline]
+	pub fn cancel(&self) {
+		self.0.store(true, AtomicOrdering::Relaxed)
+	}
+
+	/// Return `true` if cancellation was requested.
+	#[inline]
+	pub fn is_cancelled(&self) -> bool {
+		self.0.load(AtomicOrdering::Relaxed)
+	}
+
+	/// Return an error if cancellation was requested.
+	fn check(&self) -> Result<(), Cancelled> {
+		if self.is_cancelled() { Err(Cancelled) } else { Ok(()) }
+	}
+}
+
+/// Error for an installation that was cancelled with a [`CancelToken`].
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[error("installation was cancelled")]
+pub struct Cancelled;
+
+/// Event that occurred during [`InstallVersion::call`].
+#[derive(Debug, Clone, Copy)]
+pub enum InstallEvent<'a> {
+	/// An entry of the archive was processed, and extracted to `path` (relative to the toolchain directory) if it was
+	/// part of the toolchain.
+	Entry {
+		path: Option<&'a Path>,
+		progress: ExtractProgress,
+	},
+	/// All entries of the archive were processed.
+	Extracted {
+		progress: ExtractProgress,
+	},
+	/// Signature of the archive at `url` was verified.
+	Verified {
+		url: &'a str,
+		signature: &'a VerifiedSignature,
+	},
+	/// Something unexpected happened that doesn't stop the installation.
+	Warning {
+		message: &'a str,
+	},
+	/// The `post-install` hook `command` is about to be run.
+	Hook {
+		command: &'a str,
+	},
+	/// Another process that is still running is installing into the same directory, so the installation waits until
+	/// it's done.
+	Waiting {
+		/// Process that holds the lock of the directory, if known.
+		owner: Option<LockOwner>,
+	},
+}
+
+/// Progress of extracting an archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExtractProgress {
+	/// Number of entries of the archive processed so far.
+	pub entries: u64,
+	/// Number of files extracted so far.
+	pub files: u64,
+	/// Number of extracted files that were identical to the ones of the previous installation, which were linked
+	/// instead of being written again.
+	pub unchanged: u64,
+	/// Total number of entries in the archive, if known up front.
+	pub total_entries: Option<u64>,
+	/// Number of bytes of the compressed archive processed so far.
+	pub bytes: u64,
+	/// Size of the compressed archive in bytes.
+	pub total_bytes: u64,
+}
+
+impl fmt::Display for ExtractProgress {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let Self { entries, files, unchanged, bytes, total_bytes, .. } = *self;
+		match self.total_entries {
+			Some(total) => write!(f, "processed {entries}/{total} entries, extracted {files} files")?,
+			None => write!(f, "read {bytes}/{total_bytes} bytes of archive, extracted {files} files")?,
+		}
+		if unchanged > 0 {
+			write!(f, " ({} changed)", files - unchanged)?;
+		}
+		Ok(())
+	}
+}
+
+impl InstallVersion<'_> {
+	pub fn call(self) -> Result<(), InstallError> {
+		let lock = lock_path(&self.destination);
+		let wait = |owner: Option<&LockOwner>| (self.progress)(InstallEvent::Waiting { owner: owner.copied() });
+		let _lock = LockFile::acquire(lock.clone(), &wait)
+			.map_err(move |error| InstallError::Lock { path: lock, error })?;
+
+		let staging = sibling_path(&self.destination, "staging");
+		let backup = sibling_path(&self.destination, "backup");
+		restore_backup(&backup, &self.destination)
+			.map_err(|error| InstallError::Restore { path: backup.clone(), error })?;
+
+		if let Err(error) = self.extract(&staging) {
+			let rollback = remove_if_exists(&staging);
+			if error.is::<Cancelled>() && rollback.is_ok() {
+				return Err(InstallError::Cancelled { removed: staging })
+			}
+			return Err(InstallError::rolled_back(error, rollback))
+		}
+
+		let had_previous = match rename(&self.destination, &backup) {
+			Ok(..) => true,
+			Err(e) if e.kind() == IoErrorKind::NotFound => false,
+			Err(e) => {
+				let error = anyhow!(e).context(format!("failed to move previous installation to {backup:?}"));
+				return Err(InstallError::rolled_back(error, remove_if_exists(&staging)))
+			}
+		};
+
+		if let Err(e) = rename(&staging, &self.destination) {
+			let error = anyhow!(e).context(format!("failed to move {staging:?} to {:?}", self.destination));
+			let rollback = remove_if_exists(&staging).and_then(|()| {
+				if had_previous {
+					rename(&backup, &self.destination)
+				} else {
+					Ok(())
+				}
+			});
+			return Err(InstallError::rolled_back(error, rollback))
+		}
+
+		if had_previous {
+			if let Err(e) = remove_dir_all(&backup) {
+				let message = format!("failed to remove previous installation at {}: {e}", backup.display());
+				(self.progress)(InstallEvent::Warning { message: &message });
+			}
+		}
+
+		Ok(())
+	}
+
+	fn extract(&self, staging: &Path) -> AResult<()> {
+		remove_if_exists(staging)
+			.with_context(|| anyhow!("failed to remove leftover staging directory {staging:?}"))?;
+		create_dir_all(staging)
+			.with_context(|| anyhow!("failed to create staging directory {staging:?}"))?;
+
+		let mut spool = Spool::new(self.spool_threshold, cache_home().map(downloads_path));
+		let mut download = HashingReader {
+			inner: CancellableReader {
+				inner: ThrottledReader::new(self.provider.fetch(self.url, self.max_bytes)?, self.max_rate),
+				cancel: &self.cancel,
+			},
+			hasher: Sha256::new(),
+		};
+		if let Err(e) = io_copy(&mut download, &mut spool) {
+			self.cancel.check()?;
+			return Err(anyhow!(e).context(format!("failed to download archive at {:?}", self.url)))
+		}
+		let sha256 = hex(&download.hasher.finalize());
+		let mut body = spool.into_contents()
+			.with_context(|| anyhow!("failed to buffer archive at {:?}", self.url))?;
+
+		if let Some(expected) = self.expected_sha256 {
+			if !expected.eq_ignore_ascii_case(&sha256) {
+				bail!("archive at {:?} has SHA-256 digest {sha256}, but {expected} was expected", self.url)
+			}
+		}
+		self.verify_pin(&sha256)?;
+		let signature = signing::verify(self.provider, self.signing, self.url, &mut body)?;
+		body.rewind()
+			.with_context(|| anyhow!("failed to read downloaded archive at {:?}", self.url))?;
+		if let Some(signature) = signature.as_ref() {
+			(self.progress)(InstallEvent::Verified { url: self.url, signature });
+		}
+		let bytes_read = Rc::new(Cell::new(0));
+		let mut progress = ExtractProgress {
+			entries: 0,
+			files: 0,
+			unchanged: 0,
+			total_entries: None,
+			bytes: 0,
+			total_bytes: body.len()?,
+		};
+		let body = smdrop::Seekable(CountingReader {
+			inner: body,
+			count: bytes_read.clone(),
+		});
+
+		let archive_kind = smdrop::ArchiveKind::from_str(self.url)
+			.with_context(|| anyhow!("failed to determine format of archive at {:?}", self.url))?;
+		let mut archive = smdrop::Archive::new(body, archive_kind)?;
+		progress.total_entries = archive.len().map(move |n| n as _);
+		let normalized_time = self.deterministic.then(deterministic_time);
+
+		for entry in archive.entries(self.limits)? {
+			self.cancel.check()?;
+			let (name, mut entry) = entry
+				.with_context(|| anyhow!("failed to extract archive at {:?}", self.url))?;
+			progress.entries += 1;
+			progress.bytes = bytes_read.get();
+			#[cfg(not(unix))]
+			if !entry.has_utf8_name() {
+				let message = format!("name of entry {name:?} is not valid UTF-8, so invalid characters were replaced");
+				(self.progress)(InstallEvent::Warning { message: &message });
+			}
+			let Some(path) = self.layout.map_entry(&name, self.compilers, self.extras) else {
+				(self.progress)(InstallEvent::Entry { path: None, progress });
+				continue
+			};
+			if Component::of(&path, self.compilers).is_some_and(|c| !Component::or_all(self.components).contains(&c)) {
+				(self.progress)(InstallEvent::Entry { path: None, progress });
+				continue
+			}
+
+			let destination_path = staging.join(&path);
+			if !entry.is_dir() {
+				if let Some(parent) = destination_path.parent() {
+					create_dir_all(parent)
+						.with_context(|| anyhow!("failed to create directories up to {destination_path:?}"))?;
+				}
+
+				let executable = entry.is_executable()
+					|| path.file_name().and_then(move |n| n.to_str()).is_some_and(|n| self.compilers.contains(n));
+
+				// Files that didn't change since the previous installation are linked, so they aren't written again.
+				// Both installations then share the file, which rollback depends on staying as it is, so files that
+				// are changed after being extracted (like the receipt) are always written anew.
+				let previous_path = self.destination.join(&path);
+				let mut read = Vec::new();
+				let size = entry.size();
+				let unchanged = path != Path::new(RECEIPT_FILE)
+					&& self.can_link(&previous_path, executable, normalized_time)
+					&& has_same_contents(&mut entry, size, &previous_path, &mut read)
+						.with_context(|| anyhow!("failed to read data of {path:?}"))?;
+				if unchanged && hard_link(&previous_path, &destination_path).is_ok() {
+					progress.unchanged += 1;
+				} else {
+					let mut options = File::options();
+					#[cfg(unix)]
+					if executable {
+						options.mode(self.executable_mode.0);
+					}
+
+					let mut file = options.create(true).truncate(true).write(true).open(&destination_path)
+						.with_context(|| anyhow!("failed to open {destination_path:?}"))?;
+					file.write_all(&read)
+						.and_then(|()| io_copy(&mut entry, &mut file))
+						.with_context(|| anyhow!("failed to pipe data of {path:?} to {destination_path:?}"))?;
+					#[cfg(target_os = "macos")]
+					if executable {
+						clear_quarantine(&destination_path).with_context(|| {
+							anyhow!("failed to clear the quarantine attribute of {destination_path:?}")
+						})?;
+					}
+				}
+				progress.files += 1;
+			}
+			(self.progress)(InstallEvent::Entry { path: Some(&path), progress });
+		}
+		progress.bytes = bytes_read.get();
+		(self.progress)(InstallEvent::Extracted { progress });
+
+		let components = Component::or_all(self.components);
+		let compiler = self.compilers.find_in(staging);
+		if compiler.is_none() && components.contains(&Component::Compiler) {
+			bail!("archive at {:?} did not contain a compiler for {} (expected any of {:?})", self.url, self.os, self.compilers.0)
+		}
+		if !staging.join("include").is_dir() && self.components == [Component::Includes] {
+			bail!("archive at {:?} did not contain an `include` directory", self.url)
+		}
+
+		let receipt = match self.layout {
+			// Exported toolchains keep the receipt of where they were originally installed from.
+			source::Layout::Toolchain => {
+				let mut receipt = Receipt::read(staging)
+					.with_context(|| anyhow!("failed to read install receipt of archive at {:?}", self.url))?
+					.with_context(|| anyhow!("archive at {:?} has no {RECEIPT_FILE}", self.url))?;
+				receipt.version = self.version.into();
+				if !self.components.is_empty() {
+					receipt.components = self.components.to_vec();
+				}
+				if !self.runner.is_empty() {
+					receipt.runner = Some(self.runner.to_vec());
+				}
+				receipt
+			}
+			source::Layout::SourceMod | source::Layout::SourcePawn => Receipt {
+				version: self.version.into(),
+				origin: Origin::Upstream {
+					url: self.url.into(),
+					sha256: Some(sha256),
+				},
+				target_os: Some(self.os.into()),
+				signature,
+				components: self.components.to_vec(),
+				compiler: compiler.and_then(move |c| c.file_name()?.to_str().map(String::from)),
+				runner: (!self.runner.is_empty()).then(move || self.runner.to_vec()),
+			},
+		};
+		receipt.write(staging)
+			.with_context(|| anyhow!("failed to write install receipt to {staging:?}"))?;
+
+		if let Some(time) = normalized_time {
+			normalize(staging, self.executable_mode, time)
+				.with_context(|| anyhow!("failed to normalize extracted files in {staging:?}"))?;
+		}
+
+		Ok(())
+	}
+
+	/// Return `true` if the file at `previous` can be linked in place of a file extracted with `executable`, which is
+	/// the case if it's executable if and only if the extracted file would be, and normalizing files to
+	/// `normalized_time` (if set) wouldn't change it.
+	fn can_link(&self, previous: &Path, executable: bool, normalized_time: Option<FileTime>) -> bool {
+		let Ok(metadata) = previous.metadata() else {
+			return false
+		};
+		if normalized_time.is_some_and(|time| !is_normalized(&metadata, self.executable_mode, time)) {
+			return false
+		}
+		#[cfg(unix)]
+		{
+			use std::os::unix::fs::PermissionsExt;
+			(metadata.permissions().mode() & 0o111 != 0) == executable
+		}
+		#[cfg(not(unix))]
+		{
+			let _ = executable;
+			true
+		}
+	}
+
+	/// Compare the `sha256` digest of the downloaded archive against the pin database, pinning it if it's new.
+	fn verify_pin(&self, sha256: &str) -> AResult<()> {
+		let mut pins = PinDatabase::open()?;
+		match pins.check(self.url, sha256) {
+			PinCheck::Match => Ok(()),
+			PinCheck::New => {
+				pins.pins.insert(self.url.into(), Pin {
+					version: self.version.into(),
+					sha256: sha256.into(),
+				});
+				pins.save()
+			}
+			PinCheck::Mismatch { expected } => {
+				let message = format!(
+					"archive at {:?} has SHA-256 digest {sha256}, but it was {expected} when first downloaded",
+					self.url,
+				);
+				match self.pin_mismatch {
+					PinMismatch::Fail => bail!("{message}; if this is expected, remove its pin from {:?}", PinDatabase::path().unwrap_or_default()),
+					PinMismatch::Warn => {
+						(self.progress)(InstallEvent::Warning { message: &message });
+						Ok(())
+					}
+				}
+			}
+		}
+	}
+}
+
+/// Name of the environment variable with the Unix timestamp that files of deterministic installations are dated to.
+pub const SOURCE_DATE_EPOCH_VAR: &str = "SOURCE_DATE_EPOCH";
+
+/// Unix timestamp that files of deterministic installations are dated to if `SOURCE_DATE_EPOCH` isn't set, which is
+/// 1980-01-01, the earliest time that ZIP archives can store.
+pub const DETERMINISTIC_TIME: i64 = 315_532_800;
+
+/// Return the time that files of deterministic installations are dated to.
+fn deterministic_time() -> FileTime {
+	let seconds = var(SOURCE_DATE_EPOCH_VAR).ok()
+		.and_then(move |s| s.trim().parse().ok())
+		.unwrap_or(DETERMINISTIC_TIME);
+	FileTime::from_unix_time(seconds, 0)
+}
+
+/// Remove the `com.apple.quarantine` extended attribute from the file at `path`, so that Gatekeeper doesn't refuse to
+/// run it.
+#[cfg(target_os = "macos")]
+fn clear_quarantine(path: &Path) -> Result<(), IoError> {
+	use std::{
+		ffi::CString,
+		os::unix::ffi::OsStrExt,
+	};
+	let path = CString::new(path.as_os_str().as_bytes()).map_err(IoError::from)?;
+	// SAFETY: both strings are NUL-terminated and outlive the call.
+	let result = unsafe { libc::removexattr(path.as_ptr(), c"com.apple.quarantine".as_ptr(), libc::XATTR_NOFOLLOW) };
+	if result == 0 {
+		return Ok(())
+	}
+	match IoError::last_os_error() {
+		// Files that were never quarantined are fine as they are.
+		e if e.raw_os_error() == Some(libc::ENOATTR) => Ok(()),
+		e => Err(e),
+	}
+}
+
+/// Set the modification times of `dir` and everything in it to `time`, and their permissions to `0o755` for
+/// directories, `executable_mode` for executable files, and `0o644` for other files, in order of their names.
+/// 
+/// Files that are already normalized are left as they are, and ones that are hard-linked from elsewhere are copied
+/// before being changed.
+fn normalize(dir: &Path, executable_mode: FileMode, time: FileTime) -> Result<(), IoError> {
+	let mut entries = read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+	entries.sort_by_key(DirEntry::file_name);
+	for entry in entries {
+		let path = entry.path();
+		let file_type = entry.file_type()?;
+		if file_type.is_dir() {
+			normalize(&path, executable_mode, time)?;
+		} else if file_type.is_file() {
+			let metadata = entry.metadata()?;
+			if is_normalized(&metadata, executable_mode, time) {
+				continue
+			}
+			// Files linked from elsewhere (like the previous installation) are copied, so that only this one changes.
+			if is_linked(&metadata) {
+				replace_with_copy(&path)?;
+			}
+			#[cfg(unix)]
+			{
+				use std::os::unix::fs::PermissionsExt;
+				let executable = metadata.permissions().mode() & 0o111 != 0;
+				let mode = if executable { executable_mode.0 } else { 0o644 };
+				set_permissions(&path, Permissions::from_mode(mode))?;
+			}
+			set_file_mtime(&path, time)?;
+		}
+	}
+	// Directories are dated last, since changing what's in them updates their modification times.
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		set_permissions(dir, Permissions::from_mode(0o755))?;
+	}
+	set_file_mtime(dir, time)
+}
+
+/// Return `true` if [`normalize`] wouldn't change the file with `metadata`.
+fn is_normalized(metadata: &Metadata, executable_mode: FileMode, time: FileTime) -> bool {
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		let mode = metadata.permissions().mode() & 0o7777;
+		let expected = if mode & 0o111 != 0 { executable_mode.0 } else { 0o644 };
+		if mode != expected {
+			return false
+		}
+	}
+	#[cfg(not(unix))]
+	let _ = executable_mode;
+	FileTime::from_last_modification_time(metadata) == time
+}
+
+/// Return `true` if the file with `metadata` may have other hard links to it.
+/// 
+/// The number of links isn't known on other platforms than Unix, so every file may be linked there.
+fn is_linked(metadata: &Metadata) -> bool {
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::MetadataExt;
+		metadata.nlink() > 1
+	}
+	#[cfg(not(unix))]
+	{
+		let _ = metadata;
+		true
+	}
+}
+
+/// Replace the file at `path` with a copy of itself, so that changing it doesn't change other links to the same file.
+fn replace_with_copy(path: &Path) -> Result<(), IoError> {
+	let copy = sibling_path(path, "copy");
+	std::fs::copy(path, &copy)?;
+	rename(&copy, path)
+}
+
+/// Return `true` if the file at `previous` has the same contents as `entry`, which is `size` bytes long.
+/// 
+/// Whatever is read from `entry` to compare them is appended to `read`, so that it can still be extracted if they
+/// differ.
+fn has_same_contents(entry: &mut impl Read, size: u64, previous: &Path, read: &mut Vec<u8>) -> Result<bool, IoError> {
+	match previous.metadata() {
+		Ok(m) if m.is_file() && m.len() == size => {}
+		_ => return Ok(false),
+	}
+	entry.take(size).read_to_end(read)?;
+	Ok(std::fs::read(previous).is_ok_and(|contents| contents == *read))
+}
+
+/// Reader that counts the bytes read from `inner`.
+struct CountingReader<R> {
+	inner: R,
+	count: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+		let n = self.inner.read(buf)?;
+		self.count.set(self.count.get() + n as u64);
+		Ok(n)
+	}
+}
+
+impl<R: Seek> Seek for CountingReader<R> {
+	fn seek(&mut self, pos: SeekFrom) -> Result<u64, IoError> {
+		self.inner.seek(pos)
+	}
+}
+
+/// Reader that feeds the bytes read from `inner` into `hasher`.
+struct HashingReader<R> {
+	inner: R,
+	hasher: Sha256,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+		let n = self.inner.read(buf)?;
+		self.hasher.update(&buf[..n]);
+		Ok(n)
+	}
+}
+
+/// Reader that fails once cancellation is requested with `cancel`.
+struct CancellableReader<'a, R> {
+	inner: R,
+	cancel: &'a CancelToken,
+}
+
+impl<R: Read> Read for CancellableReader<'_, R> {
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+		self.cancel.check().map_err(IoError::other)?;
+		self.inner.read(buf)
+	}
+}
+
+/// Reader that waits between reads from `inner`, so that no more than `rate` bytes are read per second on average.
+/// 
+/// If `rate` is `0`, then reads aren't limited.
+struct ThrottledReader<R> {
+	inner: R,
+	rate: u64,
+	start: Instant,
+	read: u64,
+}
+
+impl<R> ThrottledReader<R> {
+	fn new(inner: R, rate: u64) -> Self {
+		Self {
+			inner,
+			rate,
+			start: Instant::now(),
+			read: 0,
+		}
+	}
+}
+
+impl<R: Read> Read for ThrottledReader<R> {
+	fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+		if self.rate == 0 {
+			return self.inner.read(buf)
+		}
+		// Reads are kept to a tenth of a second's worth of bytes, so that waits are short and cancellation is quick.
+		let max = usize::try_from(self.rate / 10).unwrap_or(usize::MAX).max(1).min(buf.len());
+		let n = self.inner.read(&mut buf[..max])?;
+		self.read += n as u64;
+		let due = Duration::from_secs_f64(self.read as f64 / self.rate as f64);
+		if let Some(ahead) = due.checked_sub(self.start.elapsed()) {
+			sleep(ahead);
+		}
+		Ok(n)
+	}
+}
+
+/// Return the path to a hidden directory next to `destination`, marked with `suffix`.
+pub(crate) fn sibling_path(destination: &Path, suffix: &str) -> PathBuf {
+	let mut name = OsString::from(".");
+	if let Some(file_name) = destination.file_name() {
+		name.push(file_name);
+	}
+	name.push(".");
+	name.push(suffix);
+	destination.with_file_name(name)
+}
+
+/// Clean up the `backup` of the previous installation at `destination` that an interrupted installation left behind,
+/// moving it back into place if nothing was installed instead, or removing it otherwise.
+fn restore_backup(backup: &Path, destination: &Path) -> Result<(), IoError> {
+	if !backup.exists() {
+		return Ok(())
+	}
+	match destination.exists() {
+		true => remove_dir_all(backup),
+		false => rename(backup, destination),
+	}
+}
+
+pub(crate) fn remove_if_exists(path: &Path) -> Result<(), IoError> {
+	match remove_dir_all(path) {
+		Err(e) if e.kind() != IoErrorKind::NotFound => Err(e),
+		_ => Ok(()),
+	}
+}
+
+/// Error that occurred in [`InstallVersion::call`].
+#[derive(Debug, thiserror::Error)]
+pub enum InstallError {
+	/// Installation was cancelled before the previous installation was replaced, and the partially extracted files
+	/// in `removed` were deleted.
+	#[error("installation was cancelled (removed partially extracted files in {removed:?})")]
+	Cancelled {
+		removed: PathBuf,
+	},
+	/// Installation failed, but everything was restored to how it was before.
+	#[error("{error:#} (changes were rolled back)")]
+	RolledBack {
+		error: anyhow::Error,
+	},
+	/// Backup at `path` that an interrupted installation left behind couldn't be restored or removed, so nothing was
+	/// installed.
+	#[error("failed to clean up backup of previous installation at {path:?}: {error}")]
+	Restore {
+		path: PathBuf,
+		error: IoError,
+	},
+	/// Lock file at `path` couldn't be created, so nothing was changed.
+	#[error("failed to lock {path:?}: {error}")]
+	Lock {
+		path: PathBuf,
+		error: IoError,
+	},
+	/// Installation failed, and the previous state could not be fully restored.
+	#[error("{error:#} (failed to roll back changes: {rollback})")]
+	RollbackFailed {
+		error: anyhow::Error,
+		rollback: IoError,
+	},
+}
+
+impl InstallError {
+	fn rolled_back(error: anyhow::Error, rollback: Result<(), IoError>) -> Self {
+		match rollback {
+			Ok(()) => Self::RolledBack { error },
+			Err(rollback) => Self::RollbackFailed { error, rollback },
+		}
+	}
+}
+
+#[test]
+fn cancelled_reader_fails() {
+	let cancel = CancelToken::default();
+	let mut reader = CancellableReader {
+		inner: std::io::Cursor::new([0u8; 16]),
+		cancel: &cancel,
+	};
+	assert_eq!(reader.read(&mut [0; 8]).unwrap(), 8);
+	cancel.cancel();
+	assert!(reader.read(&mut [0; 8]).is_err());
+}
+
+#[test]
+fn throttled_reader_limits_rate() {
+	let mut reader = ThrottledReader::new(std::io::Cursor::new([0u8; 2_000]), 10_000);
+	let start = Instant::now();
+	assert_eq!(io_copy(&mut reader, &mut std::io::sink()).unwrap(), 2_000);
+	assert!(start.elapsed() >= Duration::from_millis(150));
+}
+
+#[cfg(unix)]
+#[test]
+fn rollback_keeps_previous_files() {
+	use crate::{
+		source::Layout,
+		test_tar_gz, TestSource,
+	};
+	use std::{
+		fs::{
+			read, write,
+		},
+		os::unix::fs::PermissionsExt,
+	};
+
+	// Pins of the archive are kept away from the real configuration home.
+	crate::with_test_homes(move |root| {
+		let destination = root.join("1.12.0-7000");
+		create_dir_all(destination.join("include")).unwrap();
+		let previous = [
+			("spcomp", 0o700, "compiler"), ("include/a.inc", 0o600, "same"), ("include/b.inc", 0o640, "old"),
+		];
+		let previous_time = FileTime::from_unix_time(1_000_000_000, 0);
+		for (path, mode, contents) in previous {
+			let path = destination.join(path);
+			write(&path, contents).unwrap();
+			set_permissions(&path, Permissions::from_mode(mode)).unwrap();
+			set_file_mtime(&path, previous_time).unwrap();
+		}
+
+		let receipt = Receipt {
+			version: "1.12.0-7000".into(),
+			origin: Origin::Directory { path: "elsewhere".into() },
+			target_os: None,
+			signature: None,
+			components: Vec::new(),
+			compiler: Some("spcomp".into()),
+			runner: None,
+		}.to_toml().unwrap();
+		let provider = TestSource {
+			layout: Layout::Toolchain,
+			branches: &[],
+			archive: test_tar_gz([
+				("spcomp", 0o755, "compiler"), ("include/a.inc", 0o644, "same"), ("include/b.inc", 0o644, "new"),
+				(RECEIPT_FILE, 0o644, &receipt),
+			]),
+			pointer: None,
+		};
+
+		for deterministic in [false, true] {
+			let unchanged = Cell::new(0);
+			let progress = |event: InstallEvent<'_>| if let InstallEvent::Extracted { progress } = event {
+				unchanged.set(progress.unchanged);
+			};
+			let install = InstallVersion {
+				provider: &provider,
+				layout: Layout::Toolchain,
+				os: "linux",
+				compilers: &CompilerNames(vec!["spcomp".into()]),
+				extras: &[],
+				components: &[],
+				deterministic,
+				runner: &[],
+				version: "1.12.0-7000",
+				url: "1.12.0-7000.tar.gz",
+				max_bytes: u64::MAX,
+				max_rate: 0,
+				expected_sha256: None,
+				spool_threshold: u64::MAX,
+				limits: smdrop::Limits {
+					total_size: u64::MAX,
+					entry_size: u64::MAX,
+					entries: u64::MAX,
+				},
+				executable_mode: FileMode(0o755),
+				pin_mismatch: PinMismatch::Fail,
+				signing: &Signing::default(),
+				destination: destination.clone(),
+				progress: &progress,
+				cancel: CancelToken::default(),
+			};
+			// Whatever fails after extraction, rolling back only removes the staging directory, so extracting must
+			// leave the previous installation as it was.
+			let staging = sibling_path(&destination, "staging");
+			install.extract(&staging).unwrap();
+			remove_if_exists(&staging).unwrap();
+
+			// Normalization would change the linked files, so nothing is linked for deterministic installations.
+			assert_eq!(unchanged.get(), if deterministic { 0 } else { 2 });
+			for (path, mode, contents) in previous {
+				let path = destination.join(path);
+				let metadata = path.metadata().unwrap();
+				assert_eq!(read(&path).unwrap(), contents.as_bytes());
+				assert_eq!(metadata.permissions().mode() & 0o7777, mode);
+				assert_eq!(FileTime::from_last_modification_time(&metadata), previous_time);
+			}
+		}
+	});
+}
+
+#[cfg(unix)]
+#[test]
+fn normalize_leaves_linked_files_alone() {
+	use rookup_common::TempDir;
+	use std::{
+		fs::{
+			read, write,
+		},
+		os::unix::fs::PermissionsExt,
+	};
+
+	let root = TempDir::new("rookup-normalize-test").unwrap();
+	let dir = root.join("toolchain");
+	create_dir_all(dir.join("include")).unwrap();
+	let linked_time = FileTime::from_unix_time(1_000_000_000, 0);
+	let linked = root.join("linked.inc");
+	write(&linked, "linked").unwrap();
+	set_permissions(&linked, Permissions::from_mode(0o600)).unwrap();
+	set_file_mtime(&linked, linked_time).unwrap();
+	hard_link(&linked, dir.join("include/linked.inc")).unwrap();
+	let files = [("spcomp", 0o700), ("include/a.inc", 0o666)];
+	for (path, mode) in files {
+		let path = dir.join(path);
+		write(&path, "").unwrap();
+		set_permissions(&path, Permissions::from_mode(mode)).unwrap();
+	}
+
+	let time = FileTime::from_unix_time(DETERMINISTIC_TIME, 0);
+	normalize(&dir, FileMode(0o750), time).unwrap();
+	let expected = [
+		("", 0o755), ("include", 0o755), ("spcomp", 0o750), ("include/a.inc", 0o644), ("include/linked.inc", 0o644),
+	];
+	for (path, mode) in expected {
+		let metadata = dir.join(path).metadata().unwrap();
+		assert_eq!(metadata.permissions().mode() & 0o7777, mode, "{path:?}");
+		assert_eq!(FileTime::from_last_modification_time(&metadata), time, "{path:?}");
+	}
+	assert_eq!(read(dir.join("include/linked.inc")).unwrap(), b"linked");
+	let metadata = linked.metadata().unwrap();
+	assert_eq!(metadata.permissions().mode() & 0o7777, 0o600);
+	assert_eq!(FileTime::from_last_modification_time(&metadata), linked_time);
+}
+
+#[test]
+fn only_selected_components_are_installed() {
+	use crate::{
+		source::Layout,
+		test_tar_gz, TestSource,
+	};
+	use rookup_common::has_components;
+
+	let provider = TestSource {
+		layout: Layout::SourcePawn,
+		branches: &[],
+		archive: test_tar_gz([
+			("sourcepawn/bin/spcomp", 0o755, ""), ("sourcepawn/include/a.inc", 0o644, ""),
+			("sourcepawn/LICENSE.txt", 0o644, ""),
+		]),
+		pointer: None,
+	};
+	let compilers = CompilerNames(vec!["spcomp".into()]);
+
+	crate::with_test_homes(|root| {
+		let install = |components: &[Component]| {
+			let destination = root.join(format!("{components:?}"));
+			InstallVersion {
+				provider: &provider,
+				layout: Layout::SourcePawn,
+				os: "linux",
+				compilers: &compilers,
+				extras: &[],
+				components,
+				deterministic: false,
+				runner: &[],
+				version: "1.12.0.7200",
+				url: "sourcepawn-1.12.0.7200-linux.tar.gz",
+				max_bytes: u64::MAX,
+				max_rate: 0,
+				expected_sha256: None,
+				spool_threshold: u64::MAX,
+				limits: smdrop::Limits {
+					total_size: u64::MAX,
+					entry_size: u64::MAX,
+					entries: u64::MAX,
+				},
+				executable_mode: FileMode(0o755),
+				pin_mismatch: PinMismatch::Fail,
+				signing: &Signing::default(),
+				destination: destination.clone(),
+				progress: &|_| {},
+				cancel: CancelToken::default(),
+			}.call().unwrap();
+			destination
+		};
+
+		let includes = install(&[Component::Includes]);
+		assert!(!includes.join("spcomp").exists() && includes.join("include/a.inc").is_file());
+		assert!(has_components(&includes, &[Component::Includes], &compilers));
+		assert!(!has_components(&includes, &[], &compilers));
+		let receipt = Receipt::read(&includes).unwrap().unwrap();
+		assert_eq!((receipt.components, receipt.compiler), (vec![Component::Includes], None));
+
+		let compiler = install(&[Component::Compiler]);
+		assert!(compiler.join("spcomp").is_file() && !compiler.join("include").exists());
+		assert!(has_components(&compiler, &[Component::Compiler], &compilers));
+		assert!(!has_components(&compiler, &[Component::Includes], &compilers));
+
+		// Files outside of any component, like licenses, are installed either way.
+		let all = install(&[]);
+		assert!(has_components(&all, &[], &compilers));
+		for path in [&includes, &compiler, &all] {
+			assert!(path.join("licenses/sourcepawn/LICENSE.txt").is_file());
+		}
+	});
+}
+
+#[test]
+fn stale_backups_are_restored() {
+	use crate::{
+		source::Layout,
+		test_tar_gz, TestSource,
+	};
+	use std::fs::{
+		read_to_string, write,
+	};
+
+	let provider = TestSource {
+		layout: Layout::SourcePawn,
+		branches: &[],
+		archive: test_tar_gz([("sourcepawn/bin/spcomp", 0o755, "new")]),
+		pointer: None,
+	};
+	crate::with_test_homes(|root| {
+		// The process was killed after moving the previous installation aside, but before moving the new one in.
+		let destination = root.join("1.12.0.7200");
+		let backup = sibling_path(&destination, "backup");
+		create_dir_all(&backup).unwrap();
+		write(backup.join("spcomp"), "old").unwrap();
+		restore_backup(&backup, &destination).unwrap();
+		assert_eq!(read_to_string(destination.join("spcomp")).unwrap(), "old");
+		assert!(!backup.exists());
+
+		// Backups of installations that were replaced anyway are removed, so that the next one can be made.
+		create_dir_all(&backup).unwrap();
+		write(backup.join("spcomp"), "older").unwrap();
+		InstallVersion {
+			provider: &provider,
+			layout: Layout::SourcePawn,
+			os: "linux",
+			compilers: &CompilerNames(vec!["spcomp".into()]),
+			extras: &[],
+			components: &[],
+			deterministic: false,
+			runner: &[],
+			version: "1.12.0.7200",
+			url: "1.12.0.7200.tar.gz",
+			max_bytes: u64::MAX,
+			max_rate: 0,
+			expected_sha256: None,
+			spool_threshold: u64::MAX,
+			limits: smdrop::Limits {
+				total_size: u64::MAX,
+				entry_size: u64::MAX,
+				entries: u64::MAX,
+			},
+			executable_mode: FileMode(0o755),
+			pin_mismatch: PinMismatch::Fail,
+			signing: &Signing::default(),
+			destination: destination.clone(),
+			progress: &|_| {},
+			cancel: CancelToken::default(),
+		}.call().unwrap();
+		assert_eq!(read_to_string(destination.join("spcomp")).unwrap(), "new");
+		assert!(!backup.exists());
+	});
+}
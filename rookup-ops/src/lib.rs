@@ -0,0 +1,112 @@
+//! Operations of the Rookup CLI, like installing, updating, and removing toolchains.
+//!
+//! None of these print anything; progress is reported through callbacks, and results are returned to be displayed by
+//! the frontend.
+
+pub mod disk;
+mod error;
+pub use error::*;
+pub mod hooks;
+pub mod import;
+mod info;
+pub use info::*;
+pub mod install;
+pub mod netrc;
+pub mod package;
+pub mod pins;
+pub mod signing;
+pub mod smdrop;
+mod smdrop_util;
+pub use smdrop_util::*;
+pub mod source;
+mod sp_from_sm;
+mod sp_from_sp;
+pub mod spool;
+
+mod remote;
+pub use remote::*;
+mod toolchains;
+pub use toolchains::*;
+
+/// Run `f` with every home directory of Rookup in a new temporary directory, which is passed to it.
+///
+/// Homes are read from the environment, which all tests share, so tests that use them run one at a time.
+#[cfg(test)]
+pub(crate) fn with_test_homes<R>(f: impl FnOnce(&std::path::Path) -> R) -> R {
+	use rookup_common::{
+		TempDir, CACHE_HOME_VAR, CONFIG_HOME_VAR, CUSTOM_TOOLCHAIN_HOME_VAR, STATE_HOME_VAR, TOOLCHAIN_HOME_VAR,
+	};
+	use std::{
+		env::set_var,
+		sync::{
+			Mutex, PoisonError,
+		},
+	};
+
+	static HOMES: Mutex<()> = Mutex::new(());
+	let _guard = HOMES.lock().unwrap_or_else(PoisonError::into_inner);
+	let root = TempDir::new("rookup-ops-test").unwrap();
+	let homes = [
+		(CONFIG_HOME_VAR, "config"), (CACHE_HOME_VAR, "cache"), (STATE_HOME_VAR, "state"),
+		(TOOLCHAIN_HOME_VAR, "toolchains"), (CUSTOM_TOOLCHAIN_HOME_VAR, "custom"),
+	];
+	for (var, name) in homes {
+		set_var(var, root.join(name));
+	}
+	f(root.path())
+}
+
+/// Return a `.tar.gz` archive of `entries`, which are the paths of files with their permissions and contents.
+#[cfg(test)]
+pub(crate) fn test_tar_gz<P, C>(entries: impl IntoIterator<Item = (P, u32, C)>) -> Vec<u8>
+where
+	P: AsRef<std::path::Path>,
+	C: AsRef<[u8]>,
+{
+	use flate2::{
+		write::GzEncoder, Compression,
+	};
+
+	let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+	for (path, mode, contents) in entries {
+		let contents = contents.as_ref();
+		let mut header = tar::Header::new_gnu();
+		header.set_size(contents.len() as _);
+		header.set_mode(mode);
+		header.set_cksum();
+		builder.append_data(&mut header, path, contents).unwrap();
+	}
+	builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// Source that lists the same `archive` for every version of its branches, at `https://example.com/<version>.tar.gz`.
+#[cfg(test)]
+pub(crate) struct TestSource {
+	pub layout: source::Layout,
+	/// Names of the branches, with the versions of each.
+	pub branches: &'static [(&'static str, &'static [&'static str])],
+	pub archive: Vec<u8>,
+	/// What the `latest` pointer of every branch refers to.
+	pub pointer: Option<source::LatestPointer>,
+}
+
+#[cfg(test)]
+impl source::Provider for TestSource {
+	fn layout(&self) -> source::Layout {
+		self.layout
+	}
+	fn branch_names(&self) -> anyhow::Result<Vec<String>> {
+		Ok(self.branches.iter().map(move |(branch, _)| String::from(*branch)).collect())
+	}
+	fn relevant_urls(&self, branch: &str, _os: &str) -> anyhow::Result<Vec<RelevantUrl>> {
+		let versions = self.branches.iter().find(move |(b, _)| *b == branch).map_or(&[][..], move |(_, v)| *v);
+		let url = move |v: &&str| RelevantUrl::with_version(format!("https://example.com/{v}.tar.gz"), *v);
+		Ok(versions.iter().map(url).collect())
+	}
+	fn fetch(&self, _url: &str, _max_bytes: u64) -> anyhow::Result<Box<dyn std::io::Read>> {
+		Ok(Box::new(std::io::Cursor::new(self.archive.clone())))
+	}
+	fn latest_pointer(&self, _branch: &str, _os: &str) -> anyhow::Result<Option<source::LatestPointer>> {
+		Ok(self.pointer.clone())
+	}
+}
@@ -0,0 +1,112 @@
+//! Credentials for remote hosts from the user's `.netrc` file, as used by other tools that fetch files.
+
+use base64::{
+	engine::general_purpose::STANDARD,
+	Engine,
+};
+use rookup_common::netrc_path;
+use std::fs::read_to_string;
+
+/// Contents of a `.netrc` file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Netrc {
+	entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Entry {
+	/// Host that the entry is for, or [`None`] for the `default` entry.
+	machine: Option<String>,
+	credentials: Credentials,
+}
+
+/// Login name and password for a host.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Credentials {
+	pub login: String,
+	pub password: String,
+}
+
+impl Credentials {
+	/// Return the value of an `Authorization` header that authenticates with these credentials.
+	pub fn basic_auth(&self) -> String {
+		format!("Basic {}", STANDARD.encode(format!("{}:{}", self.login, self.password)))
+	}
+}
+
+impl Netrc {
+	/// Read the `.netrc` file (see [`netrc_path`]), returning an empty one if it doesn't exist or can't be read.
+	pub fn read() -> Self {
+		netrc_path()
+			.and_then(move |path| read_to_string(path).ok())
+			.map(move |text| Self::parse(&text))
+			.unwrap_or_default()
+	}
+
+	/// Parse the contents of a `.netrc` file.
+	///
+	/// Macro definitions (`macdef`) and `account` tokens are skipped, and tokens starting with `#` start comments.
+	pub fn parse(text: &str) -> Self {
+		let mut entries = Vec::new();
+		let mut current: Option<Entry> = None;
+		let mut lines = text.lines();
+		while let Some(line) = lines.next() {
+			let mut tokens = line.split_whitespace();
+			while let Some(token) = tokens.next() {
+				match token {
+					"machine" | "default" => {
+						entries.extend(current.take());
+						current = Some(Entry {
+							machine: (token == "machine").then(|| tokens.next().unwrap_or_default().to_string()),
+							..Default::default()
+						});
+					}
+					"login" => if let (Some(entry), Some(login)) = (current.as_mut(), tokens.next()) {
+						entry.credentials.login = login.into();
+					}
+					"password" => if let (Some(entry), Some(password)) = (current.as_mut(), tokens.next()) {
+						entry.credentials.password = password.into();
+					}
+					"account" => {
+						tokens.next();
+					}
+					"macdef" => {
+						// The macro goes on until the next empty line.
+						lines.by_ref().take_while(move |l| !l.trim().is_empty()).for_each(drop);
+						break
+					}
+					comment if comment.starts_with('#') => break,
+					_ => {}
+				}
+			}
+		}
+		entries.extend(current);
+		Self {
+			entries,
+		}
+	}
+
+	/// Return the credentials for `host`, or those of the `default` entry if there are none specifically for it.
+	pub fn get(&self, host: &str) -> Option<&Credentials> {
+		let specific = self.entries.iter()
+			.find(move |e| e.machine.as_deref().is_some_and(move |m| m.eq_ignore_ascii_case(host)));
+		specific.or_else(|| self.entries.iter().find(move |e| e.machine.is_none()))
+			.map(move |e| &e.credentials)
+	}
+}
+
+#[test]
+fn netrc_entries_are_found() {
+	let netrc = Netrc::parse(concat!(
+		"# Mirrors\n",
+		"machine mirror.example.com login ci password s3cr#t\n",
+		"machine Files.Example.com\n  login user\n  account x\n  password hunter2 # old\n",
+		"macdef init\nmachine evil.example.com login no\n\n",
+		"default login anonymous password guest\n",
+	));
+	assert_eq!(netrc.get("mirror.example.com").map(move |c| c.password.as_str()), Some("s3cr#t"));
+	assert_eq!(netrc.get("files.example.com").map(move |c| c.login.as_str()), Some("user"));
+	assert_eq!(netrc.get("evil.example.com").map(move |c| c.login.as_str()), Some("anonymous"));
+	assert_eq!(netrc.get("files.example.com").unwrap().basic_auth(), "Basic dXNlcjpodW50ZXIy");
+	assert_eq!(Netrc::parse("machine a.example.com login a\n").get("b.example.com"), None);
+}
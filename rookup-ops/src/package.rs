@@ -0,0 +1,175 @@
+//! Archives of installed toolchains, which can be installed on other machines (for e.g. ones without network access)
+//! with [`LocalArchive`](crate::source::LocalArchive).
+
+use anyhow::{
+	anyhow, bail, Context, Result as AResult,
+};
+use clean_path::clean;
+use flate2::{
+	write::GzEncoder, Compression,
+};
+use rookup_common::{
+	Receipt, RECEIPT_FILE,
+};
+use std::{
+	fs::{
+		remove_file, File,
+	},
+	io::{
+		copy as io_copy,
+		Read, Write,
+	},
+	path::{
+		Path, PathBuf,
+	},
+	str::FromStr,
+};
+use tar::{
+	Builder as TarBuilder, Header as TarHeader, HeaderMode,
+};
+use zip::{
+	write::SimpleFileOptions, CompressionMethod, ZipWriter,
+};
+
+use crate::{
+	smdrop::{
+		Archive, ArchiveKind, Limits, Seekable,
+	},
+	toolchains::toolchain_files,
+};
+
+/// Write the toolchain at `path` into a new archive at `archive`, returning the number of files in it.
+///
+/// The format of the archive (`.tar.gz` or `.zip`) is determined by its name. `receipt` is written as the install
+/// receipt of the archive instead of the one of the toolchain, and comes first so that it can be found quickly. Files
+/// are stored with normalized permissions and times, so exporting the same toolchain gives the same archive, and
+/// symbolic links are stored as the files that they point to, since installing toolchains doesn't create links.
+pub fn export_toolchain(path: &Path, receipt: &Receipt, archive: &Path) -> AResult<usize> {
+	let name = archive.to_string_lossy();
+	let Ok(kind) = ArchiveKind::from_str(&name) else {
+		bail!("{archive:?} doesn't end with `.tar.gz` or `.zip`")
+	};
+	let receipt = receipt.to_toml()?;
+	let files: Vec<_> = toolchain_files(path)
+		.with_context(|| anyhow!("failed to list files of {path:?}"))?
+		.into_iter()
+		.filter(move |(name, ..)| name != RECEIPT_FILE)
+		.collect();
+
+	let file = File::create(archive).with_context(|| anyhow!("failed to create {archive:?}"))?;
+	let result = match kind {
+		ArchiveKind::TarGz => write_tar_gz(file, &receipt, &files),
+		ArchiveKind::Zip => write_zip(file, &receipt, &files),
+	};
+	if let Err(e) = result {
+		let _ = remove_file(archive);
+		return Err(e.context(format!("failed to write {archive:?}")))
+	}
+	Ok(files.len())
+}
+
+fn write_tar_gz(file: File, receipt: &str, files: &[(String, PathBuf, bool)]) -> AResult<()> {
+	let mut builder = TarBuilder::new(GzEncoder::new(file, Compression::default()));
+	builder.mode(HeaderMode::Deterministic);
+
+	let mut header = TarHeader::new_gnu();
+	header.set_size(receipt.len() as _);
+	header.set_mode(0o644);
+	header.set_cksum();
+	builder.append_data(&mut header, RECEIPT_FILE, receipt.as_bytes())?;
+	for (name, path, _) in files {
+		builder.append_path_with_name(path, name)
+			.with_context(|| anyhow!("failed to add {path:?}"))?;
+	}
+	builder.into_inner()?.finish()?;
+	Ok(())
+}
+
+fn write_zip(file: File, receipt: &str, files: &[(String, PathBuf, bool)]) -> AResult<()> {
+	let mut zip = ZipWriter::new(file);
+	let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+	zip.start_file(RECEIPT_FILE, options.unix_permissions(0o644))?;
+	zip.write_all(receipt.as_bytes())?;
+	for (name, path, _) in files {
+		zip.start_file(name, options.unix_permissions(file_mode(path)))?;
+		let mut file = File::open(path).with_context(|| anyhow!("failed to open {path:?}"))?;
+		io_copy(&mut file, &mut zip).with_context(|| anyhow!("failed to add {path:?}"))?;
+	}
+	zip.finish()?;
+	Ok(())
+}
+
+/// Return the permissions that the file at `path` is stored with in zip archives, which are `0o755` for executable
+/// files and `0o644` for others.
+fn file_mode(path: &Path) -> u32 {
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		let executable = path.metadata().is_ok_and(move |m| m.permissions().mode() & 0o111 != 0);
+		if executable { 0o755 } else { 0o644 }
+	}
+	// Compilers are recognized by their names when the archive is installed.
+	#[cfg(not(unix))]
+	{
+		let _ = path;
+		0o644
+	}
+}
+
+/// Return the install receipt in the toolchain archive at `path`, which was written by [`export_toolchain`].
+pub fn archive_receipt(path: &Path, limits: Limits) -> AResult<Receipt> {
+	let kind = ArchiveKind::from_str(&path.to_string_lossy())
+		.with_context(|| anyhow!("failed to determine format of archive at {path:?}"))?;
+	let file = File::open(path).with_context(|| anyhow!("failed to open archive at {path:?}"))?;
+	let mut archive = Archive::new(Seekable(file), kind)
+		.with_context(|| anyhow!("failed to open archive at {path:?}"))?;
+	for entry in archive.entries(limits)? {
+		let (name, mut entry) = entry.with_context(|| anyhow!("failed to read archive at {path:?}"))?;
+		if clean(&name) != Path::new(RECEIPT_FILE) {
+			continue
+		}
+		let mut text = String::new();
+		entry.read_to_string(&mut text)
+			.with_context(|| anyhow!("failed to read {RECEIPT_FILE} in archive at {path:?}"))?;
+		return Receipt::parse(&text)
+			.with_context(|| anyhow!("failed to read {RECEIPT_FILE} in archive at {path:?}"))
+	}
+	bail!("archive at {path:?} has no {RECEIPT_FILE}, so it wasn't exported by Rookup")
+}
+
+#[test]
+fn exported_receipt_is_read_back() {
+	use rookup_common::{
+		Origin, TempDir,
+	};
+	use std::fs::{
+		create_dir_all, write,
+	};
+
+	let root = TempDir::new("rookup-package-test").unwrap();
+	let toolchain = root.join("mine");
+	create_dir_all(toolchain.join("include")).unwrap();
+	write(toolchain.join("include/sourcemod.inc"), "#pragma once\n").unwrap();
+	write(toolchain.join(RECEIPT_FILE), "stale").unwrap();
+	let receipt = Receipt {
+		version: "mine".into(),
+		origin: Origin::Directory { path: toolchain.display().to_string() },
+		target_os: None,
+		components: Vec::new(),
+		signature: None,
+		compiler: None,
+		runner: None,
+	};
+	let limits = Limits {
+		total_size: 1 << 20,
+		entry_size: 1 << 20,
+		entries: 16,
+	};
+	for name in ["mine.tar.gz", "mine.zip"] {
+		let archive = root.join(name);
+		assert_eq!(export_toolchain(&toolchain, &receipt, &archive).unwrap(), 1);
+		assert_eq!(archive_receipt(&archive, limits).unwrap(), receipt);
+	}
+	assert!(export_toolchain(&toolchain, &receipt, &root.join("mine.tar")).is_err());
+}
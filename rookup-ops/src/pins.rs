@@ -0,0 +1,88 @@
+//! Trust-on-first-use database of archive checksums.
+
+use anyhow::{
+	anyhow, Context, Result as AResult,
+};
+use rookup_common::{
+	toml_edit::{
+		de::from_str,
+		ser::to_string_pretty,
+	},
+	config_home, pins_file_path,
+};
+use serde::{
+	Deserialize, Serialize,
+};
+use std::{
+	collections::BTreeMap,
+	fs::{
+		create_dir_all, read_to_string, write,
+	},
+	io::ErrorKind as IoErrorKind,
+	path::PathBuf,
+};
+
+/// Database of SHA-256 digests of archives, as observed when they were first downloaded.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PinDatabase {
+	/// Map of archive URLs to their pins.
+	#[serde(default)]
+	pub pins: BTreeMap<String, Pin>,
+}
+
+/// Digest of an archive observed when it was first downloaded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pin {
+	pub version: String,
+	pub sha256: String,
+}
+
+/// Result of comparing a digest against a [`PinDatabase`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PinCheck<'a> {
+	/// Archive was never downloaded before.
+	New,
+	/// Archive has the same digest as when it was first downloaded.
+	Match,
+	/// Archive has a different digest than when it was first downloaded.
+	Mismatch {
+		expected: &'a str,
+	},
+}
+
+impl PinDatabase {
+	/// Return the path to the pin file, or [`None`] if it couldn't be determined.
+	pub fn path() -> Option<PathBuf> {
+		config_home().map(pins_file_path)
+	}
+
+	/// Read the pin file, returning an empty database if it doesn't exist.
+	pub fn open() -> AResult<Self> {
+		let path = Self::path().context("couldn't get path to pin file")?;
+		match read_to_string(&path) {
+			Ok(text) => from_str(&text).with_context(|| anyhow!("failed to parse {path:?}")),
+			Err(e) if e.kind() == IoErrorKind::NotFound => Ok(Self::default()),
+			Err(e) => Err(e).with_context(|| anyhow!("failed to read {path:?}")),
+		}
+	}
+
+	/// Write this database into the pin file.
+	pub fn save(&self) -> AResult<()> {
+		let path = Self::path().context("couldn't get path to pin file")?;
+		if let Some(parent) = path.parent() {
+			create_dir_all(parent).with_context(|| anyhow!("failed to create {parent:?}"))?;
+		}
+		write(&path, to_string_pretty(self)?).with_context(|| anyhow!("failed to write {path:?}"))
+	}
+
+	/// Compare the `sha256` digest of the archive at `url` against its pin.
+	pub fn check(&self, url: &str, sha256: &str) -> PinCheck<'_> {
+		match self.pins.get(url) {
+			None => PinCheck::New,
+			Some(pin) if pin.sha256.eq_ignore_ascii_case(sha256) => PinCheck::Match,
+			Some(pin) => PinCheck::Mismatch {
+				expected: &pin.sha256,
+			},
+		}
+	}
+}
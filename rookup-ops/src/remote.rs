@@ -0,0 +1,574 @@
+//! Installing and updating toolchains from remote sources.
+
+use rookup_common::{
+	version::{
+		version_ord, VersionReq,
+	},
+	find_installed, find_latest_toolchain_of, has_components, is_installed,
+	CompilerNames, Component, Config, ConfigData, Extra, Origin, Receipt, Selector,
+};
+use std::{
+	ffi::OsStr,
+	fmt,
+	path::{
+		Path, PathBuf,
+	},
+};
+
+use crate::{
+	hooks,
+	install::{
+		CancelToken, InstallEvent, InstallVersion,
+	},
+	package::archive_receipt,
+	pins::PinDatabase,
+	smdrop::{
+		self, VersionUrl,
+	},
+	source::{
+		self, DirectUrl, LatestPointer, LocalArchive, Provider,
+	},
+	toolchains::{
+		toolchain_destination, InstalledToolchain,
+	},
+	branch_of, OpsError, RelevantUrl,
+};
+
+/// Parts of toolchains to install.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstallParts {
+	pub extras: Vec<Extra>,
+	/// Components to install, or all of them if empty.
+	pub components: Vec<Component>,
+	/// `true` if installed files should be identical on every machine (see [`InstallVersion::deterministic`]).
+	pub deterministic: bool,
+	/// Command to run the compiler with, or empty to use the `runners` setting (see [`InstallVersion::runner`]).
+	pub runner: Vec<String>,
+}
+
+/// What [`plan_update`] found out about updating the toolchain selected by a selector.
+#[derive(Debug, Clone)]
+pub struct UpdatePlan {
+	/// Remote branch that the selector selects.
+	pub branch: String,
+	/// Versions available on the branch.
+	pub remotes: Vec<RelevantUrl>,
+	/// Latest version available on the branch.
+	pub latest: RelevantUrl,
+	/// Latest version of the branch that is installed, if any.
+	pub installed: Option<String>,
+	/// `true` if the latest version is newer than the installed one, or if none is installed.
+	pub is_upgrade: bool,
+	/// `true` if the latest version has to be downloaded.
+	pub needs_download: bool,
+	/// Name of a toolchain that was installed from the same archive as the latest version, but under another name (for
+	/// e.g. because it was renamed), which is used instead of downloading the archive again.
+	pub same_archive: Option<String>,
+	/// Why the `latest` pointer of the branch couldn't be read, or installed toolchains couldn't be searched for one
+	/// installed from the same archive, in which case the archive is downloaded as usual.
+	pub warnings: Vec<String>,
+}
+
+impl UpdatePlan {
+	/// Return the version that is the latest installed one once the update is applied.
+	pub fn resulting_version(&self) -> &str {
+		match self.installed.as_deref() {
+			Some(installed) if !self.is_upgrade => installed,
+			_ => self.same_archive.as_deref().unwrap_or(self.latest.version()),
+		}
+	}
+}
+
+/// Find the latest version of the remote branch that `selector` selects for the `os` target with `provider`, and
+/// whether it has to be installed with `parts`.
+///
+/// If `redownload` is `true`, the latest version is downloaded even if it's already installed.
+pub fn plan_update(
+	provider: &dyn Provider, data: &ConfigData, selector: Selector<'_>, os: &str, parts: &InstallParts,
+	redownload: bool,
+) -> Result<UpdatePlan, OpsError> {
+	let branch = provider.select_branch(data, selector).map_err(OpsError::Source)?;
+	let remotes = provider.relevant_urls(&branch, os).map_err(OpsError::Source)?;
+	let Some(latest) = remotes.iter().max_by(move |a, b| a.version_ord(b)).cloned() else {
+		return Err(OpsError::NoVersions { branch })
+	};
+
+	let compilers = CompilerNames::new(data, os);
+	let installed = find_latest_toolchain_of(&branch, &compilers).map(move |(v, ..)| v);
+	let is_upgrade = installed.as_ref()
+		.is_none_or(|v| version_ord(v.as_str(), latest.version()).is_lt());
+	// Pointers are only read to avoid downloading the archive again, or to notice that it was rebuilt, so they can't
+	// fail the update either.
+	let mut warnings = Vec::new();
+	let pointer = match redownload {
+		true => None,
+		false => provider.latest_pointer(&branch, os).unwrap_or_else(|e| {
+			warnings.push(format!("couldn't read the latest pointer of branch {branch:?}: {e}"));
+			None
+		}),
+	};
+	let pointer = pointer.filter(|p| p.url == latest.url());
+	// Archives that were uploaded again under the same name are downloaded again if their digest changed.
+	let rebuilt = pointer.as_ref().and_then(move |p| p.sha256.as_deref()).is_some_and(|published| {
+		find_installed(OsStr::new(latest.version()), &parts.components, &compilers)
+			.and_then(move |path| upstream_digest(&path))
+			.is_some_and(move |installed| !installed.eq_ignore_ascii_case(published))
+	});
+	let mut needs_download = redownload
+		|| rebuilt
+		|| (is_upgrade && !is_installed(OsStr::new(latest.version()), &parts.components, &compilers));
+	let same_archive = if needs_download && !redownload {
+		// Reusing toolchains is only done to avoid downloading the archive again, so this can't fail the update.
+		find_installed_from(&latest, pointer.as_ref(), &parts.components, &compilers).unwrap_or_else(|e| {
+			warnings.push(format!("couldn't look for toolchains installed from the same archive: {e}"));
+			None
+		})
+	} else {
+		None
+	};
+	needs_download &= same_archive.is_none();
+	Ok(UpdatePlan {
+		branch,
+		remotes,
+		latest,
+		installed,
+		is_upgrade,
+		needs_download,
+		same_archive,
+		warnings,
+	})
+}
+
+/// Return the name of an installed toolchain with all of `components` (and a compiler named after any of `compilers`)
+/// that was installed from the same archive as `remote`, according to its receipt.
+/// 
+/// If the `latest` pointer of the branch refers to `remote` with a published digest, archives are the same if the
+/// receipt has that digest. Otherwise, they are the same if they have the same URL, or if the digest in the receipt is
+/// the one that was pinned when the archive at the URL of `remote` was first downloaded.
+pub fn find_installed_from(
+	remote: &RelevantUrl, pointer: Option<&LatestPointer>, components: &[Component], compilers: &CompilerNames,
+) -> Result<Option<String>, OpsError> {
+	let published = pointer.filter(move |p| p.url == remote.url()).and_then(move |p| p.sha256.as_deref());
+	let pinned = PinDatabase::open().map_err(OpsError::Pins)?.pins.remove(remote.url()).map(move |pin| pin.sha256);
+	for toolchain in InstalledToolchain::all()? {
+		let Ok(Some(receipt)) = Receipt::read(&toolchain.path) else { continue };
+		let Origin::Upstream { url, sha256 } = &receipt.origin else { continue };
+		let has_digest = move |digest: Option<&str>| {
+			sha256.as_deref().zip(digest).is_some_and(move |(a, b)| a.eq_ignore_ascii_case(b))
+		};
+		let same = match published {
+			Some(published) => has_digest(Some(published)),
+			None => url == remote.url() || has_digest(pinned.as_deref()),
+		};
+		if same && has_components(&toolchain.path, components, compilers) {
+			return Ok(Some(toolchain.name))
+		}
+	}
+	Ok(None)
+}
+
+/// Return the digest of the archive that the toolchain at `path` was downloaded from, according to its receipt.
+fn upstream_digest(path: &Path) -> Option<String> {
+	match Receipt::read(path).ok()??.origin {
+		Origin::Upstream { sha256, .. } => sha256,
+		_ => None,
+	}
+}
+
+/// Where to install a toolchain from.
+#[derive(Debug, Clone, Copy)]
+pub enum InstallTarget<'a> {
+	/// Latest version that the selector selects on the configured source.
+	Selector(Selector<'a>),
+	/// Archive at a URL, which is installed as `as_version` if it's specified, or as the version in its file name.
+	Url {
+		url: &'a str,
+		as_version: Option<&'a str>,
+	},
+	/// Toolchain archive exported by Rookup at a local path, which is installed as the version in its receipt.
+	Archive(&'a Path),
+}
+
+/// What [`plan_install`] found out about installing a toolchain.
+pub struct InstallPlan {
+	/// Provider to download the toolchain with.
+	pub provider: Box<dyn Provider>,
+	/// Remote branch that the selector selects, if the toolchain was selected with one.
+	pub branch: Option<String>,
+	/// Version to install.
+	pub remote: RelevantUrl,
+	/// `true` if the version has to be downloaded.
+	pub needs_download: bool,
+}
+
+/// Find the version that `target` refers to for the `os` target, and whether it has to be installed with `parts`.
+///
+/// If `redownload` is `true`, the version is downloaded even if it's already installed.
+pub fn plan_install(
+	config: &Config, target: InstallTarget<'_>, os: &str, parts: &InstallParts, redownload: bool,
+) -> Result<InstallPlan, OpsError> {
+	let (provider, branch, remote): (Box<dyn Provider>, _, _) = match target {
+		InstallTarget::Url { url, as_version } => {
+			let version = version_of_url(url, as_version)?;
+			(Box::new(DirectUrl::new(config)), None, RelevantUrl::with_version(url, version))
+		}
+		InstallTarget::Archive(path) => {
+			let receipt = archive_receipt(path, archive_limits(config.with_doc.data())).map_err(OpsError::Package)?;
+			let url = path.to_str().ok_or_else(move || OpsError::NonUtf8Path { path: path.to_path_buf() })?;
+			let version = version_of_url(url, Some(&receipt.version))?;
+			(Box::new(LocalArchive), None, RelevantUrl::with_version(url, version))
+		}
+		InstallTarget::Selector(selector) => {
+			let provider = source::provider(config);
+			let (branch, remote) = select_remote_version(&*provider, config.with_doc.data(), selector, os)?;
+			(provider, Some(branch), remote)
+		}
+	};
+	let compilers = CompilerNames::new(config.with_doc.data(), os);
+	let needs_download = redownload || !is_installed(OsStr::new(remote.version()), &parts.components, &compilers);
+	Ok(InstallPlan {
+		provider,
+		branch,
+		remote,
+		needs_download,
+	})
+}
+
+/// Select the version that `selector` refers to out of those available for the `os` target with `provider`,
+/// returning it and the branch that it's on.
+pub fn select_remote_version(
+	provider: &dyn Provider, data: &ConfigData, selector: Selector<'_>, os: &str,
+) -> Result<(String, RelevantUrl), OpsError> {
+	let branch = provider.select_branch(data, selector).map_err(OpsError::Source)?;
+	let version = match selector {
+		Selector::Alias(..) => {
+			provider.latest_url(&branch, os, None).map_err(OpsError::Source)?
+				.ok_or_else(|| OpsError::NoVersions { branch: branch.clone() })?
+		}
+		Selector::Super(requested) => {
+			let req: VersionReq = requested.parse()
+				.map_err(move |error| OpsError::InvalidRequirement { requested: requested.into(), error })?;
+			provider.latest_url(&branch, os, Some(&req)).map_err(OpsError::Source)?
+				.ok_or_else(|| OpsError::VersionNotFound { requested: requested.into(), branch: branch.clone() })?
+		}
+		Selector::Path(path) => return Err(OpsError::NotRemote { path: path.into() }),
+	};
+	Ok((branch, version))
+}
+
+/// Return the version that the archive at `url` should be installed as, which is `as_version` if specified.
+pub fn version_of_url(url: &str, as_version: Option<&str>) -> Result<String, OpsError> {
+	let url = VersionUrl(url);
+	let version = match as_version {
+		Some(version) => version.to_string(),
+		None => url.version_str().map(move |v| v.normalized().into_owned())
+			.ok_or_else(move || OpsError::UnknownVersion { file_name: url.file_name().into() })?,
+	};
+	if version.is_empty() || version.starts_with('.') || version.contains(std::path::is_separator) {
+		return Err(OpsError::InvalidVersionName { version })
+	}
+	Ok(version)
+}
+
+/// Installation of the toolchain at [`remote`](Self::remote) into the toolchain home (or another directory), followed
+/// by running the `post-install` hooks.
+pub struct InstallRemote<'a> {
+	pub provider: &'a dyn Provider,
+	pub data: &'a ConfigData,
+	pub os: &'a str,
+	pub parts: &'a InstallParts,
+	pub remote: &'a RelevantUrl,
+	/// SHA-256 digest that the archive must have, as a hexadecimal string.
+	pub expected_sha256: Option<&'a str>,
+	/// Directory to install the toolchain into, or [`None`] for its directory in the toolchain home.
+	/// 
+	/// Whatever is in the directory is replaced.
+	pub destination: Option<PathBuf>,
+	/// Function to report progress of the installation to.
+	pub progress: &'a dyn Fn(InstallEvent<'_>),
+	/// Token that is checked while downloading and extracting the archive, to abort the installation early.
+	pub cancel: CancelToken,
+}
+
+impl InstallRemote<'_> {
+	/// Download and install the toolchain, returning the path that it was installed to.
+	pub fn call(self) -> Result<PathBuf, OpsError> {
+		let Self { provider, data, os, parts, remote, expected_sha256, destination, progress, cancel } = self;
+		// Archives whose size is listed by the source aren't downloaded at all if they're too large.
+		let max_bytes = data.source.max_download_size;
+		if let Some(size) = remote.size().filter(move |&size| size > max_bytes) {
+			return Err(OpsError::TooLarge { url: remote.url().into(), size, max_bytes })
+		}
+		let destination = match destination {
+			Some(destination) => destination,
+			None => toolchain_destination(remote.version())?,
+		};
+		InstallVersion {
+			provider,
+			layout: provider.layout(),
+			os,
+			compilers: &CompilerNames::new(data, os),
+			extras: &parts.extras,
+			components: &parts.components,
+			deterministic: parts.deterministic,
+			runner: &parts.runner,
+			version: remote.version(),
+			url: remote.url(),
+			max_bytes,
+			max_rate: data.source.max_download_rate,
+			expected_sha256,
+			spool_threshold: data.source.spool_threshold,
+			limits: archive_limits(data),
+			executable_mode: data.source.executable_mode,
+			pin_mismatch: data.source.pin_mismatch,
+			signing: &data.source.signing,
+			destination: destination.clone(),
+			progress,
+			cancel,
+		}.call()?;
+		hooks::post_install(&data.hooks, remote.version(), &destination, &move |command| {
+			progress(InstallEvent::Hook { command })
+		}).map_err(OpsError::Hook)?;
+		Ok(destination)
+	}
+}
+
+/// Return the limits on the contents of archives set in the `source` table of `data`.
+pub fn archive_limits(data: &ConfigData) -> smdrop::Limits {
+	smdrop::Limits {
+		total_size: data.source.max_unpacked_size,
+		entry_size: data.source.max_entry_size,
+		entries: data.source.max_entries,
+	}
+}
+
+/// Enumeration of states of a version compared to the remote, as shown by `rookup show --remote`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RemoteStatus {
+	/// Installed, and at least as new as the latest version of its branch.
+	Installed,
+	/// Installed, but there's a newer version of its branch.
+	Outdated,
+	/// Not installed, but available on the remote.
+	Available,
+}
+
+impl fmt::Display for RemoteStatus {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Installed => "installed",
+			Self::Outdated => "outdated",
+			Self::Available => "available",
+		})
+	}
+}
+
+/// Installed toolchain or remote version, compared to the latest version of its branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteComparison {
+	pub version: String,
+	pub status: RemoteStatus,
+	/// Latest version of the branch, if it's known.
+	pub latest: Option<String>,
+	/// Path to the toolchain, if it's installed.
+	pub path: Option<PathBuf>,
+}
+
+/// Compare the installed toolchains with the latest versions of every branch for the `os` target available with
+/// `provider`, which are also included if no version of their branch that is at least as new is installed.
+pub fn compare_with_remote(provider: &dyn Provider, os: &str) -> Result<Vec<RemoteComparison>, OpsError> {
+	let latest = source::latest_versions(provider, provider.branch_names().map_err(OpsError::Source)?, os);
+
+	let installed = InstalledToolchain::all()?;
+	let mut rows: Vec<RemoteComparison> = installed.iter()
+		.map(|toolchain| {
+			let remote = branch_of(&toolchain.name).and_then(|b| latest.get(&b));
+			let status = match remote {
+				Some(r) if version_ord(r.version(), toolchain.name.as_str()).is_gt() => RemoteStatus::Outdated,
+				_ => RemoteStatus::Installed,
+			};
+			RemoteComparison {
+				version: toolchain.name.clone(),
+				status,
+				latest: remote.map(move |r| r.version().to_string()),
+				path: Some(toolchain.path.clone()),
+			}
+		})
+		.collect();
+	rows.extend(
+		latest.values()
+			.filter(|r| !installed.iter().any(move |t| {
+				branch_of(&t.name) == branch_of(r.version())
+					&& version_ord(t.name.as_str(), r.version()).is_ge()
+			}))
+			.map(move |r| RemoteComparison {
+				version: r.version().to_string(),
+				status: RemoteStatus::Available,
+				latest: Some(r.version().to_string()),
+				path: None,
+			})
+	);
+	rows.sort_by(move |a, b| version_ord(a.version.as_str(), b.version.as_str()));
+	Ok(rows)
+}
+
+#[test]
+fn updates_are_planned_and_installed() {
+	use rookup_common::RECEIPT_FILE;
+
+	use crate::{
+		source::Layout,
+		test_tar_gz, TestSource,
+	};
+
+	let data = ConfigData::default();
+	let os = std::env::consts::OS;
+	let compilers = CompilerNames::new(&data, os);
+	let receipt = Receipt {
+		version: "1.12.0.7200".into(),
+		origin: Origin::Upstream { url: "https://example.com/1.12.0.7200.tar.gz".into(), sha256: None },
+		target_os: Some(os.into()),
+		signature: None,
+		components: Vec::new(),
+		compiler: Some(compilers.preferred().into()),
+		runner: None,
+	}.to_toml().unwrap();
+	let provider = TestSource {
+		layout: Layout::Toolchain,
+		branches: &[("1.11", &["1.11.0.6970"]), ("1.12", &["1.12.0.7100", "1.12.0.7200"])],
+		archive: test_tar_gz([
+			(compilers.preferred(), 0o755, ""), ("include/a.inc", 0o644, ""), (RECEIPT_FILE, 0o644, &receipt),
+		]),
+		pointer: None,
+	};
+	let parts = InstallParts {
+		extras: Vec::new(),
+		components: Vec::new(),
+		deterministic: false,
+		runner: vec!["wine".into()],
+	};
+
+	crate::with_test_homes(|root| {
+		let plan = plan_update(&provider, &data, Selector::Super("1.12"), os, &parts, false).unwrap();
+		assert_eq!((plan.branch.as_str(), plan.latest.version()), ("1.12", "1.12.0.7200"));
+		assert_eq!(plan.installed, None);
+		assert!(plan.is_upgrade && plan.needs_download);
+		let statuses = move |rows: Vec<RemoteComparison>| {
+			rows.into_iter().map(move |r| (r.version, r.status)).collect::<Vec<_>>()
+		};
+		assert_eq!(statuses(compare_with_remote(&provider, os).unwrap()), [
+			("1.11.0.6970".into(), RemoteStatus::Available), ("1.12.0.7200".into(), RemoteStatus::Available),
+		]);
+
+		let install = |remote: &RelevantUrl| InstallRemote {
+			provider: &provider,
+			data: &data,
+			os,
+			parts: &parts,
+			remote,
+			expected_sha256: None,
+			destination: None,
+			progress: &|_| {},
+			cancel: CancelToken::default(),
+		}.call();
+		let too_large = plan.latest.clone().with_size(Some(u64::MAX));
+		assert!(matches!(install(&too_large), Err(OpsError::TooLarge { size: u64::MAX, .. })));
+		let path = install(&plan.latest).unwrap();
+		assert_eq!(path, root.join("toolchains").join("1.12.0.7200"));
+		assert!(is_installed(OsStr::new("1.12.0.7200"), &[], &compilers));
+		assert_eq!(Receipt::read(&path).unwrap().unwrap().runner, Some(vec!["wine".into()]));
+
+		let plan = plan_update(&provider, &data, Selector::Super("1.12"), os, &parts, false).unwrap();
+		assert_eq!(plan.installed.as_deref(), Some("1.12.0.7200"));
+		assert!(!plan.is_upgrade && !plan.needs_download);
+		assert_eq!(plan.resulting_version(), "1.12.0.7200");
+		assert!(plan_update(&provider, &data, Selector::Super("1.12"), os, &parts, true).unwrap().needs_download);
+		assert_eq!(statuses(compare_with_remote(&provider, os).unwrap()), [
+			("1.11.0.6970".into(), RemoteStatus::Available), ("1.12.0.7200".into(), RemoteStatus::Installed),
+		]);
+
+		// Toolchains installed from the same archive are used instead of downloading it again, even if renamed.
+		std::fs::rename(&path, root.join("toolchains").join("work")).unwrap();
+		let plan = plan_update(&provider, &data, Selector::Super("1.12"), os, &parts, false).unwrap();
+		assert_eq!((plan.same_archive.as_deref(), plan.needs_download), (Some("work"), false));
+		assert_eq!((plan.resulting_version(), plan.warnings.as_slice()), ("work", &[][..]));
+		// Without a readable pin database, the archive is downloaded again instead.
+		let pins = root.join("config").join("pins.toml");
+		std::fs::create_dir_all(pins.parent().unwrap()).unwrap();
+		std::fs::write(&pins, "pins = 1").unwrap();
+		let plan = plan_update(&provider, &data, Selector::Super("1.12"), os, &parts, false).unwrap();
+		assert_eq!((plan.same_archive, plan.needs_download), (None, true));
+		assert_eq!(plan.warnings.len(), 1);
+		assert!(matches!(
+			plan_update(&provider, &data, Selector::Super("1.13"), os, &parts, false),
+			Err(OpsError::Source(..)),
+		));
+	});
+}
+
+#[test]
+fn latest_pointers_are_compared_with_receipts() {
+	use sha2::{
+		Digest, Sha256,
+	};
+
+	use crate::{
+		source::Layout,
+		hex, test_tar_gz, TestSource,
+	};
+
+	let data = ConfigData::default();
+	let os = std::env::consts::OS;
+	let archive = test_tar_gz([("sourcepawn/bin/spcomp", 0o755, ""), ("sourcepawn/include/a.inc", 0o644, "")]);
+	let pointer = move |sha256: &str| Some(LatestPointer {
+		url: "https://example.com/1.12.0.7200.tar.gz".into(),
+		sha256: Some(sha256.into()),
+	});
+	let published = hex(&Sha256::digest(&archive));
+	let provider = TestSource {
+		layout: Layout::SourcePawn,
+		branches: &[("1.12", &["1.12.0.7200"])],
+		archive,
+		pointer: pointer(&published.to_uppercase()),
+	};
+	let parts = InstallParts {
+		extras: Vec::new(),
+		components: Vec::new(),
+		deterministic: false,
+		runner: Vec::new(),
+	};
+
+	crate::with_test_homes(|root| {
+		let plan = plan_update(&provider, &data, Selector::Super("1.12"), os, &parts, false).unwrap();
+		let path = InstallRemote {
+			provider: &provider,
+			data: &data,
+			os,
+			parts: &parts,
+			remote: &plan.latest,
+			expected_sha256: None,
+			destination: None,
+			progress: &|_| {},
+			cancel: CancelToken::default(),
+		}.call().unwrap();
+		let plan = plan_update(&provider, &data, Selector::Super("1.12"), os, &parts, false).unwrap();
+		assert!(!plan.needs_download);
+
+		// A renamed toolchain is only used if its receipt has the digest published next to the pointer.
+		std::fs::rename(&path, root.join("toolchains").join("work")).unwrap();
+		let plan = plan_update(&provider, &data, Selector::Super("1.12"), os, &parts, false).unwrap();
+		assert_eq!((plan.same_archive.as_deref(), plan.needs_download), (Some("work"), false));
+		let rebuilt = TestSource {
+			archive: provider.archive.clone(),
+			pointer: pointer(&"0".repeat(64)),
+			..provider
+		};
+		let plan = plan_update(&rebuilt, &data, Selector::Super("1.12"), os, &parts, false).unwrap();
+		assert_eq!((plan.same_archive, plan.needs_download), (None, true));
+
+		// An archive that was rebuilt under the same URL is downloaded again, even if its version is installed.
+		std::fs::rename(root.join("toolchains").join("work"), &path).unwrap();
+		let plan = plan_update(&rebuilt, &data, Selector::Super("1.12"), os, &parts, false).unwrap();
+		assert_eq!((plan.installed.as_deref(), plan.needs_download), (Some("1.12.0.7200"), true));
+	});
+}
@@ -0,0 +1,172 @@
+//! Verification of signatures published alongside archives.
+
+use anyhow::{
+	anyhow, bail, Context, Result as AResult,
+};
+use minisign_verify::{
+	PublicKey, Signature,
+};
+use rookup_common::{
+	Signing, VerifiedSignature,
+};
+use std::{
+	env::temp_dir,
+	io::{
+		copy as io_copy,
+		Read, Write,
+	},
+	process::{
+		Command, Stdio,
+	},
+};
+
+use crate::{
+	smdrop::ReadSeek,
+	source::{
+		is_not_found, Provider,
+	},
+	spool::TempFile,
+};
+
+/// Maximum size of a signature file in bytes.
+const MAX_SIGNATURE_BYTES: u64 = 64 * 1024;
+
+/// Verify the signatures published next to the archive at `url` with the contents `body`.
+///
+/// `body` is read from the start, and may be read multiple times.
+/// Returns `None` if no signature was found and signatures aren't required by `signing`.
+pub fn verify(
+	provider: &dyn Provider, signing: &Signing, url: &str, body: &mut dyn ReadSeek,
+) -> AResult<Option<VerifiedSignature>> {
+	if !signing.minisign_keys.is_empty() {
+		if let Some(signature) = fetch_signature(provider, &format!("{url}.minisig"), signing.required)? {
+			return verify_minisign(signing, url, body, &signature).map(Some)
+		}
+	}
+
+	if signing.gpg_keyring.is_some() {
+		if let Some(signature) = fetch_signature(provider, &format!("{url}.asc"), signing.required)? {
+			return verify_gpg(signing, url, body, &signature).map(Some)
+		}
+	}
+
+	if signing.required {
+		bail!("archive at {url:?} has no signature that can be verified with the configured keys, but signatures are required")
+	}
+	Ok(None)
+}
+
+/// Download the signature at `url`, returning [`None`] if it isn't published.
+/// 
+/// Other failures to download it are only treated the same way if signatures aren't `required`, so that a signature
+/// that couldn't be downloaded isn't mistaken for a missing one.
+fn fetch_signature(provider: &dyn Provider, url: &str, required: bool) -> AResult<Option<String>> {
+	let mut signature = String::new();
+	let result = provider.fetch(url, MAX_SIGNATURE_BYTES)
+		.and_then(|mut reader| Ok(reader.read_to_string(&mut signature)?));
+	match result {
+		Ok(..) => Ok(Some(signature)),
+		Err(e) if is_not_found(&e) || !required => Ok(None),
+		Err(e) => Err(e.context(format!("failed to download signature at {url:?}"))),
+	}
+}
+
+fn verify_minisign(signing: &Signing, url: &str, body: &mut dyn ReadSeek, signature: &str) -> AResult<VerifiedSignature> {
+	let signature = Signature::decode(signature)
+		.with_context(|| anyhow!("failed to parse minisign signature of {url:?}"))?;
+	for key in signing.minisign_keys.iter() {
+		let public_key = PublicKey::from_base64(key)
+			.with_context(|| anyhow!("failed to parse minisign public key {key:?}"))?;
+		// Legacy signatures of unhashed archives aren't supported, so the archive can always be streamed.
+		let Ok(mut verifier) = public_key.verify_stream(&signature) else {
+			continue
+		};
+		body.rewind().with_context(|| anyhow!("failed to read archive at {url:?}"))?;
+		let mut buffer = [0; 8192];
+		loop {
+			let n = body.read(&mut buffer).with_context(|| anyhow!("failed to read archive at {url:?}"))?;
+			if n == 0 {
+				break
+			}
+			verifier.update(&buffer[..n]);
+		}
+		if verifier.finalize().is_ok() {
+			return Ok(VerifiedSignature::Minisign {
+				key: key.clone(),
+			})
+		}
+	}
+	bail!("minisign signature of archive at {url:?} is not valid for any of the configured keys")
+}
+
+fn verify_gpg(signing: &Signing, url: &str, body: &mut dyn ReadSeek, signature: &str) -> AResult<VerifiedSignature> {
+	let Some(keyring) = signing.gpg_keyring.as_deref() else {
+		bail!("no OpenPGP keyring is configured")
+	};
+
+	// The file is deleted when dropped, including if anything below fails.
+	let mut signature_file = TempFile::create(&temp_dir(), "rookup-signature")
+		.context("failed to create a temporary file for the signature")?;
+	signature_file.file().write_all(signature.as_bytes())
+		.context("failed to write signature to a temporary file")?;
+
+	let status = Command::new("gpgv")
+		.arg("--keyring").arg(keyring)
+		.arg(signature_file.path())
+		.arg("-")
+		.stdin(Stdio::piped())
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.spawn()
+		.and_then(move |mut child| {
+			if let Some(mut stdin) = child.stdin.take() {
+				body.rewind()?;
+				io_copy(body, &mut stdin)?;
+			}
+			child.wait()
+		});
+	drop(signature_file);
+
+	let status = status.context("failed to run `gpgv`")?;
+	if !status.success() {
+		bail!("OpenPGP signature of archive at {url:?} is not valid for any key in {keyring:?}")
+	}
+	Ok(VerifiedSignature::Gpg {
+		keyring: keyring.display().to_string(),
+	})
+}
+
+#[test]
+fn signature_download_failures_are_kept_apart() {
+	use crate::{
+		smdrop_util::RelevantUrl,
+		source::Layout,
+	};
+
+	struct Signatures;
+	impl Provider for Signatures {
+		fn layout(&self) -> Layout {
+			Layout::Toolchain
+		}
+		fn branch_names(&self) -> AResult<Vec<String>> {
+			Ok(Vec::new())
+		}
+		fn relevant_urls(&self, _branch: &str, _os: &str) -> AResult<Vec<RelevantUrl>> {
+			Ok(Vec::new())
+		}
+		fn fetch(&self, url: &str, _max_bytes: u64) -> AResult<Box<dyn Read>> {
+			match url {
+				"published.asc" => Ok(Box::new(&b"signature"[..])),
+				"missing.asc" => Err(std::io::Error::from(std::io::ErrorKind::NotFound).into()),
+				_ => bail!("connection reset"),
+			}
+		}
+	}
+
+	for required in [false, true] {
+		assert_eq!(fetch_signature(&Signatures, "published.asc", required).unwrap().as_deref(), Some("signature"));
+		assert_eq!(fetch_signature(&Signatures, "missing.asc", required).unwrap(), None);
+	}
+	assert_eq!(fetch_signature(&Signatures, "broken.asc", false).unwrap(), None);
+	assert!(fetch_signature(&Signatures, "broken.asc", true).is_err());
+}
@@ -0,0 +1,477 @@
+use flate2::read::GzDecoder;
+use std::{
+	borrow::Cow,
+	fmt,
+	io::{
+		Cursor, Read, Seek, Error as IoError, Result as IoResult,
+	},
+	ops::Range,
+	path::{
+		Path, PathBuf,
+	},
+	str::FromStr,
+};
+use tar::{
+	Archive as TarArchive,
+	Entries as TarEntries,
+	Entry as TarEntry,
+};
+use zip::{
+	result::ZipError,
+	ZipArchive,
+};
+
+pub trait ArchiveBody {
+	type Error;
+	fn into_boxed_slice(self) -> Result<Box<[u8]>, Self::Error>;
+	/// Convert the body into a seekable reader for zip archives, which are read into memory by default.
+	#[inline]
+	fn into_seekable(self) -> Result<Box<dyn ReadSeek>, Self::Error>
+	where
+		Self: Sized,
+	{
+		self.into_boxed_slice().map(move |body| Box::new(Cursor::new(body)) as _)
+	}
+	type Reader: Read;
+	fn into_reader(self) -> Self::Reader;
+}
+
+/// Trait for readers that can also be seeked, which can be used as trait objects.
+pub trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek + ?Sized> ReadSeek for T {}
+
+/// [`ArchiveBody`] for a reader that is already seekable, like a file, so that zip archives don't need to be read into
+/// memory.
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct Seekable<R>(pub R);
+
+impl<R: 'static + Read + Seek> ArchiveBody for Seekable<R> {
+	type Error = IoError;
+	#[inline]
+	fn into_boxed_slice(mut self) -> Result<Box<[u8]>, Self::Error> {
+		let mut buffer = Vec::new();
+		self.0.read_to_end(&mut buffer)?;
+		Ok(buffer.into_boxed_slice())
+	}
+	#[inline]
+	fn into_seekable(self) -> Result<Box<dyn ReadSeek>, Self::Error> {
+		Ok(Box::new(self.0))
+	}
+	type Reader = R;
+	#[inline]
+	fn into_reader(self) -> Self::Reader {
+		self.0
+	}
+}
+
+impl ArchiveBody for Box<dyn Read> {
+	type Error = IoError;
+	#[inline]
+	fn into_boxed_slice(mut self) -> Result<Box<[u8]>, Self::Error> {
+		let mut buffer = Vec::new();
+		self.read_to_end(&mut buffer)?;
+		Ok(buffer.into_boxed_slice())
+	}
+	type Reader = Self;
+	#[inline]
+	fn into_reader(self) -> Self::Reader {
+		self
+	}
+}
+
+impl<'a> ArchiveBody for ureq::BodyWithConfig<'a> {
+	type Error = ureq::Error;
+	#[inline]
+	fn into_boxed_slice(self) -> Result<Box<[u8]>, Self::Error> {
+		self.read_to_vec().map(move |v| v.into_boxed_slice())
+	}
+	type Reader = ureq::BodyReader<'a>;
+	#[inline]
+	fn into_reader(self) -> Self::Reader {
+		self.reader()
+	}
+}
+
+pub enum Archive<R: Read> {
+	Zip(ZipArchive<Box<dyn ReadSeek>>),
+	TarGz(Box<TarArchive<GzDecoder<R>>>),
+}
+
+impl<R: Read> Archive<R> {
+	pub fn new<B>(body: B, kind: ArchiveKind) -> Result<Self, ArchiveError<B::Error>>
+	where
+		B: ArchiveBody<Reader = R>,
+	{
+		match kind {
+			ArchiveKind::Zip => match ZipArchive::new(body.into_seekable()?) {
+				Ok(archive) => Ok(Self::Zip(archive)),
+				Err(error) => Err(match error {
+					ZipError::Io(e) => ArchiveError::Io(e),
+					ZipError::InvalidArchive(m) => ArchiveError::ZipInvalid(m),
+					ZipError::UnsupportedArchive(m) => ArchiveError::ZipUnsupported(m),
+					_ => unreachable!(),
+				}),
+			},
+			ArchiveKind::TarGz => {
+				let archive = TarArchive::new(GzDecoder::new(body.into_reader()));
+				Ok(Self::TarGz(Box::new(archive)))
+			}
+		}
+	}
+
+	/// Return the number of entries in the archive, if it's known before iterating over them.
+	pub fn len(&self) -> Option<usize> {
+		match self {
+			Self::Zip(archive) => Some(archive.len()),
+			Self::TarGz(..) => None,
+		}
+	}
+
+	/// Return `true` if the archive has no entries, if it's known before iterating over them.
+	pub fn is_empty(&self) -> Option<bool> {
+		self.len().map(move |len| len == 0)
+	}
+
+	pub fn entries(&mut self, limits: Limits) -> IoResult<Entries<'_, R>> {
+		let inner = match self {
+			Self::Zip(archive) => EntriesInner::Zip {
+				indices: 0..archive.len(),
+				archive,
+			},
+			Self::TarGz(archive) => EntriesInner::TarGz {
+				entries: archive.entries()?,
+			},
+		};
+		Ok(Entries {
+			inner,
+			limits,
+			count: 0,
+			total_size: 0,
+		})
+	}
+}
+
+/// Limits on the contents of an archive, which protect against decompression bombs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Limits {
+	/// Maximum total size of all entries in bytes.
+	pub total_size: u64,
+	/// Maximum size of a single entry in bytes.
+	pub entry_size: u64,
+	/// Maximum number of entries.
+	pub entries: u64,
+}
+
+/// Error returned by [`Entries`] when an archive exceeds its [`Limits`].
+#[derive(Debug, thiserror::Error)]
+pub enum LimitExceeded {
+	#[error("archive has more than {0} entries")]
+	Entries(u64),
+	#[error("entry {name:?} is larger than {limit} bytes")]
+	EntrySize {
+		name: String,
+		limit: u64,
+	},
+	#[error("archive unpacks to more than {0} bytes")]
+	TotalSize(u64),
+}
+
+/// Error returned by [`Entries`] for an entry that couldn't be read.
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveEntryError {
+	#[error("{0}")]
+	LimitExceeded(#[from] LimitExceeded),
+	#[error("failed to read entry {index} of zip archive: {error}")]
+	Zip {
+		index: usize,
+		error: ZipError,
+	},
+	#[error("failed to read data of entry {name:?}: {error}")]
+	Data {
+		name: String,
+		error: IoError,
+	},
+	#[error("failed to read entry of tar archive: {0}")]
+	Tar(IoError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError<E> {
+	#[error("{0}")]
+	Io(IoError),
+	#[error("{0}")]
+	ZipInvalid(Cow<'static, str>),
+	#[error("{0}")]
+	ZipUnsupported(&'static str),
+	#[error("{0}")]
+	IntoVec(#[from] E),
+}
+
+pub enum Entry<'a, R: 'a + Read> {
+	Zip {
+		cursor: Cursor<Vec<u8>>,
+		is_dir: bool,
+		mode: Option<u32>,
+	},
+	TarGz(Box<TarEntry<'a, GzDecoder<R>>>),
+}
+
+impl<'a, R: 'a + Read> Entry<'a, R> {
+	pub fn size(&self) -> u64 {
+		match self {
+			Self::Zip { cursor, .. } => cursor.get_ref().len() as _,
+			Self::TarGz(i) => i.size(),
+		}
+	}
+
+	pub fn is_dir(&self) -> bool {
+		match self {
+			Self::Zip { is_dir, .. } => *is_dir,
+			Self::TarGz(i) => i.header().entry_type().is_dir(),
+		}
+	}
+
+	/// Return `true` if the name of the entry is valid UTF-8.
+	/// 
+	/// Other names are only converted to paths losslessly on Unix-like systems (see [`path_from_bytes`]).
+	#[cfg_attr(unix, allow(dead_code))]
+	pub fn has_utf8_name(&self) -> bool {
+		match self {
+			// Names of zip entries are always decoded from either UTF-8 or CP437.
+			Self::Zip { .. } => true,
+			Self::TarGz(i) => core::str::from_utf8(&i.path_bytes()).is_ok(),
+		}
+	}
+
+	/// Return `true` if the entry is marked as executable by anyone in the archive.
+	pub fn is_executable(&self) -> bool {
+		let mode = match self {
+			Self::Zip { mode, .. } => *mode,
+			Self::TarGz(i) => i.header().mode().ok(),
+		};
+		mode.is_some_and(move |m| m & 0o111 != 0)
+	}
+}
+
+impl<'a, R: 'a + Read> Read for Entry<'a, R> {
+	#[inline]
+	fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+		match self {
+			Self::Zip { cursor, .. } => cursor.read(buf),
+			Self::TarGz(i) => i.read(buf),
+		}
+	}
+}
+
+/// Convert the raw name of an archive entry to a path.
+/// 
+/// On Unix-like systems, paths are arbitrary bytes, so this is lossless.
+/// Elsewhere, names that aren't valid UTF-8 have their invalid sequences replaced with `U+FFFD`.
+fn path_from_bytes(bytes: Cow<'_, [u8]>) -> PathBuf {
+	#[cfg(unix)]
+	{
+		use std::os::unix::ffi::OsStringExt;
+		std::ffi::OsString::from_vec(bytes.into_owned()).into()
+	}
+	#[cfg(not(unix))]
+	{
+		String::from_utf8_lossy(&bytes).into_owned().into()
+	}
+}
+
+pub struct Entries<'a, R: 'a + Read> {
+	inner: EntriesInner<'a, R>,
+	limits: Limits,
+	count: u64,
+	total_size: u64,
+}
+
+enum EntriesInner<'a, R: 'a + Read> {
+	Zip {
+		archive: &'a mut ZipArchive<Box<dyn ReadSeek>>,
+		indices: Range<usize>,
+	},
+	TarGz {
+		entries: TarEntries<'a, GzDecoder<R>>,
+	},
+}
+
+impl<'a, R: 'a + Read> Iterator for Entries<'a, R> {
+	type Item = Result<(PathBuf, Entry<'a, R>), ArchiveEntryError>;
+	fn next(&mut self) -> Option<Self::Item> {
+		let result = match &mut self.inner {
+			EntriesInner::Zip { archive, indices } => {
+				let index = indices.next()?;
+				Self::next_zip(archive, index, &self.limits, self.total_size)
+			}
+			EntriesInner::TarGz { entries } => match entries.next()? {
+				Ok(entry) => Ok((path_from_bytes(entry.path_bytes()), Entry::TarGz(Box::new(entry)))),
+				Err(e) => Err(ArchiveEntryError::Tar(e)),
+			},
+		};
+		Some(result.and_then(|(name, entry)| {
+			self.check_limits(&name, &entry)?;
+			Ok((name, entry))
+		}))
+	}
+}
+
+impl<'a, R: 'a + Read> Entries<'a, R> {
+	fn next_zip(
+		archive: &mut ZipArchive<Box<dyn ReadSeek>>, index: usize, limits: &Limits, total_size: u64,
+	) -> Result<(PathBuf, Entry<'a, R>), ArchiveEntryError> {
+		let mut file = archive.by_index(index)
+			.map_err(move |error| ArchiveEntryError::Zip { index, error })?;
+		let name = file.name().to_string();
+		// Sizes in zip headers can't be trusted, so read at most one byte more than allowed.
+		let limit = limits.entry_size.min(limits.total_size.saturating_sub(total_size));
+		let mut buffer = Vec::with_capacity(file.size().min(limit) as _);
+		if let Err(error) = (&mut file).take(limit + 1).read_to_end(&mut buffer) {
+			return Err(ArchiveEntryError::Data { name, error })
+		}
+		let entry = Entry::Zip {
+			cursor: Cursor::new(buffer),
+			is_dir: file.is_dir(),
+			mode: file.unix_mode(),
+		};
+		Ok((name.into(), entry))
+	}
+
+	fn check_limits(&mut self, name: &Path, entry: &Entry<'a, R>) -> Result<(), LimitExceeded> {
+		self.count += 1;
+		if self.count > self.limits.entries {
+			return Err(LimitExceeded::Entries(self.limits.entries))
+		}
+		let size = entry.size();
+		if size > self.limits.entry_size {
+			return Err(LimitExceeded::EntrySize {
+				name: name.display().to_string(),
+				limit: self.limits.entry_size,
+			})
+		}
+		self.total_size += size;
+		if self.total_size > self.limits.total_size {
+			return Err(LimitExceeded::TotalSize(self.limits.total_size))
+		}
+		Ok(())
+	}
+}
+
+impl<R: Read + fmt::Debug> fmt::Debug for Archive<R> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Archive::Zip(..) => {
+				// The reader of the archive is a trait object.
+				f.debug_tuple("Archive::Zip").finish_non_exhaustive()
+			}
+			Archive::TarGz(..) => {
+				struct TarArchiveDbg;
+				impl fmt::Debug for TarArchiveDbg {
+					fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+						f.debug_struct("TarArchive").finish_non_exhaustive()
+					}
+				}
+
+				f.debug_tuple("Archive::TarGz")
+					.field(&TarArchiveDbg)
+					.finish_non_exhaustive()
+			}
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ArchiveKind {
+	Zip,
+	TarGz,
+}
+
+impl FromStr for ArchiveKind {
+	type Err = ArchiveKindErr;
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		if s.ends_with(".zip") {
+			Ok(Self::Zip)
+		} else if s.ends_with(".tar.gz") {
+			Ok(Self::TarGz)
+		} else {
+			Err(ArchiveKindErr::Unsupported)
+		}
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveKindErr {
+	#[error("unsupported archive format")]
+	Unsupported,
+}
+
+#[test]
+fn entry_limits_are_enforced() {
+	let body = crate::test_tar_gz(["a", "b"].map(move |name| (name, 0o644, [0u8; 1024])));
+
+	let limits = Limits {
+		total_size: 1536,
+		entry_size: 1024,
+		entries: 2,
+	};
+	let body: Box<dyn Read> = Box::new(Cursor::new(body));
+	let mut archive = Archive::new(body, ArchiveKind::TarGz).unwrap();
+	let mut entries = archive.entries(limits).unwrap();
+	assert!(entries.next().unwrap().is_ok());
+	assert!(matches!(entries.next(), Some(Err(ArchiveEntryError::LimitExceeded(LimitExceeded::TotalSize(1536))))));
+}
+
+#[test]
+fn corrupted_entries_are_reported() {
+	use flate2::{
+		write::GzEncoder, Compression,
+	};
+
+	let mut builder = tar::Builder::new(Vec::new());
+	for name in ["a", "b"] {
+		let mut header = tar::Header::new_gnu();
+		header.set_size(1024);
+		header.set_cksum();
+		builder.append_data(&mut header, name, &[0u8; 1024][..]).unwrap();
+	}
+	let mut tar = builder.into_inner().unwrap();
+	// Corrupt the checksum of the second header.
+	tar[1536 + 148] ^= 0xff;
+	let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+	std::io::Write::write_all(&mut encoder, &tar).unwrap();
+	let body: Box<dyn Read> = Box::new(Cursor::new(encoder.finish().unwrap()));
+
+	let limits = Limits {
+		total_size: u64::MAX,
+		entry_size: u64::MAX,
+		entries: u64::MAX,
+	};
+	let mut archive = Archive::new(body, ArchiveKind::TarGz).unwrap();
+	let mut entries = archive.entries(limits).unwrap();
+	let (name, mut entry) = entries.next().unwrap().unwrap();
+	assert_eq!(name, Path::new("a"));
+	std::io::copy(&mut entry, &mut std::io::sink()).unwrap();
+	drop(entry);
+	assert!(matches!(entries.next(), Some(Err(ArchiveEntryError::Tar(..)))));
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_names_are_kept() {
+	use std::os::unix::ffi::OsStrExt;
+
+	let name = Path::new(std::ffi::OsStr::from_bytes(b"include/\xff.inc"));
+	let body: Box<dyn Read> = Box::new(Cursor::new(crate::test_tar_gz([(name, 0o644, [0u8])])));
+
+	let limits = Limits {
+		total_size: u64::MAX,
+		entry_size: u64::MAX,
+		entries: u64::MAX,
+	};
+	let mut archive = Archive::new(body, ArchiveKind::TarGz).unwrap();
+	let mut entries = archive.entries(limits).unwrap();
+	let (entry_name, entry) = entries.next().unwrap().unwrap();
+	assert_eq!(entry_name, name);
+	assert!(!entry.has_utf8_name());
+}
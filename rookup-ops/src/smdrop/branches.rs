@@ -3,7 +3,7 @@ use ureq::Error;
 
 use super::{
 	listing::{
-		DirectoryItem, OwnedDirectoryItems,
+		DirectoryItem, DirectoryItemError, OwnedDirectoryItems,
 	},
 	Client,
 	Versions,
@@ -44,6 +44,15 @@ impl Branch {
 	}
 }
 
+impl From<String> for Branch {
+	#[inline]
+	fn from(id: String) -> Self {
+		Self {
+			id,
+		}
+	}
+}
+
 impl From<Branch> for String {
 	#[inline]
 	fn from(value: Branch) -> Self {
@@ -61,16 +70,19 @@ impl fmt::Display for Branch {
 /// Iterator over [`Branch`]es available on a remote server.
 pub struct Branches(pub(crate) OwnedDirectoryItems);
 impl Iterator for Branches {
-	type Item = Branch;
+	type Item = Result<Branch, DirectoryItemError>;
 	fn next(&mut self) -> Option<Self::Item> {
 		loop {
-			let item = self.0.next()?.ok()?;
+			let item = match self.0.next()? {
+				Ok(item) => item,
+				Err(e) => break Some(Err(e)),
+			};
 			if let DirectoryItem::Directory(mut path) = item {
 				if !path.starts_with('/') {
 					path.pop();
-					break Some(Branch {
+					break Some(Ok(Branch {
 						id: path,
-					})
+					}))
 				}
 			}
 		}
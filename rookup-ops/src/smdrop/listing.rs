@@ -0,0 +1,329 @@
+use quick_xml::events::{
+	attributes::Attributes, Event
+};
+use std::{
+	mem::take,
+	time::SystemTime,
+};
+
+pub use quick_xml::{
+	events::attributes::AttrError,
+	escape::EscapeError,
+	Reader as XmlReader, Error as XmlError,
+};
+
+// KLUDGE: `BytesStart`, for some unknown reason, never provides a way to get `Cow<'a, str>` from itself.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DirectoryItem {
+	Directory(String),
+	File(String),
+}
+
+impl From<String> for DirectoryItem {
+	fn from(href: String) -> Self {
+		if href.ends_with('/') {
+			// href.pop().expect("`href` ends with a character that can be popped");
+			DirectoryItem::Directory(href)
+		} else {
+			DirectoryItem::File(href)
+		}
+	}
+}
+
+impl From<&str> for DirectoryItem {
+	fn from(href: &str) -> Self {
+		Self::from(String::from(href))
+	}
+}
+
+/// Details of a [`DirectoryItem`] that are shown next to it in a listing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ItemDetails {
+	/// When the item was last modified.
+	pub modified: Option<SystemTime>,
+	/// Size of the file in bytes.
+	/// 
+	/// Apache listings round sizes to units (for e.g. `20M`), so this is the smallest size that the file may have.
+	pub size: Option<u64>,
+}
+
+pub struct DirectoryItems<'a> {
+	/// [`XmlReader`] that iterates over bytes, which are *always* valid UTF-8.
+	reader: XmlReader<&'a [u8]>,
+	/// Item that was read, but whose details may still follow.
+	pending: Option<DirectoryItem>,
+	pending_details: ItemDetails,
+}
+
+impl<'a> DirectoryItems<'a> {
+	#[inline]
+	pub unsafe fn from_utf8_reader(mut reader: XmlReader<&'a [u8]>) -> Self {
+		// Listings are HTML, which has elements like `<img>` and `<hr>` that are never closed.
+		reader.config_mut().check_end_names = false;
+		Self {
+			reader,
+			pending: None,
+			pending_details: ItemDetails::default(),
+		}
+	}
+
+	// TODO: Remove this?
+	#[allow(dead_code)]
+	pub fn from_str(s: &'a str) -> Self {
+		unsafe { Self::from_utf8_reader(XmlReader::from_str(s)) }
+	}
+
+	#[cfg(debug_assertions)]
+	#[inline]
+	unsafe fn str_from_utf8_unchecked(b: &[u8]) -> &str {
+		core::str::from_utf8(b).expect("`str_from_utf8_unchecked` failed")
+	}
+
+	#[cfg(not(debug_assertions))]
+	#[inline]
+	unsafe fn str_from_utf8_unchecked(b: &[u8]) -> &str {
+		core::str::from_utf8_unchecked(b)
+	}
+}
+
+impl DirectoryItems<'_> {
+	/// Return the next item, along with the details that the listing shows for it.
+	/// 
+	/// Apache and nginx listings show dates like `2024-01-31 12:34` and `31-Jan-2024 12:34` after every link, which
+	/// are assumed to be in UTC, followed by sizes like `20M` (Apache) or `20971520` (nginx).
+	pub fn next_detailed(&mut self) -> Option<Result<(DirectoryItem, ItemDetails), DirectoryItemError>> {
+		loop {
+			let event = match self.reader.read_event() {
+				Ok(e) => e,
+				Err(e) => break Some(Err(e.into())),
+			};
+			match event {
+				Event::Eof => {
+					let item = self.pending.take()?;
+					break Some(Ok((item, take(&mut self.pending_details))))
+				}
+				Event::Text(text) if self.pending.is_some() => {
+					let text = String::from_utf8_lossy(&text);
+					let details = &mut self.pending_details;
+					if details.modified.is_none() {
+						// nginx shows the size in the same text as the date.
+						let mut words = text.split_whitespace();
+						details.modified = parse_listing_date(&mut words);
+						details.size = details.modified.and(words.next()).and_then(parse_listing_size);
+					} else if details.size.is_none() {
+						// Apache shows the size in the column after the date.
+						details.size = text.split_whitespace().next().and_then(parse_listing_size);
+					}
+				}
+				Event::Start(e) => {
+					let tag_name = e.name();
+					if tag_name.0 != b"a" { continue }
+
+					let mut href = None;
+					for result in Attributes::html(unsafe { Self::str_from_utf8_unchecked(e.attributes_raw()) }, 0) {
+						let attr = match result {
+							Ok(a) => a,
+							Err(e) => return Some(Err(e.into())),
+						};
+						if attr.key.0 != b"href" { continue }
+						match attr.unescape_value() {
+							Ok(v) => {
+								href = Some(v);
+								break
+							}
+							Err(e) => return Some(Err(e.into())),
+						}
+					}
+
+					let Some(href) = href else {
+						continue
+					};
+
+					let item = DirectoryItem::from(href.into_owned());
+					if let Some(previous) = self.pending.replace(item) {
+						break Some(Ok((previous, take(&mut self.pending_details))))
+					}
+				}
+				_ => {}
+			}
+		}
+	}
+}
+
+impl Iterator for DirectoryItems<'_> {
+	type Item = Result<DirectoryItem, DirectoryItemError>;
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.next_detailed().map(move |r| r.map(move |(item, ..)| item))
+	}
+}
+
+/// Parse the first date and time in `words` as shown in an Apache or nginx listing, leaving the words after them.
+fn parse_listing_date<'a>(words: &mut impl Iterator<Item = &'a str>) -> Option<SystemTime> {
+	const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+	while let Some(word) = words.next() {
+		let date = match word.split('-').collect::<Vec<_>>()[..] {
+			[year, month, day] if year.len() == 4 => format!("{year}-{month}-{day}"),
+			[day, month, year] if year.len() == 4 => {
+				let Some(month) = MONTHS.iter().position(move |m| *m == month) else { continue };
+				format!("{year}-{:02}-{day}", month + 1)
+			}
+			_ => continue,
+		};
+		let time = words.next().unwrap_or("00:00");
+		let seconds = if time.len() == 5 { ":00" } else { "" };
+		if let Ok(t) = humantime::parse_rfc3339(&format!("{date}T{time}{seconds}Z")) {
+			return Some(t)
+		}
+	}
+	None
+}
+
+/// Parse a size as shown in an Apache or nginx listing, which is either a number of bytes, or (in Apache listings) a
+/// number of kibibytes, mebibytes, and so on, rounded to one decimal below 10 (for e.g. `20M` or `1.5K`).
+/// 
+/// Rounded sizes are parsed as the smallest size that rounds to them, so they're never larger than the actual size.
+fn parse_listing_size(word: &str) -> Option<u64> {
+	if let Ok(bytes) = word.parse() {
+		return Some(bytes)
+	}
+	let (number, unit) = word.split_at_checked(word.len().checked_sub(1)?)?;
+	let exponent = ["K", "M", "G", "T"].iter().position(move |u| *u == unit)? as i32 + 1;
+	let value: f64 = number.parse().ok().filter(move |v: &f64| v.is_finite() && *v >= 0.0)?;
+	let precision = if number.contains('.') { 0.05 } else { 0.5 };
+	Some(((value - precision).max(0.0) * 1024f64.powi(exponent)) as u64)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DirectoryItemError {
+	#[error("{0}")]
+	Xml(#[from] XmlError),
+	#[error("{0}")]
+	Attr(#[from] AttrError),
+	#[error("{0}")]
+	Escape(#[from] EscapeError),
+}
+
+pub struct OwnedDirectoryItems {
+	inner: DirectoryItems<'static>,
+	owned_ptr: *mut u8,
+	len_cap: usize,
+}
+
+impl OwnedDirectoryItems {
+	pub fn new(s: String) -> Self {
+		// SAFETY: `String` always contains valid UTF-8 bytes.
+		unsafe { Self::from_utf8_unchecked(s.into_bytes()) }
+	}
+
+	pub unsafe fn from_utf8_unchecked(mut utf8_bytes: Vec<u8>) -> Self {
+		utf8_bytes.shrink_to_fit();
+		let owned = utf8_bytes.leak();
+		let owned_ptr = owned.as_mut_ptr();
+		let len_cap = owned.len();
+		Self {
+			inner: DirectoryItems::from_utf8_reader(XmlReader::from_reader(owned)),
+			owned_ptr,
+			len_cap,
+		}
+	}
+}
+
+impl Drop for OwnedDirectoryItems {
+	fn drop(&mut self) {
+		// SAFETY: We always construct `OwnedDirectoryItems` with an exclusive slice that we own.
+		drop(unsafe { Vec::from_raw_parts(self.owned_ptr, self.len_cap, self.len_cap) });
+	}
+}
+
+impl OwnedDirectoryItems {
+	/// See [`DirectoryItems::next_detailed`].
+	#[inline]
+	pub fn next_detailed(&mut self) -> Option<Result<(DirectoryItem, ItemDetails), DirectoryItemError>> {
+		self.inner.next_detailed()
+	}
+}
+
+impl Iterator for OwnedDirectoryItems {
+	type Item = Result<DirectoryItem, DirectoryItemError>;
+	#[inline]
+	fn next(&mut self) -> Option<Self::Item> {
+		self.inner.next()
+	}
+}
+
+#[test]
+fn listing_works() {
+	let listing_str = r#"
+<!DOCTYPE HTML PUBLIC "-//W3C//DTD HTML 3.2 Final//EN">
+<html>
+ <head>
+  <title>Index of /smdrop/1.12</title>
+ </head>
+ <body>
+<h1>Index of /smdrop/1.12</h1>
+<ul><li><a href="/smdrop/"> Parent Directory</a></li>
+<li><a href="sourcemod-1.12.0-git7177-linux.tar.gz"> sourcemod-1.12.0-git7177-linux.tar.gz</a></li>
+<li><a href="sourcemod-1.12.0-git7177-windows.zip"> sourcemod-1.12.0-git7177-windows.zip</a></li>
+<li><a href="sourcemod-latest-linux"> sourcemod-latest-linux</a></li>
+<li><a href="sourcemod-latest-windows"> sourcemod-latest-windows</a></li>
+</ul>
+<address>Apache/2.4.41 (Unix) OpenSSL/1.1.1n mod_fcgid/2.3.9 mod_wsgi/4.7.1 Python/2.7 mod_perl/2.0.11 Perl/v5.28.1 Server at sm.alliedmods.net Port 80</address>
+</body></html>
+"#;
+
+	check_items({
+		let result: Result<Vec<_>, _> = DirectoryItems::from_str(listing_str).collect();
+		result.unwrap()
+	});
+	check_items({
+		let result: Result<Vec<_>, _> = OwnedDirectoryItems::new(listing_str.into()).collect();
+		result.unwrap()
+	});
+	check_items({
+		let result: Result<Vec<_>, _> = OwnedDirectoryItems::new(listing_str.into()).collect();
+		result.unwrap()
+	});
+
+	fn check_items(items: Vec<DirectoryItem>) {
+		assert_eq!(items[0], DirectoryItem::Directory("/smdrop/".into()));
+		assert_eq!(items[1], DirectoryItem::File("sourcemod-1.12.0-git7177-linux.tar.gz".into()));
+		assert_eq!(
+			items,
+			vec![
+				"/smdrop/".into(),
+				"sourcemod-1.12.0-git7177-linux.tar.gz".into(), "sourcemod-1.12.0-git7177-windows.zip".into(),
+				"sourcemod-latest-linux".into(), "sourcemod-latest-windows".into(),
+			]
+		);
+	}
+}
+
+#[test]
+fn listing_details_are_parsed() {
+	let apache = r#"<table>
+<tr><th><img src="/icons/blank.gif" alt="[ICO]"></th><th><a href="?C=N;O=D">Name</a></th><th><a href="?C=M;O=A">Last modified</a></th></tr>
+<tr><td><img src="/icons/back.gif" alt="[PARENTDIR]"></td><td><a href="/smdrop/">Parent Directory</a></td><td>&nbsp;</td></tr>
+<tr><td><img src="/icons/compressed.gif" alt="[   ]"></td><td><a href="sourcemod-1.12.0-git7177-linux.tar.gz">sourcemod-1.12.0-git7177-linux.tar.gz</a></td><td align="right">2024-12-13 09:41  </td><td align="right"> 20M</td></tr>
+</table>"#;
+	let nginx = r#"<pre><a href="../">../</a>
+<a href="sourcemod-1.12.0-git7177-linux.tar.gz">sourcemod-1.12.0-git7177-linux.tar.gz</a>          13-Dec-2024 09:41            20971520
+</pre>"#;
+
+	let expected = humantime::parse_rfc3339("2024-12-13T09:41:00Z").unwrap();
+	for (listing, size) in [(apache, 19 * 1024 * 1024 + 512 * 1024), (nginx, 20_971_520)] {
+		let mut items = DirectoryItems::from_str(listing);
+		let (.., details) = std::iter::from_fn(move || items.next_detailed())
+			.map(Result::unwrap)
+			.find(move |(item, ..)| *item == DirectoryItem::File("sourcemod-1.12.0-git7177-linux.tar.gz".into()))
+			.unwrap();
+		assert_eq!(details.modified, Some(expected));
+		assert_eq!(details.size, Some(size));
+	}
+	// Words with unknown month names are skipped instead of ending the search.
+	assert_eq!(parse_listing_date(&mut ["13-Foo-2024", "13-Dec-2024", "09:41"].into_iter()), Some(expected));
+	assert_eq!(parse_listing_size("1.5K"), Some(1484));
+	assert_eq!(parse_listing_size("512"), Some(512));
+	assert_eq!(parse_listing_size("-"), None);
+}
@@ -28,7 +28,8 @@ pub struct ClientParams {
 }
 
 impl Client {
-	const fn with_agent(params: ClientParams, agent: Agent) -> Self {
+	/// Create a new client that makes requests with `agent`.
+	pub const fn with_agent(params: ClientParams, agent: Agent) -> Self {
 		Self {
 			agent,
 			params,
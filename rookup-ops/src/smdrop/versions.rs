@@ -1,4 +1,3 @@
-use rookup_common::version::Version as _;
 use std::{
 	borrow::Cow,
 	convert::Infallible,
@@ -6,19 +5,36 @@ use std::{
 	ops::{
 		Deref, DerefMut,
 	},
+	time::SystemTime,
 };
 
 use super::listing::{
-	DirectoryItem, OwnedDirectoryItems
+	DirectoryItem, DirectoryItemError, OwnedDirectoryItems,
 };
 
 /// Version available on a [`Branch`](super::Branch) of a remote server.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Version {
 	url: VersionUrl<Box<str>>,
+	modified: Option<SystemTime>,
+	size: Option<u64>,
 }
 
 impl Version {
+	/// Return the time that this version was published at, if the server listed it.
+	#[inline]
+	pub const fn modified(&self) -> Option<SystemTime> {
+		self.modified
+	}
+
+	/// Return the size of the archive in bytes, if the server listed it.
+	/// 
+	/// See [`ItemDetails::size`](super::listing::ItemDetails::size) for how exact it is.
+	#[inline]
+	pub const fn size(&self) -> Option<u64> {
+		self.size
+	}
+
 	/// Convert this version into the URL pointing to the archive with the toolchain.
 	#[inline]
 	pub fn into_url(self) -> VersionUrl<Box<str>> {
@@ -53,30 +69,6 @@ impl<S: AsRef<str>> VersionUrl<S> {
 	}
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-#[repr(transparent)]
-pub struct UrlVersionStr<S: AsRef<str>>(pub S);
-impl<S: AsRef<str>> fmt::Display for UrlVersionStr<S> {
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		let mut parts = self.0.as_ref().iter_parts().peekable();
-		let Some(first) = parts.next() else {
-			return Ok(())
-		};
-	
-		f.write_str(first.0)?;
-		while let Some(part) = parts.next() {
-			if parts.peek().is_some() {
-				f.write_str(".")?;
-			} else {
-				f.write_str("-git")?;
-			}
-			f.write_str(part.0)?;
-		}
-	
-		Ok(())
-	}
-}
-
 impl<S: AsRef<str>> Deref for VersionUrl<S> {
 	type Target = S;
 	#[inline]
@@ -144,17 +136,22 @@ pub struct Versions {
 	pub(crate) root: String,
 }
 impl Iterator for Versions {
-	type Item = Version;
+	type Item = Result<Version, DirectoryItemError>;
 	fn next(&mut self) -> Option<Self::Item> {
 		loop {
-			let item = self.inner.next()?.ok()?;
+			let (item, details) = match self.inner.next_detailed()? {
+				Ok(item) => item,
+				Err(e) => break Some(Err(e)),
+			};
 
 			if let DirectoryItem::File(mut file_name) = item {
 				file_name.insert_str(0, &self.root);
 				let version = Version {
 					url: VersionUrl(file_name.into_boxed_str()),
+					modified: details.modified,
+					size: details.size,
 				};
-				break Some(version)
+				break Some(Ok(version))
 			}
 		}
 	}
@@ -206,3 +203,13 @@ const fn before<'a>(p: (&'a str, &'a str)) -> &'a str {
 const fn after<'a>(p: (&'a str, &'a str)) -> &'a str {
 	p.1
 }
+
+#[test]
+fn listing_errors_are_surfaced() {
+	let listing = r#"<a href="sourcemod-1.12.0-git7200-linux.tar.gz">a</a><a href="&bogus;">b</a>"#;
+	let versions = Versions {
+		inner: OwnedDirectoryItems::new(listing.into()),
+		root: String::new(),
+	};
+	assert!(versions.collect::<Result<Vec<_>, _>>().is_err());
+}
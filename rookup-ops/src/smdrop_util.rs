@@ -0,0 +1,288 @@
+use anyhow::{
+	anyhow, Result as AResult
+};
+use ureq::{
+	http::{
+		header::AUTHORIZATION,
+		HeaderValue, Request, Response, Uri,
+	},
+	middleware::MiddlewareNext,
+	Agent, Body, ResponseExt, SendBody,
+};
+use core::{
+	cmp::Ordering,
+	fmt::Write as _,
+	str::FromStr,
+};
+use std::time::{
+	Duration, SystemTime,
+};
+use rookup_common::{
+	version::version_ord,
+	Config, ConfigData,
+};
+
+use crate::{
+	netrc::Netrc,
+	source::MAX_CONCURRENT_FETCHES,
+	smdrop::{
+		ArchiveKind, Branch, Client, ClientParams, VersionUrl, USER_AGENT,
+	},
+};
+
+/// Return the operating system that archives should be selected for, preferring `overridden` over the `target-os`
+/// setting in `data`, and then the host operating system.
+pub fn target_os<'a>(overridden: Option<&'a str>, data: &'a ConfigData) -> &'a str {
+	overridden
+		.or(data.source.target_os.as_deref())
+		.unwrap_or(std::env::consts::OS)
+}
+
+/// Return the name that archives for the `os` operating system end with on SourceMod's servers, which is `mac` for
+/// `macos`, and `os` itself otherwise.
+pub fn archive_target(os: &str) -> &str {
+	match os {
+		"macos" | "darwin" => "mac",
+		os => os,
+	}
+}
+
+pub fn smdrop_client(config: &Config) -> Client {
+	let data = config.with_doc.data();
+	let params = ClientParams {
+		root_url: data.source.root_url.clone(),
+	};
+	Client::with_agent(params, http_agent(data))
+}
+
+/// Create an HTTP agent that follows redirects as allowed by the `source` table of `data`.
+/// 
+/// Requests without an `Authorization` header are authenticated with the credentials for their host in the user's
+/// `.netrc` file, if it has any.
+pub fn http_agent(data: &ConfigData) -> Agent {
+	let max_redirects = data.source.max_redirects;
+	let allow_cross_host = data.source.allow_cross_host_redirects;
+	let netrc = Netrc::read();
+	let config = Agent::config_builder()
+		.user_agent(USER_AGENT)
+		.max_redirects(max_redirects)
+		.save_redirect_history(!allow_cross_host)
+		// Connections of listings that are fetched at the same time are all kept alive for reuse.
+		.max_idle_connections_per_host(MAX_CONCURRENT_FETCHES)
+		.middleware(move |mut request: Request<SendBody>, next: MiddlewareNext| {
+			let host = request.uri().host().map(String::from);
+			let credentials = host.as_deref().and_then(|host| netrc.get(host));
+			if let Some(credentials) = credentials.filter(|_| !request.headers().contains_key(AUTHORIZATION)) {
+				if let Ok(mut value) = HeaderValue::from_str(&credentials.basic_auth()) {
+					value.set_sensitive(true);
+					request.headers_mut().insert(AUTHORIZATION, value);
+				}
+			}
+			let response = next.handle(request)?;
+			// Redirect responses are only returned instead of followed if redirects are disabled.
+			if response.status().is_redirection() && max_redirects == 0 {
+				return Err(ureq::Error::Other(Box::new(RedirectError::Disabled(response.get_uri().clone()))))
+			}
+			let foreign = response.get_redirect_history()
+				.and_then(move |history| history.iter().find(|uri| uri.host() != host.as_deref()));
+			if let Some(uri) = foreign {
+				return Err(ureq::Error::Other(Box::new(RedirectError::CrossHost(uri.clone()))))
+			}
+			Ok::<Response<Body>, _>(response)
+		})
+		.build();
+	Agent::new_with_config(config)
+}
+
+/// Error for a request that was redirected in a way that isn't allowed by the configuration file.
+#[derive(Debug, thiserror::Error)]
+pub enum RedirectError {
+	#[error("request to {0} was redirected, but `max-redirects` is 0")]
+	Disabled(Uri),
+	#[error("request was redirected to {0}, which is on a different host (see `allow-cross-host-redirects`)")]
+	CrossHost(Uri),
+}
+
+#[derive(Debug, Clone)]
+pub struct RelevantUrl {
+	url: VersionUrl<Box<str>>,
+	version: Box<str>,
+	published: Option<SystemTime>,
+	size: Option<u64>,
+	notes: Option<ReleaseNotes>,
+}
+
+/// Release notes of a version, as published by the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseNotes {
+	/// URL of a page with the release notes, if there is one.
+	pub url: Option<String>,
+	/// Text of the release notes (usually Markdown), if it was provided.
+	pub body: Option<String>,
+}
+impl RelevantUrl {
+	/// Create a new relevant URL if `url` points to an archive for the `os` target.
+	#[inline]
+	pub fn new(url: VersionUrl<Box<str>>, os: &str) -> Option<Self> {
+		if
+			url.target().is_none_or(|t| t != archive_target(os))
+			|| url.version_str().is_none_or(move |v| v.0 == "latest")
+			|| !Self::is_archive(&url)
+		{
+			return None
+		}
+
+		let version = url.version_str().map(move |v| v.normalized().into_owned().into_boxed_str())?;
+		Some(Self {
+			url,
+			version,
+			published: None,
+			size: None,
+			notes: None,
+		})
+	}
+
+	/// Return `true` if `url` points to an archive of a supported format.
+	#[inline]
+	pub fn is_archive(url: &str) -> bool {
+		ArchiveKind::from_str(url).is_ok()
+	}
+
+	/// Create a new relevant URL from a URL that doesn't follow the naming scheme of `smdrop`, given its `version`.
+	#[inline]
+	pub fn with_version(url: impl Into<Box<str>>, version: impl Into<Box<str>>) -> Self {
+		Self {
+			url: VersionUrl(url.into()),
+			version: version.into(),
+			published: None,
+			size: None,
+			notes: None,
+		}
+	}
+
+	/// Set the time that the archive was published at.
+	#[inline]
+	pub fn with_published(self, published: Option<SystemTime>) -> Self {
+		Self {
+			published,
+			..self
+		}
+	}
+
+	/// Set the size of the archive in bytes, as listed by the source.
+	#[inline]
+	pub fn with_size(self, size: Option<u64>) -> Self {
+		Self {
+			size,
+			..self
+		}
+	}
+
+	/// Set the release notes of the version.
+	#[inline]
+	pub fn with_notes(self, notes: Option<ReleaseNotes>) -> Self {
+		Self {
+			notes,
+			..self
+		}
+	}
+
+	/// Return the release notes of the version, if the source provides them.
+	#[inline]
+	pub const fn notes(&self) -> Option<&ReleaseNotes> {
+		self.notes.as_ref()
+	}
+
+	/// Return the time that the archive was published at, if known.
+	#[inline]
+	pub const fn published(&self) -> Option<SystemTime> {
+		self.published
+	}
+
+	/// Return the size of the archive in bytes, if the source listed it.
+	/// 
+	/// Listings may round sizes, in which case this is the smallest size that the archive may have.
+	#[inline]
+	pub const fn size(&self) -> Option<u64> {
+		self.size
+	}
+
+	#[inline]
+	pub fn url(&self) -> &str {
+		&self.url
+	}
+
+	#[inline]
+	pub fn version(&self) -> &str {
+		&self.version
+	}
+
+	pub fn version_ord(&self, other: &Self) -> Ordering {
+		version_ord(self.version.as_ref(), other.version.as_ref())
+	}
+}
+
+pub trait BranchExt {
+	fn relevant_urls(&self, client: &Client, os: &str) -> AResult<Vec<RelevantUrl>>;
+}
+impl BranchExt for Branch {
+	fn relevant_urls(&self, client: &Client, os: &str) -> AResult<Vec<RelevantUrl>> {
+		let versions = self.versions(client)
+			.map_err(move |e| anyhow!("couldn't fetch versions for branch {:?}: {e}", self.name()))?;
+		let mut urls = Vec::new();
+		for version in versions {
+			let version = version
+				.map_err(move |e| anyhow!("couldn't parse listing of versions for branch {:?}: {e}", self.name()))?;
+			let (published, size) = (version.modified(), version.size());
+			urls.extend(
+				RelevantUrl::new(version.into_url(), os).map(move |url| url.with_published(published).with_size(size))
+			);
+		}
+		Ok(urls)
+	}
+}
+
+/// Return the branch (for e.g. `1.12`) that the toolchain version `name` belongs to.
+pub fn branch_of(name: &str) -> Option<String> {
+	let mut parts = name.split('.');
+	let (major, minor) = (parts.next()?, parts.next()?);
+	(major.bytes().all(|b| b.is_ascii_digit()) && minor.bytes().all(|b| b.is_ascii_digit()))
+		.then(move || format!("{major}.{minor}"))
+}
+
+/// Return the lowercase hexadecimal representation of `bytes`.
+pub fn hex(bytes: &[u8]) -> String {
+	let mut buffer = String::with_capacity(bytes.len() * 2);
+	for b in bytes {
+		let _ = write!(buffer, "{b:02x}");
+	}
+	buffer
+}
+
+/// Format `time` as a date and time in UTC.
+pub fn display_time(time: SystemTime) -> String {
+	let time = humantime::format_rfc3339_seconds(time).to_string();
+	format!("{} UTC", time[..16].replace('T', " "))
+}
+
+/// Format the time between `earlier` and `later` in whole days or hours, or return [`None`] if `later` isn't later.
+pub fn display_age(earlier: SystemTime, later: SystemTime) -> Option<String> {
+	const HOUR: u64 = 60 * 60;
+	const DAY: u64 = 24 * HOUR;
+	let age = later.duration_since(earlier).ok().filter(move |d| *d >= Duration::from_secs(HOUR))?;
+	Some(match age.as_secs() {
+		s if s >= DAY => format!("{} days", s / DAY),
+		s => format!("{} hours", s / HOUR),
+	})
+}
+
+#[test]
+fn mac_archives_are_relevant_for_macos() {
+	let url = move |s: &str| VersionUrl(Box::<str>::from(s));
+	let mac = "https://sm.alliedmods.net/smdrop/1.12/sourcemod-1.12.0-git7200-mac.zip";
+	assert!(RelevantUrl::new(url(mac), "macos").is_some());
+	assert!(RelevantUrl::new(url(mac), "mac").is_some());
+	assert!(RelevantUrl::new(url(mac), "linux").is_none());
+	let linux = "https://sm.alliedmods.net/smdrop/1.12/sourcemod-1.12.0-git7200-linux.tar.gz";
+	assert!(RelevantUrl::new(url(linux), "macos").is_none());
+}
@@ -0,0 +1,137 @@
+use anyhow::{
+	anyhow, bail, Context, Result as AResult,
+};
+use rookup_common::Config;
+use serde::Deserialize;
+use std::{
+	io::{
+		Error as IoError, Read, Result as IoResult,
+	},
+	process::{
+		Child, ChildStdout, Command, Stdio,
+	},
+};
+
+use crate::smdrop_util::RelevantUrl;
+use super::{
+	Layout, Provider,
+};
+
+/// Provider that runs an external program to list and fetch toolchains.
+/// 
+/// The program is run with these arguments appended to the configured command:
+/// - `branches`, printing a JSON array of branch names to standard output;
+/// - `versions <branch> <os>`, printing a JSON array of objects with `version` and `url` fields (and optionally a
+///   `published` RFC 3339 timestamp);
+/// - `fetch <url>`, writing the archive identified by `url` to standard output.
+/// 
+/// `url`s are opaque to Rookup, except that they must end with the extension of the archive format.
+#[derive(Debug, Clone)]
+pub struct CommandProvider {
+	command: Vec<String>,
+}
+
+/// Archive as listed by the `versions` subcommand.
+#[derive(Debug, Clone, Deserialize)]
+struct CommandVersion {
+	version: String,
+	url: String,
+	#[serde(default)]
+	published: Option<String>,
+}
+
+impl CommandProvider {
+	/// Create a new provider that runs the program specified by `command` in the `source` table.
+	pub fn new(config: &Config) -> Self {
+		Self {
+			command: config.with_doc.data().source.command.clone(),
+		}
+	}
+
+	fn command(&self, args: &[&str]) -> AResult<Command> {
+		let (program, base_args) = self.command.split_first()
+			.context("`command` in the `source` table of the configuration file is empty")?;
+		let mut command = Command::new(program);
+		command.args(base_args).args(args).stdin(Stdio::null());
+		Ok(command)
+	}
+
+	fn run_json<T: for<'de> Deserialize<'de>>(&self, args: &[&str]) -> AResult<T> {
+		let output = self.command(args)?
+			.stderr(Stdio::inherit())
+			.output().with_context(|| anyhow!("failed to run source command {:?}", self.command))?;
+		if !output.status.success() {
+			bail!("source command {:?} with arguments {args:?} failed with {}", self.command, output.status)
+		}
+		serde_json::from_slice(&output.stdout)
+			.with_context(|| anyhow!("failed to parse output of source command {:?} with arguments {args:?}", self.command))
+	}
+}
+
+impl Provider for CommandProvider {
+	#[inline]
+	fn layout(&self) -> Layout {
+		Layout::SourceMod
+	}
+
+	fn branch_names(&self) -> AResult<Vec<String>> {
+		self.run_json(&["branches"])
+	}
+
+	fn relevant_urls(&self, branch: &str, os: &str) -> AResult<Vec<RelevantUrl>> {
+		let versions: Vec<CommandVersion> = self.run_json(&["versions", branch, os])?;
+		Ok(
+			versions.into_iter()
+				.filter(move |v| RelevantUrl::is_archive(&v.url))
+				.map(move |v| {
+					let published = v.published.and_then(move |p| humantime::parse_rfc3339_weak(&p).ok());
+					RelevantUrl::with_version(v.url, v.version).with_published(published)
+				})
+				.collect()
+		)
+	}
+
+	fn fetch(&self, url: &str, max_bytes: u64) -> AResult<Box<dyn Read>> {
+		let mut child = self.command(&["fetch", url])?
+			.stdout(Stdio::piped()).stderr(Stdio::inherit())
+			.spawn().with_context(|| anyhow!("failed to run source command {:?}", self.command))?;
+		let stdout = child.stdout.take().context("failed to capture output of source command")?;
+		Ok(Box::new(ChildReader {
+			child,
+			stdout,
+			remaining: max_bytes,
+		}))
+	}
+}
+
+/// Reader of the standard output of a child process, which fails if the process fails or writes more than `remaining`
+/// bytes.
+struct ChildReader {
+	child: Child,
+	stdout: ChildStdout,
+	remaining: u64,
+}
+
+impl Read for ChildReader {
+	fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+		let n = self.stdout.read(buf)?;
+		if n == 0 {
+			let status = self.child.wait()?;
+			if !status.success() {
+				return Err(IoError::other(format!("source command failed with {status}")))
+			}
+			return Ok(0)
+		}
+
+		self.remaining = self.remaining.checked_sub(n as u64)
+			.ok_or_else(move || IoError::other("archive is larger than `max-download-size`"))?;
+		Ok(n)
+	}
+}
+
+impl Drop for ChildReader {
+	fn drop(&mut self) {
+		let _ = self.child.kill();
+		let _ = self.child.wait();
+	}
+}
@@ -0,0 +1,57 @@
+use anyhow::{
+	bail, Result as AResult,
+};
+use rookup_common::{
+	Config, SourceKind,
+};
+use std::io::Read;
+use ureq::Agent;
+
+use crate::smdrop_util::{
+	http_agent, RelevantUrl,
+};
+use super::{
+	fetch_with, Layout, Provider,
+};
+
+/// Provider that downloads archives straight from their URLs, for installing archives that aren't listed by any
+/// source (for e.g. one-off builds or CI artifacts).
+#[derive(Debug, Clone)]
+pub struct DirectUrl {
+	agent: Agent,
+	layout: Layout,
+}
+
+impl DirectUrl {
+	/// Create a new provider for archives with the same layout as those of the source in `config`.
+	pub fn new(config: &Config) -> Self {
+		let layout = match config.with_doc.data().source.kind {
+			SourceKind::Smdrop | SourceKind::Command | SourceKind::Oci => Layout::SourceMod,
+			SourceKind::Sourcepawn => Layout::SourcePawn,
+		};
+		Self {
+			agent: http_agent(config.with_doc.data()),
+			layout,
+		}
+	}
+}
+
+impl Provider for DirectUrl {
+	#[inline]
+	fn layout(&self) -> Layout {
+		self.layout
+	}
+
+	fn branch_names(&self) -> AResult<Vec<String>> {
+		bail!("archives installed from URLs don't belong to any branch")
+	}
+
+	fn relevant_urls(&self, branch: &str, _os: &str) -> AResult<Vec<RelevantUrl>> {
+		bail!("archives installed from URLs don't belong to branch {branch:?}")
+	}
+
+	#[inline]
+	fn fetch(&self, url: &str, max_bytes: u64) -> AResult<Box<dyn Read>> {
+		fetch_with(&self.agent, url, max_bytes)
+	}
+}
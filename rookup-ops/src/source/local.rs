@@ -0,0 +1,41 @@
+use anyhow::{
+	anyhow, bail, Context, Result as AResult,
+};
+use std::{
+	fs::File,
+	io::Read,
+};
+
+use crate::smdrop_util::RelevantUrl;
+use super::{
+	Layout, Provider,
+};
+
+/// Provider that reads toolchain archives exported by Rookup from local files, for installing toolchains on machines
+/// without access to any source.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LocalArchive;
+
+impl Provider for LocalArchive {
+	#[inline]
+	fn layout(&self) -> Layout {
+		Layout::Toolchain
+	}
+
+	fn branch_names(&self) -> AResult<Vec<String>> {
+		bail!("exported toolchain archives don't belong to any branch")
+	}
+
+	fn relevant_urls(&self, branch: &str, _os: &str) -> AResult<Vec<RelevantUrl>> {
+		bail!("exported toolchain archives don't belong to branch {branch:?}")
+	}
+
+	fn fetch(&self, url: &str, max_bytes: u64) -> AResult<Box<dyn Read>> {
+		let file = File::open(url).with_context(|| anyhow!("failed to open archive at {url:?}"))?;
+		let size = file.metadata().with_context(|| anyhow!("failed to read metadata of {url:?}"))?.len();
+		if size > max_bytes {
+			bail!("archive at {url:?} is larger than {max_bytes} bytes")
+		}
+		Ok(Box::new(file))
+	}
+}
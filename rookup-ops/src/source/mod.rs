@@ -0,0 +1,456 @@
+//! Providers of SourcePawn toolchains that can be installed by Rookup.
+
+use anyhow::{
+	anyhow, bail, Context, Result as AResult,
+};
+use rookup_common::{
+	version::{
+		version_ord, VersionReq,
+	},
+	CompilerNames, Config, ConfigData, Extra, Selector, SourceKind, Suggestions,
+};
+use clean_path::clean;
+use rustc_hash::FxHashMap;
+use std::{
+	io::{
+		Error as IoError, ErrorKind as IoErrorKind, Read,
+	},
+	panic::resume_unwind,
+	path::{
+		Component as PathComponent, Path, PathBuf,
+	},
+	sync::atomic::{
+		AtomicUsize, Ordering,
+	},
+	thread::scope,
+};
+use ureq::Agent;
+
+use crate::{
+	smdrop::{
+		Branch, Client,
+	},
+	smdrop_util::{
+		archive_target, smdrop_client, BranchExt, RelevantUrl,
+	},
+	sp_from_sm, sp_from_sp,
+};
+
+mod command;
+pub use command::*;
+mod direct;
+pub use direct::*;
+mod local;
+pub use local::*;
+mod oci;
+pub use oci::*;
+mod sourcepawn;
+pub use sourcepawn::*;
+
+/// Layout of the archives provided by a [`Provider`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layout {
+	/// Archive is a SourceMod distribution, with SourcePawn in `addons/sourcemod/scripting`.
+	SourceMod,
+	/// Archive is a standalone SourcePawn compiler distribution.
+	SourcePawn,
+	/// Archive is a toolchain exported by Rookup, with the files of the toolchain (and its receipt) at its root.
+	Toolchain,
+}
+
+impl Layout {
+	/// Map the name of an archive entry for the `os` target to a path relative to the toolchain root, or return
+	/// [`None`] if the entry shouldn't be extracted.
+	/// 
+	/// `extras` are only available in SourceMod distributions.
+	pub fn map_entry(self, name: &Path, compilers: &CompilerNames, extras: &[Extra]) -> Option<PathBuf> {
+		match self {
+			Self::SourceMod => sp_from_sm::map_entry(name, compilers, extras),
+			Self::SourcePawn => sp_from_sp::map_to_sp_root(name, compilers),
+			Self::Toolchain => {
+				let path = clean(name);
+				let is_inside = path.components().all(move |c| matches!(c, PathComponent::Normal(..)));
+				(is_inside && !path.as_os_str().is_empty()).then_some(path)
+			}
+		}
+	}
+}
+
+/// Trait for sources of SourcePawn toolchains.
+/// 
+/// Providers are shared between threads to fetch listings of multiple branches at the same time.
+pub trait Provider: Sync {
+	/// Return the layout of the archives provided.
+	fn layout(&self) -> Layout;
+
+	/// Return the names of all branches available.
+	fn branch_names(&self) -> AResult<Vec<String>>;
+
+	/// Return all archives for the `os` target that are available on `branch`.
+	fn relevant_urls(&self, branch: &str, os: &str) -> AResult<Vec<RelevantUrl>>;
+
+	/// Start downloading the archive at `url`, failing if it is larger than `max_bytes`.
+	fn fetch(&self, url: &str, max_bytes: u64) -> AResult<Box<dyn Read>>;
+
+	/// Return the latest archive for the `os` target on `branch` whose version matches `req` (or any version, if
+	/// [`None`]), if there is one.
+	/// 
+	/// Sources that make a request for every version they list (like [`OciRepository`]) override this to only make
+	/// requests for the newest versions, until one with an archive is found.
+	fn latest_url(&self, branch: &str, os: &str, req: Option<&VersionReq>) -> AResult<Option<RelevantUrl>> {
+		let urls = self.relevant_urls(branch, os)?.into_iter();
+		Ok(urls.filter(move |u| req.is_none_or(|r| r.matches(u.version()))).max_by(RelevantUrl::version_ord))
+	}
+
+	/// Return the archive that the `latest` pointer of `branch` for the `os` target refers to, or [`None`] if the source
+	/// doesn't have such pointers.
+	fn latest_pointer(&self, branch: &str, os: &str) -> AResult<Option<LatestPointer>> {
+		let _ = (branch, os);
+		Ok(None)
+	}
+
+	/// Return the URL of the changelog of `branch`, if there is one.
+	/// 
+	/// Release notes of individual versions are returned by [`RelevantUrl::notes`] instead.
+	fn changelog_url(&self, branch: &str) -> Option<String> {
+		match self.layout() {
+			Layout::SourceMod => Some(format!("https://wiki.alliedmods.net/SourceMod_{branch}.0_Release_Notes")),
+			Layout::SourcePawn | Layout::Toolchain => None,
+		}
+	}
+
+	/// Select the name of the branch that `selector` refers to.
+	fn select_branch(&self, data: &ConfigData, selector: Selector<'_>) -> AResult<String> {
+		select_branch(self.branch_names()?, data, selector)
+	}
+}
+
+/// Archive that the `latest` pointer of a branch refers to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatestPointer {
+	pub url: String,
+	/// SHA-256 digest that the source published for the archive, as a hexadecimal string, if there is one.
+	pub sha256: Option<String>,
+}
+
+/// Create the [`Provider`] specified by `config`.
+pub fn provider(config: &Config) -> Box<dyn Provider> {
+	match config.with_doc.data().source.kind {
+		SourceKind::Smdrop => Box::new(smdrop_client(config)),
+		SourceKind::Sourcepawn => Box::new(SourcePawnReleases::new(config)),
+		SourceKind::Command => Box::new(CommandProvider::new(config)),
+		SourceKind::Oci => Box::new(OciRepository::new(config)),
+	}
+}
+
+/// Select the branch that `selector` refers to out of `branches`.
+pub fn select_branch(mut branches: Vec<String>, data: &ConfigData, selector: Selector<'_>) -> AResult<String> {
+	fn with_ver(branches: Vec<String>, version: &str) -> AResult<String> {
+		let req: VersionReq = version.parse()
+			.with_context(|| anyhow!("invalid version requirement {version:?}"))?;
+		let branch = branches.iter()
+			.filter(move |b| req.may_match_within(b))
+			.max_by(move |a, b| version_ord(a.as_str(), b.as_str()));
+		branch.cloned().with_context(|| {
+			let suggestions = Suggestions::new(version, branches.iter().map(String::as_str));
+			anyhow!("couldn't select branch with selector {version:?}{suggestions}")
+		})
+	}
+
+	let branch_ord = move |a: &String, b: &String| version_ord(a.as_str(), b.as_str());
+	match selector {
+		Selector::Alias("latest") => {
+			branches.into_iter().max_by(branch_ord).context("couldn't select latest branch")
+		}
+		Selector::Alias("stable") => {
+			branches.sort_by(branch_ord);
+			branches.pop();
+			branches.pop().context("couldn't select latest stable branch")
+		}
+		Selector::Alias(Selector::INSTALLED) => {
+			bail!("alias {:?} only selects toolchains that are already installed", Selector::INSTALLED)
+		}
+		Selector::Alias(s) => {
+			let version = data.aliases.get(s).with_context(|| {
+				let candidates = ["latest", "stable"].into_iter()
+					.chain(data.aliases.keys().map(String::as_str))
+					.chain(branches.iter().map(String::as_str));
+				anyhow!("failed to resolve alias {s:?}{}", Suggestions::for_alias(s, candidates))
+			})?;
+			with_ver(branches, version)
+		}
+		Selector::Super(s) => with_ver(branches, s),
+		Selector::Path(s) => bail!("path {s:?} doesn't refer to a remote branch"),
+	}
+}
+
+/// Maximum number of listings that are fetched from a provider at the same time.
+pub const MAX_CONCURRENT_FETCHES: usize = 4;
+
+/// Return all archives for the `os` target on each of `branches`, in the same order.
+/// 
+/// Listings of up to [`MAX_CONCURRENT_FETCHES`] branches are fetched at the same time, so listing many branches doesn't
+/// take a round trip to the remote for each of them.
+pub fn relevant_urls_of(
+	provider: &dyn Provider, branches: Vec<String>, os: &str,
+) -> Vec<(String, AResult<Vec<RelevantUrl>>)> {
+	let fetched = for_each_branch(&branches, move |branch| provider.relevant_urls(branch, os));
+	branches.into_iter().zip(fetched).collect()
+}
+
+/// Call `fetch` with each of `branches` and return the results in the same order, calling it for up to
+/// [`MAX_CONCURRENT_FETCHES`] branches at the same time.
+fn for_each_branch<T: Send>(branches: &[String], fetch: impl Fn(&str) -> T + Sync) -> Vec<T> {
+	let next = AtomicUsize::new(0);
+	let mut fetched: Vec<_> = scope(|scope| {
+		let workers: Vec<_> = (0..branches.len().min(MAX_CONCURRENT_FETCHES))
+			.map(|_| scope.spawn(|| {
+				let mut fetched = Vec::new();
+				loop {
+					let i = next.fetch_add(1, Ordering::Relaxed);
+					let Some(branch) = branches.get(i) else { break };
+					fetched.push((i, fetch(branch)));
+				}
+				fetched
+			}))
+			.collect();
+		workers.into_iter()
+			.flat_map(move |worker| worker.join().unwrap_or_else(move |panic| resume_unwind(panic)))
+			.collect()
+	});
+	fetched.sort_by_key(move |(i, ..)| *i);
+	fetched.into_iter().map(move |(.., result)| result).collect()
+}
+
+/// Return the latest version for the `os` target on each of `branches`.
+/// 
+/// Branches that can't be fetched (for e.g. because they aren't on the remote) are skipped.
+pub fn latest_versions(
+	provider: &dyn Provider, branches: impl IntoIterator<Item = String>, os: &str,
+) -> FxHashMap<String, RelevantUrl> {
+	let branches: Vec<String> = branches.into_iter().collect();
+	let latest = for_each_branch(&branches, move |branch| provider.latest_url(branch, os, None));
+	branches.into_iter().zip(latest)
+		.filter_map(move |(branch, latest)| Some((branch, latest.ok()??)))
+		.collect()
+}
+
+/// [`Provider`] that answers with the branches and listings that were fetched ahead of time, for selecting versions
+/// with many selectors without fetching the same listings again.
+/// 
+/// Everything else (including listings that failed to be fetched ahead of time) is forwarded to the provider that they
+/// were fetched with.
+pub struct Prefetched<'a> {
+	provider: &'a dyn Provider,
+	os: String,
+	branch_names: Option<Vec<String>>,
+	listings: FxHashMap<String, Vec<RelevantUrl>>,
+}
+
+impl<'a> Prefetched<'a> {
+	/// Fetch the branch names of `provider`, and the listings for the `os` target of the branches that `selectors`
+	/// select.
+	/// 
+	/// Selectors that don't select a branch are skipped, and fail again when they're used with the returned provider.
+	pub fn new<'s>(
+		provider: &'a dyn Provider, data: &ConfigData, selectors: impl IntoIterator<Item = Selector<'s>>, os: &str,
+	) -> Self {
+		let branch_names = provider.branch_names().ok();
+		let mut branches: Vec<String> = match &branch_names {
+			Some(names) => selectors.into_iter()
+				.filter_map(move |s| select_branch(names.clone(), data, s).ok())
+				.collect(),
+			None => Vec::new(),
+		};
+		branches.sort();
+		branches.dedup();
+		let listings = relevant_urls_of(provider, branches, os).into_iter()
+			.filter_map(move |(branch, urls)| Some((branch, urls.ok()?)))
+			.collect();
+		Self {
+			provider,
+			os: os.to_string(),
+			branch_names,
+			listings,
+		}
+	}
+}
+
+impl Provider for Prefetched<'_> {
+	#[inline]
+	fn layout(&self) -> Layout {
+		self.provider.layout()
+	}
+
+	fn branch_names(&self) -> AResult<Vec<String>> {
+		match &self.branch_names {
+			Some(names) => Ok(names.clone()),
+			None => self.provider.branch_names(),
+		}
+	}
+
+	fn relevant_urls(&self, branch: &str, os: &str) -> AResult<Vec<RelevantUrl>> {
+		match self.listings.get(branch) {
+			Some(urls) if os == self.os => Ok(urls.clone()),
+			_ => self.provider.relevant_urls(branch, os),
+		}
+	}
+
+	#[inline]
+	fn fetch(&self, url: &str, max_bytes: u64) -> AResult<Box<dyn Read>> {
+		self.provider.fetch(url, max_bytes)
+	}
+
+	fn latest_url(&self, branch: &str, os: &str, req: Option<&VersionReq>) -> AResult<Option<RelevantUrl>> {
+		match self.listings.get(branch) {
+			Some(urls) if os == self.os => {
+				let urls = urls.iter().filter(move |u| req.is_none_or(|r| r.matches(u.version())));
+				Ok(urls.max_by(move |a, b| a.version_ord(b)).cloned())
+			}
+			_ => self.provider.latest_url(branch, os, req),
+		}
+	}
+
+	#[inline]
+	fn latest_pointer(&self, branch: &str, os: &str) -> AResult<Option<LatestPointer>> {
+		self.provider.latest_pointer(branch, os)
+	}
+
+	#[inline]
+	fn changelog_url(&self, branch: &str) -> Option<String> {
+		self.provider.changelog_url(branch)
+	}
+
+	fn select_branch(&self, data: &ConfigData, selector: Selector<'_>) -> AResult<String> {
+		match &self.branch_names {
+			Some(names) => select_branch(names.clone(), data, selector),
+			None => self.provider.select_branch(data, selector),
+		}
+	}
+}
+
+/// Return `true` if `error` from [`Provider::fetch`] means that there is nothing at the URL, rather than that it
+/// couldn't be downloaded.
+pub fn is_not_found(error: &anyhow::Error) -> bool {
+	error.chain().any(move |e| {
+		matches!(e.downcast_ref::<ureq::Error>(), Some(ureq::Error::StatusCode(404 | 410)))
+			|| e.downcast_ref::<IoError>().is_some_and(move |e| e.kind() == IoErrorKind::NotFound)
+	})
+}
+
+/// Start downloading the archive at `url` with `agent`, failing if it is larger than `max_bytes`.
+pub fn fetch_with(agent: &Agent, url: &str, max_bytes: u64) -> AResult<Box<dyn Read>> {
+	let reader = agent.get(url)
+		.call().with_context(|| anyhow!("failed to fetch archive at {url:?}"))?
+		.into_body().into_with_config()
+		.limit(max_bytes)
+		.reader();
+	Ok(Box::new(reader))
+}
+
+/// Return the target of the `rel="next"` link in the value of a `Link` header.
+fn next_link(header: &str) -> Option<&str> {
+	header.split(',').find_map(move |link| {
+		let (target, params) = link.split_once(';')?;
+		params.split(';').any(move |p| matches!(p.trim(), "rel=next" | "rel=\"next\""))
+			.then(move || target.trim().trim_start_matches('<').trim_end_matches('>'))
+	})
+}
+
+/// Return the text at `url`, or [`None`] if there is nothing there.
+fn fetch_text(agent: &Agent, url: &str) -> AResult<Option<String>> {
+	match agent.get(url).call() {
+		Ok(response) => {
+			let text = response.into_body().read_to_string().with_context(|| anyhow!("failed to read {url:?}"))?;
+			Ok(Some(text))
+		}
+		Err(ureq::Error::StatusCode(404 | 410)) => Ok(None),
+		Err(e) => Err(anyhow!(e).context(format!("failed to fetch {url:?}"))),
+	}
+}
+
+impl Provider for Client {
+	#[inline]
+	fn layout(&self) -> Layout {
+		Layout::SourceMod
+	}
+
+	fn branch_names(&self) -> AResult<Vec<String>> {
+		let branches = self.branches().map_err(move |e| anyhow!("couldn't fetch branches: {e}"))?;
+		branches.map(move |b| b.map(String::from))
+			.collect::<Result<_, _>>()
+			.context("couldn't parse listing of branches")
+	}
+
+	fn relevant_urls(&self, branch: &str, os: &str) -> AResult<Vec<RelevantUrl>> {
+		Branch::from(branch.to_string()).relevant_urls(self, os)
+	}
+
+	/// Pointers are files named like `sourcemod-latest-linux` with the file name of the latest archive, whose digest may
+	/// be published next to it in a `.sha256` file (as mirrors made with `rookup mirror` do).
+	fn latest_pointer(&self, branch: &str, os: &str) -> AResult<Option<LatestPointer>> {
+		let branch_url = Branch::from(branch.to_string()).url(self);
+		let pointer_url = format!("{branch_url}/sourcemod-latest-{}", archive_target(os));
+		let Some(file_name) = fetch_text(&self.agent, &pointer_url)? else {
+			return Ok(None)
+		};
+		let url = format!("{branch_url}/{}", file_name.trim());
+		let sha256 = fetch_text(&self.agent, &format!("{url}.sha256"))?
+			.and_then(move |text| text.split_whitespace().next().map(String::from));
+		Ok(Some(LatestPointer {
+			url,
+			sha256,
+		}))
+	}
+
+	#[inline]
+	fn fetch(&self, url: &str, max_bytes: u64) -> AResult<Box<dyn Read>> {
+		fetch_with(&self.agent, url, max_bytes)
+	}
+}
+
+#[test]
+fn listings_are_fetched_in_order() {
+	struct Branches;
+	impl Provider for Branches {
+		fn layout(&self) -> Layout {
+			Layout::SourcePawn
+		}
+		fn branch_names(&self) -> AResult<Vec<String>> {
+			bail!("not listed")
+		}
+		fn relevant_urls(&self, branch: &str, _os: &str) -> AResult<Vec<RelevantUrl>> {
+			if branch == "missing" {
+				bail!("no such branch")
+			}
+			Ok(vec![RelevantUrl::with_version(format!("https://example.com/{branch}.zip"), format!("{branch}.0"))])
+		}
+		fn fetch(&self, url: &str, _max_bytes: u64) -> AResult<Box<dyn Read>> {
+			bail!("can't fetch {url}")
+		}
+	}
+
+	let branches: Vec<String> = (0..10).map(move |i| format!("1.{i}")).chain(["missing".into()]).collect();
+	let fetched = relevant_urls_of(&Branches, branches.clone(), "linux");
+	assert_eq!(fetched.iter().map(move |(b, ..)| b.clone()).collect::<Vec<_>>(), branches);
+	for (branch, urls) in &fetched[..10] {
+		assert_eq!(urls.as_ref().unwrap()[0].version(), format!("{branch}.0"));
+	}
+	assert!(fetched[10].1.is_err());
+	assert_eq!(latest_versions(&Branches, branches, "linux").len(), 10);
+}
+
+#[test]
+fn next_links_are_found() {
+	assert_eq!(
+		next_link(r#"</v2/example/sourcemod/tags/list?last=1.12&n=100>; rel="next""#),
+		Some("/v2/example/sourcemod/tags/list?last=1.12&n=100"),
+	);
+	let github = concat!(
+		r#"<https://api.github.com/repositories/1/releases?page=1>; rel="prev", "#,
+		r#"<https://api.github.com/repositories/1/releases?page=3>; rel="next", "#,
+		r#"<https://api.github.com/repositories/1/releases?page=5>; rel="last""#,
+	);
+	assert_eq!(next_link(github), Some("https://api.github.com/repositories/1/releases?page=3"));
+	assert_eq!(next_link(r#"<https://api.github.com/repositories/1/releases?page=1>; rel="first""#), None);
+}
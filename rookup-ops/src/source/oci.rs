@@ -0,0 +1,500 @@
+use anyhow::{
+	anyhow, bail, Context, Result as AResult,
+};
+use base64::{
+	engine::general_purpose::STANDARD,
+	Engine,
+};
+use rookup_common::{
+	version::{
+		version_ord, Version, VersionReq,
+	},
+	docker_config_path, Config,
+};
+use rustc_hash::FxHashMap;
+use serde::Deserialize;
+use std::{
+	fs::read_to_string,
+	io::{
+		Read, Write,
+	},
+	process::{
+		Command, Stdio,
+	},
+	sync::Mutex,
+};
+use ureq::{
+	http::{
+		header::{
+			ACCEPT, AUTHORIZATION, LINK, WWW_AUTHENTICATE,
+		},
+		Response, StatusCode,
+	},
+	Agent, Body,
+};
+
+use crate::smdrop_util::{
+	branch_of, http_agent, RelevantUrl,
+};
+use super::{
+	sourcepawn::is_for_os, next_link, Layout, Provider,
+};
+
+/// Media type of the manifests of OCI artifacts.
+const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+/// Annotation with the file name of a layer, as set by ORAS.
+const TITLE_ANNOTATION: &str = "org.opencontainers.image.title";
+/// Annotation with the time that an artifact was created at.
+const CREATED_ANNOTATION: &str = "org.opencontainers.image.created";
+
+/// Provider of SourceMod builds pushed to a container registry as OCI artifacts, like with
+/// `oras push <registry>/<repository>:<version> sourcemod-<version>-linux.tar.gz ...`.
+///
+/// Tags of the repository are the versions, and the files of each artifact are its layers, named by their
+/// `org.opencontainers.image.title` annotations.
+/// URLs of archives are of the form `<root-url>:<tag>#<file name>`.
+///
+/// Registries that require authentication are logged into with the token flow of the Docker registry API, using
+/// credentials for the registry from the Docker configuration file (see [`docker_config_path`]) or the `.netrc` file.
+/// Credentials that the configuration file keeps in a credential store are read with its `docker-credential-*` helper.
+#[derive(Debug)]
+pub struct OciRepository {
+	agent: Agent,
+	root_url: String,
+	/// Credentials from the Docker configuration file, as the value of an `Authorization` header, or why they
+	/// couldn't be read.
+	credentials: Result<Option<String>, String>,
+	/// Value of the `Authorization` header that the registry last accepted.
+	authorization: Mutex<Option<String>>,
+	/// Manifests fetched so far, by their tags, so that the manifest of a listed version isn't fetched again to
+	/// download it.
+	manifests: Mutex<FxHashMap<String, Manifest>>,
+}
+
+/// Artifact manifest as returned by the registry.
+#[derive(Debug, Clone, Deserialize)]
+struct Manifest {
+	#[serde(default)]
+	layers: Vec<Descriptor>,
+	#[serde(default)]
+	annotations: FxHashMap<String, String>,
+}
+
+/// Layer of an artifact.
+#[derive(Debug, Clone, Deserialize)]
+struct Descriptor {
+	digest: String,
+	#[serde(default)]
+	size: Option<u64>,
+	#[serde(default)]
+	annotations: FxHashMap<String, String>,
+}
+
+impl Descriptor {
+	fn title(&self) -> Option<&str> {
+		self.annotations.get(TITLE_ANNOTATION).map(String::as_str)
+	}
+}
+
+/// Listing of tags as returned by the registry.
+#[derive(Debug, Clone, Deserialize)]
+struct TagList {
+	#[serde(default)]
+	tags: Option<Vec<String>>,
+}
+
+/// Token as returned by the authorization service of a registry.
+#[derive(Debug, Clone, Deserialize)]
+struct TokenResponse {
+	#[serde(default)]
+	token: Option<String>,
+	#[serde(default)]
+	access_token: Option<String>,
+}
+
+/// Contents of the Docker configuration file that are relevant to logging into registries.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DockerConfig {
+	#[serde(default)]
+	auths: FxHashMap<String, DockerAuth>,
+	/// Name of the credential helper that keeps the credentials of all registries, like `desktop` for
+	/// `docker-credential-desktop`.
+	#[serde(default)]
+	creds_store: Option<String>,
+	/// Map of registries to the names of the credential helpers that keep their credentials, which take precedence
+	/// over [`creds_store`](Self::creds_store).
+	#[serde(default)]
+	cred_helpers: FxHashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+struct DockerAuth {
+	/// Base64 encoded `login:password`.
+	#[serde(default)]
+	auth: Option<String>,
+}
+
+/// Credentials as printed by the `get` command of a credential helper.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct HelperCredentials {
+	username: String,
+	secret: String,
+}
+
+/// Way to authenticate that the registry asked for in a `WWW-Authenticate` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Challenge {
+	/// Get a token from the authorization service at `realm`, with the given query parameters.
+	Bearer {
+		realm: String,
+		params: Vec<(String, String)>,
+	},
+	/// Send credentials with every request.
+	Basic,
+}
+
+impl Challenge {
+	/// Parse the value of a `WWW-Authenticate` header, like
+	/// `Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:org/sm:pull"`.
+	fn parse(header: &str) -> Option<Self> {
+		let (scheme, rest) = header.trim().split_once(' ').unwrap_or((header.trim(), ""));
+		if scheme.eq_ignore_ascii_case("basic") {
+			return Some(Self::Basic)
+		}
+		if !scheme.eq_ignore_ascii_case("bearer") {
+			return None
+		}
+
+		let mut realm = None;
+		let mut params = Vec::new();
+		let mut rest = rest.trim_start();
+		while let Some((key, after)) = rest.split_once('=') {
+			let key = key.trim().trim_start_matches(',').trim();
+			let (value, after) = match after.strip_prefix('"') {
+				Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+				None => after.split_once(',').unwrap_or((after, "")),
+			};
+			if key.eq_ignore_ascii_case("realm") {
+				realm = Some(value.to_string());
+			} else {
+				params.push((key.to_ascii_lowercase(), value.to_string()));
+			}
+			rest = after.trim_start().trim_start_matches(',').trim_start();
+		}
+		Some(Self::Bearer {
+			realm: realm?,
+			params,
+		})
+	}
+}
+
+impl OciRepository {
+	/// Create a new provider for the repository at `root-url`.
+	pub fn new(config: &Config) -> Self {
+		let root_url = config.with_doc.data().source.root_url.trim_end_matches('/').to_string();
+		let credentials = match split_repository(&root_url) {
+			Ok((registry, _)) => docker_credentials(host_of(registry)).map_err(move |e| format!("{e:#}")),
+			Err(..) => Ok(None),
+		};
+		Self {
+			agent: http_agent(config.with_doc.data()),
+			root_url,
+			credentials,
+			authorization: Mutex::new(None),
+			manifests: Mutex::new(FxHashMap::default()),
+		}
+	}
+
+	/// Return the URL of the registry API endpoint at `path` (relative to `/v2/<repository>/`).
+	fn api_url(&self, path: &str) -> AResult<String> {
+		let (registry, repository) = split_repository(&self.root_url)?;
+		Ok(format!("{registry}/v2/{repository}/{path}"))
+	}
+
+	/// Send a `GET` request to `url`, logging into the registry if it asks for it, and fail if it isn't successful.
+	fn get(&self, url: &str, accept: Option<&str>) -> AResult<Response<Body>> {
+		let send = move |authorization: Option<&str>| {
+			let mut request = self.agent.get(url);
+			if let Some(accept) = accept {
+				request = request.header(ACCEPT, accept);
+			}
+			if let Some(authorization) = authorization {
+				request = request.header(AUTHORIZATION, authorization);
+			}
+			request.config().http_status_as_error(false).build()
+				.call().with_context(|| anyhow!("failed to fetch {url:?}"))
+		};
+
+		let authorization = self.authorization.lock().unwrap_or_else(move |e| e.into_inner()).clone();
+		let mut response = send(authorization.as_deref())?;
+		if response.status() == StatusCode::UNAUTHORIZED {
+			let challenge = response.headers().get(WWW_AUTHENTICATE)
+				.and_then(move |v| v.to_str().ok())
+				.and_then(Challenge::parse);
+			if let Some(authorization) = challenge.map(|c| self.authorize(&c)).transpose()?.flatten() {
+				response = send(Some(&authorization))?;
+				if response.status().is_success() {
+					*self.authorization.lock().unwrap_or_else(move |e| e.into_inner()) = Some(authorization);
+				}
+			}
+		}
+
+		let status = response.status();
+		if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+			if let Err(e) = &self.credentials {
+				bail!("registry denied access to {url:?} ({status}), and its credentials couldn't be read: {e}")
+			}
+			bail!("registry denied access to {url:?} ({status}); log into it with `docker login` or add it to the .netrc file")
+		} else if !status.is_success() {
+			bail!("failed to fetch {url:?}: registry returned {status}")
+		}
+		Ok(response)
+	}
+
+	/// Return the value of the `Authorization` header that answers `challenge`, or [`None`] if there are no credentials
+	/// to send.
+	fn authorize(&self, challenge: &Challenge) -> AResult<Option<String>> {
+		let (realm, params) = match challenge {
+			Challenge::Basic => return Ok(self.credentials.clone().ok().flatten()),
+			Challenge::Bearer { realm, params } => (realm, params),
+		};
+		let mut request = self.agent.get(realm.as_str());
+		for (key, value) in params.iter() {
+			request = request.query(key, value);
+		}
+		// Without Docker credentials, those from the `.netrc` file are sent, if there are any.
+		if let Ok(Some(credentials)) = &self.credentials {
+			request = request.header(AUTHORIZATION, credentials);
+		}
+		let response: TokenResponse = request
+			.call().with_context(|| anyhow!("failed to get a token for the registry from {realm:?}"))?
+			.into_body().read_json()
+			.with_context(|| anyhow!("failed to parse token from {realm:?}"))?;
+		let token = response.token.or(response.access_token)
+			.with_context(|| anyhow!("authorization service at {realm:?} didn't return a token"))?;
+		Ok(Some(format!("Bearer {token}")))
+	}
+
+	fn tags(&self) -> AResult<Vec<String>> {
+		let mut tags = Vec::new();
+		let mut url = self.api_url("tags/list")?;
+		loop {
+			let response = self.get(&url, None)?;
+			let next = response.headers().get(LINK)
+				.and_then(move |v| v.to_str().ok())
+				.and_then(next_link)
+				.map(String::from);
+			let list: TagList = response.into_body().read_json()
+				.with_context(|| anyhow!("failed to parse tags from {url:?}"))?;
+			tags.extend(list.tags.unwrap_or_default());
+			match next {
+				// Links are usually relative to the registry.
+				Some(next) if next.starts_with('/') => {
+					let (registry, _) = split_repository(&self.root_url)?;
+					url = format!("{registry}{next}");
+				}
+				Some(next) => url = next,
+				None => return Ok(tags),
+			}
+		}
+	}
+
+	/// Return the tags of the versions on `branch`, in order of their versions, with one tag for each version.
+	fn tags_of(&self, branch: &str) -> AResult<Vec<String>> {
+		let mut tags = self.tags()?;
+		tags.retain(move |t| branch_of(version_of(t)).is_some() && version_of(t).is_sub_version_of(branch));
+		tags.sort_by(move |a, b| version_ord(version_of(a), version_of(b)));
+		tags.dedup_by(move |a, b| version_of(a) == version_of(b));
+		Ok(tags)
+	}
+
+	fn manifest(&self, tag: &str) -> AResult<Manifest> {
+		if let Some(manifest) = self.manifests.lock().unwrap_or_else(move |e| e.into_inner()).get(tag) {
+			return Ok(manifest.clone())
+		}
+		let url = self.api_url(&format!("manifests/{tag}"))?;
+		let manifest: Manifest = self.get(&url, Some(MANIFEST_MEDIA_TYPE))?
+			.into_body().read_json()
+			.with_context(|| anyhow!("failed to parse manifest of {}:{tag}", self.root_url))?;
+		self.manifests.lock().unwrap_or_else(move |e| e.into_inner()).insert(tag.into(), manifest.clone());
+		Ok(manifest)
+	}
+
+	/// Return the archive for the `os` target in the artifact tagged `tag`, if it has one.
+	fn relevant_url(&self, tag: &str, os: &str) -> AResult<Option<RelevantUrl>> {
+		let manifest = self.manifest(tag)?;
+		let layer = manifest.layers.iter()
+			.filter_map(move |l| Some((l.title()?, l.size)))
+			.find(move |(t, ..)| RelevantUrl::is_archive(t) && is_for_os(t, os));
+		let Some((title, size)) = layer else {
+			return Ok(None)
+		};
+		let published = manifest.annotations.get(CREATED_ANNOTATION)
+			.and_then(move |c| humantime::parse_rfc3339_weak(c).ok());
+		Ok(Some(
+			RelevantUrl::with_version(format!("{}:{tag}#{title}", self.root_url), version_of(tag))
+				.with_published(published)
+				.with_size(size)
+		))
+	}
+}
+
+impl Provider for OciRepository {
+	#[inline]
+	fn layout(&self) -> Layout {
+		Layout::SourceMod
+	}
+
+	fn branch_names(&self) -> AResult<Vec<String>> {
+		let mut branches: Vec<String> = self.tags()?.iter().filter_map(move |t| branch_of(version_of(t))).collect();
+		branches.sort();
+		branches.dedup();
+		Ok(branches)
+	}
+
+	fn relevant_urls(&self, branch: &str, os: &str) -> AResult<Vec<RelevantUrl>> {
+		let mut urls = Vec::new();
+		for tag in self.tags_of(branch)? {
+			urls.extend(self.relevant_url(&tag, os)?);
+		}
+		Ok(urls)
+	}
+
+	fn latest_url(&self, branch: &str, os: &str, req: Option<&VersionReq>) -> AResult<Option<RelevantUrl>> {
+		let mut tags = self.tags_of(branch)?;
+		tags.retain(move |t| req.is_none_or(|r| r.matches(version_of(t))));
+		// Manifests are fetched from the newest version down, until one has an archive for the target.
+		for tag in tags.iter().rev() {
+			if let Some(url) = self.relevant_url(tag, os)? {
+				return Ok(Some(url))
+			}
+		}
+		Ok(None)
+	}
+
+	fn fetch(&self, url: &str, max_bytes: u64) -> AResult<Box<dyn Read>> {
+		let (tag, title) = url.strip_prefix(self.root_url.as_str())
+			.and_then(move |r| r.strip_prefix(':'))
+			.and_then(move |r| r.split_once('#'))
+			.with_context(|| anyhow!("{url:?} isn't a file in the repository {:?}", self.root_url))?;
+		let manifest = self.manifest(tag)?;
+		let layer = manifest.layers.iter()
+			.find(move |l| l.title() == Some(title))
+			.with_context(|| anyhow!("{}:{tag} has no file named {title:?}", self.root_url))?;
+		let blob_url = self.api_url(&format!("blobs/{}", layer.digest))?;
+		let reader = self.get(&blob_url, None)
+			.with_context(|| anyhow!("failed to fetch archive at {url:?}"))?
+			.into_body().into_with_config()
+			.limit(max_bytes)
+			.reader();
+		Ok(Box::new(reader))
+	}
+}
+
+/// Return the version that `tag` is for, which is the tag without a `v` prefix.
+fn version_of(tag: &str) -> &str {
+	tag.strip_prefix('v').unwrap_or(tag)
+}
+
+/// Split the URL of a repository into the URL of its registry and the name of the repository.
+fn split_repository(url: &str) -> AResult<(&str, &str)> {
+	let scheme_end = url.find("://").map(move |i| i + 3)
+		.with_context(|| anyhow!("repository URL {url:?} has no scheme (like `https://`)"))?;
+	match url[scheme_end..].split_once('/') {
+		Some((host, repository)) if !repository.is_empty() => Ok((&url[..scheme_end + host.len()], repository)),
+		_ => bail!("repository URL {url:?} has no repository name after the registry"),
+	}
+}
+
+/// Return the host (with the port, if any) of the registry at `url`.
+fn host_of(url: &str) -> &str {
+	let host = url.split_once("://").map_or(url, move |(_, rest)| rest);
+	host.split_once('/').map_or(host, move |(host, _)| host)
+}
+
+/// Return the credentials for the registry at `host` from the Docker configuration file, as the value of an
+/// `Authorization` header, or [`None`] if there are none.
+/// 
+/// Like Docker, credentials are read with the helper for the registry in `credHelpers`, or else the one in
+/// `credsStore`, and are only taken from `auths` if there is no helper.
+fn docker_credentials(host: &str) -> AResult<Option<String>> {
+	let Some(text) = docker_config_path().and_then(move |path| read_to_string(path).ok()) else {
+		return Ok(None)
+	};
+	let config: DockerConfig = serde_json::from_str(&text).context("failed to parse the Docker configuration file")?;
+	let find = move |key: &&String| host_of(key).eq_ignore_ascii_case(host);
+	let server = config.auths.keys().find(find).map_or(host, String::as_str);
+	let helper = config.cred_helpers.iter().find(move |(key, _)| find(key)).map(move |(_, helper)| helper)
+		.or(config.creds_store.as_ref());
+	match helper {
+		Some(helper) => helper_credentials(helper, server),
+		None => Ok(
+			config.auths.get(server)
+				.and_then(move |auth| auth.auth.as_deref())
+				.map(move |auth| format!("Basic {auth}"))
+		),
+	}
+}
+
+/// Return the credentials for `server` that the credential helper `docker-credential-<helper>` keeps, as the value of
+/// an `Authorization` header, or [`None`] if it has none.
+fn helper_credentials(helper: &str, server: &str) -> AResult<Option<String>> {
+	let program = format!("docker-credential-{helper}");
+	let mut child = Command::new(&program).arg("get")
+		.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped())
+		.spawn().with_context(|| anyhow!("failed to run credential helper `{program}`"))?;
+	if let Some(mut stdin) = child.stdin.take() {
+		stdin.write_all(server.as_bytes())
+			.with_context(|| anyhow!("failed to send the registry to credential helper `{program}`"))?;
+	}
+	let output = child.wait_with_output().with_context(|| anyhow!("failed to run credential helper `{program}`"))?;
+	if !output.status.success() {
+		let message = String::from_utf8_lossy(&output.stdout);
+		// Helpers fail with this message (or one like it) for registries that weren't logged into.
+		if message.contains("credentials not found") {
+			return Ok(None)
+		}
+		bail!("credential helper `{program}` failed with {}: {}", output.status, message.trim())
+	}
+	let credentials: HelperCredentials = serde_json::from_slice(&output.stdout)
+		.with_context(|| anyhow!("failed to parse credentials printed by `{program}`"))?;
+	// Identity tokens are only accepted by the OAuth flow of the registry, which isn't supported.
+	if credentials.username == "<token>" {
+		bail!("credential helper `{program}` returned an identity token, which isn't supported")
+	}
+	let basic = format!("{}:{}", credentials.username, credentials.secret);
+	Ok(Some(format!("Basic {}", STANDARD.encode(basic))))
+}
+
+#[test]
+fn registry_references_are_parsed() {
+	assert_eq!(
+		split_repository("https://ghcr.io/example/sourcemod").unwrap(),
+		("https://ghcr.io", "example/sourcemod"),
+	);
+	assert!(split_repository("https://ghcr.io").is_err());
+	assert_eq!(host_of("https://index.docker.io/v1/"), "index.docker.io");
+	assert_eq!(
+		Challenge::parse(r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:example/sm:pull""#),
+		Some(Challenge::Bearer {
+			realm: "https://ghcr.io/token".into(),
+			params: vec![("service".into(), "ghcr.io".into()), ("scope".into(), "repository:example/sm:pull".into())],
+		}),
+	);
+	assert_eq!(Challenge::parse("Basic realm=\"registry\""), Some(Challenge::Basic));
+}
+
+#[test]
+fn docker_credential_helpers_are_read() {
+	let config: DockerConfig = serde_json::from_str(r#"{
+		"auths": {"ghcr.io": {}, "https://index.docker.io/v1/": {"auth": "dXNlcjpwYXNz"}},
+		"credsStore": "desktop",
+		"credHelpers": {"registry.example.com": "ecr-login"}
+	}"#).unwrap();
+	assert_eq!(config.creds_store.as_deref(), Some("desktop"));
+	assert_eq!(config.cred_helpers["registry.example.com"], "ecr-login");
+	assert_eq!(config.auths["https://index.docker.io/v1/"].auth.as_deref(), Some("dXNlcjpwYXNz"));
+	assert!(helper_credentials("rookup-missing-helper", "ghcr.io").unwrap_err().to_string().contains("failed to run"));
+}
@@ -0,0 +1,175 @@
+use anyhow::{
+	anyhow, Context, Result as AResult,
+};
+use rookup_common::{
+	version::Version,
+	Config,
+};
+use serde::Deserialize;
+use std::io::Read;
+use ureq::{
+	http::header::{
+		ACCEPT, LINK,
+	},
+	Agent,
+};
+
+use crate::smdrop_util::{
+	http_agent, RelevantUrl, ReleaseNotes,
+};
+use super::{
+	fetch_with, next_link, Layout, Provider,
+};
+
+/// Provider of standalone SourcePawn compiler builds published as GitHub releases.
+#[derive(Debug, Clone)]
+pub struct SourcePawnReleases {
+	agent: Agent,
+	releases_url: String,
+}
+
+/// Release as returned by the GitHub releases API.
+#[derive(Debug, Clone, Deserialize)]
+struct Release {
+	tag_name: String,
+	#[serde(default)]
+	draft: bool,
+	#[serde(default)]
+	published_at: Option<String>,
+	#[serde(default)]
+	html_url: Option<String>,
+	#[serde(default)]
+	body: Option<String>,
+	assets: Vec<Asset>,
+}
+
+/// Release asset as returned by the GitHub releases API.
+#[derive(Debug, Clone, Deserialize)]
+struct Asset {
+	name: String,
+	browser_download_url: String,
+	#[serde(default)]
+	size: Option<u64>,
+}
+
+impl Release {
+	/// Return the version of this release, which is the tag name without a `v` prefix.
+	fn version(&self) -> &str {
+		self.tag_name.strip_prefix('v').unwrap_or(&self.tag_name)
+	}
+
+	/// Return the release notes of this release, if it has any.
+	fn notes(&self) -> Option<ReleaseNotes> {
+		let body = self.body.as_deref().map(str::trim).filter(move |b| !b.is_empty());
+		(self.html_url.is_some() || body.is_some()).then(|| ReleaseNotes {
+			url: self.html_url.clone(),
+			body: body.map(String::from),
+		})
+	}
+
+	/// Return the name of the branch this release belongs to (for e.g. `1.12` for `1.12.0.7000`).
+	fn branch(&self) -> String {
+		let mut parts = self.version().split('.');
+		match (parts.next(), parts.next()) {
+			(Some(major), Some(minor)) => format!("{major}.{minor}"),
+			_ => self.version().into(),
+		}
+	}
+}
+
+impl SourcePawnReleases {
+	/// Create a new provider that uses the releases API endpoint at `root-url`.
+	pub fn new(config: &Config) -> Self {
+		Self {
+			agent: http_agent(config.with_doc.data()),
+			releases_url: config.with_doc.data().source.root_url.clone(),
+		}
+	}
+
+	/// Return every release that isn't a draft, following the `Link` headers of the API to the pages after the first.
+	fn releases(&self) -> AResult<Vec<Release>> {
+		let mut releases = Vec::new();
+		let mut url = self.releases_url.clone();
+		loop {
+			let response = self.agent.get(url.as_str())
+				.header(ACCEPT, "application/vnd.github+json")
+				.call().with_context(|| anyhow!("failed to fetch releases from {url:?}"))?;
+			let next = response.headers().get(LINK)
+				.and_then(move |v| v.to_str().ok())
+				.and_then(next_link)
+				.map(String::from);
+			let page: Vec<Release> = response.into_body().read_json()
+				.with_context(|| anyhow!("failed to parse releases from {url:?}"))?;
+			releases.extend(page.into_iter().filter(move |r| !r.draft));
+			match next {
+				Some(next) => url = next,
+				None => return Ok(releases),
+			}
+		}
+	}
+}
+
+impl Provider for SourcePawnReleases {
+	#[inline]
+	fn layout(&self) -> Layout {
+		Layout::SourcePawn
+	}
+
+	fn branch_names(&self) -> AResult<Vec<String>> {
+		let mut branches: Vec<String> = self.releases()?.iter().map(Release::branch).collect();
+		branches.sort();
+		branches.dedup();
+		Ok(branches)
+	}
+
+	fn relevant_urls(&self, branch: &str, os: &str) -> AResult<Vec<RelevantUrl>> {
+		let mut urls = Vec::new();
+		for release in self.releases()? {
+			if !release.version().is_sub_version_of(branch) {
+				continue
+			}
+			let asset = release.assets.iter()
+				.filter(move |a| RelevantUrl::is_archive(&a.name) && is_for_os(&a.name, os))
+				.min_by_key(move |a| !is_for_this_arch(&a.name));
+			if let Some(asset) = asset {
+				let published = release.published_at.as_deref().and_then(move |p| humantime::parse_rfc3339(p).ok());
+				urls.push(
+					RelevantUrl::with_version(asset.browser_download_url.as_str(), release.version())
+						.with_published(published)
+						.with_size(asset.size)
+						.with_notes(release.notes())
+				);
+			}
+		}
+		Ok(urls)
+	}
+
+	#[inline]
+	fn fetch(&self, url: &str, max_bytes: u64) -> AResult<Box<dyn Read>> {
+		fetch_with(&self.agent, url, max_bytes)
+	}
+}
+
+/// Return `true` if an asset named `name` has any of the `-` or `.` separated `tokens`, ignoring case.
+fn has_any_token(name: &str, tokens: &[&str]) -> bool {
+	name.split(['-', '.']).any(move |part| tokens.iter().any(move |t| part.eq_ignore_ascii_case(t)))
+}
+
+/// Return `true` if an asset named `name` is built for the `os` operating system.
+pub(super) fn is_for_os(name: &str, os: &str) -> bool {
+	match os {
+		"windows" => has_any_token(name, &["windows", "win", "win32", "win64"]),
+		"macos" => has_any_token(name, &["mac", "macos", "darwin"]),
+		os => has_any_token(name, &[os]),
+	}
+}
+
+/// Return `true` if an asset named `name` is built for this architecture.
+fn is_for_this_arch(name: &str) -> bool {
+	match std::env::consts::ARCH {
+		"x86_64" => has_any_token(name, &["x86_64", "x64", "amd64"]),
+		"x86" => has_any_token(name, &["x86", "i386", "i686"]),
+		"aarch64" => has_any_token(name, &["aarch64", "arm64"]),
+		arch => has_any_token(name, &[arch]),
+	}
+}
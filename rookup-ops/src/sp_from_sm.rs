@@ -0,0 +1,71 @@
+use clean_path::clean;
+use rookup_common::{
+	is_license_file, CompilerNames, Extra, EXTRAS_PATH, LICENSES_PATH,
+};
+use std::path::{
+	Component, Path, PathBuf,
+};
+
+pub const SM_ROOT: &str = "addons/sourcemod/";
+pub const SM_SP_ROOT: &str = "addons/sourcemod/scripting/";
+
+/// Map the name of an entry in a SourceMod archive to a path relative to the toolchain root, or return [`None`] if the
+/// entry shouldn't be extracted.
+pub fn map_entry(name: &Path, compilers: &CompilerNames, extras: &[Extra]) -> Option<PathBuf> {
+	let extra = map_to_extra(name, extras);
+	match map_to_sp_root(name) {
+		Some(path) if is_sp_file(&path, compilers) => Some(path),
+		_ => extra.or_else(move || map_to_license(name)),
+	}
+}
+
+/// Map the name of an entry in a SourceMod archive to a path in the licenses directory of the toolchain, if it is a
+/// license file in the root of SourceMod (like `addons/sourcemod/GPLv3.txt`).
+pub fn map_to_license(name: &Path) -> Option<PathBuf> {
+	let path = clean(name.strip_prefix(SM_ROOT).ok()?);
+	let mut components = path.components();
+	match (components.next(), components.next()) {
+		(Some(Component::Normal(file_name)), None) if is_license_file(file_name.to_str()?) => {
+			Some(Path::new(LICENSES_PATH).join(file_name))
+		}
+		_ => None,
+	}
+}
+
+/// Map the name of an entry in a SourceMod archive to a path in the extras directory of the toolchain, if it is part of
+/// any of the `extras`.
+pub fn map_to_extra(name: &Path, extras: &[Extra]) -> Option<PathBuf> {
+	let path = clean(name.strip_prefix(SM_ROOT).ok()?);
+	if !path.components().all(move |c| matches!(c, Component::Normal(..))) {
+		return None
+	}
+
+	let is_extra = extras.iter().any(|extra| match extra {
+		Extra::Scripts => path.starts_with("scripting") && path.extension().is_some_and(move |e| e == "sp"),
+		Extra::Plugins => path.starts_with("plugins"),
+		Extra::Translations => path.starts_with("translations"),
+	});
+	is_extra.then(move || Path::new(EXTRAS_PATH).join(path))
+}
+
+pub fn map_to_sp_root(name: &Path) -> Option<PathBuf> {
+	let name = name.strip_prefix(SM_SP_ROOT).ok()?;
+	(!name.as_os_str().is_empty()).then(move || clean(name))
+}
+
+pub fn is_sp_file(path: &Path, compilers: &CompilerNames) -> bool {
+	if path.starts_with("include") {
+		true
+	} else {
+		let file_name = path.file_name().and_then(move |n| n.to_str());
+		file_name.is_some_and(move |n| compilers.contains(n))
+	}
+}
+
+#[test]
+fn license_files_are_mapped() {
+	assert_eq!(map_to_license(Path::new("addons/sourcemod/GPLv3.txt")), Some(PathBuf::from("licenses/GPLv3.txt")));
+	assert_eq!(map_to_license(Path::new("addons/sourcemod/LICENSE.txt")), Some(PathBuf::from("licenses/LICENSE.txt")));
+	assert_eq!(map_to_license(Path::new("addons/sourcemod/scripting/LICENSE.txt")), None);
+	assert_eq!(map_to_license(Path::new("addons/sourcemod/configs/core.cfg")), None);
+}
@@ -0,0 +1,41 @@
+use clean_path::clean;
+use rookup_common::{
+	is_license_file, CompilerNames, LICENSES_PATH,
+};
+use std::path::{
+	Component, Path, PathBuf,
+};
+
+/// Map the name of an entry in a standalone SourcePawn archive to a path relative to the toolchain root.
+/// 
+/// The compiler executable is put into the root, no matter where it is in the archive, and anything inside of an
+/// `include` directory is put into the `include` directory of the toolchain.
+/// License files outside of it are put into the licenses directory of the toolchain, at the same path relative to it as
+/// in the archive, so that license files with the same name in different directories are all kept.
+pub fn map_to_sp_root(name: &Path, compilers: &CompilerNames) -> Option<PathBuf> {
+	let path = clean(name);
+	let file_name = path.file_name().and_then(move |n| n.to_str());
+	if file_name.is_some_and(move |n| compilers.contains(n)) {
+		return path.file_name().map(PathBuf::from)
+	}
+
+	let mut components = path.components();
+	if components.by_ref().any(move |c| c == Component::Normal("include".as_ref())) {
+		let rest = components.as_path();
+		return (!rest.as_os_str().is_empty()).then(move || Path::new("include").join(rest))
+	}
+	let is_relative = path.components().all(move |c| matches!(c, Component::Normal(..)));
+	(is_relative && file_name.is_some_and(is_license_file)).then(move || Path::new(LICENSES_PATH).join(path))
+}
+
+#[test]
+fn license_files_keep_their_directories() {
+	let compilers = CompilerNames::defaults("linux", "x86_64");
+	let map = move |name: &str| map_to_sp_root(Path::new(name), &compilers);
+	assert_eq!(map("sourcepawn/LICENSE.txt"), Some(PathBuf::from("licenses/sourcepawn/LICENSE.txt")));
+	let zlib = "sourcepawn/third_party/zlib/LICENSE.txt";
+	assert_eq!(map(zlib), Some(Path::new("licenses").join(zlib)));
+	assert_eq!(map("sourcepawn/include/LICENSE.txt"), Some(PathBuf::from("include/LICENSE.txt")));
+	assert_eq!(map("../LICENSE.txt"), None);
+	assert_eq!(map("sourcepawn/README.md"), None);
+}
@@ -0,0 +1,184 @@
+//! Buffering of downloaded archives, which are moved to a temporary file once they grow large.
+
+use std::{
+	fs::{
+		create_dir_all, remove_file, File,
+	},
+	io::{
+		Cursor, Read, Result as IoResult, Seek, SeekFrom, Write,
+	},
+	path::{
+		Path, PathBuf,
+	},
+	sync::atomic::{
+		AtomicU64, Ordering as AtomicOrdering,
+	},
+	time::{
+		SystemTime, UNIX_EPOCH,
+	},
+};
+
+/// Buffer that keeps what is written to it in memory until it exceeds `threshold` bytes, after which everything is
+/// moved to a temporary file in `dir`.
+///
+/// If `dir` is [`None`], the buffer always stays in memory.
+pub struct Spool {
+	threshold: u64,
+	dir: Option<PathBuf>,
+	contents: Contents,
+}
+
+impl Spool {
+	pub const fn new(threshold: u64, dir: Option<PathBuf>) -> Self {
+		Self {
+			threshold,
+			dir,
+			contents: Contents::Memory(Cursor::new(Vec::new())),
+		}
+	}
+
+	/// Consume the buffer and return its contents, positioned at the start.
+	pub fn into_contents(mut self) -> IoResult<Contents> {
+		self.contents.rewind()?;
+		Ok(self.contents)
+	}
+}
+
+impl Write for Spool {
+	fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+		if let (Contents::Memory(cursor), Some(dir)) = (&self.contents, self.dir.as_deref()) {
+			let size = cursor.get_ref().len() as u64;
+			if size.saturating_add(buf.len() as u64) > self.threshold {
+				let mut file = TempFile::create(dir, "download")?;
+				file.file().write_all(cursor.get_ref())?;
+				self.contents = Contents::File(file);
+			}
+		}
+		self.contents.write(buf)
+	}
+
+	fn flush(&mut self) -> IoResult<()> {
+		self.contents.flush()
+	}
+}
+
+/// Contents of a [`Spool`].
+#[derive(Debug)]
+pub enum Contents {
+	Memory(Cursor<Vec<u8>>),
+	File(TempFile),
+}
+
+impl Contents {
+	/// Return the size of the contents in bytes.
+	pub fn len(&self) -> IoResult<u64> {
+		match self {
+			Self::Memory(cursor) => Ok(cursor.get_ref().len() as _),
+			Self::File(file) => file.path.metadata().map(move |m| m.len()),
+		}
+	}
+	/// Return `true` if there are no contents.
+	pub fn is_empty(&self) -> IoResult<bool> {
+		self.len().map(move |len| len == 0)
+	}
+}
+
+impl Read for Contents {
+	fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+		match self {
+			Self::Memory(cursor) => cursor.read(buf),
+			Self::File(file) => file.file().read(buf),
+		}
+	}
+}
+
+impl Write for Contents {
+	fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+		match self {
+			Self::Memory(cursor) => cursor.write(buf),
+			Self::File(file) => file.file().write(buf),
+		}
+	}
+
+	fn flush(&mut self) -> IoResult<()> {
+		match self {
+			Self::Memory(..) => Ok(()),
+			Self::File(file) => file.file().flush(),
+		}
+	}
+}
+
+impl Seek for Contents {
+	fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+		match self {
+			Self::Memory(cursor) => cursor.seek(pos),
+			Self::File(file) => file.file().seek(pos),
+		}
+	}
+}
+
+/// File that is deleted when dropped.
+#[derive(Debug)]
+pub struct TempFile {
+	/// Handle to the file, which is only [`None`] while it's being dropped.
+	file: Option<File>,
+	path: PathBuf,
+}
+
+impl TempFile {
+	/// Create a new, empty file in `dir` with a name that starts with `prefix`, creating `dir` if needed.
+	/// 
+	/// The file is always newly created, so an existing file or symbolic link (for e.g. one planted in a shared
+	/// temporary directory) is never opened instead.
+	pub fn create(dir: &Path, prefix: &str) -> IoResult<Self> {
+		static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+		create_dir_all(dir)?;
+		let n = COUNTER.fetch_add(1, AtomicOrdering::Relaxed);
+		let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().subsec_nanos();
+		let path = dir.join(format!("{prefix}-{}-{n}-{nanos:08x}", std::process::id()));
+		let file = File::options().read(true).write(true).create_new(true).open(&path)?;
+		Ok(Self {
+			file: Some(file),
+			path,
+		})
+	}
+
+	/// Return the path to the file.
+	#[inline]
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+
+	/// Return the handle to the file.
+	pub fn file(&mut self) -> &mut File {
+		self.file.as_mut().expect("file should only be taken when dropped")
+	}
+}
+
+impl Drop for TempFile {
+	fn drop(&mut self) {
+		// Windows doesn't allow deleting files that are still open.
+		drop(self.file.take());
+		let _ = remove_file(&self.path);
+	}
+}
+
+#[test]
+fn large_contents_are_spooled() {
+	let dir = rookup_common::TempDir::new("rookup-spool-test").unwrap();
+	let mut spool = Spool::new(4, Some(dir.path().to_path_buf()));
+	spool.write_all(b"abc").unwrap();
+	assert!(matches!(spool.contents, Contents::Memory(..)));
+	spool.write_all(b"def").unwrap();
+	assert!(matches!(spool.contents, Contents::File(..)));
+
+	let mut contents = spool.into_contents().unwrap();
+	assert_eq!(contents.len().unwrap(), 6);
+	let mut read = String::new();
+	contents.read_to_string(&mut read).unwrap();
+	assert_eq!(read, "abcdef");
+
+	drop(contents);
+	assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+}
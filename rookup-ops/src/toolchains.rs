@@ -0,0 +1,500 @@
+//! Installed toolchains, and removing them.
+
+use rookup_common::{
+	version::version_ord,
+	cache_home, custom_toolchain_homes, find_toolchain, has_compiler, invocation_log_path, is_executable, state_home,
+	toolchain_home,
+	CompilerNames, ConfigData, Component, DirNames, Receipt, Selector, ToolchainVersions,
+	LICENSES_PATH, RECEIPT_FILE,
+};
+use rustc_hash::{
+	FxHashMap, FxHashSet,
+};
+use sha2::{
+	Digest, Sha256,
+};
+use std::{
+	fs::{
+		read_dir, read_link, read_to_string, remove_dir_all, symlink_metadata, File,
+	},
+	io::{
+		copy as io_copy,
+		ErrorKind as IoErrorKind, Result as IoResult, Write,
+	},
+	path::{
+		Path, PathBuf,
+	},
+	time::SystemTime,
+};
+
+use crate::{
+	disk::dir_size,
+	hex, OpsError,
+};
+
+/// Return the path that the toolchain of `version` is installed to in the toolchain home.
+pub fn toolchain_destination<P: AsRef<Path>>(version: P) -> Result<PathBuf, OpsError> {
+	let mut buffer = toolchain_home().ok_or(OpsError::NoToolchainHome)?;
+	buffer.push(version);
+	Ok(buffer)
+}
+
+/// Return the names of the toolchains in the toolchain home, and the path to it.
+pub fn installed_toolchains() -> Result<(DirNames, PathBuf), OpsError> {
+	let home = toolchain_home().ok_or(OpsError::NoToolchainHome)?;
+	match read_dir(&home) {
+		Ok(entries) => Ok((DirNames::new(entries).follow_symlinks(true), home)),
+		Err(error) => Err(OpsError::ReadDir { path: home, error }),
+	}
+}
+
+/// Toolchain installed in any of the toolchain homes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstalledToolchain {
+	pub name: String,
+	/// Path to the toolchain that is used when selecting it.
+	pub path: PathBuf,
+	/// `true` if the toolchain is in a custom toolchain home.
+	pub is_custom: bool,
+	/// Paths to toolchains of the same name in other homes, which are never selected.
+	pub shadowed: Vec<PathBuf>,
+}
+
+impl InstalledToolchain {
+	/// Return all installed toolchains, merged across toolchain homes and sorted by version.
+	pub fn all() -> Result<Vec<Self>, OpsError> {
+		let custom_homes = custom_toolchain_homes();
+		let mut toolchains: Vec<Self> = Vec::new();
+		for (home, version_names) in ToolchainVersions::new() {
+			let version_names = match version_names {
+				Ok(i) => i,
+				Err(e) if e.kind() == IoErrorKind::NotFound => continue,
+				Err(error) => return Err(OpsError::ReadDir { path: home, error }),
+			};
+			let is_custom = custom_homes.contains(&home);
+			for result in version_names {
+				let version_name = match result {
+					Ok(version_name) => version_name,
+					Err(error) => return Err(OpsError::ReadDir { path: home, error }),
+				};
+				let path = home.join(&version_name);
+				let name = version_name.to_string_lossy().into_owned();
+				match toolchains.iter_mut().find(|t| t.name == name) {
+					Some(existing) => existing.shadowed.push(path),
+					None => toolchains.push(Self {
+						name,
+						path,
+						is_custom,
+						shadowed: Vec::new(),
+					}),
+				}
+			}
+		}
+		toolchains.sort_by(move |a, b| version_ord(a.name.as_str(), b.name.as_str()));
+		Ok(toolchains)
+	}
+
+	/// Return a description of where this toolchain came from.
+	pub fn origin(&self) -> String {
+		match Receipt::read(&self.path) {
+			Ok(Some(receipt)) => format!("from {}", receipt.origin),
+			Ok(None) if self.is_custom => "custom".into(),
+			Ok(None) => "unknown origin".into(),
+			Err(e) => format!("unreadable receipt: {e}"),
+		}
+	}
+}
+
+/// Installed toolchain, along with how it's used by the configuration, as shown by `rookup show`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolchainSummary {
+	pub toolchain: InstalledToolchain,
+	/// Components that the toolchain was installed with, or all of them if empty.
+	pub components: Vec<Component>,
+	/// `true` if the toolchain should have a compiler, but doesn't.
+	pub missing_compiler: bool,
+	/// `true` if the toolchain is selected by the default selector.
+	pub is_default: bool,
+	/// Aliases that refer to the toolchain, sorted by name.
+	pub aliases: Vec<String>,
+}
+
+impl ToolchainSummary {
+	/// Return summaries of all installed toolchains, given the configuration `data`.
+	pub fn all(data: &ConfigData) -> Result<Vec<Self>, OpsError> {
+		let default = find_toolchain(data, data.default.as_selector()).ok().map(move |t| t.name);
+		let compilers = CompilerNames::new(data, std::env::consts::OS);
+		let summaries = InstalledToolchain::all()?.into_iter()
+			.map(|toolchain| {
+				let components = Receipt::read(&toolchain.path).ok().flatten()
+					.map(move |r| r.components).unwrap_or_default();
+				let missing_compiler = Component::or_all(&components).contains(&Component::Compiler)
+					&& !has_compiler(&toolchain.path, &compilers);
+				let mut aliases: Vec<_> = data.aliases.iter()
+					.filter(|(.., version)| **version == toolchain.name)
+					.map(move |(alias, ..)| alias.clone())
+					.collect();
+				aliases.sort();
+				Self {
+					is_default: default.as_ref().is_some_and(|d| *d == toolchain.name),
+					toolchain,
+					components,
+					missing_compiler,
+					aliases,
+				}
+			})
+			.collect();
+		Ok(summaries)
+	}
+}
+
+/// Toolchains in the toolchain home that aren't selected by the default selector or any alias.
+#[derive(Debug, Clone)]
+pub struct UnusedToolchains {
+	pub home: PathBuf,
+	pub versions: FxHashSet<String>,
+}
+
+impl UnusedToolchains {
+	pub fn new(data: &ConfigData) -> Result<Self, OpsError> {
+		let (versions, home) = installed_toolchains()?;
+
+		let mut versions = {
+			let result: Result<FxHashSet<_>, _> = versions
+				.filter_map(move |r| match r {
+					Ok(v) => match v.into_string() {
+						Ok(v) => Some(Ok(v)),
+						Err(..) => None,
+					},
+					Err(e) => Some(Err(e)),
+				})
+				.collect();
+			result.map_err(|error| OpsError::ReadDir { path: home.clone(), error })?
+		};
+
+		if let Ok(default_toolchain) = find_toolchain(data, data.default.as_selector()) {
+			versions.remove(&default_toolchain.name);
+		}
+		for version in data.aliases.values() {
+			versions.remove(version);
+		}
+
+		Ok(Self {
+			home,
+			versions
+		})
+	}
+
+	/// Remove every toolchain in [`versions`](Self::versions).
+	///
+	/// Removal stops at the first toolchain that fails to be removed, which is the last one returned.
+	pub fn remove(self) -> Vec<Removal> {
+		let mut removals = Vec::new();
+		for version in self.versions {
+			let removal = Removal::remove(&self.home, version);
+			let failed = removal.result.is_err();
+			removals.push(removal);
+			if failed {
+				break
+			}
+		}
+		removals
+	}
+}
+
+/// Times that toolchains were last used by `rookup-spcomp`, as recorded in the invocation log.
+#[derive(Default, Debug, Clone)]
+pub struct LastUsed(FxHashMap<String, SystemTime>);
+
+impl LastUsed {
+	/// Read the invocation log, which has no entries unless `log-invocations` is enabled in the configuration file.
+	/// 
+	/// The log in the cache home, where older versions of Rookup kept it, is read too, so that toolchains used before
+	/// the log was moved aren't mistaken for unused ones.
+	pub fn read() -> Result<Self, OpsError> {
+		let path = state_home().map(invocation_log_path).ok_or(OpsError::NoStateHome)?;
+		let mut log = String::new();
+		for path in [Some(path), cache_home().map(invocation_log_path)].into_iter().flatten() {
+			match read_to_string(&path) {
+				Ok(text) => log.push_str(&text),
+				Err(e) if e.kind() == IoErrorKind::NotFound => {}
+				Err(error) => return Err(OpsError::InvocationLog { path, error }),
+			}
+		}
+		Ok(Self::parse(&log))
+	}
+
+	/// Parse the lines of an invocation log, ignoring ones that are malformed.
+	pub fn parse(log: &str) -> Self {
+		let mut last_used = FxHashMap::<String, SystemTime>::default();
+		for line in log.lines() {
+			let mut fields = line.split('\t');
+			let (Some(time), Some(toolchain)) = (fields.next(), fields.next()) else {
+				continue
+			};
+			let Ok(time) = humantime::parse_rfc3339(time) else {
+				continue
+			};
+			let entry = last_used.entry(toolchain.to_string()).or_insert(time);
+			*entry = time.max(*entry);
+		}
+		Self(last_used)
+	}
+
+	/// Return the time that the toolchain of `version` was last used, if it was recorded.
+	pub fn get(&self, version: &str) -> Option<SystemTime> {
+		self.0.get(version).copied()
+	}
+}
+
+/// Toolchain that was removed (or failed to be removed) from the toolchain home.
+#[derive(Debug)]
+pub struct Removal {
+	pub version: String,
+	pub path: PathBuf,
+	/// Size of the toolchain in bytes, measured before removing it, if it could be measured.
+	pub size: Option<u64>,
+	pub result: Result<(), OpsError>,
+}
+
+impl Removal {
+	fn remove(home: &Path, version: String) -> Self {
+		let path = home.join(&version);
+		let size = dir_size(&path).ok();
+		let result = remove_dir_all(&path).map_err(|error| OpsError::Remove { path: path.clone(), error });
+		Self {
+			version,
+			path,
+			size,
+			result,
+		}
+	}
+
+	/// Return the number of bytes of disk space that were freed by removing the toolchain, if it was removed and its
+	/// size is known.
+	pub fn freed(&self) -> Option<u64> {
+		self.size.filter(|_| self.result.is_ok())
+	}
+
+	/// Return the total number of bytes of disk space freed by `removals`.
+	pub fn total_freed(removals: &[Self]) -> u64 {
+		removals.iter().filter_map(Self::freed).sum()
+	}
+}
+
+/// Remove every toolchain in the toolchain home that `selector` selects, given the configuration `data`.
+///
+/// Failing to remove one toolchain doesn't stop the others from being removed.
+pub fn remove_matching(data: &ConfigData, selector: Selector<'_>) -> Result<Vec<Removal>, OpsError> {
+	let (toolchains, home) = installed_toolchains()?;
+	let mut removals = Vec::new();
+	for version in toolchains {
+		let version = version.map_err(|error| OpsError::ReadDir { path: home.clone(), error })?;
+		let version = version.into_string().map_err(move |name| OpsError::NonUtf8Name { name })?;
+		if selector.test(data, &version) {
+			removals.push(Removal::remove(&home, version));
+		}
+	}
+	Ok(removals)
+}
+
+/// Remove every toolchain in the toolchain home that is unused (see [`UnusedToolchains`]), given the configuration
+/// `data`.
+///
+/// Removal stops at the first toolchain that fails to be removed, which is the last one returned.
+pub fn purge_unused(data: &ConfigData) -> Result<Vec<Removal>, OpsError> {
+	UnusedToolchains::new(data).map(UnusedToolchains::remove)
+}
+
+/// License file of an installed toolchain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct License {
+	pub path: PathBuf,
+	/// First line of the file that isn't empty, which is usually the name of the license.
+	pub title: Option<String>,
+}
+
+/// Return the license files of the toolchain at `path`, in order of their names.
+/// 
+/// This is empty for toolchains that were installed before license files were extracted, and for custom toolchains
+/// without a licenses directory.
+pub fn licenses(path: &Path) -> IoResult<Vec<License>> {
+	// License files of standalone SourcePawn builds are in the same directories as in the archive.
+	let files = match toolchain_files(&path.join(LICENSES_PATH)) {
+		Ok(files) => files,
+		Err(e) if e.kind() == IoErrorKind::NotFound => return Ok(Vec::new()),
+		Err(e) => return Err(e),
+	};
+	let licenses = files.into_iter()
+		.map(move |(_, path, _)| {
+			let title = read_to_string(&path).ok().and_then(move |text| {
+				text.lines().map(str::trim).find(move |l| !l.is_empty()).map(String::from)
+			});
+			License {
+				path,
+				title,
+			}
+		})
+		.collect();
+	Ok(licenses)
+}
+
+/// Return the SHA-256 digest of the files of the toolchain at `path`, as a hexadecimal string.
+/// 
+/// The digest covers the path (relative to the toolchain, with `/` separators) and the contents of every file except for
+/// the install receipt, so toolchains with the same files have the same digest wherever they were installed from.
+/// Symbolic links are hashed by their targets instead of being followed, and executable files are listed with the
+/// marker of `sha256sum --binary`, so making a file executable changes the digest too.
+pub fn content_digest(path: &Path) -> IoResult<String> {
+	// The digest is of a listing like that of `sha256sum`, so that it can be reproduced with other tools.
+	let mut listing = Sha256::new();
+	for (name, path, is_symlink) in toolchain_files(path)? {
+		if name == RECEIPT_FILE {
+			continue
+		}
+		let mut hasher = Sha256::new();
+		let marker = if is_symlink {
+			hasher.update(read_link(&path)?.to_string_lossy().as_bytes());
+			' '
+		} else {
+			io_copy(&mut File::open(&path)?, &mut hasher)?;
+			if is_executable(&path) { '*' } else { ' ' }
+		};
+		writeln!(listing, "{} {marker}{name}", hex(&hasher.finalize()))?;
+	}
+	Ok(hex(&listing.finalize()))
+}
+
+/// Return the files and symbolic links in the toolchain at `path`, as their names relative to it (with `/` separators),
+/// their paths, and whether they are symbolic links, in order of their names.
+pub(crate) fn toolchain_files(path: &Path) -> IoResult<Vec<(String, PathBuf, bool)>> {
+	let mut files = Vec::new();
+	let mut to_visit = vec![(path.to_path_buf(), String::new())];
+	while let Some((dir, prefix)) = to_visit.pop() {
+		for entry in read_dir(&dir)? {
+			let entry = entry?;
+			let name = format!("{prefix}{}", entry.file_name().to_string_lossy());
+			let metadata = symlink_metadata(entry.path())?;
+			if metadata.is_dir() {
+				to_visit.push((entry.path(), format!("{name}/")));
+			} else {
+				files.push((name, entry.path(), metadata.is_symlink()));
+			}
+		}
+	}
+	files.sort();
+	Ok(files)
+}
+
+#[test]
+fn failed_removals_free_nothing() {
+	let removal = move |size, result| Removal {
+		version: "1.12.0.7200".into(),
+		path: PathBuf::new(),
+		size,
+		result,
+	};
+	let removals = [
+		removal(Some(100), Ok(())),
+		removal(Some(20), Err(OpsError::NoToolchainHome)),
+		removal(None, Ok(())),
+		removal(Some(3), Ok(())),
+	];
+	assert_eq!(removals[1].freed(), None);
+	assert_eq!(Removal::total_freed(&removals), 103);
+}
+
+#[test]
+fn last_used_times_are_parsed() {
+	let log = "2026-01-02T03:04:05Z\t1.12.0.7200\t0123456789abcdef\t0\t120ms\n\
+		2026-03-02T03:04:05Z\t1.12.0.7200\t0123456789abcdef\t1\t80ms\n\
+		2026-02-02T03:04:05Z\t1.12.0.7200\t0123456789abcdef\t0\t90ms\n\
+		garbage\n\
+		2026-01-01T00:00:00Z\t1.11.0.6970\t0123456789abcdef\t0\t100ms\n";
+	let last_used = LastUsed::parse(log);
+	assert_eq!(last_used.get("1.12.0.7200"), humantime::parse_rfc3339("2026-03-02T03:04:05Z").ok());
+	assert!(last_used.get("1.11.0.6970").is_some());
+	assert_eq!(last_used.get("1.13.0.7300"), None);
+}
+
+#[test]
+fn matching_and_unused_toolchains_are_removed() {
+	use rookup_common::SelectorBuf;
+	use std::fs::{
+		create_dir_all, File,
+	};
+
+	crate::with_test_homes(|root| {
+		let mut data = ConfigData {
+			default: SelectorBuf::parse(":1.12"),
+			..ConfigData::default()
+		};
+		data.aliases.insert("work".into(), "1.12.0.7100".into());
+		let compilers = CompilerNames::new(&data, std::env::consts::OS);
+		let home = root.join("toolchains");
+		for version in ["1.11.0.6970", "1.12.0.7100", "1.12.0.7200", "1.13.0.7300"] {
+			let path = home.join(version);
+			create_dir_all(&path).unwrap();
+			let mut options = File::options();
+			#[cfg(unix)]
+			std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o755);
+			options.create(true).truncate(true).write(true).open(path.join(compilers.preferred())).unwrap();
+		}
+
+		let removals = remove_matching(&data, Selector::Super("1.11")).unwrap();
+		assert_eq!(removals.len(), 1);
+		assert_eq!(removals[0].version, "1.11.0.6970");
+		assert!(removals[0].result.is_ok() && !removals[0].path.exists());
+
+		// The default selector selects the latest toolchain of 1.12, and the alias selects the other one.
+		let removals = purge_unused(&data).unwrap();
+		let removed: Vec<_> = removals.iter().map(move |r| r.version.as_str()).collect();
+		assert_eq!(removed, ["1.13.0.7300"]);
+		let names: Vec<_> = InstalledToolchain::all().unwrap().into_iter().map(move |t| t.name).collect();
+		assert_eq!(names, ["1.12.0.7100", "1.12.0.7200"]);
+		assert!(purge_unused(&data).unwrap().is_empty());
+	});
+}
+
+#[test]
+fn content_digests_depend_only_on_files() {
+	use rookup_common::TempDir;
+	use std::{
+		fs::{
+			create_dir_all, write,
+		},
+		time::Duration,
+	};
+
+	let (a, b) = (TempDir::new("rookup-digest-a").unwrap(), TempDir::new("rookup-digest-b").unwrap());
+	let files = [("spcomp", "compiler"), ("include/a.inc", "a"), ("include/b.inc", "b")];
+	for (name, contents) in files {
+		create_dir_all(a.join(name).parent().unwrap()).unwrap();
+		write(a.join(name), contents).unwrap();
+	}
+	// Files are created in another order and at another time, and the receipt is left out of the digest.
+	for (name, contents) in files.into_iter().rev() {
+		create_dir_all(b.join(name).parent().unwrap()).unwrap();
+		write(b.join(name), contents).unwrap();
+		let file = File::options().write(true).open(b.join(name)).unwrap();
+		file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1)).unwrap();
+	}
+	write(b.join(RECEIPT_FILE), "origin = \"upstream\"\n").unwrap();
+	let digest = content_digest(a.path()).unwrap();
+	assert_eq!(content_digest(b.path()).unwrap(), digest);
+
+	#[cfg(unix)]
+	{
+		use std::{
+			fs::set_permissions,
+			os::unix::fs::PermissionsExt,
+		};
+		set_permissions(b.join("spcomp"), PermissionsExt::from_mode(0o755)).unwrap();
+		assert_ne!(content_digest(b.path()).unwrap(), digest);
+		set_permissions(b.join("spcomp"), PermissionsExt::from_mode(0o644)).unwrap();
+		assert_eq!(content_digest(b.path()).unwrap(), digest);
+	}
+
+	write(b.join("include/a.inc"), "changed").unwrap();
+	assert_ne!(content_digest(b.path()).unwrap(), digest);
+}
@@ -1,110 +1,294 @@
-use anyhow::{
-	Result as AResult,
-	anyhow,
-};
 use rookup_common::{
-	current_toolchain, find_toolchain,
-	Config, ConfigExt,
-	ToolchainSource, Selector, FindToolchainError,
-	SPCOMP_EXE,
+	current_toolchain, find_toolchain_traced, state_home, invocation_log_path,
+	Config, ConfigData, ConfigExt,
+	ProxyError, ToolchainSource,
+	CompilerCommand, CompilerNames,
+};
+use sha2::{
+	Digest, Sha256,
 };
 use std::{
-	env::args_os,
-	error::Error,
+	env::{
+		args_os, current_dir, current_exe, split_paths, var_os,
+		consts::{
+			ARCH, EXE_SUFFIX, OS,
+		},
+	},
 	ffi::OsString,
-	fmt,
+	fmt::Write as _,
+	fs::{
+		File, canonicalize, create_dir_all,
+	},
+	io::{
+		ErrorKind as IoErrorKind, Result as IoResult, Write,
+	},
+	path::{
+		Path, PathBuf,
+	},
 	process::{
-		exit, Command, ExitCode, Stdio,
-	}
+		exit, ExitCode, ExitStatus, Stdio,
+	},
+	time::{
+		Duration, Instant, SystemTime,
+	},
 };
 
+/// Name of the environment variable that makes the proxy print which toolchain and compiler it runs.
+const VERBOSE_VAR: &str = "ROOKUP_VERBOSE";
+/// Name of the environment variable that the proxy sets for the compiler it runs, so that a proxy that it runs as the
+/// system compiler (for e.g. a copy of it named `spcomp` in `PATH`) doesn't fall back to a system compiler again.
+const DEPTH_VAR: &str = "ROOKUP_PROXY_DEPTH";
+/// Argument that makes the proxy print the compiler it would run and how its toolchain was selected, instead of running
+/// it.
+const WHICH_ARG: &str = "--rookup-which";
+
 fn main() -> ExitCode {
 	let mut args = args_os();
 	let exe = args.next();
 	match spcomp_main(args) {
-		Ok(Some(code)) => exit(code),
-		Ok(None) => {}
+		Ok(status) => exit_like(status),
 		Err(e) => {
 			if let Some(exe) = exe.as_ref().and_then(move |s| s.to_str()) {
 				eprint!("{exe}: ");
 			}
 			eprintln!("{e}");
+			ExitCode::from(e.exit_code())
 		}
 	}
-	ExitCode::FAILURE
 }
 
-#[derive(Debug)]
-enum NotFoundBailKind {
-	LatestCompatibleWith {
-		version: String,
-	},
-	Aliased {
-		version: String,
-		alias: String,
-	},
+/// Exit the process the same way as the compiler, which exited with `status`.
+/// 
+/// On Unix, if the compiler was terminated by a signal, the same signal is raised for this process, so that callers
+/// (like `make`) see the failure as it is.
+fn exit_like(status: ExitStatus) -> ExitCode {
+	if let Some(code) = status.code() {
+		exit(code)
+	}
+
+	#[cfg(unix)]
+	if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+		eprintln!("rookup-spcomp: compiler was terminated by signal {signal}");
+		// SAFETY: Resetting the handler of the signal to the default one and raising it has no memory safety
+		// implications.
+		unsafe {
+			libc::signal(signal, libc::SIG_DFL);
+			libc::raise(signal);
+		}
+		// The signal may be ignored or blocked, so fall back to the shell convention.
+		exit(128 + signal)
+	}
+
+	ExitCode::FAILURE
 }
 
-fn spcomp_main(args: impl Iterator<Item = OsString>) -> AResult<Option<i32>> {
-	let data = Config::open_default(false)?.with_doc.into();
-	let (toolchain, source) = current_toolchain(&data)
-		.map_err(move |e| anyhow!("failed to get current toolchain: {e}"))?;
-
-	let parsed = Selector::parse(&toolchain);
-	let toolchain_path = match find_toolchain(&data, parsed) {
-		Ok(toolchain) => toolchain.into_path(),
-		Err(FindToolchainError::LatestNotFound(version)) => {
-			return Err(NotFoundBail {
-				source,
-				kind: NotFoundBailKind::LatestCompatibleWith { version }
-			}.into())
+fn spcomp_main(args: impl Iterator<Item = OsString>) -> Result<ExitStatus, ProxyError> {
+	let mut args: Vec<OsString> = args.collect();
+	let which = args.first().is_some_and(move |a| a == WHICH_ARG);
+	let config = Config::open_default(false)?;
+	let include_dirs = current_dir()
+		.map(|dir| config.with_doc.include_dirs(&config.path, &dir))
+		.unwrap_or_default();
+	let data: ConfigData = config.with_doc.into();
+	let (toolchain, source) = current_toolchain(&data)?;
+
+	let mut steps = Vec::new();
+	let found = find_toolchain_traced(&data, toolchain.as_selector(), &mut |step| steps.push(step.to_string()));
+	let (toolchain_name, command) = match found {
+		Ok(toolchain) => {
+			let name = toolchain.name.clone();
+			let command = CompilerCommand::new(&data, toolchain.into_path())?;
+			(name, command)
 		}
-		Err(FindToolchainError::NotFound { version, alias }) => {
-			return Err(NotFoundBail {
-				source,
-				kind: NotFoundBailKind::Aliased { version, alias }
-			}.into())
+		Err(e) => {
+			// What was searched is only shown when asked which compiler would be run, to diagnose why none was found.
+			if which {
+				eprintln!("rookup-spcomp: toolchain selected by {:?} wasn't found:", toolchain.to_string());
+				for step in steps.iter() {
+					eprintln!("rookup-spcomp:   {step}");
+				}
+			}
+			let bail = match ProxyError::from_find(e, source) {
+				ProxyError::NotInstalled(bail) => bail,
+				e => return Err(e),
+			};
+			let depth = proxy_depth();
+			if data.fallback_to_system && depth > 0 {
+				eprintln!("rookup-spcomp: warning: {bail}; not falling back to a system compiler from another proxy");
+			}
+			match (data.fallback_to_system && depth == 0).then(find_system_compiler).flatten() {
+				Some(compiler) => {
+					eprintln!("rookup-spcomp: warning: {bail}; falling back to {}", compiler.display());
+					let command = CompilerCommand {
+						runner: Vec::new(),
+						compiler,
+						fallback_from: None,
+					};
+					("system".into(), command)
+				}
+				None => return Err(ProxyError::NotInstalled(bail)),
+			}
 		}
-		Err(e) => return Err(e.into()),
 	};
 
-	let spcomp_path = {
-		let mut buffer = toolchain_path;
-		buffer.push(SPCOMP_EXE);
-		buffer
-	};
+	if which {
+		let source = match source {
+			ToolchainSource::Env => "env",
+			ToolchainSource::Config => "config",
+		};
+		println!("{}", command.compiler.display());
+		println!("{toolchain_name}\t{toolchain}\t{source}");
+		return Ok(ExitStatus::default())
+	}
+
+	if var_os(VERBOSE_VAR).is_some_and(move |v| !v.is_empty() && v != "0") {
+		eprintln!("rookup-spcomp: using toolchain {toolchain_name} (selected by {:?})", toolchain.to_string());
+		match command.fallback_from.as_deref() {
+			Some(preferred) => eprintln!(
+				"rookup-spcomp: {preferred} is missing, falling back to {}", command.compiler.display(),
+			),
+			None => eprintln!("rookup-spcomp: running {}", command.compiler.display()),
+		}
+	}
 
-	let mut spcomp = Command::new(&spcomp_path)
+	args.extend(include_dirs.into_iter().map(move |dir| {
+		let mut arg = OsString::from("-i");
+		arg.push(dir);
+		arg
+	}));
+	let started_at = SystemTime::now();
+	let start = Instant::now();
+	let mut spcomp = command.to_command()
+		.env(DEPTH_VAR, (proxy_depth() + 1).to_string())
 		.stdin(Stdio::inherit())
 		.stdout(Stdio::inherit()).stderr(Stdio::inherit())
-		.args(args)
+		.args(&args)
 		.spawn()
-		.map_err(move |e| anyhow!("{}: {e}", spcomp_path.display()))?;
-	let status = spcomp.wait()?;
-	Ok(status.code())
+		.map_err(|error| match error.kind() {
+			IoErrorKind::NotFound if !command.compiler.exists() => ProxyError::CompilerMissing {
+				compiler: command.compiler.clone(),
+			},
+			_ => ProxyError::Spawn {
+				error,
+				compiler: command.compiler.clone(),
+			},
+		})?;
+	let status = spcomp.wait().map_err(ProxyError::Wait)?;
+	let duration = start.elapsed();
+
+	if data.time_compiles {
+		let exit = match status.code() {
+			Some(code) => format!("exit code {code}"),
+			None => signal_description(status),
+		};
+		eprintln!("rookup-spcomp: {toolchain_name} took {:.2}s ({exit})", duration.as_secs_f64());
+	}
+
+	if data.log_invocations {
+		let invocation = Invocation {
+			started_at,
+			toolchain: &toolchain_name,
+			args: &args,
+			status,
+			duration,
+		};
+		if let Err(e) = invocation.log() {
+			eprintln!("rookup-spcomp: failed to log invocation: {e}");
+		}
+	}
+
+	Ok(status)
 }
 
-#[derive(Debug)]
-struct NotFoundBail {
-	pub source: ToolchainSource,
-	pub kind: NotFoundBailKind,
+/// Return how many proxies this one is nested in, according to [`DEPTH_VAR`].
+fn proxy_depth() -> u32 {
+	var_os(DEPTH_VAR).and_then(move |v| v.to_str()?.parse().ok()).unwrap_or(0)
 }
-impl Error for NotFoundBail {}
-impl fmt::Display for NotFoundBail {
-	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-		f.write_str(match self.source {
-			ToolchainSource::Env => "the `ROOKUP_TOOLCHAIN` environment variable",
-			ToolchainSource::Config => "the Rookup configuration file",
-		})?;
-		f.write_str(" specifies that a toolchain of ")?;
-		match &self.kind {
-			NotFoundBailKind::LatestCompatibleWith { version } => {
-				write!(f, "the latest version compatible with {version:?}")?;
-			}
-			NotFoundBailKind::Aliased { version, alias } => {
-				write!(f, "version {version:?} (as specified by alias {alias:?})")?;
+
+/// Search `PATH` for a compiler that isn't managed by Rookup, skipping this proxy and links to it.
+/// 
+/// Copies of this proxy can't be recognized, so they're kept from recursing with [`DEPTH_VAR`] instead.
+fn find_system_compiler() -> Option<PathBuf> {
+	let mut names = CompilerNames::defaults(OS, ARCH).0;
+	let plain = format!("spcomp{EXE_SUFFIX}");
+	if !names.contains(&plain) {
+		names.push(plain);
+	}
+
+	let this_exe = current_exe().and_then(canonicalize).ok()?;
+	let path = var_os("PATH")?;
+	split_paths(&path)
+		.flat_map(move |dir| names.iter().map(move |name| dir.join(name)).collect::<Vec<_>>())
+		.filter(move |candidate| candidate.is_file())
+		.find(move |candidate| !is_same_file(candidate, &this_exe))
+}
+
+/// Return `true` if `a` and `b` are the same file, including through symbolic or hard links.
+fn is_same_file(a: &Path, b: &Path) -> bool {
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::MetadataExt;
+		if let (Ok(a), Ok(b)) = (a.metadata(), b.metadata()) {
+			return a.dev() == b.dev() && a.ino() == b.ino()
+		}
+	}
+	canonicalize(a).is_ok_and(move |a| a == b)
+}
+
+/// Return a description of how the process that exited with `status` without an exit code was terminated.
+fn signal_description(status: ExitStatus) -> String {
+	#[cfg(unix)]
+	if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+		return format!("signal {signal}")
+	}
+	let _ = status;
+	"signal".into()
+}
+
+/// Record of a single compiler invocation, written to the invocation log.
+struct Invocation<'a> {
+	pub started_at: SystemTime,
+	pub toolchain: &'a str,
+	pub args: &'a [OsString],
+	pub status: ExitStatus,
+	pub duration: Duration,
+}
+
+impl Invocation<'_> {
+	/// Append this invocation to the invocation log.
+	pub fn log(&self) -> IoResult<()> {
+		let Some(state_home) = state_home() else {
+			return Err(std::io::Error::other("couldn't determine state directory"))
+		};
+		create_dir_all(&state_home)?;
+
+		let mut line = String::new();
+		let _ = write!(line, "{}\t{}\t", humantime::format_rfc3339_seconds(self.started_at), self.toolchain);
+		let _ = write!(line, "{}\t", self.args_digest());
+		match self.status.code() {
+			Some(code) => { let _ = write!(line, "{code}\t"); }
+			None => {
+				line.push_str(&signal_description(self.status));
+				line.push('\t');
 			}
 		}
-		f.write_str(" should be used, but that toolchain is not installed")
+		let _ = writeln!(line, "{}ms", self.duration.as_millis());
+
+		File::options().create(true).append(true)
+			.open(invocation_log_path(state_home))?
+			.write_all(line.as_bytes())
+	}
+
+	/// Return the first 16 hexadecimal digits of the SHA-256 digest of the arguments.
+	fn args_digest(&self) -> String {
+		let mut hasher = Sha256::new();
+		for arg in self.args {
+			hasher.update(arg.as_encoded_bytes());
+			hasher.update([0]);
+		}
+		hasher.finalize().iter().take(8).fold(String::new(), |mut s, b| {
+			let _ = write!(s, "{b:02x}");
+			s
+		})
 	}
 }
@@ -0,0 +1,59 @@
+//! Reading and writing files with maps of aliases to versions.
+
+use anyhow::{
+	anyhow, Context, Result as AResult,
+};
+use rookup_common::toml_edit::{
+	de::from_str as toml_from_str,
+	ser::to_string_pretty as toml_to_string,
+};
+use std::{
+	collections::BTreeMap,
+	fs::{
+		read_to_string, write,
+	},
+	path::Path,
+};
+
+/// Map of aliases to versions, sorted by alias.
+pub type AliasMap = BTreeMap<String, String>;
+
+/// Enumeration of supported alias file formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AliasFileFormat {
+	Toml,
+	Json,
+}
+
+impl AliasFileFormat {
+	/// Determine the format of the file at `path` from its extension, defaulting to TOML.
+	pub fn of(path: &Path) -> Self {
+		match path.extension().and_then(move |e| e.to_str()) {
+			Some(e) if e.eq_ignore_ascii_case("json") => Self::Json,
+			_ => Self::Toml,
+		}
+	}
+}
+
+/// Read the map of aliases to versions stored in the file at `path`.
+pub fn read_aliases(path: &Path) -> AResult<AliasMap> {
+	let text = read_to_string(path).with_context(|| anyhow!("failed to read {path:?}"))?;
+	let aliases = match AliasFileFormat::of(path) {
+		AliasFileFormat::Toml => toml_from_str(&text).map_err(anyhow::Error::from),
+		AliasFileFormat::Json => serde_json::from_str(&text).map_err(anyhow::Error::from),
+	};
+	aliases.with_context(|| anyhow!("failed to parse aliases in {path:?}"))
+}
+
+/// Write `aliases` into the file at `path`.
+pub fn write_aliases(path: &Path, aliases: &AliasMap) -> AResult<()> {
+	let text = match AliasFileFormat::of(path) {
+		AliasFileFormat::Toml => toml_to_string(aliases)?,
+		AliasFileFormat::Json => {
+			let mut text = serde_json::to_string_pretty(aliases)?;
+			text.push('\n');
+			text
+		}
+	};
+	write(path, text).with_context(|| anyhow!("failed to write {path:?}"))
+}
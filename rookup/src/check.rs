@@ -0,0 +1,119 @@
+//! Verification of the layout of installed toolchains.
+
+use rookup_common::{
+	is_executable, CompilerNames, Component, Receipt,
+};
+use std::{
+	fmt,
+	fs::read_dir,
+	io::Error as IoError,
+	path::{
+		Path, PathBuf,
+	},
+};
+
+/// Problem found in the layout of a toolchain by [`check_toolchain`].
+#[derive(Debug)]
+pub enum Problem {
+	/// None of the `expected` compiler executables exist.
+	MissingCompiler {
+		expected: Vec<String>,
+	},
+	/// The compiler exists, but isn't executable.
+	CompilerNotExecutable(PathBuf),
+	/// The `include` directory doesn't exist.
+	MissingIncludeDir,
+	/// `include/sourcemod.inc` doesn't exist.
+	MissingSourceModInc,
+	/// A file in the toolchain is empty.
+	EmptyFile(PathBuf),
+	/// A directory of the toolchain couldn't be read.
+	Unreadable {
+		path: PathBuf,
+		error: IoError,
+	},
+}
+
+impl fmt::Display for Problem {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::MissingCompiler { expected } => write!(f, "compiler is missing (expected any of {})", expected.join(", ")),
+			Self::CompilerNotExecutable(path) => write!(f, "compiler {} is not executable", path.display()),
+			Self::MissingIncludeDir => f.write_str("`include` directory is missing"),
+			Self::MissingSourceModInc => f.write_str("`include/sourcemod.inc` is missing"),
+			Self::EmptyFile(path) => write!(f, "{} is empty", path.display()),
+			Self::Unreadable { path, error } => write!(f, "couldn't read {}: {error}", path.display()),
+		}
+	}
+}
+
+/// Check that the toolchain at `path` has an executable compiler, an `include` directory with `sourcemod.inc`, and no
+/// empty files, returning every problem found.
+/// 
+/// Components that the toolchain wasn't installed with aren't checked.
+pub fn check_toolchain(path: &Path) -> Vec<Problem> {
+	let mut problems = Vec::new();
+
+	let (names, components) = match Receipt::read(path) {
+		Ok(Some(receipt)) => (receipt.compiler_names(), receipt.components),
+		_ => (CompilerNames::defaults(std::env::consts::OS, std::env::consts::ARCH), Vec::new()),
+	};
+	let components = Component::or_all(&components);
+
+	if components.contains(&Component::Compiler) {
+		check_compiler(path, names, &mut problems);
+	}
+
+	if components.contains(&Component::Includes) {
+		check_includes(path, &mut problems);
+	}
+
+	find_empty_files(path, &mut problems);
+	problems
+}
+
+fn check_compiler(path: &Path, names: CompilerNames, problems: &mut Vec<Problem>) {
+	let compilers: Vec<_> = names.0.iter().map(move |n| path.join(n)).filter(move |p| p.is_file()).collect();
+	if compilers.is_empty() {
+		problems.push(Problem::MissingCompiler {
+			expected: names.0,
+		});
+	} else if !compilers.iter().any(move |p| is_executable(p)) {
+		problems.extend(compilers.into_iter().map(Problem::CompilerNotExecutable));
+	}
+}
+
+fn check_includes(path: &Path, problems: &mut Vec<Problem>) {
+	let include = path.join("include");
+	if !include.is_dir() {
+		problems.push(Problem::MissingIncludeDir);
+	} else if !include.join("sourcemod.inc").is_file() {
+		problems.push(Problem::MissingSourceModInc);
+	}
+}
+
+fn find_empty_files(dir: &Path, problems: &mut Vec<Problem>) {
+	let entries = match read_dir(dir) {
+		Ok(entries) => entries,
+		Err(error) => {
+			problems.push(Problem::Unreadable { path: dir.into(), error });
+			return
+		}
+	};
+	for entry in entries {
+		let entry = match entry {
+			Ok(entry) => entry,
+			Err(error) => {
+				problems.push(Problem::Unreadable { path: dir.into(), error });
+				continue
+			}
+		};
+		let path = entry.path();
+		match entry.metadata() {
+			Ok(m) if m.is_dir() => find_empty_files(&path, problems),
+			Ok(m) if m.is_file() && m.len() == 0 => problems.push(Problem::EmptyFile(path)),
+			Ok(..) => {}
+			Err(error) => problems.push(Problem::Unreadable { path, error }),
+		}
+	}
+}
@@ -0,0 +1,125 @@
+//! Colored output, which is controlled by `--color` and the `NO_COLOR` environment variable.
+
+use clap::ValueEnum;
+use std::{
+	env::var_os,
+	fmt,
+	io::{
+		stderr, stdout, IsTerminal,
+	},
+	path::Path,
+	sync::atomic::{
+		AtomicBool, Ordering,
+	},
+};
+
+static STDOUT_ENABLED: AtomicBool = AtomicBool::new(false);
+static STDERR_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Enumeration of choices of when to color output.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+	/// Color output written to terminals, unless `NO_COLOR` is set to a non-empty value.
+	#[default]
+	Auto,
+	/// Always color output.
+	Always,
+	/// Never color output.
+	Never,
+}
+
+impl ColorChoice {
+	fn enables(self, is_terminal: bool) -> bool {
+		match self {
+			Self::Auto => is_terminal && var_os("NO_COLOR").is_none_or(move |v| v.is_empty()),
+			Self::Always => true,
+			Self::Never => false,
+		}
+	}
+}
+
+/// Decide whether standard output and standard error should be colored according to `choice`.
+pub fn init(choice: ColorChoice) {
+	STDOUT_ENABLED.store(choice.enables(stdout().is_terminal()), Ordering::Relaxed);
+	STDERR_ENABLED.store(choice.enables(stderr().is_terminal()), Ordering::Relaxed);
+}
+
+/// Stream that a [`Styled`] value is written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stream {
+	Stdout,
+	Stderr,
+}
+
+impl Stream {
+	fn is_enabled(self) -> bool {
+		match self {
+			Self::Stdout => STDOUT_ENABLED.load(Ordering::Relaxed),
+			Self::Stderr => STDERR_ENABLED.load(Ordering::Relaxed),
+		}
+	}
+}
+
+/// Value that is displayed with an SGR style if its stream is colored.
+#[derive(Debug, Clone, Copy)]
+pub struct Styled<T> {
+	sgr: &'static str,
+	stream: Stream,
+	value: T,
+}
+
+impl<T> Styled<T> {
+	const fn new(sgr: &'static str, stream: Stream, value: T) -> Self {
+		Self {
+			sgr,
+			stream,
+			value,
+		}
+	}
+
+	/// Return this value for writing to standard error instead of standard output.
+	pub const fn stderr(mut self) -> Self {
+		self.stream = Stream::Stderr;
+		self
+	}
+}
+
+impl<T: fmt::Display> fmt::Display for Styled<T> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if !self.stream.is_enabled() {
+			return fmt::Display::fmt(&self.value, f)
+		}
+		write!(f, "\x1b[{}m", self.sgr)?;
+		fmt::Display::fmt(&self.value, f)?;
+		f.write_str("\x1b[0m")
+	}
+}
+
+/// Style a toolchain version, or anything else that names a toolchain.
+pub const fn version<T: fmt::Display>(version: T) -> Styled<T> {
+	Styled::new("32", Stream::Stdout, version)
+}
+
+/// Style a path.
+pub fn path(path: &Path) -> Styled<std::path::Display<'_>> {
+	Styled::new("2", Stream::Stdout, path.display())
+}
+
+/// Style `Yes` or `No`, highlighting `Yes`.
+pub const fn yes_no(b: bool) -> Styled<&'static str> {
+	if b {
+		Styled::new("1;33", Stream::Stdout, "Yes")
+	} else {
+		Styled::new("0", Stream::Stdout, "No")
+	}
+}
+
+/// Style the prefix of an error message, which is written to standard error.
+pub const fn error<T: fmt::Display>(prefix: T) -> Styled<T> {
+	Styled::new("1;31", Stream::Stderr, prefix)
+}
+
+/// Style the prefix of a warning, which is written to standard error.
+pub const fn warning<T: fmt::Display>(prefix: T) -> Styled<T> {
+	Styled::new("1;33", Stream::Stderr, prefix)
+}
@@ -0,0 +1,46 @@
+//! Detection of problems with Rookup's own files, as done by `rookup doctor`.
+
+use rookup_common::{
+	inspect_lock, lock_path, toolchain_homes, LockInfo,
+};
+use std::{
+	fs::read_dir,
+	io::{
+		ErrorKind as IoErrorKind, Result as IoResult,
+	},
+	path::{
+		Path, PathBuf,
+	},
+};
+
+/// Return the lock files of the configuration file at `config_path` and of installations into the toolchain homes that
+/// exist, along with what is known about them.
+pub fn find_locks(config_path: &Path) -> IoResult<Vec<(PathBuf, LockInfo)>> {
+	let mut paths = vec![lock_path(config_path)];
+	for home in toolchain_homes() {
+		let entries = match read_dir(&home) {
+			Ok(entries) => entries,
+			Err(e) if e.kind() == IoErrorKind::NotFound => continue,
+			Err(e) => return Err(e),
+		};
+		for entry in entries {
+			let entry = entry?;
+			let name = entry.file_name();
+			let name = name.to_string_lossy();
+			if name.starts_with('.') && name.ends_with(".lock") {
+				paths.push(entry.path());
+			}
+		}
+	}
+
+	// The lock of the configuration file comes first.
+	paths[1..].sort();
+
+	let mut locks = Vec::new();
+	for path in paths {
+		if let Some(info) = inspect_lock(&path)? {
+			locks.push((path, info));
+		}
+	}
+	Ok(locks)
+}
@@ -0,0 +1,255 @@
+//! Tracing of how the current toolchain is resolved, as shown by `rookup explain` and `rookup which --explain`.
+
+use anyhow::Result as AResult;
+use rookup_common::{
+	toml_edit::DocumentMut,
+	current_toolchain, env_var_name, find_toolchain_traced, system_config_path,
+	CompilerCommand, Config, ConfigData, ConfigLayer, FindStep, FindToolchainError, FoundToolchainKinded, Selector,
+	ToolchainSource, ValueOrigin,
+	TOOLCHAIN_VAR,
+};
+use std::{
+	fmt,
+	path::PathBuf,
+};
+
+/// Step of the resolution of the current toolchain, and what came of it.
+#[derive(Debug, Clone)]
+pub struct Step {
+	pub name: String,
+	pub outcome: Outcome,
+}
+
+#[derive(Debug, Clone)]
+pub enum Outcome {
+	/// Step determined the result, with details of how.
+	Used(String),
+	/// Step didn't apply, for the reason given.
+	Skipped(String),
+	/// Step failed with the given error, which ends the resolution.
+	Failed(String),
+}
+
+impl fmt::Display for Step {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.outcome {
+			Outcome::Used(details) => write!(f, "{}: {details}", self.name),
+			Outcome::Skipped(reason) => write!(f, "{}: skipped ({reason})", self.name),
+			Outcome::Failed(error) => write!(f, "{}: failed ({error})", self.name),
+		}
+	}
+}
+
+/// Resolve the current toolchain with `config`, returning every step that was taken along the way, and the path to the
+/// toolchain if it was resolved.
+pub fn explain(config: &Config) -> (Vec<Step>, AResult<PathBuf>) {
+	let mut trace = Trace::default();
+	let result = trace.resolve(config);
+	(trace.steps, result)
+}
+
+#[derive(Default)]
+struct Trace {
+	steps: Vec<Step>,
+}
+
+impl Trace {
+	fn push(&mut self, name: impl Into<String>, outcome: Outcome) {
+		self.steps.push(Step {
+			name: name.into(),
+			outcome,
+		});
+	}
+
+	fn resolve(&mut self, config: &Config) -> AResult<PathBuf> {
+		let data = config.with_doc.data();
+		let origins = config.with_doc.origins(&config.path);
+		let (toolchain, source) = current_toolchain(data).inspect_err(|e| {
+			self.push(TOOLCHAIN_VAR, Outcome::Failed(e.to_string()));
+		})?;
+		match source {
+			ToolchainSource::Env => self.push(TOOLCHAIN_VAR, Outcome::Used(format!("selects {:?}", toolchain.to_string()))),
+			ToolchainSource::Config => self.push(TOOLCHAIN_VAR, Outcome::Skipped("not set".into())),
+		}
+		self.defaults(config, &origins, source);
+
+		let path = self.find(data, &origins, toolchain.as_selector())?;
+		self.push("toolchain", Outcome::Used(path.display().to_string()));
+
+		let command = CompilerCommand::new(data, path.clone()).inspect_err(|e| {
+			self.push("compiler", Outcome::Failed(e.to_string()));
+		})?;
+		let mut details = command.compiler.display().to_string();
+		if !command.runner.is_empty() {
+			details = format!("{details} (run with {})", command.runner.join(" "));
+		}
+		self.push("compiler", Outcome::Used(details));
+		Ok(path)
+	}
+
+	/// Add a step for every layer of the configuration that may set `default`, in order of precedence.
+	fn defaults(&mut self, config: &Config, origins: &[ValueOrigin], source: ToolchainSource) {
+		let layer_file = move |layer| config.with_doc.layers().iter().find(move |l| l.layer == layer);
+		let value_in = move |document: &DocumentMut| document.get("default")?.as_str().map(String::from);
+		let env = layer_file(ConfigLayer::Env);
+		let project = layer_file(ConfigLayer::Project);
+		let system = layer_file(ConfigLayer::System);
+		let user_value = value_in(config.with_doc.document());
+		let user_sets = user_value.is_some();
+
+		let path_name = move |name: &str, path: Option<PathBuf>| match path {
+			Some(path) => format!("{name} {}", path.display()),
+			None => name.to_string(),
+		};
+		let candidates = [
+			(ConfigLayer::Env, env_var_name("default"), env.and_then(move |l| value_in(&l.document)), "not set"),
+			(
+				ConfigLayer::Project,
+				path_name("project file", project.and_then(move |l| l.path.clone())),
+				project.and_then(move |l| value_in(&l.document)),
+				if project.is_some() { "doesn't set `default`" } else { "no rookup.toml in the current directory or its parents" },
+			),
+			(
+				ConfigLayer::User,
+				path_name("user config file", Some(config.path.clone())),
+				user_value,
+				"doesn't set `default`",
+			),
+			(
+				ConfigLayer::System,
+				path_name("system config file", system_config_path()),
+				system.and_then(move |l| value_in(&l.document)),
+				if system.is_some() { "doesn't set `default`" } else { "doesn't exist" },
+			),
+			(ConfigLayer::Default, "built-in default".into(), Some(ConfigData::default().default.to_string()), ""),
+		];
+
+		// Values of the user's file that are the same as the defaults are attributed to the default layer.
+		let winner = match origins.iter().find(move |o| o.key == "default").map(move |o| o.layer) {
+			Some(ConfigLayer::Default) if user_sets => ConfigLayer::User,
+			layer => layer.unwrap_or(ConfigLayer::Default),
+		};
+		let winner_name = candidates.iter()
+			.find(move |(layer, ..)| *layer == winner)
+			.map(move |(.., name, _, _)| name.clone())
+			.unwrap_or_default();
+
+		for (layer, name, value, reason) in candidates {
+			let outcome = match value {
+				None => Outcome::Skipped(reason.into()),
+				Some(value) if source == ToolchainSource::Env => {
+					Outcome::Skipped(format!("sets `default` to {value:?}, but is overridden by {TOOLCHAIN_VAR}"))
+				}
+				Some(value) if layer == winner => Outcome::Used(format!("selects {value:?}")),
+				Some(value) => Outcome::Skipped(format!("sets `default` to {value:?}, but is overridden by {winner_name}")),
+			};
+			self.push(name, outcome);
+		}
+	}
+
+	/// Add steps for finding the toolchain that `selector` selects, returning its path.
+	fn find(&mut self, data: &ConfigData, origins: &[ValueOrigin], selector: Selector<'_>) -> AResult<PathBuf> {
+		let name = match selector {
+			Selector::Alias(Selector::INSTALLED) => "latest installed toolchain".into(),
+			Selector::Alias(alias) => format!("alias {alias}"),
+			Selector::Super(version) => format!("latest toolchain compatible with {version}"),
+			Selector::Path(path) => format!("path {path}"),
+		};
+
+		// Aliases are resolved by the same function as when invoking the compiler, which reports each of its steps.
+		let result = find_toolchain_traced(data, selector, &mut |step| match step {
+			FindStep::Alias { alias, version } => {
+				let key = format!("aliases.{alias}");
+				let details = match origins.iter().find(move |o| o.key == key).map(move |o| o.layer) {
+					Some(layer) => format!("{version} (set by the {layer} configuration)"),
+					None => version.to_string(),
+				};
+				self.push(format!("alias {alias}"), Outcome::Used(details));
+			}
+			FindStep::Home { home, path, found } => {
+				let version = path.file_name().unwrap_or_default().to_string_lossy();
+				let outcome = match found {
+					true => Outcome::Used(format!("has {version}")),
+					false if path.exists() => Outcome::Skipped(format!("{version} has no compiler")),
+					false => Outcome::Skipped(format!("no {version}")),
+				};
+				self.push(format!("toolchain home {}", home.display()), outcome);
+			}
+		});
+		let found = match result {
+			Ok(found) => found,
+			Err(e) => {
+				// Aliases that are set were already reported, followed by the homes that didn't have their toolchain.
+				let name = if matches!(e, FindToolchainError::NotFound { .. }) { "toolchain".into() } else { name };
+				self.push(name, Outcome::Failed(e.to_string()));
+				return Err(e.into())
+			}
+		};
+		match &found.kinded {
+			FoundToolchainKinded::Latest { home } => {
+				self.push(name, Outcome::Used(found.name.clone()));
+				self.push(format!("toolchain home {}", home.display()), Outcome::Used(format!("has {}", found.name)));
+			}
+			FoundToolchainKinded::Aliased { .. } => {}
+			FoundToolchainKinded::Path { .. } => self.push(name, Outcome::Used("has a compiler".into())),
+		}
+		Ok(found.into_path())
+	}
+}
+
+#[test]
+fn toolchain_resolution_is_traced() {
+	use rookup_common::{
+		TempDir, CUSTOM_TOOLCHAIN_HOME_VAR, SPCOMP_EXE, TOOLCHAIN_HOME_VAR,
+	};
+
+	let root = TempDir::new("rookup-explain").unwrap();
+	let (custom, cached) = (root.join("custom"), root.join("toolchains"));
+	for path in [custom.join("1.12.0.7100"), cached.join("1.12.0.7200")] {
+		std::fs::create_dir_all(&path).unwrap();
+		let mut options = std::fs::File::options();
+		#[cfg(unix)]
+		std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o755);
+		options.create(true).truncate(true).write(true).open(path.join(SPCOMP_EXE)).unwrap();
+	}
+	// Failed install without a compiler.
+	std::fs::create_dir_all(custom.join("1.11.0.6970")).unwrap();
+	std::env::set_var(CUSTOM_TOOLCHAIN_HOME_VAR, &custom);
+	std::env::set_var(TOOLCHAIN_HOME_VAR, &cached);
+
+	let mut data = ConfigData::default();
+	data.aliases.insert("work".into(), "1.12.0.7200".into());
+	data.aliases.insert("old".into(), "1.11.0.6970".into());
+	let find = |selector: &str| {
+		let mut trace = Trace::default();
+		let result = trace.find(&data, &[], Selector::parse(selector));
+		let steps: Vec<_> = trace.steps.iter().map(ToString::to_string).collect();
+		(steps, result)
+	};
+
+	let (steps, result) = find("work");
+	assert_eq!(result.unwrap(), cached.join("1.12.0.7200"));
+	assert_eq!(steps, [
+		"alias work: 1.12.0.7200".into(),
+		format!("toolchain home {}: skipped (no 1.12.0.7200)", custom.display()),
+		format!("toolchain home {}: has 1.12.0.7200", cached.display()),
+	]);
+
+	let (steps, result) = find("old");
+	assert!(result.is_err());
+	assert_eq!(steps[1], format!("toolchain home {}: skipped (1.11.0.6970 has no compiler)", custom.display()));
+	assert!(steps[3].starts_with("toolchain: failed"));
+
+	let (steps, result) = find("missing");
+	assert!(result.is_err());
+	assert!(steps[0].starts_with("alias missing: failed"));
+
+	let (steps, result) = find(":1.12");
+	assert_eq!(result.unwrap(), cached.join("1.12.0.7200"));
+	assert_eq!(steps[0], "latest toolchain compatible with 1.12: 1.12.0.7200");
+
+	let selector = format!("path:{}", custom.join("1.12.0.7100").display());
+	let (steps, result) = find(&selector);
+	assert_eq!(result.unwrap(), custom.join("1.12.0.7100"));
+	assert_eq!(steps, [format!("path {}: has a compiler", custom.join("1.12.0.7100").display())]);
+}
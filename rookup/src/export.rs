@@ -0,0 +1,119 @@
+//! Export of the entire state of Rookup for external tools.
+
+use anyhow::{
+	anyhow, Result as AResult,
+};
+use rookup_common::{
+	current_toolchain, custom_toolchain_homes, find_toolchain, toolchain_homes,
+	Config, ConfigData, FoundToolchain, Receipt, SelectorBuf, ToolchainSource,
+};
+use rookup_ops::{
+	disk::dir_size,
+	InstalledToolchain,
+};
+use serde::Serialize;
+use std::{
+	collections::BTreeMap,
+	path::PathBuf,
+};
+
+/// Entire state of Rookup, as written by `rookup export`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct State {
+	/// Path to the configuration file.
+	pub config_path: PathBuf,
+	pub config: ConfigData,
+	pub toolchain_homes: Vec<PathBuf>,
+	pub custom_toolchain_homes: Vec<PathBuf>,
+	pub default: DefaultToolchain,
+	/// Map of aliases to the versions they resolve to.
+	pub aliases: BTreeMap<String, Alias>,
+	/// Installed toolchains, sorted by version.
+	pub toolchains: Vec<Toolchain>,
+}
+
+/// Currently selected toolchain.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DefaultToolchain {
+	pub selector: SelectorBuf,
+	pub source: ToolchainSource,
+	/// Toolchain that the selector resolves to, if it is installed.
+	pub toolchain: Option<FoundToolchain>,
+	/// Reason that the selector couldn't be resolved, if it couldn't.
+	pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Alias {
+	pub version: String,
+	pub installed: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Toolchain {
+	pub name: String,
+	pub path: PathBuf,
+	pub custom: bool,
+	/// Paths to toolchains of the same name in other homes, which are never selected.
+	pub shadowed: Vec<PathBuf>,
+	/// Total size of the files of the toolchain in bytes, if it could be determined.
+	pub size: Option<u64>,
+	pub receipt: Option<Receipt>,
+}
+
+impl State {
+	/// Collect the state of Rookup with the configuration file `config`.
+	pub fn collect(config: Config) -> AResult<Self> {
+		let config_path = config.path;
+		let data: ConfigData = config.with_doc.into();
+
+		let (selector, source) = current_toolchain(&data)
+			.map_err(move |e| anyhow!("failed to get current toolchain: {e}"))?;
+		let (toolchain, error) = match find_toolchain(&data, selector.as_selector()) {
+			Ok(found) => (Some(found), None),
+			Err(e) => (None, Some(e.to_string())),
+		};
+		let default = DefaultToolchain {
+			selector,
+			source,
+			toolchain,
+			error,
+		};
+
+		let installed = InstalledToolchain::all()?;
+		let aliases = data.aliases.iter()
+			.map(|(name, version)| {
+				let alias = Alias {
+					version: version.clone(),
+					installed: installed.iter().any(move |t| t.name == *version),
+				};
+				(name.clone(), alias)
+			})
+			.collect();
+
+		let toolchains = installed.into_iter()
+			.map(move |t| Toolchain {
+				size: dir_size(&t.path).ok(),
+				receipt: Receipt::read(&t.path).ok().flatten(),
+				name: t.name,
+				path: t.path,
+				custom: t.is_custom,
+				shadowed: t.shadowed,
+			})
+			.collect();
+
+		Ok(Self {
+			config_path,
+			config: data,
+			toolchain_homes: toolchain_homes(),
+			custom_toolchain_homes: custom_toolchain_homes(),
+			default,
+			aliases,
+			toolchains,
+		})
+	}
+}
@@ -0,0 +1,58 @@
+//! Integration with GitHub Actions workflows.
+
+use anyhow::{
+	anyhow, Context, Result as AResult,
+};
+use rookup_ops::InstallParts;
+use std::{
+	env::var_os,
+	fs::OpenOptions,
+	io::Write,
+	path::PathBuf,
+};
+
+/// Name of the environment variable with the path to the file that step outputs are appended to.
+const OUTPUT_VAR: &str = "GITHUB_OUTPUT";
+
+/// Group of log lines that is folded in the log of a workflow run, until it is dropped.
+pub struct LogGroup(());
+
+impl LogGroup {
+	pub fn new(title: &str) -> Self {
+		println!("::group::{title}");
+		Self(())
+	}
+}
+
+impl Drop for LogGroup {
+	fn drop(&mut self) {
+		println!("::endgroup::");
+	}
+}
+
+/// Append `outputs` as step outputs to the file specified by `GITHUB_OUTPUT`.
+pub fn write_outputs(outputs: &[(&str, &str)]) -> AResult<()> {
+	let path = var_os(OUTPUT_VAR)
+		.map(PathBuf::from)
+		.with_context(|| anyhow!("`{OUTPUT_VAR}` is not set (is Rookup running in a GitHub Actions workflow?)"))?;
+	let mut file = OpenOptions::new().create(true).append(true).open(&path)
+		.with_context(|| anyhow!("failed to open {path:?}"))?;
+	for (name, value) in outputs {
+		writeln!(file, "{name}={value}").with_context(|| anyhow!("failed to write to {path:?}"))?;
+	}
+	Ok(())
+}
+
+/// Return a key for caching the toolchain of `version` for the `os` target with `parts` between workflow runs.
+pub fn cache_key(os: &str, version: &str, parts: &InstallParts) -> String {
+	let mut key = format!("rookup-{os}-{version}");
+	for component in parts.components.iter() {
+		key.push('-');
+		key.push_str(&component.to_string());
+	}
+	for extra in parts.extras.iter() {
+		key.push_str("-extra-");
+		key.push_str(&extra.to_string());
+	}
+	key
+}
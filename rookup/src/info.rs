@@ -0,0 +1,84 @@
+//! Display of everything known about a selector, as shown by `rookup info`.
+
+use rookup_common::{
+	Component, FindToolchainError,
+};
+use rookup_ops::{
+	disk::ByteSize,
+	display_time, SelectorInfo,
+};
+
+use crate::color;
+
+/// Print `info` about the selector `selector`, with one labelled line per piece of information.
+pub fn print(selector: &str, info: &SelectorInfo) {
+	println!("Selector: {selector}");
+	if let Some(alias) = info.alias.as_ref() {
+		println!("Alias: {} => {} ({})", alias.name, color::version(&alias.version), alias.policy);
+	}
+	if let Some(branch) = info.branch() {
+		println!("Branch: {}", color::version(branch));
+	}
+
+	match info.installed.as_ref() {
+		Ok(installed) => {
+			println!("Installed: {}", color::yes_no(true));
+			println!("Installed version: {}", color::version(&installed.name));
+			println!("Path: {}", color::path(&installed.path));
+			if let Some(size) = installed.size {
+				println!("Size: {}", ByteSize(size));
+			}
+			match installed.receipt.as_ref() {
+				Ok(Some(receipt)) => {
+					println!("Installed from: {}", receipt.origin);
+					if let Some(os) = receipt.target_os.as_deref() {
+						println!("Target OS: {os}");
+					}
+					if !receipt.components.is_empty() {
+						let components: Vec<_> = receipt.components.iter().map(Component::to_string).collect();
+						println!("Components: {}", components.join(", "));
+					}
+					if let Some(signature) = receipt.signature.as_ref() {
+						println!("Verified with: {signature}");
+					}
+				}
+				// Custom toolchains aren't installed by Rookup.
+				Ok(None) => {}
+				Err(e) => eprintln!("{} {e}", color::warning("Warning:")),
+			}
+		}
+		Err(
+			FindToolchainError::NotFound { .. }
+			| FindToolchainError::LatestNotFound(..)
+			| FindToolchainError::NoneInstalled
+		) => {
+			println!("Installed: {}", color::yes_no(false));
+		}
+		// Aliases like `stable` only select remote versions.
+		Err(FindToolchainError::NoAliasDefault(..)) if matches!(info.remote, Some(Ok(..))) => {
+			println!("Installed: {}", color::yes_no(false));
+		}
+		Err(e) => {
+			println!("Installed: {}", color::yes_no(false));
+			eprintln!("{} {e}", color::warning("Warning:"));
+		}
+	}
+
+	match info.remote.as_ref() {
+		Some(Ok(remote)) => {
+			println!("Remote version: {}", color::version(remote.latest.version()));
+			println!("Remote URL: {}", remote.latest.url());
+			if let Some(published) = remote.latest.published() {
+				println!("Published: {}", display_time(published));
+			}
+			if let Some(size) = remote.latest.size() {
+				println!("Download size: {}", ByteSize(size));
+			}
+			if let Some(update_available) = info.update_available() {
+				println!("Update available: {}", color::yes_no(update_available));
+			}
+		}
+		Some(Err(e)) => eprintln!("{} couldn't fetch the remote version: {e}", color::warning("Warning:")),
+		None => {}
+	}
+}
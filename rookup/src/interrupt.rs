@@ -0,0 +1,67 @@
+//! Handling of Ctrl+C during installations.
+
+use std::{
+	process::exit,
+	sync::{
+		Mutex, Once,
+	},
+};
+
+use rookup_ops::install::CancelToken;
+
+/// Exit code used when Rookup is interrupted with Ctrl+C.
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Token of the installation that is currently running, if any.
+static CURRENT: Mutex<Option<CancelToken>> = Mutex::new(None);
+
+/// Guard that makes Ctrl+C cancel an installation instead of exiting, until it is dropped.
+///
+/// Pressing Ctrl+C a second time exits immediately, in case the installation is stuck (for e.g. waiting on the
+/// network).
+pub struct InterruptGuard {
+	token: CancelToken,
+}
+
+impl InterruptGuard {
+	pub fn new() -> Self {
+		static HANDLER: Once = Once::new();
+		HANDLER.call_once(move || {
+			let result = ctrlc::set_handler(move || {
+				let current = CURRENT.lock().ok().and_then(move |c| c.clone());
+				match current {
+					Some(token) if !token.is_cancelled() => {
+						eprintln!("\nInterrupted; rolling back (press Ctrl+C again to exit immediately)");
+						token.cancel();
+					}
+					_ => exit(INTERRUPTED_EXIT_CODE),
+				}
+			});
+			if let Err(e) = result {
+				eprintln!("Failed to set up Ctrl+C handler: {e}");
+			}
+		});
+
+		let token = CancelToken::default();
+		if let Ok(mut current) = CURRENT.lock() {
+			*current = Some(token.clone());
+		}
+		Self {
+			token,
+		}
+	}
+
+	/// Return the token that is cancelled when Ctrl+C is pressed.
+	#[inline]
+	pub fn token(&self) -> CancelToken {
+		self.token.clone()
+	}
+}
+
+impl Drop for InterruptGuard {
+	fn drop(&mut self) {
+		if let Ok(mut current) = CURRENT.lock() {
+			*current = None;
+		}
+	}
+}
@@ -3,43 +3,108 @@ use anyhow::{
 	Context, Result as AResult,
 };
 use clap::{
-	Parser, Subcommand,
+	Args, Parser, Subcommand,
 };
 use rookup_common::{
+	doc_map::config_keys,
 	version::{
-		Version, version_ord,
+		version_ord, VersionReq,
 	},
-	current_toolchain, find_toolchain, find_latest_toolchain_of, is_installed, toolchain_home,
-	Config, ConfigData, ConfigExt,
-	ToolchainVersions, Selector,
-	DirNames,
+	clear_stale_lock, current_toolchain, find_project_config, find_toolchain, find_toolchain_path, find_installed, toolchain_env, build_env, has_components, env_var_name,
+	toml_edit::Value,
+	AliasPolicy, Config, ConfigData, ConfigError, ConfigExt, ConfigLayer, LayerFile,
+	CompilerCommand, CompilerNames, Component, Extra, Origin, Receipt, SourceKind,
+	ToolchainSource, Selector, SelectorBuf, FindToolchainError,
+	journal_path, journaled_value, state_home, Change, Journal,
+	NO_CREATE_CONFIG_VAR, TOOLCHAIN_HOME_VAR,
+};
+use rookup_ops::{
+	disk::{
+		dir_size, ByteSize,
+	},
+	install::{
+		InstallError, InstallEvent,
+	},
+	source::{
+		self, Provider,
+	},
+	*,
 };
-use rustc_hash::FxHashSet;
 use std::{
-	ffi::OsStr,
+	env::{
+		consts::EXE_SUFFIX,
+		current_dir, current_exe, set_var, var_os,
+	},
+	ffi::{
+		OsStr, OsString,
+	},
 	fs::{
-		File, create_dir_all, read_dir, remove_dir_all,
+		File,
 	},
 	io::{
-		copy as io_copy,
-		ErrorKind as IoErrorKind,
+		stderr, stdin, stdout,
+		ErrorKind as IoErrorKind, IsTerminal,
 	},
-	path::PathBuf,
-	process::ExitCode,
-	str::FromStr,
+	path::{
+		absolute, Path, PathBuf,
+	},
+	process::{
+		exit, Command as ProcessCommand, ExitCode,
+	},
+	time::SystemTime,
 };
-#[cfg(unix)]
-use std::os::unix::fs::OpenOptionsExt;
-use ureq::Agent;
 
-mod smdrop;
-mod smdrop_util;
-use smdrop_util::*;
-mod sp_from_sm;
+mod alias_file;
+mod check;
+mod color;
+mod doctor;
+use alias_file::{
+	read_aliases, write_aliases, AliasMap,
+};
+mod explain;
+mod export;
+mod github;
+mod info;
+mod interrupt;
+mod mirror;
+mod paths;
+mod picker;
+use picker::pick;
+mod removal;
+#[cfg(feature = "tui")]
+mod tui;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 pub struct Cli {
+	/// Select archives for this operating system (for e.g. `linux` or `windows`), instead of the host's.
+	/// 
+	/// This overrides the `target-os` setting in the `source` table of the configuration file.
+	/// Toolchains for other operating systems should be installed into a separate toolchain home.
+	#[arg(long, global = true)]
+	pub target_os: Option<String>,
+	/// Use this directory as the only toolchain home, instead of the ones specified by `ROOKUP_TOOLCHAIN_HOME` or the
+	/// default one.
+	/// 
+	/// Commands run by Rookup (like with `rookup run`) use this toolchain home too.
+	#[arg(long, global = true, value_name = "DIR")]
+	pub toolchain_home: Option<PathBuf>,
+	/// Use the source with this name in the `sources` table of the configuration file, instead of the default one.
+	/// 
+	/// This overrides the `default-source` setting of the configuration file.
+	#[arg(long, global = true, value_name = "NAME")]
+	pub source: Option<String>,
+	/// Fail instead of creating the configuration file if it doesn't exist and a command would change it.
+	/// 
+	/// Commands that only read the configuration never create the file, and use the built-in defaults instead. This
+	/// can also be set with the `ROOKUP_NO_CREATE_CONFIG` environment variable.
+	#[arg(long, global = true)]
+	pub no_create_config: bool,
+	/// When to color output.
+	/// 
+	/// With `auto`, output is colored if it's written to a terminal and `NO_COLOR` isn't set to a non-empty value.
+	#[arg(long, global = true, value_name = "WHEN", default_value = "auto")]
+	pub color: color::ColorChoice,
 	#[command(subcommand)]
 	pub command: Command,
 }
@@ -47,371 +112,1631 @@ pub struct Cli {
 #[derive(Debug, Clone, Subcommand)]
 pub enum Command {
 	/// Show current configuration data.
-	Config,
+	/// 
+	/// Every time Rookup changes the configuration file, its previous contents are kept in `config.toml.bak` next to
+	/// it.
+	Config {
+		#[command(subcommand)]
+		command: Option<ConfigCommand>,
+		/// Show every value with the layer (`default`, `system`, `user`, `project`, or `environment`) and file that it came from.
+		#[arg(long)]
+		show_origin: bool,
+	},
 	/// Get or set the default version selector.
+	/// 
+	/// If no selector is specified and Rookup is run in a terminal, an interactive list of installed toolchains and
+	/// aliases is shown to pick the new default from.
 	Default {
 		/// If set, then this string will be the new default version selector.
 		default: Option<String>,
+		/// Only print the current default version selector, even when run in a terminal.
+		#[arg(long, conflicts_with = "default")]
+		print: bool,
+		/// Also accept a selector that doesn't select an installed toolchain, as long as it selects a version that is
+		/// available on the remote.
+		#[arg(long, requires = "default")]
+		allow_remote: bool,
+		/// Set the new default even if it doesn't select any toolchain, only printing a warning.
+		#[arg(long, requires = "default")]
+		force: bool,
 	},
 	/// Get or set an alias.
 	Alias {
-		alias: String,
+		#[arg(required_unless_present_any = ["import", "export"])]
+		alias: Option<String>,
 		version: Option<String>,
+		/// Set every alias in this file, which contains a table of aliases to versions.
+		/// 
+		/// Files ending with `.json` are read as JSON, and all other files as TOML.
+		#[arg(long, value_name = "FILE", conflicts_with_all = ["alias", "export"])]
+		import: Option<PathBuf>,
+		/// Write every alias into this file, in the same format as `--import`.
+		#[arg(long, value_name = "FILE", conflicts_with = "alias")]
+		export: Option<PathBuf>,
+		/// Set aliases to versions that are neither installed nor available on the remote, only printing a warning.
+		#[arg(long, conflicts_with = "export")]
+		force: bool,
+		/// Set how `rookup update` may change the alias: `track` follows the latest build of its branch, `pin` never
+		/// changes it, and `frozen` makes `rookup update` fail if it would change it.
+		#[arg(long, conflicts_with_all = ["import", "export"])]
+		policy: Option<AliasPolicy>,
+	},
+	/// Show recent changes to the default version selector and aliases, newest first.
+	/// 
+	/// Every command that changes them records the change in `history.toml` in the state directory of Rookup, which
+	/// keeps the last 50 changes.
+	History,
+	/// Revert the most recent change to the default version selector and aliases.
+	Undo {
+		/// Revert the change even if the values were changed since in another way, such as by editing the configuration
+		/// file.
+		#[arg(long)]
+		force: bool,
 	},
 	/// Show a list of installed toolchains.
-	Show,
+	Show {
+		/// Also fetch the latest version of every remote branch, and show installed toolchains and remote versions in
+		/// one table, marking each as `installed`, `outdated` or `available`.
+		#[arg(long)]
+		remote: bool,
+	},
+	/// Show everything known about what `selector` selects: the alias and branch it resolves through, the installed
+	/// toolchain with its size and install receipt, and the latest remote version and whether it's newer.
+	/// 
+	/// If not specified, the selector defaults to the default toolchain.
+	Info {
+		selector: Option<String>,
+	},
+	/// Show the license files of installed toolchains, with the first line of each, which is usually the name of the
+	/// license.
+	/// 
+	/// If `selector` is specified, only the toolchain that it selects is shown.
+	Licenses {
+		selector: Option<String>,
+	},
+	/// Write a SHA-256 digest of the files of the installed toolchain selected by `selector` to standard output.
+	/// 
+	/// The digest covers the paths and contents of all files except for the install receipt, so it's the same for
+	/// identical toolchains on any machine.
+	Hash {
+		selector: String,
+		/// Write the digest as a cache key for CI, like `rookup-1.12.0.7200-<first 16 digits of the digest>`, instead.
+		#[arg(long)]
+		cache_key: bool,
+	},
+	/// Show a list of the branches available on the remote, which can be selected with `:<branch>`.
+	/// 
+	/// Branches are marked with the aliases `latest` and `stable` if those select them, and with the versions that are
+	/// installed from them.
+	Branches,
 	/// Fetch the latest version of SourcePawn, download it if needed, and default to it.
 	Update {
 		selector: Option<String>,
 		/// Set this alias to the version that was installed.
 		/// 
 		/// If not specified, then, if the selector string specifies an alias, it is used as the alias.
+		/// Aliases with the `pin` policy are left as they are, and ones with the `frozen` policy make this fail if their
+		/// version would change.
 		alias: Option<String>,
+		/// Update every alias that refers to a version of a remote branch, according to its policy.
+		#[arg(long, conflicts_with_all = ["selector", "alias", "github_output"])]
+		all: bool,
 		/// Re-download the toolchain, regardless of whether it is already installed or not.
+		/// 
+		/// Files that are identical to the ones already installed aren't written again.
 		#[arg(long)]
 		redownload: bool,
+		#[command(flatten)]
+		install: InstallArgs,
+		/// Fold the installation log, and append `toolchain-path`, `version`, and `cache-key` to `$GITHUB_OUTPUT`, for
+		/// GitHub Actions workflows.
+		#[arg(long)]
+		github_output: bool,
 	},
 	/// Install a specific SourcePawn toolchain.
 	Install {
-		selector: String,
+		#[arg(required_unless_present_any = ["url", "from_archive"])]
+		selector: Option<String>,
+		/// Install the archive at this URL instead of one listed by the source.
+		/// 
+		/// The version is taken from the file name (for e.g. `sourcemod-1.12.0-git7200-linux.tar.gz`), unless
+		/// `--as-version` is specified.
+		#[arg(long, conflicts_with = "selector")]
+		url: Option<String>,
+		/// Install the toolchain in this archive, which was written by `rookup toolchain export`, instead of one listed
+		/// by the source.
+		/// 
+		/// The toolchain is installed as the version recorded in the archive.
+		#[arg(long, value_name = "FILE", conflicts_with_all = ["selector", "url"])]
+		from_archive: Option<PathBuf>,
+		/// Install the archive at `--url` as this version.
+		#[arg(long, requires = "url")]
+		as_version: Option<String>,
+		/// Refuse to install the archive unless it has this SHA-256 digest, written in hexadecimal.
+		#[arg(long)]
+		sha256: Option<String>,
 		/// Re-download the toolchain, regardless of whether it is already installed or not.
+		/// 
+		/// Files that are identical to the ones already installed aren't written again.
 		#[arg(long)]
 		redownload: bool,
+		/// Install the toolchain into this directory instead of the toolchain home, and print the path to its compiler.
+		/// 
+		/// The toolchain isn't registered anywhere, so it can't be selected by Rookup afterwards. The directory must be
+		/// empty or have a toolchain installed by Rookup, which is replaced.
+		#[arg(long)]
+		prefix: Option<PathBuf>,
+		#[command(flatten)]
+		install: InstallArgs,
+		/// Fold the installation log, and append `toolchain-path`, `version`, and `cache-key` to `$GITHUB_OUTPUT`, for
+		/// GitHub Actions workflows.
+		#[arg(long)]
+		github_output: bool,
 	},
-	/// Delete a specific SourcePawn toolchain.
+	/// Install everything that the project in the current directory needs to be compiled, failing if anything couldn't
+	/// be.
+	/// 
+	/// The project is declared by the nearest `rookup.toml`: the toolchain that it selects is installed if it isn't
+	/// yet, and every directory of its `include-dirs` must exist.
+	Sync {
+		#[command(flatten)]
+		install: InstallArgs,
+	},
+	/// Delete a specific SourcePawn toolchain, and report how much disk space was freed.
 	Remove {
 		selector: String,
+		/// Write the removed toolchains and the disk space freed as JSON instead.
+		#[arg(long)]
+		json: bool,
 	},
 	/// List all SourcePawn toolchains that aren't used.
 	/// 
 	/// Any toolchain version that has an alias associated with it is marked as used.
 	/// The default version is also implied to be in use.
 	ListUnused,
-	/// Delete all SourcePawn toolchains that aren't used.
+	/// Delete all SourcePawn toolchains that aren't used, and report how much disk space was freed.
 	/// 
 	/// See the subcommand `list-unused` for more information.
-	Purge,
+	Purge {
+		/// Write the removed toolchains and the disk space freed as JSON instead.
+		#[arg(long)]
+		json: bool,
+		/// List the unused toolchains with their sizes and when they were last used, and choose which ones to delete
+		/// before confirming.
+		/// 
+		/// Times of last use are only known for toolchains that were used while `log-invocations` was enabled.
+		#[arg(long, short, conflicts_with = "json")]
+		interactive: bool,
+	},
 	/// Write the directory of the currently selected toolchain to standard output, without a newline.
-	Which,
+	Which {
+		/// Also write how the toolchain was resolved to standard error, as `rookup explain` does.
+		#[arg(long)]
+		explain: bool,
+	},
+	/// Show how the currently selected toolchain is resolved, step by step: the `ROOKUP_TOOLCHAIN` environment
+	/// variable, the configuration files that may set the default selector, the alias, the toolchain homes that were
+	/// searched, and the resulting toolchain and compiler.
+	/// 
+	/// Steps that were skipped are shown with the reason why.
+	Explain,
+	/// Show the currently selected toolchain and whether it is installed.
+	/// 
+	/// This never accesses the network, so it is suitable for shell prompts.
+	Status {
+		/// Print a single line in a stable format for scripts, with these fields separated by tabs: the selector, where
+		/// it came from (`env` or `config`), the version it resolves to (or `-`), and either `installed` or
+		/// `not-installed`.
+		#[arg(long)]
+		porcelain: bool,
+	},
+	/// Run a command with the toolchain selected by `selector`.
+	/// 
+	/// The toolchain directory is prepended to `PATH`, and `ROOKUP_TOOLCHAIN`, `ROOKUP_TOOLCHAIN_DIR` and
+	/// `ROOKUP_INCLUDE_PATH` are set for the command.
+	Run {
+		selector: String,
+		/// Program to run, followed by its arguments.
+		#[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+		command: Vec<OsString>,
+	},
+	/// Run a command (like a build system) with the currently selected toolchain.
+	/// 
+	/// In addition to the environment variables set by `run`, `SPCOMP` is set to the compiler executable, `SPCOMP_DIR`
+	/// to the toolchain directory, and `SOURCEMOD_INCLUDE` to its `include` directory.
+	Exec {
+		/// Select the toolchain with this selector instead of the current one.
+		#[arg(long)]
+		toolchain: Option<String>,
+		/// Program to run, followed by its arguments.
+		#[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+		command: Vec<OsString>,
+	},
+	/// Start an interactive shell that uses a toolchain, until the shell exits.
+	/// 
+	/// The shell gets the same environment variables as commands run with `exec`, so `spcomp` and build systems use the
+	/// toolchain inside of it.
+	Shell {
+		/// Select the toolchain with this selector instead of the current one.
+		selector: Option<String>,
+		/// Shell to start, instead of the one in `SHELL` (or `COMSPEC` on Windows).
+		#[arg(long)]
+		shell: Option<OsString>,
+	},
+	/// Show an interactive dashboard of installed toolchains.
+	#[cfg(feature = "tui")]
+	Tui {
+		#[command(flatten)]
+		install: InstallArgs,
+	},
+	/// Manage a local mirror of the configured source.
+	Mirror {
+		#[command(subcommand)]
+		command: MirrorCommand,
+	},
+	/// Write the entire state of Rookup as a JSON document to standard output.
+	/// 
+	/// This includes the configuration, the currently selected toolchain, aliases, and installed toolchains with
+	/// their paths, sizes, and install receipts.
+	Export,
+	/// Show the directories and files used by Rookup, and the environment variables that determined them.
+	Paths {
+		/// Write the paths as a JSON array instead.
+		#[arg(long)]
+		json: bool,
+	},
+	/// Check Rookup's own files for problems, like locks that were left behind by processes that crashed.
+	/// 
+	/// Stale locks are also cleared whenever another process would wait for them, so this is only needed to tidy up.
+	Doctor {
+		/// Remove locks that were left behind by processes that are gone.
+		#[arg(long)]
+		fix_locks: bool,
+	},
+	/// Inspect installed toolchains.
+	Toolchain {
+		#[command(subcommand)]
+		command: ToolchainCommand,
+	},
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum ConfigCommand {
+	/// Replace the configuration file with its backup from before it was last changed.
+	/// 
+	/// The replaced configuration becomes the new backup, so running this again undoes it.
+	Restore,
+	/// List every key of the configuration file with its documentation, type, and default value.
+	Keys,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum ToolchainCommand {
+	/// Check that the toolchain selected by `selector` has an executable compiler, an `include` directory with
+	/// `sourcemod.inc`, and no empty files.
+	/// 
+	/// Unlike other commands, this also finds toolchains that are missing their compiler.
+	Check {
+		selector: String,
+	},
+	/// Copy the compiler and includes of an existing SourceMod installation (for e.g. on a game server) into a new
+	/// custom toolchain named `name`.
+	/// 
+	/// `dir` may be the directory with `addons/sourcemod`, or the `addons/sourcemod` or `scripting` directory itself.
+	Import {
+		dir: PathBuf,
+		name: String,
+	},
+	/// Write the installed toolchain selected by `selector` into an archive (ending with `.tar.gz` or `.zip`), which
+	/// can be installed on another machine with `rookup install --from-archive`.
+	/// 
+	/// The archive has the install receipt of the toolchain, so it's installed as the same version.
+	Export {
+		selector: String,
+		archive: PathBuf,
+	},
+}
+
+/// Options for installing toolchains.
+#[derive(Debug, Clone, Args)]
+pub struct InstallArgs {
+	/// Also extract these optional parts of SourceMod distributions into the `extras` directory of the toolchain, in
+	/// addition to the ones in the configuration file.
+	/// 
+	/// May be any of `scripts`, `plugins`, and `translations`, separated by commas.
+	#[arg(long, value_delimiter = ',')]
+	pub extras: Vec<Extra>,
+	/// Only install these parts of the toolchain, instead of the ones in the configuration file.
+	/// 
+	/// May be any of `compiler` and `includes`, separated by commas.
+	#[arg(long = "component", value_delimiter = ',')]
+	pub components: Vec<Component>,
+	/// Normalize the permissions and modification times of extracted files, so that installing the same version gives
+	/// identical files on every machine (for e.g. for Docker layer caching).
+	/// 
+	/// Files are dated to `SOURCE_DATE_EPOCH` if it's set, or to 1980-01-01 otherwise.
+	#[arg(long)]
+	pub deterministic: bool,
+	/// Run the compiler of the installed toolchain with this command (for e.g. `wine`), instead of the one in the
+	/// `runners` table of the configuration file.
+	/// 
+	/// The command is recorded in the install receipt of the toolchain. Its arguments are separated by spaces.
+	#[arg(long, value_name = "COMMAND", value_delimiter = ' ')]
+	pub runner: Vec<String>,
+}
+
+impl InstallArgs {
+	/// Return the parts of toolchains to install, given the configuration `data`.
+	pub fn parts(&self, data: &ConfigData) -> InstallParts {
+		let mut extras = data.source.extras.clone();
+		extras.extend_from_slice(&self.extras);
+		extras.sort();
+		extras.dedup();
+
+		let mut components = if self.components.is_empty() {
+			data.source.components.clone()
+		} else {
+			self.components.clone()
+		};
+		components.sort();
+		components.dedup();
+		if components == Component::ALL {
+			components.clear();
+		}
+
+		InstallParts {
+			extras,
+			components,
+			deterministic: self.deterministic,
+			runner: self.runner.iter().filter(move |a| !a.is_empty()).cloned().collect(),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum MirrorCommand {
+	/// Download branches from the configured source into a directory that can be served as a `root-url`.
+	Sync {
+		/// Directory to put the mirror into.
+		dir: PathBuf,
+		/// Name of a branch to mirror. May be specified multiple times.
+		/// 
+		/// If not specified, then all branches are mirrored.
+		#[arg(long = "branch")]
+		branches: Vec<String>,
+		/// Only mirror the latest version of each branch for each platform.
+		#[arg(long)]
+		latest_only: bool,
+		/// Re-download archives that are already present in the mirror.
+		#[arg(long)]
+		redownload: bool,
+	},
+	/// Serve a mirror directory over HTTP.
+	#[cfg(feature = "mirror-serve")]
+	Serve {
+		/// Directory of the mirror to serve.
+		dir: PathBuf,
+		/// Address to listen on.
+		#[arg(long, default_value = "127.0.0.1:8000")]
+		bind: String,
+	},
 }
 
 fn real_main() -> AResult<()> {
 	let cli = Cli::parse();
+	color::init(cli.color);
+	if let Some(home) = cli.toolchain_home.as_deref() {
+		let home = absolute(home).with_context(|| anyhow!("invalid toolchain home {home:?}"))?;
+		// Toolchain homes are always read from the environment, which also makes child processes use this one.
+		set_var(TOOLCHAIN_HOME_VAR, home);
+	}
+	let overrides = Overrides::new(&cli);
+	if cli.no_create_config {
+		// Child processes (like ones run with `rookup run`) don't create the configuration file either.
+		set_var(NO_CREATE_CONFIG_VAR, "1");
+	}
 	match cli.command {
-		Command::Config => {
-			let config = Config::open_create(false)?;
-			println!("@{}", config.path.display());
+		Command::Config { command: None, show_origin: false } => {
+			let config = overrides.open_create(false)?;
+			println!("@{}", color::path(&config.path));
+			for layer in config.with_doc.layers() {
+				if let Some(path) = &layer.path {
+					println!("@{} ({})", color::path(path), layer.layer);
+				}
+			}
 			println!("{:#?}", config.with_doc.data());
 		}
 
-		Command::Default { default: new_default } => {
-			if let Some(new_default) = new_default {
-				let mut config = Config::open_create(true)?;
+		Command::Config { command: None, show_origin: true } => {
+			let config = overrides.open_create(false)?;
+			for origin in config.with_doc.origins(&config.path) {
+				let value = origin.value.to_string();
+				match origin.path {
+					Some(path) => println!("{} = {} ({}: {})", origin.key, value.trim(), origin.layer, path.display()),
+					None if origin.layer == ConfigLayer::Env => {
+						println!("{} = {} ({}: {})", origin.key, value.trim(), origin.layer, env_var_name(&origin.key))
+					}
+					None => println!("{} = {} ({})", origin.key, value.trim(), origin.layer),
+				}
+			}
+		}
+
+		Command::Config { command: Some(ConfigCommand::Restore), .. } => {
+			let mut config = overrides.open_create(true)?;
+			let backup_path = config.backup_path();
+			let backup = match File::open(&backup_path) {
+				Ok(file) => Config::with_file(file, backup_path)
+					.map_err(move |e| anyhow!("backup of configuration file is invalid: {e}"))?,
+				Err(e) if e.kind() == IoErrorKind::NotFound => bail!("configuration file has no backup to restore"),
+				Err(e) => bail!("failed to open backup of configuration file: {e}"),
+			};
+			config.with_doc = backup.with_doc;
+			config.rewrite().context("failed to write changes to configuration file")?;
+			println!("Restored {} from {}", config.path.display(), backup.path.display());
+		}
+
+		Command::Config { command: Some(ConfigCommand::Keys), .. } => {
+			let keys = config_keys().map_err(move |e| anyhow!("failed to serialize default configuration: {e}"))?;
+			for (i, key) in keys.iter().enumerate() {
+				if i > 0 {
+					println!();
+				}
+				match key.default.as_ref() {
+					Some(default) if default.is_table_like() => println!("{} (table)", key.key),
+					Some(default) => println!("{} ({}, default: {})", key.key, default.type_name(), default.to_string().trim()),
+					None => println!("{} (not set by default)", key.key),
+				}
+				for line in key.docs.lines() {
+					if line.is_empty() {
+						println!();
+					} else {
+						println!("    {line}");
+					}
+				}
+			}
+		}
+
+		Command::Default { default: new_default, print, allow_remote, force } => {
+			let new_default = match new_default {
+				Some(d) => {
+					let config = overrides.open_create(false)?;
+					let os = target_os(cli.target_os.as_deref(), config.with_doc.data());
+					match resolve_selector(&config, Selector::parse(&d), allow_remote, os) {
+						Ok(resolved) => println!("{d} selects {resolved}"),
+						Err(e) if force => eprintln!("{} {e:#}", color::warning("Warning:")),
+						Err(e) => bail!("{e:#} (use `--force` to set it anyway)"),
+					}
+					Some(d)
+				}
+				None if !print && stdin().is_terminal() && stdout().is_terminal() => {
+					let data: ConfigData = overrides.open_create(false)?.with_doc.into();
+					let choices = default_choices(&data)?;
+					let labels: Vec<_> = choices.iter()
+						.map(|c| if SelectorBuf::parse(c) == data.default { format!("{c} (current)") } else { c.clone() })
+						.collect();
+					pick("Default toolchain", &labels)?.map(|i| choices[i].clone())
+				}
+				None => None,
+			};
+
+			if let Some(new_default) = new_default.as_deref().map(SelectorBuf::parse) {
+				let mut config = overrides.open_create(true)?;
 				let old_default = &config.with_doc.data().default;
-				println!("{old_default} => {new_default}");
+				println!("{} => {}", color::version(old_default), color::version(&new_default));
 				if old_default != &new_default {
 					config.with_doc.set_default(new_default);
 					config.rewrite()?;
 				}
 			} else {
-				println!("{}", Config::open_create(false)?.with_doc.data().default);
+				println!("{}", overrides.open_create(false)?.with_doc.data().default);
 			}
 		}
 
-		Command::Alias { alias, version: value } => {
+		Command::Alias { import: Some(path), force, .. } => {
+			let aliases = read_aliases(&path)?;
+			if let Some(alias) = aliases.keys().find(|a| !Selector::parse(a).is_alias()) {
+				bail!("alias name {alias:?} in {path:?} is invalid");
+			}
+			if aliases.contains_key(Selector::INSTALLED) {
+				bail!("alias {:?} in {path:?} is built in, and can't be set", Selector::INSTALLED);
+			}
+
+			let mut config = overrides.open_create(true)?;
+			let os = target_os(cli.target_os.as_deref(), config.with_doc.data());
+			let mut checks = Vec::with_capacity(aliases.len());
+			for (alias, version) in aliases.iter() {
+				checks.push((alias, version, check_alias_target(&config, version, os, force)?));
+			}
+			for (alias, version, check) in checks {
+				println!("{alias} => {} ({check})", color::version(version));
+				config.with_doc.set_alias(alias.as_str(), version.as_str());
+			}
+			config.rewrite()?;
+		}
+
+		Command::Alias { export: Some(path), .. } => {
+			let data: ConfigData = overrides.open_create(false)?.with_doc.into();
+			let aliases: AliasMap = data.aliases.into_iter().collect();
+			write_aliases(&path, &aliases)?;
+		}
+
+		Command::Alias { alias, version: value, force, policy, .. } => {
+			let alias = alias.context("no alias specified")?;
 			if !Selector::parse(&alias).is_alias() {
 				bail!("alias name {alias:?} is invalid");
 			}
+			if alias == Selector::INSTALLED && (value.is_some() || policy.is_some()) {
+				bail!("alias {alias:?} is built in, and can't be set");
+			}
 
-			let mut config = Config::open_create(true)?;
+			// Only setting the alias or its policy writes the configuration, so only then does it have to be created.
+			let mut config = overrides.open_create(value.is_some() || policy.is_some())?;
 			if let Some(version) = value {
+				let os = target_os(cli.target_os.as_deref(), config.with_doc.data());
+				let check = check_alias_target(&config, &version, os, force)?;
+				println!("{alias} => {} ({check})", color::version(&version));
+				if let Some(policy) = policy {
+					println!("Policy: {policy}");
+					config.with_doc.set_alias_policy(&alias, policy);
+				}
 				config.with_doc.set_alias(alias, version);
 				config.rewrite()?;
+			} else if let Some(policy) = policy {
+				let version = config.with_doc.data().aliases.get(&alias)
+					.with_context(|| anyhow!("alias {alias:?} isn't set"))?;
+				println!("{alias} => {}", color::version(version));
+				println!("Policy: {policy}");
+				config.with_doc.set_alias_policy(&alias, policy);
+				config.rewrite()?;
+			} else if alias == Selector::INSTALLED {
+				println!("{}", find_toolchain(config.with_doc.data(), Selector::parse(&alias))?.name);
 			} else if let Some(version) = config.with_doc.data().aliases.get(&alias) {
 				println!("{version}");
 			}
 		}
 
-		Command::Show => {
-			for (home, version_names) in ToolchainVersions::new() {
-				println!("{}:", home.display());
-				let version_names = match version_names {
-					Ok(i) => i,
-					Err(e) if e.kind() == IoErrorKind::NotFound => {
-						continue
-					}
-					Err(e) => bail!("couldn't read {}: {e}", home.display())
-				};
-				for result in version_names {
-					let version_name = result.with_context(|| anyhow!("encountered error while iterating over {home:?}"))?;
-					println!("  {} => {}", version_name.to_string_lossy(), home.join(&version_name).display());
+		Command::History => {
+			let path = state_home().map(journal_path).context("couldn't determine state directory")?;
+			let journal = Journal::read(&path).context("failed to read configuration history")?;
+			if journal.entries.is_empty() {
+				println!("No changes recorded");
+			}
+			for entry in journal.entries.iter().rev() {
+				println!("{} {}", display_time(entry.time()), entry.command);
+				for change in entry.changes.iter() {
+					println!("  {}", display_change(change, false));
 				}
 			}
 		}
 
-		Command::Update { selector, redownload, alias } => {
-			let mut config = Config::open_create(true)?;
+		Command::Undo { force } => {
+			let mut config = overrides.open_create(true)?;
+			let path = config.journal.clone().context("couldn't determine state directory")?;
+			let mut journal = Journal::read(&path).context("failed to read configuration history")?;
+			let entry = journal.entries.pop().context("no changes to undo")?;
+			for change in entry.changes.iter() {
+				let current = journaled_value(config.with_doc.document(), &change.key);
+				if current != change.new && !force {
+					bail!(
+						"{} was changed since `{}` (use `--force` to revert it anyway)",
+						change.key, entry.command,
+					);
+				}
+			}
 
-			let selector = unwrap_selector(selector, &config);
-			let parsed_selector = Selector::parse(&selector);
+			for change in entry.changes.iter().rev() {
+				config.with_doc.revert(change);
+			}
+			config.rewrite_unrecorded().context("failed to write changes to configuration file")?;
+			journal.write(&path).context("failed to write configuration history")?;
+			println!("Undid `{}` from {}", entry.command, display_time(entry.time()));
+			for change in entry.changes.iter() {
+				println!("  {}", display_change(change, true));
+			}
+		}
 
-			let client = smdrop_client(&config);
-			let branch = client.select_branch(config.with_doc.data(), parsed_selector)?;
-			println!("Remote branch: {}", branch.name());
+		Command::Show { remote: true } => {
+			let config = overrides.open_create(false)?;
+			let provider = source::provider(&config);
+			let os = target_os(cli.target_os.as_deref(), config.with_doc.data());
 
-			let remote = branch.relevant_urls(&client)?
-				.max_by(RelevantUrl::version_ord)
-				.with_context(|| anyhow!("received no versions for branch {:?}", branch.name()))?;
+			println!("{:<16} {:<10} {:<16} PATH", "VERSION", "STATUS", "LATEST");
+			for row in compare_with_remote(&*provider, os)? {
+				let latest = row.latest.as_deref().unwrap_or("-");
+				let path = row.path.unwrap_or_default();
+				println!(
+					"{:<16} {:<10} {:<16} {}",
+					color::version(row.version), row.status.to_string(), color::version(latest), color::path(&path),
+				);
+			}
+		}
 
-			let remote_ver = remote.version();
-			println!("Remote version: {remote_ver}");
+		Command::Licenses { selector } => {
+			let data: ConfigData = overrides.open_create(false)?.with_doc.into();
+			let toolchains = match selector.as_deref() {
+				Some(selector) => {
+					let found = find_toolchain(&data, Selector::parse(selector))?;
+					vec![(found.name.clone(), found.into_path())]
+				}
+				None => InstalledToolchain::all()?.into_iter().map(move |t| (t.name, t.path)).collect(),
+			};
+			for (name, path) in toolchains {
+				println!("{} => {}", color::version(&name), color::path(&path));
+				let licenses = licenses(&path)
+					.with_context(|| anyhow!("failed to read the license files of {}", path.display()))?;
+				if licenses.is_empty() {
+					// Only toolchains installed by Rookup can be reinstalled to extract them.
+					if matches!(Receipt::read(&path), Ok(Some(..))) {
+						println!("  no license files (reinstall it with `--redownload` to extract them)");
+					} else {
+						println!("  no license files");
+					}
+				}
+				for license in licenses {
+					match license.title.as_deref() {
+						Some(title) => println!("  {}: {title}", color::path(&license.path)),
+						None => println!("  {}", color::path(&license.path)),
+					}
+				}
+			}
+		}
 
-			let remote_url = remote.url();
-			println!("Remote URL: {remote_url}");
+		Command::Hash { selector, cache_key } => {
+			let data: ConfigData = overrides.open_create(false)?.with_doc.into();
+			let found = find_toolchain(&data, Selector::parse(&selector))?;
+			let name = found.name.clone();
+			let path = found.into_path();
+			let digest = content_digest(&path)
+				.with_context(|| anyhow!("failed to hash the files of {}", path.display()))?;
+			if cache_key {
+				println!("rookup-{name}-{}", &digest[..16]);
+			} else {
+				println!("{digest}");
+			}
+		}
 
-			let installed_ver = find_latest_toolchain_of(branch.name()).map(move |(v, ..)| v);
-			if let Some(latest_installed_ver) = installed_ver.as_ref() {
-				println!("Installed version: {latest_installed_ver}");
+		Command::Info { selector } => {
+			let config = overrides.open_create(false)?;
+			let provider = source::provider(&config);
+			let data = config.with_doc.data();
+			let os = target_os(cli.target_os.as_deref(), data);
+			let selector = unwrap_selector(selector, &config);
+
+			let info = SelectorInfo::collect(&*provider, data, selector.as_selector(), os);
+			// Nothing is known about aliases that are neither set nor select a remote version.
+			if let (Err(e @ FindToolchainError::NoAliasDefault(..)), None | Some(Err(..))) = (&info.installed, &info.remote) {
+				bail!("{e}");
 			}
+			info::print(&selector.to_string(), &info);
+		}
 
-			let upgrading = installed_ver
-				.is_none_or(|v| version_ord(v.as_str(), remote_ver).is_lt());
-			println!("Is upgrade: {}", bool_display(upgrading));
+		Command::Branches => {
+			let config = overrides.open_create(false)?;
+			let provider = source::provider(&config);
+			let data = config.with_doc.data();
+			let mut branches = provider.branch_names()?;
+			branches.sort_by(move |a, b| version_ord(a.as_str(), b.as_str()));
+			let channels: Vec<_> = ["latest", "stable"].into_iter()
+				.filter_map(|alias| {
+					let branch = source::select_branch(branches.clone(), data, Selector::Alias(alias)).ok()?;
+					Some((alias, branch))
+				})
+				.collect();
 
-			let needs_download = redownload || (upgrading && !is_installed(OsStr::new(remote_ver)));
-			println!("Needs download: {}", bool_display(needs_download));
-			if needs_download {
-				let destination = toolchain_destination(remote_ver)?;
-				println!("Destination: {}", destination.display());
+			let installed = InstalledToolchain::all()?;
+			for branch in branches {
+				let mut marks: Vec<_> = channels.iter()
+					.filter(|(.., b)| *b == branch)
+					.map(move |(alias, ..)| alias.to_string())
+					.collect();
+				let versions: Vec<_> = installed.iter()
+					.filter(|t| branch_of(&t.name).is_some_and(|b| b == branch))
+					.map(move |t| t.name.as_str())
+					.collect();
+				if !versions.is_empty() {
+					marks.push(format!("installed: {}", versions.join(", ")));
+				}
 
-				InstallVersion {
-					agent: &client.agent,
-					url: remote_url,
-					max_bytes: config.with_doc.data().source.max_download_size,
-					destination,
-				}.call()?;
+				print!("{}", color::version(branch));
+				if !marks.is_empty() {
+					print!(" [{}]", marks.join(", "));
+				}
+				println!();
 			}
+		}
+
+		Command::Show { remote: false } => {
+			let data: ConfigData = overrides.open_create(false)?.with_doc.into();
+
+			for ToolchainSummary { toolchain, components, missing_compiler, is_default, aliases } in ToolchainSummary::all(&data)? {
+				let mut marks = Vec::new();
+				if !components.is_empty() {
+					let components: Vec<_> = components.iter().map(Component::to_string).collect();
+					marks.push(format!("{} only", components.join(", ")));
+				}
+				if missing_compiler {
+					marks.push("missing compiler".to_string());
+				}
+				if is_default {
+					marks.push("default".to_string());
+				}
+				marks.extend(aliases.into_iter().map(|alias| match data.alias_policy(&alias) {
+					AliasPolicy::Track => alias,
+					policy => format!("{alias}: {policy}"),
+				}));
 
-			if let Some(alias) = alias.as_deref().or(parsed_selector.to_alias()) {
-				println!("Alias: {alias}");
-				config.with_doc.set_alias(alias, remote_ver);
+				print!("{}", color::version(&toolchain.name));
+				if !marks.is_empty() {
+					print!(" [{}]", marks.join(", "));
+				}
+				println!(" => {} ({})", color::path(&toolchain.path), toolchain.origin());
+				for path in toolchain.shadowed.iter() {
+					println!("  also in {} (unused)", color::path(path));
+				}
 			}
-			config.rewrite().context("failed to write changes to configuration file")?;
 		}
-	
-		Command::Install { selector, redownload } => {
-			let config = Config::open_create(false)?;
 
-			let parsed_selector = Selector::parse(&selector);
+		Command::Update { selector, redownload, alias, all, install, github_output } => {
+			let mut config = overrides.open_create(true)?;
+			let provider = source::provider(&config);
+			let options = UpdateOptions {
+				os: target_os(cli.target_os.as_deref(), config.with_doc.data()).to_string(),
+				parts: install.parts(config.with_doc.data()),
+				redownload,
+				github_output,
+			};
 
-			let client = smdrop_client(&config);
-			let branch = client.select_branch(config.with_doc.data(), parsed_selector)?;
-			println!("Remote branch: {}", branch.name());
-
-			let versions = branch.relevant_urls(&client)?;
-			let version = match parsed_selector {
-				Selector::Alias(..) => {
-					versions.max_by(RelevantUrl::version_ord)
-						.with_context(move || anyhow!("received no versions for branch {:?}", branch.name()))?
+			if all {
+				let mut aliases: Vec<_> = config.with_doc.data().aliases.iter()
+					.filter(move |(.., version)| branch_of(version).is_some())
+					.map(move |(alias, ..)| alias.clone())
+					.collect();
+				aliases.sort();
+				// Listings of the branches of all aliases are fetched at the same time, instead of one after another.
+				let data = config.with_doc.data();
+				let selectors = aliases.iter()
+					.filter(move |alias| data.alias_policy(alias) != AliasPolicy::Pin)
+					.map(move |alias| Selector::Alias(alias));
+				let provider = source::Prefetched::new(&*provider, data, selectors, &options.os);
+				let mut failed = 0;
+				for (i, alias) in aliases.iter().enumerate() {
+					if i > 0 {
+						println!();
+					}
+					println!("Updating alias {}", color::version(alias));
+					if let Err(e) = update(&mut config, &provider, Selector::Alias(alias), Some(alias), &options) {
+						eprintln!("{} {e}", color::error("Error:"));
+						failed += 1;
+					}
 				}
-				Selector::Super(requested) => {
-					versions.filter(move |v| v.version().is_sub_version_of(requested))
-						.max_by(RelevantUrl::version_ord)
-						.with_context(move || anyhow!("couldn't find version {requested:?} in branch {:?}", branch.name()))?
+				config.rewrite().context("failed to write changes to configuration file")?;
+				if failed > 0 {
+					bail!("failed to update {failed} of {} aliases", aliases.len());
 				}
+			} else {
+				let selector = unwrap_selector(selector, &config);
+				let parsed_selector = selector.as_selector();
+				let alias = alias.as_deref().or(parsed_selector.to_alias());
+				update(&mut config, &*provider, parsed_selector, alias, &options)?;
+				config.rewrite().context("failed to write changes to configuration file")?;
+			}
+		}
+	
+		Command::Install { selector, url, from_archive, as_version, sha256, redownload, prefix, install, github_output } => {
+			let config = overrides.open_create(false)?;
+			let os = target_os(cli.target_os.as_deref(), config.with_doc.data());
+			let prefix = prefix.map(absolute).transpose().context("failed to get absolute path to the prefix")?;
+			if let Some(prefix) = prefix.as_deref() {
+				check_prefix(prefix)?;
+			}
+
+			let from_archive = from_archive.map(absolute).transpose().context("failed to get absolute path to the archive")?;
+			let target = match (url.as_deref(), from_archive.as_deref()) {
+				(Some(url), _) => InstallTarget::Url { url, as_version: as_version.as_deref() },
+				(None, Some(archive)) => InstallTarget::Archive(archive),
+				(None, None) => InstallTarget::Selector(Selector::parse(selector.as_deref().context("no selector or URL was specified")?)),
 			};
+			let parts = install.parts(config.with_doc.data());
+			let redownload = redownload || prefix.is_some();
+			let InstallPlan { provider, branch, remote: version, needs_download } = plan_install(&config, target, os, &parts, redownload)?;
+			if let Some(branch) = branch {
+				println!("Remote branch: {branch}");
+			}
 
 			let remote_ver = version.version();
-			println!("Remote version: {remote_ver}");
+			println!("Remote version: {}", color::version(remote_ver));
 
 			let remote_url = version.url();
 			println!("Remote URL: {remote_url}");
+			if let Some(published) = version.published() {
+				println!("Published: {}", display_time(published));
+			}
+			if let Some(size) = version.size() {
+				println!("Download size: {}", ByteSize(size));
+			}
 
-			let needs_download = redownload || !is_installed(OsStr::new(remote_ver));
-			println!("Needs download: {}", bool_display(needs_download));
+			println!("Needs download: {}", color::yes_no(needs_download));
 			if needs_download {
-				let destination = toolchain_destination(remote_ver)?;
-				println!("Destination: {}", destination.display());
+				let _group = github_output.then(|| github::LogGroup::new(&format!("Installing {remote_ver}")));
+				install_remote(&*provider, config.with_doc.data(), os, &parts, &version, sha256.as_deref(), prefix.clone())?;
+			}
 
-				InstallVersion {
-					agent: &client.agent,
-					url: remote_url,
-					max_bytes: config.with_doc.data().source.max_download_size,
-					destination,
-				}.call()?;
+			if github_output {
+				write_github_outputs(config.with_doc.data(), os, remote_ver, &parts, prefix.as_deref())?;
 			}
+			if let Some(prefix) = prefix.as_deref() {
+				if let Some(compiler) = CompilerNames::new(config.with_doc.data(), os).find_in(prefix) {
+					println!("Compiler: {}", color::path(&compiler));
+				}
+			}
+		}
+
+		Command::Sync { install } => {
+			let config = overrides.open_create(false)?;
+			let os = target_os(cli.target_os.as_deref(), config.with_doc.data());
+			sync(&config, os, &install.parts(config.with_doc.data()))?;
 		}
 
-		Command::Remove { selector } => {
-			let data: ConfigData = Config::open_default(false)?.with_doc.into();
+		Command::Remove { selector, json } => {
+			let data: ConfigData = overrides.open_default(false)?.with_doc.into();
 	
-			let parsed_selector = Selector::parse(&selector);
-			let (toolchains, home) = installed_toolchains()?;
-			for version in toolchains {
-				let version = version.with_context(|| anyhow!("failed to read directory contents of {home:?}"))?;
-				let version = version.into_string().ok().context("installed version name is not UTF-8")?;
-				if parsed_selector.test(&data, &version) {
-					print!("{version} => ");
-					let path = home.join(version);
-					println!("{}", path.display());
-					if let Err(e) = remove_dir_all(&path)
-						.with_context(|| anyhow!("failed to recursively delete toolchain at {path:?}"))
-					{
-						println!("{e}");
-					}
-				}
-			}
+			let removals = remove_matching(&data, Selector::parse(&selector))?;
+			removal::print(&removals, json)?;
 		}
 
 		Command::ListUnused => {
-			let data: ConfigData = Config::open_default(false)?.with_doc.into();
+			let data: ConfigData = overrides.open_default(false)?.with_doc.into();
 
 			let UnusedToolchains { home, versions } = UnusedToolchains::new(&data)?;
 			for version in versions {
-				print!("{version} => ");
+				print!("{} => ", color::version(&version));
 				let path = home.join(version);
-				println!("{}", path.display());
+				println!("{}", color::path(&path));
 			}
 		}
 
-		Command::Purge => {
-			let data: ConfigData = Config::open_default(false)?.with_doc.into();
+		Command::Purge { json, interactive } => {
+			let data: ConfigData = overrides.open_default(false)?.with_doc.into();
 
-			let UnusedToolchains { home, versions } = UnusedToolchains::new(&data)?;
-			for version in versions {
-				print!("{version} => ");
-				let path = home.join(version);
-				println!("{}", path.display());
-				remove_dir_all(&path)
-					.with_context(|| anyhow!("failed to recursively delete toolchain at {path:?}"))?;
+			let mut unused = UnusedToolchains::new(&data)?;
+			if interactive {
+				let Some(chosen) = choose_unused(&unused.home, unused.versions.drain().collect())? else {
+					println!("No toolchains were deleted");
+					return Ok(())
+				};
+				unused.versions.extend(chosen);
+			}
+			let removals = unused.remove();
+			removal::print(&removals, json)?;
+			// Purging stops at the first toolchain that couldn't be removed.
+			removals.into_iter().try_for_each(move |r| r.result)?;
+		}
+
+		Command::Which { explain: true } => {
+			let config = overrides.open_default(false)?;
+			let (steps, result) = explain::explain(&config);
+			for (i, step) in steps.iter().enumerate() {
+				eprintln!("{}. {step}", i + 1);
 			}
+			print!("{}", result?.display());
 		}
 
-		Command::Which => {
-			let data = Config::open_default(false)?.with_doc.into();
+		Command::Explain => {
+			let config = overrides.open_default(false)?;
+			let (steps, result) = explain::explain(&config);
+			for (i, step) in steps.iter().enumerate() {
+				println!("{}. {step}", i + 1);
+			}
+			result?;
+		}
+
+		Command::Which { explain: false } => {
+			let data = overrides.open_default(false)?.with_doc.into();
 			let (toolchain, ..) = current_toolchain(&data)
 				.map_err(move |e| anyhow!("failed to get current toolchain: {e}"))?;
 
-			let parsed = Selector::parse(&toolchain);
-			let toolchain_path = find_toolchain(&data, parsed)?.into_path();
+			let toolchain_path = find_toolchain(&data, toolchain.as_selector())?.into_path();
 			print!("{}", toolchain_path.display());
 		}
+
+		Command::Status { porcelain } => {
+			let data = overrides.open_default(false)?.with_doc.into();
+			let (toolchain, source) = current_toolchain(&data)
+				.map_err(move |e| anyhow!("failed to get current toolchain: {e}"))?;
+
+			let (version, installed) = match find_toolchain(&data, toolchain.as_selector()) {
+				Ok(found) => (Some(found.name), true),
+				Err(FindToolchainError::NotFound { version, .. }) => (Some(version), false),
+				Err(
+					FindToolchainError::LatestNotFound(..)
+					| FindToolchainError::NoAliasDefault(..)
+					| FindToolchainError::NoCompiler(..)
+					| FindToolchainError::NoneInstalled
+				) => (None, false),
+				Err(e) => return Err(e.into()),
+			};
+
+			if porcelain {
+				let source = match source {
+					ToolchainSource::Env => "env",
+					ToolchainSource::Config => "config",
+				};
+				let installed = if installed { "installed" } else { "not-installed" };
+				println!("{toolchain}\t{source}\t{}\t{installed}", version.as_deref().unwrap_or("-"));
+			} else {
+				let source = match source {
+					ToolchainSource::Env => "the `ROOKUP_TOOLCHAIN` environment variable",
+					ToolchainSource::Config => "the configuration file",
+				};
+				println!("Selector: {toolchain} (from {source})");
+				println!("Version: {}", version.as_deref().unwrap_or("unknown"));
+				println!("Installed: {}", color::yes_no(installed));
+			}
+		}
+
+		Command::Run { selector, command } => {
+			let data = overrides.open_default(false)?.with_doc.into();
+			let selector = Selector::parse(&selector);
+			let toolchain_path = find_toolchain(&data, selector)?.into_path();
+			let env = toolchain_env(selector, &toolchain_path)
+				.context("failed to construct environment for toolchain")?;
+			run_command(&command, env)?;
+		}
+
+		Command::Exec { toolchain, command } => {
+			let data: ConfigData = overrides.open_default(false)?.with_doc.into();
+			let (_, env) = exec_env(&data, toolchain)?;
+			run_command(&command, env)?;
+		}
+
+		Command::Shell { selector, shell } => {
+			let data: ConfigData = overrides.open_default(false)?.with_doc.into();
+			let (toolchain_path, env) = exec_env(&data, selector)?;
+			let shell = shell.unwrap_or_else(default_shell);
+			eprintln!("Starting {} with toolchain {}; run `exit` to leave it", shell.display(), toolchain_path.display());
+			run_command(&[shell], env)?;
+		}
+
+		Command::Export => {
+			let state = export::State::collect(overrides.open_create(false)?)?;
+			println!("{}", serde_json::to_string_pretty(&state)?);
+		}
+
+		Command::Paths { json } => {
+			let config = overrides.open_default(false).ok();
+			let paths = paths::resolve(cli.toolchain_home.is_some(), config.as_ref().map(move |c| c.with_doc.data()));
+			if json {
+				println!("{}", serde_json::to_string_pretty(&paths)?);
+			} else {
+				for path in paths {
+					println!("{path}");
+				}
+			}
+		}
+
+		#[cfg(feature = "tui")]
+		Command::Tui { install } => {
+			let config = overrides.open_create(true)?;
+			let parts = install.parts(config.with_doc.data());
+			tui::run(config, cli.target_os.as_deref(), &parts)?;
+		}
+
+		Command::Mirror { command: MirrorCommand::Sync { dir, branches, latest_only, redownload } } => {
+			let config = overrides.open_create(false)?;
+			if config.with_doc.data().source.kind != SourceKind::Smdrop {
+				bail!("only `smdrop` sources can be mirrored");
+			}
+			let client = smdrop_client(&config);
+			mirror::sync(&client, &dir, &mirror::SyncOptions {
+				branches: &branches,
+				latest_only,
+				redownload,
+				max_bytes: config.with_doc.data().source.max_download_size,
+			})?;
+		}
+
+		#[cfg(feature = "mirror-serve")]
+		Command::Mirror { command: MirrorCommand::Serve { dir, bind } } => {
+			mirror::serve::serve(dir, bind.as_str())?;
+		}
+
+		Command::Doctor { fix_locks } => {
+			let config = overrides.open_create(false)?;
+			let locks = doctor::find_locks(&config.path).context("failed to look for lock files")?;
+			let mut stale = 0;
+			for (path, info) in locks {
+				let owner = match info.owner {
+					Some(owner) => format!("process {} (since {})", owner.pid, display_time(owner.since)),
+					None => "a process that crashed while taking it".into(),
+				};
+				if !info.is_stale {
+					println!("{}: held by {owner}", color::path(&path));
+				} else if fix_locks {
+					if clear_stale_lock(&path).with_context(|| anyhow!("failed to remove {}", path.display()))? {
+						println!("{}: removed stale lock left behind by {owner}", color::path(&path));
+					}
+				} else {
+					stale += 1;
+					println!("{}: stale lock left behind by {owner}", color::path(&path));
+				}
+			}
+			if stale > 0 {
+				bail!("found {stale} stale locks (run `rookup doctor --fix-locks` to remove them)");
+			}
+			println!("No problems found");
+		}
+
+		Command::Toolchain { command: ToolchainCommand::Check { selector } } => {
+			let data: ConfigData = overrides.open_create(false)?.with_doc.into();
+			let parsed_selector = Selector::parse(&selector);
+			let toolchain = InstalledToolchain::all()?.into_iter()
+				.rfind(|t| t.name == selector || parsed_selector.test(&data, &t.name))
+				.with_context(|| anyhow!("no installed toolchain matches {selector:?}"))?;
+
+			let problems = check::check_toolchain(&toolchain.path);
+			println!("{} => {}", color::version(&toolchain.name), color::path(&toolchain.path));
+			for problem in problems.iter() {
+				println!("  {problem}");
+			}
+			if !problems.is_empty() {
+				bail!("found {} problems in toolchain {}", problems.len(), toolchain.name);
+			}
+			println!("  no problems found");
+		}
+
+		Command::Toolchain { command: ToolchainCommand::Import { dir, name } } => {
+			let data: ConfigData = overrides.open_create(false)?.with_doc.into();
+			let compilers = CompilerNames::new(&data, std::env::consts::OS);
+			let path = import::import_toolchain(&dir, &name, &compilers)?;
+			println!("Imported {} => {}", color::version(&name), color::path(&path));
+			println!("Select it with an alias (for e.g. `rookup alias <alias> {name}`).");
+		}
+
+		Command::Toolchain { command: ToolchainCommand::Export { selector, archive } } => {
+			let data: ConfigData = overrides.open_create(false)?.with_doc.into();
+			let found = find_toolchain(&data, Selector::parse(&selector))?;
+			let name = found.name.clone();
+			let path = found.into_path();
+			let receipt = Receipt::read(&path)
+				.with_context(|| anyhow!("failed to read the install receipt of {}", path.display()))?
+				// Custom toolchains weren't installed by Rookup, so they're recorded as copied from their directory.
+				.unwrap_or_else(|| Receipt {
+					version: name.clone(),
+					origin: Origin::Directory { path: path.display().to_string() },
+					target_os: None,
+					components: Vec::new(),
+					signature: None,
+					compiler: None,
+					runner: None,
+				});
+			let files = package::export_toolchain(&path, &receipt, &archive)?;
+			println!("Exported {} ({files} files) => {}", color::version(&name), color::path(&archive));
+		}
+	}
+
+	Ok(())
+}
+
+/// Options of `rookup update` that apply to every selector that it updates.
+struct UpdateOptions {
+	os: String,
+	parts: InstallParts,
+	redownload: bool,
+	github_output: bool,
+}
+
+/// Install the latest version of the remote branch that `selector` selects, and set `alias` (if any) to it, as
+/// allowed by the alias's policy.
+fn update(
+	config: &mut Config, provider: &dyn Provider, selector: Selector<'_>, alias: Option<&str>, options: &UpdateOptions,
+) -> AResult<()> {
+	let UpdateOptions { os, parts, redownload, github_output } = options;
+	let data = config.with_doc.data();
+	let current = alias.and_then(|a| Some((a, data.aliases.get(a)?.clone(), data.alias_policy(a))));
+	if let Some((alias, version, AliasPolicy::Pin)) = current.as_ref() {
+		println!("Alias {alias} is pinned to {}; skipping", color::version(version));
+		return Ok(())
+	}
+
+	let plan = plan_update(provider, data, selector, os, parts, *redownload)?;
+	for warning in plan.warnings.iter() {
+		eprintln!("{} {warning}", color::warning("Warning:"));
+	}
+	println!("Remote branch: {}", color::version(&plan.branch));
+
+	let remote = &plan.latest;
+	let remote_ver = remote.version();
+	println!("Remote version: {}", color::version(remote_ver));
+
+	let remote_url = remote.url();
+	println!("Remote URL: {remote_url}");
+	if let Some(published) = remote.published() {
+		println!("Published: {}", display_time(published));
+	}
+	if let Some(size) = remote.size() {
+		println!("Download size: {}", ByteSize(size));
+	}
+
+	if let Some(latest_installed_ver) = plan.installed.as_ref() {
+		println!("Installed version: {}", color::version(latest_installed_ver));
+		let installed_published = plan.remotes.iter()
+			.find(move |r| r.version() == latest_installed_ver.as_str())
+			.and_then(RelevantUrl::published);
+		let age = installed_published.zip(remote.published())
+			.and_then(move |(installed, remote)| display_age(installed, remote));
+		if let Some(age) = age {
+			println!("Installed version is {age} older");
+		}
+	}
+
+	// Toolchains that were installed from the same archive under another name are used instead of downloading it again.
+	let target = plan.same_archive.as_deref().unwrap_or(remote_ver);
+	let frozen = current.as_ref().filter(move |(.., policy)| *policy == AliasPolicy::Frozen);
+	if let Some((alias, version, ..)) = frozen {
+		// Aliases that are set to a requirement (like `1.12`) don't change as long as it still matches.
+		let req = version.parse::<VersionReq>().ok();
+		let unchanged = [remote_ver, target].into_iter()
+			.any(|v| v == version || req.as_ref().is_some_and(move |r| r.matches(v)));
+		if !unchanged {
+			bail!(
+				"alias {alias:?} is frozen at {version}, but updating would change it to {remote_ver} (use `rookup alias \
+				{alias} --policy track` to allow it)"
+			);
+		}
+	}
+
+	println!("Is upgrade: {}", color::yes_no(plan.is_upgrade));
+	if plan.is_upgrade {
+		print_release_notes(provider, &plan.branch, &plan.remotes, plan.installed.as_deref(), remote);
+	}
+
+	println!("Needs download: {}", color::yes_no(plan.needs_download));
+	if let Some(same_archive) = plan.same_archive.as_deref() {
+		println!("Installed from the same archive: {}", color::version(same_archive));
+	}
+	if plan.needs_download {
+		let _group = github_output.then(|| github::LogGroup::new(&format!("Installing {remote_ver}")));
+		install_remote(provider, config.with_doc.data(), os, parts, remote, None, None)?;
 	}
 
-	const fn bool_display(b: bool) -> &'static str {
-		if b { "Yes" } else { "No" }
+	if *github_output {
+		write_github_outputs(config.with_doc.data(), os, plan.resulting_version(), parts, None)?;
 	}
 
+	if let Some(alias) = alias {
+		println!("Alias: {alias}");
+		let version = frozen.map_or(target, move |(_, version, ..)| version.as_str());
+		config.with_doc.set_alias(alias, version);
+	}
 	Ok(())
 }
 
-fn toolchain_destination<P: AsRef<std::path::Path>>(version: P) -> AResult<PathBuf> {
-	let mut buffer = toolchain_home().context("couldn't get toolchain destination directory")?;
-	buffer.push(version);
-	Ok(buffer)
+/// Install the toolchain that the project in the current directory selects if it's missing, and check that its
+/// include directories exist, failing if any of these couldn't be satisfied.
+fn sync(config: &Config, os: &str, parts: &InstallParts) -> AResult<()> {
+	let data = config.with_doc.data();
+	let current_dir = current_dir().context("failed to get current directory")?;
+	let project = find_project_config(&current_dir)
+		.context("no rookup.toml was found in the current directory or any of its parents")?;
+	println!("Project file: {}", color::path(&project));
+
+	let mut failures = Vec::new();
+	let (toolchain, ..) = current_toolchain(data).map_err(move |e| anyhow!("failed to get current toolchain: {e}"))?;
+	println!("Selector: {toolchain}");
+	match sync_toolchain(config, toolchain.as_selector(), os, parts) {
+		Ok(version) => println!("Toolchain: {}", color::version(version)),
+		Err(e) => failures.push(format!("toolchain {:?} couldn't be installed: {e}", toolchain.to_string())),
+	}
+
+	let include_dirs = config.with_doc.include_dirs(&config.path, &current_dir);
+	for dir in include_dirs.iter() {
+		if dir.is_dir() {
+			println!("Include directory: {}", color::path(dir));
+		} else {
+			failures.push(format!("include directory {} doesn't exist", dir.display()));
+		}
+	}
+
+	for failure in failures.iter() {
+		eprintln!("{} {failure}", color::error("Error:"));
+	}
+	if !failures.is_empty() {
+		bail!(
+			"failed to satisfy {} of {} requirements of {}", failures.len(), include_dirs.len() + 1, project.display(),
+		);
+	}
+	Ok(())
 }
 
-fn unwrap_selector(selector: Option<String>, config: &Config) -> String {
-	selector.unwrap_or_else(move || config.with_doc.data().default.clone())
+/// Install the toolchain that `selector` selects if it isn't installed yet, returning its version.
+/// 
+/// Aliases that are set to versions are installed at exactly those versions, rather than the latest ones of their
+/// branches.
+fn sync_toolchain(config: &Config, selector: Selector<'_>, os: &str, parts: &InstallParts) -> AResult<String> {
+	let data = config.with_doc.data();
+	let remote_selector = match selector {
+		Selector::Alias(alias) => match data.aliases.get(alias) {
+			Some(version) if branch_of(version).is_some() => Selector::Super(version),
+			// Custom toolchains can't be installed, so they're only looked up.
+			Some(..) => return Ok(find_toolchain(data, selector)?.name),
+			None => selector,
+		},
+		Selector::Super(..) => selector,
+		Selector::Path(..) => return Ok(find_toolchain(data, selector)?.name),
+	};
+	if let Ok(found) = find_toolchain(data, selector) {
+		if has_components(&found.clone().into_path(), &parts.components, &CompilerNames::new(data, os)) {
+			return Ok(found.name)
+		}
+	}
+
+	let InstallPlan { provider, remote, needs_download, .. } = plan_install(
+		config, InstallTarget::Selector(remote_selector), os, parts, false,
+	)?;
+	if needs_download {
+		install_remote(&*provider, data, os, parts, &remote, None, None)?;
+	}
+	Ok(remote.version().to_string())
 }
 
-fn installed_toolchains() -> AResult<(DirNames, PathBuf)> {
-	let home = toolchain_home().context("couldn't get toolchain destination directory")?;
-	let toolchains = read_dir(&home).map(DirNames).with_context(|| anyhow!("failed to iterate over {home:?}"))?;
-	Ok((toolchains, home))
+/// Print the changelog of `branch`, and the release notes of the versions in `remotes` that are newer than
+/// `installed`, up to `latest`.
+/// 
+/// If nothing is `installed`, only the release notes of `latest` are printed.
+fn print_release_notes(
+	provider: &dyn Provider, branch: &str,
+	remotes: &[RelevantUrl], installed: Option<&str>, latest: &RelevantUrl,
+) {
+	if let Some(url) = provider.changelog_url(branch) {
+		println!("Changelog: {url}");
+	}
+
+	let mut newer: Vec<_> = remotes.iter()
+		.filter(move |r| match installed {
+			Some(installed) => version_ord(r.version(), installed).is_gt() && r.version_ord(latest).is_le(),
+			None => r.version() == latest.version(),
+		})
+		.filter_map(move |r| Some((r.version(), r.notes()?)))
+		.collect();
+	newer.sort_by(move |(a, ..), (b, ..)| version_ord(*a, *b));
+	for (version, notes) in newer {
+		println!("Release notes for {version}:");
+		if let Some(url) = notes.url.as_deref() {
+			println!("  {url}");
+		}
+		for line in notes.body.iter().flat_map(move |b| b.lines()) {
+			println!("  {line}");
+		}
+	}
 }
 
-struct UnusedToolchains {
-	pub home: PathBuf,
-	pub versions: FxHashSet<String>,
+/// Download the toolchain at `remote` with `provider`, install it into the toolchain home, and run the `post-install`
+/// hooks, printing progress and cancelling the installation if interrupted.
+/// 
+/// If `expected_sha256` is specified, the archive is only installed if it has that digest.
+/// If `prefix` is specified, the toolchain is installed there instead of the toolchain home.
+fn install_remote(
+	provider: &dyn Provider, data: &ConfigData,
+	os: &str, parts: &InstallParts,
+	remote: &RelevantUrl, expected_sha256: Option<&str>, prefix: Option<PathBuf>,
+) -> AResult<()> {
+	let destination = match prefix {
+		Some(prefix) => prefix,
+		None => toolchain_destination(remote.version())?,
+	};
+	println!("Destination: {}", color::path(&destination));
+	if destination.exists() && !has_components(&destination, &parts.components, &CompilerNames::new(data, os)) {
+		println!("Existing installation is missing components; reinstalling");
+	}
+
+	let interrupt = interrupt::InterruptGuard::new();
+	let result = InstallRemote {
+		provider,
+		data,
+		os,
+		parts,
+		remote,
+		expected_sha256,
+		destination: Some(destination.clone()),
+		progress: &report_install(destination),
+		cancel: interrupt.token(),
+	}.call();
+	if result.is_err() && stderr().is_terminal() {
+		// Clear the progress line before the error is printed.
+		eprint!("\r\x1b[K");
+	}
+	result?;
+	Ok(())
 }
 
-impl UnusedToolchains {
-	pub fn new(data: &ConfigData) -> AResult<Self> {
-		let (versions, home) = installed_toolchains()?;
-
-		let mut versions = {
-			let result: Result<FxHashSet<_>, _> = versions
-				.filter_map(move |r| match r {
-					Ok(v) => match v.into_string() {
-						Ok(v) => Some(Ok(v)),
-						Err(..) => None,
-					},
-					Err(e) => Some(Err(e)),
-				})
-				.collect();
-			result.with_context(|| anyhow!("failed to read directory contents of {home:?}"))?
-		};
+/// Fail if installing a toolchain into `prefix` would replace anything other than a toolchain installed by Rookup.
+fn check_prefix(prefix: &Path) -> AResult<()> {
+	let is_empty = match prefix.read_dir() {
+		Ok(mut entries) => entries.next().is_none(),
+		Err(e) if e.kind() == IoErrorKind::NotFound => true,
+		Err(e) => return Err(anyhow!(e).context(format!("failed to read {}", prefix.display()))),
+	};
+	if !is_empty && !matches!(Receipt::read(prefix), Ok(Some(..))) {
+		bail!("{} isn't empty or a toolchain installed by Rookup, so it won't be replaced", prefix.display())
+	}
+	Ok(())
+}
 
-		if let Ok(default_toolchain) = find_toolchain(data, Selector::parse(&data.default)) {
-			versions.remove(&default_toolchain.name);
+/// Return a function that prints extracted files, and keeps a progress line updated if standard error is a terminal.
+fn report_install(destination: PathBuf) -> impl Fn(InstallEvent<'_>) {
+	let is_terminal = stderr().is_terminal();
+	move |event| match event {
+		InstallEvent::Entry { path, progress } => {
+			if is_terminal {
+				eprint!("\r\x1b[K");
+			}
+			if let Some(path) = path {
+				eprintln!("{} => {}", path.display(), color::path(&destination.join(path)).stderr());
+			}
+			if is_terminal {
+				eprint!("{progress}");
+			}
+		}
+		InstallEvent::Extracted { progress } => {
+			if is_terminal {
+				eprint!("\r\x1b[K");
+			}
+			eprintln!("Done: {progress}");
 		}
-		for version in data.aliases.values() {
-			versions.remove(version);
+		InstallEvent::Verified { url, signature } => eprintln!("Verified signature of {url} with {signature}"),
+		InstallEvent::Warning { message } => eprintln!("{} {message}", color::warning("Warning:")),
+		InstallEvent::Hook { command } => println!("Running hook: {command}"),
+		InstallEvent::Waiting { owner } => match owner {
+			Some(owner) => eprintln!("Waiting for process {} to finish installing into {}", owner.pid, destination.display()),
+			None => eprintln!("Waiting for another process to finish installing into {}", destination.display()),
+		},
+	}
+}
+
+/// Check that `selector` selects an installed toolchain or, if `allow_remote` is `true`, a version for the `os` target
+/// that is available on the remote, returning a description of what it selects.
+fn resolve_selector(config: &Config, selector: Selector<'_>, allow_remote: bool, os: &str) -> AResult<String> {
+	let data = config.with_doc.data();
+	let local_error = match find_toolchain(data, selector) {
+		Ok(found) => return Ok(format!("installed toolchain {}", found.name)),
+		Err(e) => e,
+	};
+	if !allow_remote {
+		return Err(anyhow!(local_error).context(format!("{selector} doesn't select an installed toolchain")))
+	}
+
+	let provider = source::provider(config);
+	let branch = provider.select_branch(data, selector)
+		.with_context(|| anyhow!("{selector} doesn't select an installed toolchain or a remote branch"))?;
+	let versions = provider.relevant_urls(&branch, os)?;
+	// `stable` and `latest` select a branch on their own if they aren't aliases.
+	let is_special = selector.to_alias().is_some_and(move |a| !data.aliases.contains_key(a));
+	let latest = versions.iter()
+		.filter(move |v| is_special || selector.test(data, v.version()))
+		.max_by(move |a, b| a.version_ord(b))
+		.with_context(|| anyhow!("{selector} doesn't select any version on remote branch {branch} for {os}"))?;
+	Ok(format!("remote version {} (not installed)", latest.version()))
+}
+
+/// Format `change` as its key and values, from the new value to the old one if `reverted` is `true`.
+fn display_change(change: &Change, reverted: bool) -> String {
+	let display = move |value: &Option<String>| match value {
+		Some(value) => color::version(value).to_string(),
+		None => "(unset)".into(),
+	};
+	let (from, to) = if reverted { (&change.new, &change.old) } else { (&change.old, &change.new) };
+	format!("{}: {} => {}", change.key, display(from), display(to))
+}
+
+/// Check that `version` is installed or available on the remote for the `os` target, returning a description of where
+/// it was found.
+/// 
+/// If it was found in neither place, then this fails, or, if `force` is `true`, prints a warning.
+fn check_alias_target(config: &Config, version: &str, os: &str, force: bool) -> AResult<&'static str> {
+	if find_toolchain_path(OsStr::new(version), &CompilerNames::new(config.with_doc.data(), os)).is_some() {
+		return Ok("installed")
+	}
+
+	let provider = source::provider(config);
+	let remote = branch_of(version)
+		.with_context(|| anyhow!("it doesn't belong to any branch"))
+		.and_then(|branch| provider.relevant_urls(&branch, os))
+		.and_then(|versions| {
+			versions.iter().any(move |v| v.version() == version).then_some(())
+				.with_context(|| anyhow!("it isn't available on the remote for {os}"))
+		});
+	match remote {
+		Ok(()) => Ok("available on the remote, not installed"),
+		Err(e) if force => {
+			eprintln!("{} version {version:?} is not installed, and {e:#}", color::warning("Warning:"));
+			Ok("unverified")
 		}
+		Err(e) => bail!("version {version:?} is not installed, and {e:#} (use `--force` to set it anyway)"),
+	}
+}
 
-		Ok(Self {
-			home,
-			versions
+/// Let the user choose which of the unused toolchains of `versions` in `home` to delete, and confirm it.
+/// 
+/// Returns the chosen versions, or [`None`] if the user chose none of them or didn't confirm.
+fn choose_unused(home: &Path, mut versions: Vec<String>) -> AResult<Option<Vec<String>>> {
+	if versions.is_empty() {
+		return Ok(Some(versions))
+	}
+	versions.sort_by(move |a, b| version_ord(a.as_str(), b.as_str()));
+	let last_used = LastUsed::read().unwrap_or_else(move |e| {
+		eprintln!("{} {e:#}", color::warning("Warning:"));
+		LastUsed::default()
+	});
+	let sizes: Vec<_> = versions.iter().map(move |v| dir_size(&home.join(v)).ok()).collect();
+	let now = SystemTime::now();
+	let labels: Vec<_> = versions.iter().zip(sizes.iter())
+		.map(|(version, size)| {
+			let size = size.map(move |s| ByteSize(s).to_string()).unwrap_or_else(move || "unknown size".into());
+			let used = match last_used.get(version) {
+				Some(time) => match display_age(time, now) {
+					Some(age) => format!("last used {age} ago"),
+					None => "last used within the hour".into(),
+				},
+				None => "last use unknown".into(),
+			};
+			format!("{} ({size}, {used})", color::version(version))
 		})
+		.collect();
+
+	let Some(chosen) = picker::toggle("Toolchains to delete", &labels)? else {
+		return Ok(None)
+	};
+	let chosen: Vec<_> = versions.into_iter().zip(sizes).zip(chosen)
+		.filter_map(move |(version, chosen)| chosen.then_some(version))
+		.collect();
+	let total: u64 = chosen.iter().filter_map(move |(.., size)| *size).sum();
+	if chosen.is_empty() || !picker::confirm(&format!("Delete {} toolchains, freeing {}?", chosen.len(), ByteSize(total)))? {
+		return Ok(None)
 	}
+	Ok(Some(chosen.into_iter().map(move |(version, ..)| version).collect()))
 }
 
-struct InstallVersion<'a> {
-	pub agent: &'a Agent,
-	pub url: &'a str,
-	pub max_bytes: u64,
-	pub destination: PathBuf,
+/// Return the selectors that can be picked as the default: aliases first, then installed toolchains, newest first.
+fn default_choices(data: &ConfigData) -> AResult<Vec<String>> {
+	let mut aliases: Vec<_> = ["stable", "latest", Selector::INSTALLED].into_iter()
+		.map(String::from)
+		.chain(data.aliases.keys().cloned())
+		.collect();
+	aliases.sort();
+	aliases.dedup();
+
+	let mut choices = aliases;
+	choices.extend(
+		InstalledToolchain::all()?.into_iter().rev()
+			.filter(move |t| t.name.parse::<VersionReq>().is_ok())
+			.map(move |t| format!(":{}", t.name))
+	);
+	Ok(choices)
 }
 
-impl InstallVersion<'_> {
-	pub fn call(self) -> AResult<()> {
-		let body = self.agent.get(self.url)
-			.call().with_context(|| anyhow!("failed to fetch archive at {:?}", self.url))?
-			.into_body().into_with_config()
-			.limit(self.max_bytes);
+/// Append the path to the installed toolchain of `version` (or `prefix`, if it was installed there), `version` itself,
+/// and a cache key for it to `$GITHUB_OUTPUT`.
+fn write_github_outputs(
+	data: &ConfigData, os: &str, version: &str, parts: &InstallParts, prefix: Option<&Path>,
+) -> AResult<()> {
+	let path = match prefix {
+		Some(prefix) => prefix.to_path_buf(),
+		None => find_installed(OsStr::new(version), &parts.components, &CompilerNames::new(data, os))
+			.with_context(|| anyhow!("toolchain of version {version} is not installed"))?,
+	};
+	github::write_outputs(&[
+		("toolchain-path", &path.display().to_string()),
+		("version", version),
+		("cache-key", &github::cache_key(os, version, parts)),
+	])
+}
 
-		let archive_kind = smdrop::ArchiveKind::from_str(self.url)
-			.with_context(|| anyhow!("failed to determine format of archive at {:?}", self.url))?;
-		let mut archive = smdrop::Archive::new(body, archive_kind)?;
-	
-		for (path, mut entry) in archive.entries()?
-			.filter_map(move |(name, entry)| String::from_utf8(name).ok().map(move |path| (path, entry)))
-			.filter_map(move |(name, entry)| sp_from_sm::map_to_sp_root(name).map(move |path| (path, entry)))
-			.filter(move |(path, ..)| sp_from_sm::is_sp_file(path))
-		{
-			let destination_path = self.destination.join(&path);
-			if !entry.is_dir() {
-				if let Some(parent) = destination_path.parent() {
-					create_dir_all(parent)
-						.with_context(|| anyhow!("failed to create directories up to {destination_path:?}"))?;
-				}
+/// Return the path to the toolchain selected by `selector` (or the current one) and the environment that uses it.
+fn exec_env(data: &ConfigData, selector: Option<String>) -> AResult<(PathBuf, Vec<(&'static str, OsString)>)> {
+	let selector = match selector {
+		Some(selector) => SelectorBuf::parse(&selector),
+		None => current_toolchain(data).map_err(move |e| anyhow!("failed to get current toolchain: {e}"))?.0,
+	};
+	let toolchain_path = find_toolchain(data, selector.as_selector())?.into_path();
+	let compiler = CompilerCommand::new(data, toolchain_path.clone())
+		.context("failed to read toolchain receipt")?;
+	// Toolchains that need a runner can only be run by a single executable through the proxy.
+	let spcomp = if compiler.runner.is_empty() {
+		compiler.compiler
+	} else {
+		current_exe().context("couldn't get path to Rookup")?
+			.with_file_name(format!("rookup-spcomp{EXE_SUFFIX}"))
+	};
 
-				let mut options = File::options();
-				#[cfg(unix)]
-				if path.file_name().and_then(move |n| n.to_str()).is_some_and(rookup_common::is_compiler) {
-					options.mode(0o777);
-				}
+	let mut env = toolchain_env(selector.as_selector(), &toolchain_path)
+		.context("failed to construct environment for toolchain")?;
+	env.extend(build_env(&spcomp, &toolchain_path));
+	Ok((toolchain_path, env))
+}
+
+/// Return the shell of the user, from `SHELL` (or `COMSPEC` on Windows).
+fn default_shell() -> OsString {
+	if cfg!(windows) {
+		var_os("COMSPEC").unwrap_or_else(move || "cmd.exe".into())
+	} else {
+		var_os("SHELL").unwrap_or_else(move || "/bin/sh".into())
+	}
+}
 
-				let mut file = options.create(true).truncate(true).write(true).open(&destination_path)
-					.with_context(|| anyhow!("failed to open {destination_path:?}"))?;
-				eprintln!("{} => {}", path.display(), destination_path.display());
+/// Run `command` (a program followed by its arguments) with the environment variables `env`, and exit with its exit
+/// code.
+/// Run `command` with `env`, and exit with the same status as it, like the proxy does with the compiler.
+/// 
+/// On Unix, if the command was terminated by a signal, the same signal is raised for this process.
+fn run_command(command: &[OsString], env: Vec<(&'static str, OsString)>) -> AResult<()> {
+	let (program, args) = command.split_first().context("no command specified")?;
+	let status = ProcessCommand::new(program)
+		.args(args)
+		.envs(env)
+		.status()
+		.with_context(|| anyhow!("failed to run {program:?}"))?;
+	if let Some(code) = status.code() {
+		exit(code)
+	}
 
-				io_copy(&mut entry, &mut file)
-					.with_context(|| anyhow!("failed to pipe data of {path:?} to {destination_path:?}"))?;
-			}
+	#[cfg(unix)]
+	if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+		eprintln!("{} {program:?} was terminated by signal {signal}", color::error("Error:"));
+		// SAFETY: Resetting the handler of the signal to the default one and raising it has no memory safety
+		// implications.
+		unsafe {
+			libc::signal(signal, libc::SIG_DFL);
+			libc::raise(signal);
 		}
-	
-		Ok(())
+		// The signal may be ignored or blocked, so fall back to the shell convention.
+		exit(128 + signal)
 	}
+
+	exit(1)
+}
+
+/// Configuration keys that are overridden by options on the command line.
+struct Overrides(Option<LayerFile>);
+
+impl Overrides {
+	fn new(cli: &Cli) -> Self {
+		Self(LayerFile::from_args(cli.source.as_deref().map(move |source| ("default-source", Value::from(source)))))
+	}
+
+	/// Open the configuration file like [`ConfigExt::open_create`], merged with the overrides.
+	fn open_create(&self, with_write: bool) -> Result<Config, ConfigError> {
+		self.apply(Config::open_create(with_write)?)
+	}
+
+	/// Open the configuration file like [`ConfigExt::open_default`], merged with the overrides.
+	fn open_default(&self, with_write: bool) -> Result<Config, ConfigError> {
+		self.apply(Config::open_default(with_write)?)
+	}
+
+	fn apply(&self, mut config: Config) -> Result<Config, ConfigError> {
+		if let Some(layer) = self.0.clone() {
+			config.with_doc.add_layer(layer)?;
+		}
+		Ok(config)
+	}
+}
+
+fn unwrap_selector(selector: Option<String>, config: &Config) -> SelectorBuf {
+	selector.map_or_else(move || config.with_doc.data().default.clone(), move |s| SelectorBuf::parse(&s))
 }
 
 fn main() -> ExitCode {
 	match real_main() {
 		Ok(..) => ExitCode::SUCCESS,
-		Err(e) => {
-			eprintln!("Fatal error: {e}");
-			ExitCode::FAILURE
+		Err(e) => match e.downcast_ref::<OpsError>() {
+			Some(OpsError::Install(InstallError::Cancelled { removed })) => {
+				eprintln!("{} removed partially extracted files in {}; no installed toolchains were changed", color::error("Interrupted:"), removed.display());
+				ExitCode::from(interrupt::INTERRUPTED_EXIT_CODE as u8)
+			}
+			_ => {
+				eprintln!("{} {e}", color::error("Fatal error:"));
+				ExitCode::FAILURE
+			}
 		}
 	}
 }
@@ -0,0 +1,257 @@
+use anyhow::{
+	anyhow, Context, Result as AResult,
+};
+use quick_xml::escape::escape;
+use rookup_common::version::version_ord;
+use rustc_hash::FxHashMap;
+use sha2::{
+	Digest, Sha256,
+};
+use std::{
+	fmt::Write as _,
+	fs::{
+		File, create_dir_all, remove_file, rename,
+	},
+	io::{
+		Read, Write, Result as IoResult,
+	},
+	path::{
+		Path, PathBuf,
+	},
+};
+
+use rookup_ops::{
+	smdrop::{
+		Branch, Client, VersionUrl,
+	},
+	hex, RelevantUrl,
+};
+
+#[cfg(feature = "mirror-serve")]
+pub mod serve;
+
+/// File extension of generated checksum files.
+pub const CHECKSUM_EXT: &str = "sha256";
+
+/// Name of the generated directory listing files.
+pub const INDEX_FILE: &str = "index.html";
+
+/// Options for [`sync`].
+#[derive(Debug, Clone)]
+pub struct SyncOptions<'a> {
+	/// Names of branches to mirror. If empty, then all branches are mirrored.
+	pub branches: &'a [String],
+	/// Only mirror the latest version for each target platform of a branch.
+	pub latest_only: bool,
+	/// Download archives even if they already exist in the mirror.
+	pub redownload: bool,
+	/// Maximum size, in bytes, of one downloaded file.
+	pub max_bytes: u64,
+}
+
+/// Download the branches selected by `options` from the server of `client` into `root`, laying them out such that
+/// `root` can be served by a static file server and used as a `root-url`.
+pub fn sync(client: &Client, root: &Path, options: &SyncOptions<'_>) -> AResult<()> {
+	create_dir_all(root).with_context(|| anyhow!("failed to create mirror directory at {root:?}"))?;
+
+	let mut branches: Vec<Branch> = client.branches().context("couldn't fetch branches")?
+		.collect::<Result<Vec<_>, _>>().context("couldn't parse listing of branches")?
+		.into_iter()
+		.filter(|b| options.branches.is_empty() || options.branches.iter().any(|s| s == b.name()))
+		.collect();
+	for requested in options.branches {
+		if !branches.iter().any(|b| b.name() == requested) {
+			eprintln!("Branch {requested:?} was not found on the server");
+		}
+	}
+	branches.sort_by(|a, b| version_ord(a.name(), b.name()));
+
+	for branch in branches.iter() {
+		println!("Branch: {}", branch.name());
+		sync_branch(client, branch, &root.join(branch.name()), options)?;
+	}
+
+	let names: Vec<String> = read_listing_names(root)?;
+	write_index(root, &names)
+		.with_context(|| anyhow!("failed to write listing for {root:?}"))?;
+	Ok(())
+}
+
+fn sync_branch(client: &Client, branch: &Branch, dir: &Path, options: &SyncOptions<'_>) -> AResult<()> {
+	create_dir_all(dir).with_context(|| anyhow!("failed to create directory at {dir:?}"))?;
+
+	let urls: Vec<VersionUrl<Box<str>>> = branch.versions(client)
+		.map_err(|e| anyhow!("couldn't fetch versions for branch {:?}: {e}", branch.name()))?
+		.map(move |v| v.map(move |v| v.into_url()))
+		.collect::<Result<_, _>>()
+		.map_err(|e| anyhow!("couldn't parse listing of versions for branch {:?}: {e}", branch.name()))?;
+
+	let urls: Vec<&VersionUrl<Box<str>>> = if options.latest_only {
+		let mut latest: FxHashMap<&str, (String, &VersionUrl<Box<str>>)> = FxHashMap::default();
+		for url in urls.iter() {
+			let (Some(target), Some(version)) = (url.target(), url.version_str()) else { continue };
+			if version.0 == "latest" { continue }
+			let version = version.normalized().into_owned();
+			match latest.get(target) {
+				Some((other, ..)) if version_ord(other.as_str(), version.as_str()).is_ge() => {}
+				_ => {
+					latest.insert(target, (version, url));
+				}
+			}
+		}
+		// Keep `latest` pointers around so that the mirror looks like the original server.
+		urls.iter()
+			.filter(|u| {
+				u.version_str().is_some_and(|v| v.0 == "latest")
+				|| latest.values().any(move |(.., l)| l == u)
+			})
+			.collect()
+	} else {
+		urls.iter().collect()
+	};
+
+	for url in urls {
+		let file_name = url.file_name();
+		let destination = dir.join(file_name);
+		if !options.redownload && destination.exists() {
+			println!("  {file_name} (exists)");
+			continue
+		}
+
+		println!("  {file_name}");
+		let digest = download(client, url, &destination, options.max_bytes)?;
+		if RelevantUrl::is_archive(url) {
+			let checksum_path = dir.join(format!("{file_name}.{CHECKSUM_EXT}"));
+			std::fs::write(&checksum_path, format!("{digest}  {file_name}\n"))
+				.with_context(|| anyhow!("failed to write checksum to {checksum_path:?}"))?;
+		}
+	}
+
+	let names = read_listing_names(dir)?;
+	write_index(dir, &names).with_context(|| anyhow!("failed to write listing for {dir:?}"))?;
+	Ok(())
+}
+
+/// Download the file at `url` into `destination`, returning the hex-encoded SHA-256 digest of its contents.
+fn download(client: &Client, url: &str, destination: &Path, max_bytes: u64) -> AResult<String> {
+	let mut reader = client.agent.get(url)
+		.call().with_context(|| anyhow!("failed to fetch {url:?}"))?
+		.into_body().into_with_config()
+		.limit(max_bytes)
+		.reader();
+
+	let part_path = {
+		let mut buffer = destination.as_os_str().to_owned();
+		buffer.push(".part");
+		PathBuf::from(buffer)
+	};
+	let mut part = PartFile::create(part_path)?;
+
+	let mut hasher = Sha256::new();
+	let mut buffer = vec![0; 64 * 1024];
+	loop {
+		let n = reader.read(&mut buffer).with_context(|| anyhow!("failed to download {url:?}"))?;
+		if n == 0 { break }
+		hasher.update(&buffer[..n]);
+		part.write_all(&buffer[..n])?;
+	}
+	part.persist(destination)?;
+	Ok(hex(&hasher.finalize()))
+}
+
+/// File that is being downloaded, which is removed when dropped unless it was moved to its destination.
+struct PartFile {
+	path: PathBuf,
+	file: Option<File>,
+	persisted: bool,
+}
+
+impl PartFile {
+	fn create(path: PathBuf) -> AResult<Self> {
+		let file = File::create(&path).with_context(|| anyhow!("failed to open {path:?}"))?;
+		Ok(Self {
+			path,
+			file: Some(file),
+			persisted: false,
+		})
+	}
+
+	fn write_all(&mut self, buffer: &[u8]) -> AResult<()> {
+		let file = self.file.as_mut().expect("file should only be taken when persisted");
+		file.write_all(buffer).with_context(|| anyhow!("failed to write to {:?}", self.path))
+	}
+
+	/// Move the file to `destination`.
+	fn persist(mut self, destination: &Path) -> AResult<()> {
+		if let Some(mut file) = self.file.take() {
+			file.flush().with_context(|| anyhow!("failed to write to {:?}", self.path))?;
+		}
+		rename(&self.path, destination)
+			.with_context(|| anyhow!("failed to move {:?} to {destination:?}", self.path))?;
+		self.persisted = true;
+		Ok(())
+	}
+}
+
+impl Drop for PartFile {
+	fn drop(&mut self) {
+		// Windows doesn't allow deleting files that are still open.
+		drop(self.file.take());
+		if !self.persisted {
+			let _ = remove_file(&self.path);
+		}
+	}
+}
+
+/// Return the sorted names of files and directories (with a trailing `/`) that should be listed in `dir`.
+pub fn read_listing_names(dir: &Path) -> AResult<Vec<String>> {
+	let mut names = Vec::new();
+	for entry in std::fs::read_dir(dir).with_context(|| anyhow!("failed to iterate over {dir:?}"))? {
+		let entry = entry.with_context(|| anyhow!("failed to iterate over {dir:?}"))?;
+		let Ok(mut name) = entry.file_name().into_string() else { continue };
+		if name == INDEX_FILE || name.ends_with(".part") { continue }
+		if entry.file_type()?.is_dir() {
+			name.push('/');
+		}
+		names.push(name);
+	}
+	names.sort();
+	Ok(names)
+}
+
+/// Write a directory listing in the format understood by the `smdrop` client.
+fn write_index(dir: &Path, names: &[String]) -> IoResult<()> {
+	std::fs::write(dir.join(INDEX_FILE), render_index(names))
+}
+
+/// Render a directory listing of `names` in the format understood by the `smdrop` client.
+pub fn render_index(names: &[String]) -> String {
+	let mut html = String::from(concat!(
+		"<!DOCTYPE HTML PUBLIC \"-//W3C//DTD HTML 3.2 Final//EN\">\n",
+		"<html>\n",
+		" <body>\n",
+		"<ul>",
+	));
+	for name in names {
+		let name = escape(name.as_str());
+		let _ = writeln!(html, "<li><a href=\"{name}\"> {name}</a></li>");
+	}
+	html.push_str("</ul>\n</body></html>\n");
+	html
+}
+
+#[test]
+fn partial_downloads_are_removed() {
+	let dir = rookup_common::TempDir::new("rookup-mirror-part").unwrap();
+	let part_path = dir.path().join("a.tar.gz.part");
+	let mut part = PartFile::create(part_path.clone()).unwrap();
+	part.write_all(b"abc").unwrap();
+	drop(part);
+	assert!(!part_path.exists());
+
+	let mut part = PartFile::create(part_path.clone()).unwrap();
+	part.write_all(b"abc").unwrap();
+	part.persist(&dir.path().join("a.tar.gz")).unwrap();
+	assert!(!part_path.exists());
+	assert_eq!(std::fs::read(dir.path().join("a.tar.gz")).unwrap(), b"abc");
+}
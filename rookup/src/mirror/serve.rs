@@ -0,0 +1,176 @@
+//! Minimal static file server for mirrors created with [`sync`](super::sync).
+
+use anyhow::{
+	anyhow, Context, Result as AResult,
+};
+use std::{
+	fs::File,
+	io::{
+		copy as io_copy,
+		BufRead, BufReader, Write, Result as IoResult,
+	},
+	net::{
+		TcpListener, TcpStream, ToSocketAddrs,
+	},
+	path::{
+		Component, Path, PathBuf,
+	},
+	sync::Arc,
+	thread,
+};
+
+use super::{
+	INDEX_FILE,
+	read_listing_names, render_index,
+};
+
+/// Serve the mirror at `root` on `address` until the process is terminated.
+pub fn serve(root: PathBuf, address: impl ToSocketAddrs) -> AResult<()> {
+	let listener = TcpListener::bind(address).context("failed to bind mirror server")?;
+	let local_address = listener.local_addr()?;
+	println!("Serving {} at http://{local_address}/", root.display());
+
+	let root = Arc::new(root);
+	for stream in listener.incoming() {
+		let stream = match stream {
+			Ok(s) => s,
+			Err(e) => {
+				eprintln!("Failed to accept connection: {e}");
+				continue
+			}
+		};
+
+		let root = Arc::clone(&root);
+		thread::spawn(move || {
+			let peer = stream.peer_addr().ok();
+			if let Err(e) = handle(&root, stream) {
+				match peer {
+					Some(peer) => eprintln!("{peer}: {e}"),
+					None => eprintln!("{e}"),
+				}
+			}
+		});
+	}
+
+	Ok(())
+}
+
+fn handle(root: &Path, stream: TcpStream) -> AResult<()> {
+	let mut reader = BufReader::new(stream.try_clone()?);
+	let mut request_line = String::new();
+	reader.read_line(&mut request_line)?;
+	// Skip headers; nothing in them matters for a static mirror.
+	loop {
+		let mut line = String::new();
+		if reader.read_line(&mut line)? == 0 || line == "\r\n" || line == "\n" {
+			break
+		}
+	}
+
+	let mut parts = request_line.split_whitespace();
+	let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+		return respond_status(stream, 400, "Bad Request").map_err(Into::into)
+	};
+	let with_body = match method {
+		"GET" => true,
+		"HEAD" => false,
+		_ => return respond_status(stream, 405, "Method Not Allowed").map_err(Into::into),
+	};
+
+	let target = target.split(['?', '#']).next().unwrap_or_default();
+	let Some(path) = resolve(root, target) else {
+		return respond_status(stream, 404, "Not Found").map_err(Into::into)
+	};
+	println!("{method} {target}");
+
+	if path.is_dir() {
+		if !target.ends_with('/') {
+			let location = format!("{target}/");
+			return respond(stream, 301, "Moved Permanently", &[("Location", &location)], None).map_err(Into::into)
+		}
+
+		let index_path = path.join(INDEX_FILE);
+		if index_path.is_file() {
+			send_file(stream, &index_path, "text/html; charset=utf-8", with_body)
+		} else {
+			let listing = render_index(&read_listing_names(&path)?);
+			let body = with_body.then_some(listing.as_bytes());
+			let length = listing.len().to_string();
+			respond(
+				stream, 200, "OK",
+				&[("Content-Type", "text/html; charset=utf-8"), ("Content-Length", &length)],
+				body,
+			).map_err(Into::into)
+		}
+	} else if path.is_file() {
+		let content_type = match path.extension().and_then(move |e| e.to_str()) {
+			Some("html") => "text/html; charset=utf-8",
+			Some("gz") => "application/gzip",
+			Some("zip") => "application/zip",
+			_ => "text/plain; charset=utf-8",
+		};
+		send_file(stream, &path, content_type, with_body)
+	} else {
+		respond_status(stream, 404, "Not Found").map_err(Into::into)
+	}
+}
+
+/// Map the request `target` to a path inside of `root`, refusing anything that would escape it.
+fn resolve(root: &Path, target: &str) -> Option<PathBuf> {
+	let decoded = percent_decode(target.strip_prefix('/')?)?;
+	let relative = Path::new(&decoded);
+	relative.components()
+		.all(move |c| matches!(c, Component::Normal(..) | Component::CurDir))
+		.then(move || root.join(relative))
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+	let mut bytes = Vec::with_capacity(s.len());
+	let mut iter = s.bytes();
+	while let Some(b) = iter.next() {
+		if b == b'%' {
+			let hi = (iter.next()? as char).to_digit(16)?;
+			let lo = (iter.next()? as char).to_digit(16)?;
+			bytes.push((hi * 16 + lo) as u8);
+		} else {
+			bytes.push(b);
+		}
+	}
+	String::from_utf8(bytes).ok()
+}
+
+fn send_file(mut stream: TcpStream, path: &Path, content_type: &str, with_body: bool) -> AResult<()> {
+	let mut file = File::open(path).with_context(|| anyhow!("failed to open {path:?}"))?;
+	let length = file.metadata()?.len().to_string();
+	respond(&mut stream, 200, "OK", &[("Content-Type", content_type), ("Content-Length", &length)], None)?;
+	if with_body {
+		io_copy(&mut file, &mut stream)?;
+	}
+	Ok(())
+}
+
+fn respond_status(stream: TcpStream, code: u16, reason: &str) -> IoResult<()> {
+	let length = reason.len().to_string();
+	respond(
+		stream, code, reason,
+		&[("Content-Type", "text/plain; charset=utf-8"), ("Content-Length", &length)],
+		Some(reason.as_bytes()),
+	)
+}
+
+fn respond(
+	mut stream: impl Write,
+	code: u16, reason: &str,
+	headers: &[(&str, &str)],
+	body: Option<&[u8]>,
+) -> IoResult<()> {
+	write!(stream, "HTTP/1.1 {code} {reason}\r\n")?;
+	for (name, value) in headers {
+		write!(stream, "{name}: {value}\r\n")?;
+	}
+	stream.write_all(b"Connection: close\r\n\r\n")?;
+	if let Some(body) = body {
+		stream.write_all(body)?;
+	}
+	stream.flush()
+}
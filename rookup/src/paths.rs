@@ -0,0 +1,104 @@
+//! Resolution of the directories and files used by Rookup, as shown by `rookup paths`.
+
+use rookup_common::{
+	cache_home, config_backup_path, config_file_path, config_home, current_toolchain, custom_toolchain_homes,
+	downloads_path, find_project_config, find_toolchain, invocation_log_path, journal_path, netrc_path, pins_file_path,
+	state_home, system_config_path, toolchain_homes,
+	ConfigData, FoundToolchain, ToolchainSource,
+	CACHE_HOME_VAR, CONFIG_HOME_VAR, CUSTOM_TOOLCHAIN_HOME_VAR, NETRC_VAR, STATE_HOME_VAR, SYSTEM_CONFIG_VAR,
+	TOOLCHAIN_HOME_VAR, TOOLCHAIN_VAR,
+};
+use serde::Serialize;
+use std::{
+	env::{
+		current_dir, var_os,
+	},
+	fmt,
+	path::PathBuf,
+};
+
+/// Directory or file used by Rookup.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ResolvedPath {
+	pub name: &'static str,
+	/// Paths that were resolved, which is empty if they couldn't be determined.
+	pub paths: Vec<PathBuf>,
+	/// Environment variable or command-line option that determined the paths, if they aren't the default ones.
+	pub set_by: Option<&'static str>,
+}
+
+impl ResolvedPath {
+	fn new(name: &'static str, paths: impl IntoIterator<Item = PathBuf>, set_by: Option<&'static str>) -> Self {
+		Self {
+			name,
+			paths: paths.into_iter().collect(),
+			set_by,
+		}
+	}
+}
+
+impl fmt::Display for ResolvedPath {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let set_by = match self.set_by {
+			Some(set_by) => format!(" (set by {set_by})"),
+			None => String::new(),
+		};
+		if self.paths.is_empty() {
+			return write!(f, "{}: unknown{set_by}", self.name)
+		}
+		for (i, path) in self.paths.iter().enumerate() {
+			if i > 0 {
+				writeln!(f)?;
+			}
+			write!(f, "{}: {}{set_by}", self.name, path.display())?;
+		}
+		Ok(())
+	}
+}
+
+/// Resolve every directory and file used by Rookup.
+///
+/// `toolchain_home_option` should be `true` if the toolchain home was set with `--toolchain-home`.
+/// The current toolchain is only resolved if the configuration file could be read into `data`.
+pub fn resolve(toolchain_home_option: bool, data: Option<&ConfigData>) -> Vec<ResolvedPath> {
+	let set_by_var = move |var: &'static str| var_os(var).is_some().then_some(var);
+
+	let config_home = config_home();
+	let config_set_by = set_by_var(CONFIG_HOME_VAR);
+	let cache_home = cache_home();
+	let cache_set_by = set_by_var(CACHE_HOME_VAR);
+	let state_home = state_home();
+	let state_set_by = set_by_var(STATE_HOME_VAR);
+	let toolchain_set_by = if toolchain_home_option { Some("--toolchain-home") } else { set_by_var(TOOLCHAIN_HOME_VAR) };
+
+	let mut paths = vec![
+		ResolvedPath::new("config home", config_home.clone(), config_set_by),
+		ResolvedPath::new("config file", config_home.clone().map(config_file_path), config_set_by),
+		ResolvedPath::new("config backup", config_home.clone().map(config_backup_path), config_set_by),
+		ResolvedPath::new("system config file", system_config_path(), set_by_var(SYSTEM_CONFIG_VAR)),
+		ResolvedPath::new("project config file", current_dir().ok().and_then(move |d| find_project_config(&d)), None),
+		ResolvedPath::new("pins file", config_home.map(pins_file_path), config_set_by),
+		ResolvedPath::new("netrc file", netrc_path(), set_by_var(NETRC_VAR)),
+		ResolvedPath::new("cache home", cache_home.clone(), cache_set_by),
+		ResolvedPath::new("downloads", cache_home.map(downloads_path), cache_set_by),
+		ResolvedPath::new("state home", state_home.clone(), state_set_by),
+		ResolvedPath::new("invocation log", state_home.clone().map(invocation_log_path), state_set_by),
+		ResolvedPath::new("config history", state_home.map(journal_path), state_set_by),
+		ResolvedPath::new("toolchain home", toolchain_homes(), toolchain_set_by),
+		ResolvedPath::new("custom toolchain home", custom_toolchain_homes(), set_by_var(CUSTOM_TOOLCHAIN_HOME_VAR)),
+	];
+
+	if let Some((selector, source)) = data.and_then(move |data| current_toolchain(data).ok()) {
+		let set_by = match source {
+			ToolchainSource::Env => Some(TOOLCHAIN_VAR),
+			ToolchainSource::Config => None,
+		};
+		let toolchain = data
+			.and_then(move |data| find_toolchain(data, selector.as_selector()).ok())
+			.map(FoundToolchain::into_path);
+		paths.push(ResolvedPath::new("current toolchain", toolchain.clone(), set_by));
+		paths.push(ResolvedPath::new("includes", toolchain.map(move |t| t.join("include")), set_by));
+	}
+	paths
+}
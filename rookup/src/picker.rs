@@ -0,0 +1,151 @@
+//! Line-based interactive picker for terminals.
+
+use std::io::{
+	stdin, stdout,
+	BufRead, Result as IoResult, Write,
+};
+
+/// Maximum number of items that are listed at once.
+const MAX_SHOWN: usize = 20;
+
+/// Let the user pick one of `items` by number, narrowing the list down by typing (fuzzy-matched) text.
+/// 
+/// Returns the index of the picked item, or [`None`] if the user cancelled with an empty line or end of input.
+pub fn pick(prompt: &str, items: &[String]) -> IoResult<Option<usize>> {
+	let mut stdin = stdin().lock();
+	let mut stdout = stdout().lock();
+	let mut shown: Vec<usize> = (0..items.len()).collect();
+	loop {
+		for (n, &i) in shown.iter().enumerate().take(MAX_SHOWN) {
+			writeln!(stdout, "{:>3}) {}", n + 1, items[i])?;
+		}
+		if shown.len() > MAX_SHOWN {
+			writeln!(stdout, "     ... and {} more", shown.len() - MAX_SHOWN)?;
+		}
+		write!(stdout, "{prompt} (number, or text to filter; empty to cancel): ")?;
+		stdout.flush()?;
+
+		let mut line = String::new();
+		if stdin.read_line(&mut line)? == 0 {
+			writeln!(stdout)?;
+			return Ok(None)
+		}
+		let line = line.trim();
+		if line.is_empty() {
+			return Ok(None)
+		}
+
+		// Numbers that aren't in the list are used for filtering instead (for e.g. `112` for version 1.12).
+		if let Some(&i) = line.parse::<usize>().ok().and_then(|n| n.checked_sub(1)).and_then(|n| shown.get(n)) {
+			return Ok(Some(i))
+		}
+
+		shown = (0..items.len()).filter(|&i| fuzzy_matches(line, &items[i])).collect();
+		match shown.as_slice() {
+			[] => {
+				writeln!(stdout, "Nothing matches {line:?}")?;
+				shown = (0..items.len()).collect();
+			}
+			&[i] => return Ok(Some(i)),
+			_ => {}
+		}
+	}
+}
+
+/// Let the user choose any number of `items`, all of which are chosen at first, by toggling them by number or range
+/// (for e.g. `1 3-5`), `a` for all of them, or `n` for none.
+/// 
+/// Returns whether each item was chosen once the user enters an empty line, or [`None`] if the user cancelled with `q`
+/// or end of input.
+pub fn toggle(prompt: &str, items: &[String]) -> IoResult<Option<Vec<bool>>> {
+	let mut stdin = stdin().lock();
+	let mut stdout = stdout().lock();
+	let mut chosen = vec![true; items.len()];
+	loop {
+		for (i, item) in items.iter().enumerate() {
+			let mark = if chosen[i] { 'x' } else { ' ' };
+			writeln!(stdout, "{:>3}) [{mark}] {item}", i + 1)?;
+		}
+		write!(stdout, "{prompt} (numbers or ranges to toggle, `a` for all, `n` for none; empty to continue, `q` to cancel): ")?;
+		stdout.flush()?;
+
+		let mut line = String::new();
+		if stdin.read_line(&mut line)? == 0 {
+			writeln!(stdout)?;
+			return Ok(None)
+		}
+		match line.trim() {
+			"" => return Ok(Some(chosen)),
+			"q" => return Ok(None),
+			"a" => chosen.fill(true),
+			"n" => chosen.fill(false),
+			line => match parse_toggles(line, items.len()) {
+				Some(indices) => {
+					for i in indices {
+						chosen[i] = !chosen[i];
+					}
+				}
+				None => writeln!(stdout, "{line:?} isn't a list of numbers from 1 to {}", items.len())?,
+			},
+		}
+	}
+}
+
+/// Ask the user a yes-or-no `question`, which is answered with "no" by default.
+pub fn confirm(question: &str) -> IoResult<bool> {
+	let mut stdout = stdout().lock();
+	write!(stdout, "{question} [y/N]: ")?;
+	stdout.flush()?;
+
+	let mut line = String::new();
+	if stdin().lock().read_line(&mut line)? == 0 {
+		writeln!(stdout)?;
+		return Ok(false)
+	}
+	Ok(matches!(line.trim(), "y" | "Y" | "yes"))
+}
+
+/// Parse numbers and ranges of numbers (starting from 1) separated by commas or whitespace into indices less than
+/// `len`.
+fn parse_toggles(line: &str, len: usize) -> Option<Vec<usize>> {
+	let parse = move |n: &str| n.trim().parse::<usize>().ok().filter(move |n| (1..=len).contains(n)).map(move |n| n - 1);
+	let mut indices = Vec::new();
+	for part in line.split(move |c: char| c == ',' || c.is_whitespace()).filter(move |p| !p.is_empty()) {
+		match part.split_once('-') {
+			Some((start, end)) => {
+				let (start, end) = (parse(start)?, parse(end)?);
+				if start > end {
+					return None
+				}
+				indices.extend(start..=end);
+			}
+			None => indices.push(parse(part)?),
+		}
+	}
+	Some(indices)
+}
+
+/// Return `true` if all characters of `pattern` appear in `text` in order, ignoring case.
+fn fuzzy_matches(pattern: &str, text: &str) -> bool {
+	let mut text = text.chars().flat_map(char::to_lowercase);
+	pattern.chars().flat_map(char::to_lowercase).all(move |p| text.any(move |t| t == p))
+}
+
+#[test]
+fn fuzzy_matching_works() {
+	assert!(fuzzy_matches("112", ":1.12.0.7200"));
+	assert!(fuzzy_matches("STb", "stable"));
+	assert!(fuzzy_matches("", "latest"));
+	assert!(!fuzzy_matches("121", ":1.12"));
+	assert!(!fuzzy_matches("lts", "stable"));
+}
+
+#[test]
+fn toggles_are_parsed() {
+	assert_eq!(parse_toggles("1 3-5", 5), Some(vec![0, 2, 3, 4]));
+	assert_eq!(parse_toggles("2,4", 5), Some(vec![1, 3]));
+	assert_eq!(parse_toggles("6", 5), None);
+	assert_eq!(parse_toggles("0", 5), None);
+	assert_eq!(parse_toggles("x", 5), None);
+	assert_eq!(parse_toggles("4-2", 5), None);
+}
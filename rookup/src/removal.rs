@@ -0,0 +1,69 @@
+//! Reporting of toolchains removed by `rookup remove` and `rookup purge`.
+
+use anyhow::Result as AResult;
+use rookup_ops::{
+	disk::ByteSize,
+	Removal,
+};
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::color;
+
+/// Toolchains that were removed, as written by `--json`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Report {
+	pub toolchains: Vec<RemovedToolchain>,
+	/// Total number of bytes of disk space freed.
+	pub freed: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RemovedToolchain {
+	pub version: String,
+	pub path: PathBuf,
+	/// Number of bytes of disk space freed, if the toolchain was removed and its size is known.
+	pub freed: Option<u64>,
+	/// Reason that the toolchain couldn't be removed, if it couldn't.
+	pub error: Option<String>,
+}
+
+impl Report {
+	pub fn new(removals: &[Removal]) -> Self {
+		Self {
+			toolchains: removals.iter()
+				.map(move |r| RemovedToolchain {
+					version: r.version.clone(),
+					path: r.path.clone(),
+					freed: r.freed(),
+					error: r.result.as_ref().err().map(move |e| format!("{e:#}")),
+				})
+				.collect(),
+			freed: Removal::total_freed(removals),
+		}
+	}
+}
+
+/// Print every removed toolchain with the disk space it freed, followed by the total, or a [`Report`] if `json` is
+/// `true`.
+pub fn print(removals: &[Removal], json: bool) -> AResult<()> {
+	if json {
+		println!("{}", serde_json::to_string_pretty(&Report::new(removals))?);
+		return Ok(())
+	}
+
+	for removal in removals {
+		print!("{} => {}", color::version(&removal.version), color::path(&removal.path));
+		match (&removal.result, removal.freed()) {
+			(Err(e), ..) => println!("\n{e}"),
+			(Ok(()), Some(freed)) => println!(" (freed {})", ByteSize(freed)),
+			(Ok(()), None) => println!(),
+		}
+	}
+	if !removals.is_empty() {
+		println!("Freed {} in total", ByteSize(Removal::total_freed(removals)));
+	}
+	Ok(())
+}
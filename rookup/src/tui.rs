@@ -0,0 +1,269 @@
+//! Interactive terminal dashboard for managing installed toolchains.
+
+use anyhow::{
+	anyhow, Context, Result as AResult,
+};
+use ratatui::{
+	crossterm::event::{
+		self, Event, KeyCode, KeyEventKind,
+	},
+	layout::{
+		Constraint, Layout,
+	},
+	style::{
+		Modifier, Style,
+	},
+	text::Line,
+	widgets::{
+		Block, Cell, Paragraph, Row, Table, TableState,
+	},
+	DefaultTerminal, Frame,
+};
+use rookup_common::{
+	version::version_ord,
+	find_toolchain,
+	Config, ConfigData,
+};
+use rookup_ops::{
+	disk::{
+		dir_size, ByteSize,
+	},
+	branch_of, display_time, source, target_os, InstallParts, InstalledToolchain, RelevantUrl,
+};
+use rustc_hash::FxHashMap;
+use std::{
+	fs::remove_dir_all,
+	io::stdin,
+};
+
+use crate::install_remote;
+
+/// Run the dashboard for `config` until the user quits.
+pub fn run(config: Config, target_os_override: Option<&str>, parts: &InstallParts) -> AResult<()> {
+	let mut app = App::new(config, target_os_override, parts)?;
+	let mut terminal = ratatui::init();
+	let result = app.run(&mut terminal);
+	ratatui::restore();
+	result
+}
+
+struct App<'a> {
+	config: Config,
+	target_os_override: Option<&'a str>,
+	parts: &'a InstallParts,
+	toolchains: Vec<(InstalledToolchain, Option<u64>)>,
+	default: Option<String>,
+	/// Latest remote version for every branch, fetched on request.
+	remote: FxHashMap<String, RelevantUrl>,
+	state: TableState,
+	status: String,
+	confirm_remove: bool,
+}
+
+impl<'a> App<'a> {
+	pub fn new(config: Config, target_os_override: Option<&'a str>, parts: &'a InstallParts) -> AResult<Self> {
+		let mut app = Self {
+			config,
+			target_os_override,
+			parts,
+			toolchains: Vec::new(),
+			default: None,
+			remote: FxHashMap::default(),
+			state: TableState::default(),
+			status: String::new(),
+			confirm_remove: false,
+		};
+		app.reload()?;
+		Ok(app)
+	}
+
+	fn data(&self) -> &ConfigData {
+		self.config.with_doc.data()
+	}
+
+	fn reload(&mut self) -> AResult<()> {
+		self.toolchains = InstalledToolchain::all()?.into_iter()
+			.rev()
+			.map(move |t| {
+				let size = dir_size(&t.path).ok();
+				(t, size)
+			})
+			.collect();
+		self.default = find_toolchain(self.data(), self.data().default.as_selector()).ok().map(move |t| t.name);
+		if self.toolchains.is_empty() {
+			self.state.select(None);
+		} else {
+			let selected = self.state.selected().unwrap_or(0).min(self.toolchains.len() - 1);
+			self.state.select(Some(selected));
+		}
+		Ok(())
+	}
+
+	fn selected(&self) -> Option<&InstalledToolchain> {
+		self.state.selected().and_then(|i| self.toolchains.get(i)).map(move |(t, ..)| t)
+	}
+
+	pub fn run(&mut self, terminal: &mut DefaultTerminal) -> AResult<()> {
+		loop {
+			terminal.draw(|frame| self.draw(frame))?;
+			let Event::Key(key) = event::read()? else {
+				continue
+			};
+			if key.kind != KeyEventKind::Press {
+				continue
+			}
+
+			if self.confirm_remove {
+				self.confirm_remove = false;
+				if matches!(key.code, KeyCode::Char('y' | 'Y')) {
+					self.status = self.remove_selected().unwrap_or_else(move |e| format!("{e:#}"));
+				} else {
+					self.status = "Cancelled".into();
+				}
+				continue
+			}
+
+			let result = match key.code {
+				KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+				KeyCode::Up | KeyCode::Char('k') => {
+					self.state.select_previous();
+					Ok(String::new())
+				}
+				KeyCode::Down | KeyCode::Char('j') => {
+					self.state.select_next();
+					Ok(String::new())
+				}
+				KeyCode::Char('d') => self.set_default(),
+				KeyCode::Char('x') | KeyCode::Delete => match self.selected().map(move |t| t.name.clone()) {
+					Some(name) => {
+						self.confirm_remove = true;
+						Ok(format!("Remove {name}? (y/n)"))
+					}
+					None => Ok(String::new()),
+				},
+				KeyCode::Char('r') => {
+					self.status = "Fetching remote versions...".into();
+					terminal.draw(|frame| self.draw(frame))?;
+					self.refresh_remote()
+				}
+				KeyCode::Char('u') => self.update_selected(terminal),
+				_ => continue,
+			};
+			self.status = result.unwrap_or_else(move |e| format!("{e:#}"));
+		}
+	}
+
+	fn set_default(&mut self) -> AResult<String> {
+		let Some(name) = self.selected().map(move |t| t.name.clone()) else {
+			return Ok(String::new())
+		};
+		let selector = format!(":{name}");
+		self.config.with_doc.set_default(selector.as_str());
+		self.config.rewrite().context("failed to write changes to configuration file")?;
+		self.reload()?;
+		Ok(format!("Default set to {selector}"))
+	}
+
+	fn remove_selected(&mut self) -> AResult<String> {
+		let Some(toolchain) = self.selected() else {
+			return Ok(String::new())
+		};
+		let (name, path) = (toolchain.name.clone(), toolchain.path.clone());
+		remove_dir_all(&path).with_context(|| anyhow!("failed to recursively delete toolchain at {path:?}"))?;
+		self.reload()?;
+		Ok(format!("Removed {name}"))
+	}
+
+	fn refresh_remote(&mut self) -> AResult<String> {
+		let provider = source::provider(&self.config);
+		let os = target_os(self.target_os_override, self.data()).to_string();
+		let mut branches: Vec<_> = self.toolchains.iter().filter_map(|(t, ..)| branch_of(&t.name)).collect();
+		branches.sort();
+		branches.dedup();
+
+		self.remote = source::latest_versions(&*provider, branches, &os);
+		Ok(format!("Fetched latest versions of {} branches", self.remote.len()))
+	}
+
+	fn update_selected(&mut self, terminal: &mut DefaultTerminal) -> AResult<String> {
+		let Some(branch) = self.selected().and_then(move |t| branch_of(&t.name)) else {
+			return Ok(String::new())
+		};
+		let Some(remote) = self.remote.get(&branch) else {
+			return Ok(format!("No remote versions known for branch {branch}; press r to fetch them"))
+		};
+		if self.toolchains.iter().any(|(t, ..)| t.name == remote.version()) {
+			return Ok(format!("Latest version of branch {branch} is already installed"))
+		}
+
+		// Installing prints progress, so leave the dashboard until it's done.
+		ratatui::restore();
+		let provider = source::provider(&self.config);
+		let os = target_os(self.target_os_override, self.data());
+		let result = install_remote(&*provider, self.data(), os, self.parts, remote, None, None);
+		if let Err(e) = result.as_ref() {
+			eprintln!("{e:#}");
+		}
+		println!("Press Enter to return to the dashboard");
+		let _ = stdin().read_line(&mut String::new());
+		*terminal = ratatui::init();
+
+		result?;
+		let version = remote.version().to_string();
+		self.reload()?;
+		Ok(format!("Installed {version}"))
+	}
+
+	fn draw(&mut self, frame: &mut Frame) {
+		let [table_area, status_area] = Layout::vertical([Constraint::Min(3), Constraint::Length(3)])
+			.areas(frame.area());
+
+		let data = self.config.with_doc.data();
+		let rows = self.toolchains.iter().map(|(t, size)| {
+			let mut marks = Vec::new();
+			if self.default.as_ref().is_some_and(|d| *d == t.name) {
+				marks.push("default".to_string());
+			}
+			let mut aliases: Vec<_> = data.aliases.iter()
+				.filter(|(.., version)| **version == t.name)
+				.map(move |(alias, ..)| alias.clone())
+				.collect();
+			aliases.sort();
+			marks.extend(aliases);
+
+			let remote = match branch_of(&t.name).and_then(|b| self.remote.get(&b)) {
+				Some(r) if version_ord(r.version(), t.name.as_str()).is_gt() => match r.published() {
+					Some(published) => format!("update: {} ({})", r.version(), display_time(published)),
+					None => format!("update: {}", r.version()),
+				},
+				Some(..) => "up to date".into(),
+				None => String::new(),
+			};
+
+			Row::new([
+				Cell::from(t.name.clone()),
+				Cell::from(marks.join(", ")),
+				Cell::from(size.map(move |s| ByteSize(s).to_string()).unwrap_or_default()),
+				Cell::from(remote),
+				Cell::from(t.path.display().to_string()),
+			])
+		});
+
+		let table = Table::new(rows, [
+			Constraint::Length(16),
+			Constraint::Length(24),
+			Constraint::Length(10),
+			Constraint::Length(44),
+			Constraint::Fill(1),
+		])
+			.header(Row::new(["Version", "Marks", "Size", "Remote", "Path"]).style(Style::new().add_modifier(Modifier::BOLD)))
+			.row_highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+			.block(Block::bordered().title(" Installed toolchains "));
+		frame.render_stateful_widget(table, table_area, &mut self.state);
+
+		let help = "↑/↓ select  d set default  x remove  r fetch remote  u update branch  q quit";
+		let status = Paragraph::new(vec![Line::from(self.status.as_str()), Line::from(help)])
+			.block(Block::bordered());
+		frame.render_widget(status, status_area);
+	}
+}